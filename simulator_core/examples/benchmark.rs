@@ -0,0 +1,159 @@
+//! Compares `Graph::propagate_from` with the compiled engine on a clocked accumulator
+//! built from full adder subcircuits.
+//!
+//! Run with `cargo run --release --example benchmark -- [width] [cycles]`.
+
+use std::{env, time::Instant};
+
+use simulator_core::{
+    components::{
+        gates::{And, Or, Xor},
+        memory::{FlipFlop, FlipFlopKind},
+        simple::{Constant, DebugOutput, Fork},
+        subcircuit::Subcircuit,
+    },
+    graph::{compiled::CompiledGraph, id::TypedId, Graph},
+};
+
+fn full_adder() -> Subcircuit {
+    let mut graph = Graph::new();
+    let [a, b, c] = [(); 3].map(|_| graph.add_comp(Constant::default()));
+    let [fork_a, fork_b, fork_c, fork_x] = [(); 4].map(|_| graph.add_comp(Fork::new(1, 2)));
+    let [xor_ab, xor_c] = [(); 2].map(|_| graph.add_comp(Xor));
+    let [and_ab, and_c] = [(); 2].map(|_| graph.add_comp(And));
+    let or = graph.add_comp(Or);
+    let [sum, carry] = [(); 2].map(|_| graph.add_comp(DebugOutput::default()));
+
+    graph.add_conn(a, 0, fork_a, 0);
+    graph.add_conn(b, 0, fork_b, 0);
+    graph.add_conn(c, 0, fork_c, 0);
+    graph.add_conn(fork_a, 0, xor_ab, 0);
+    graph.add_conn(fork_b, 0, xor_ab, 1);
+    graph.add_conn(fork_a, 1, and_ab, 0);
+    graph.add_conn(fork_b, 1, and_ab, 1);
+    graph.add_conn(xor_ab, 0, fork_x, 0);
+    graph.add_conn(fork_x, 0, xor_c, 0);
+    graph.add_conn(fork_c, 0, xor_c, 1);
+    graph.add_conn(fork_x, 1, and_c, 0);
+    graph.add_conn(fork_c, 1, and_c, 1);
+    graph.add_conn(and_ab, 0, or, 0);
+    graph.add_conn(and_c, 0, or, 1);
+    graph.add_conn(xor_c, 0, sum, 0);
+    graph.add_conn(or, 0, carry, 0);
+
+    Subcircuit::new("full_adder", graph, vec![a, b, c], vec![sum, carry])
+}
+
+struct Accumulator {
+    graph: Graph,
+    clock: TypedId<Constant>,
+    addend: Vec<TypedId<Constant>>,
+    outputs: Vec<TypedId<DebugOutput>>,
+}
+
+fn accumulator(width: u8) -> Accumulator {
+    let mut graph = Graph::new();
+    let clock = graph.add_comp(Constant::default());
+    let clock_fork = graph.add_comp(Fork::new(1, width));
+    graph.add_conn(clock, 0, clock_fork, 0);
+
+    let mut addend = Vec::new();
+    let mut outputs = Vec::new();
+    let mut carry = None;
+    for bit in 0..width as usize {
+        let a = graph.add_comp(Constant::default());
+        let adder = graph.add_comp(full_adder());
+        let register = graph.add_comp(FlipFlop::new(FlipFlopKind::D));
+        let fork = graph.add_comp(Fork::new(1, 2));
+        let output = graph.add_comp(DebugOutput::default());
+
+        graph.add_conn(a, 0, adder, 0);
+        graph.add_conn(fork, 0, adder, 1);
+        if let Some(carry) = carry {
+            graph.add_conn(carry, 1, adder, 2);
+        }
+        graph.add_conn(adder, 0, register, 0);
+        graph.add_conn(clock_fork, bit, register, 1);
+        graph.add_conn(register, 0, fork, 0);
+        graph.add_conn(fork, 1, output, 0);
+
+        carry = Some(adder);
+        addend.push(a);
+        outputs.push(output);
+    }
+    graph.propagate_all();
+
+    Accumulator {
+        graph,
+        clock,
+        addend,
+        outputs,
+    }
+}
+
+/// Addend bits of a cycle, the same for both engines.
+fn addend_bits(cycle: usize, width: usize) -> impl Iterator<Item = bool> {
+    let value = (cycle as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    (0..width).map(move |bit| value >> (bit % 64) & 1 == 1)
+}
+
+pub fn main() {
+    let mut args = env::args()
+        .skip(1)
+        .map(|arg| arg.parse().expect("not a number"));
+    let width = args.next().unwrap_or(32);
+    let cycles = args.next().unwrap_or(2_000);
+
+    let Accumulator {
+        mut graph,
+        clock,
+        addend,
+        outputs,
+    } = accumulator(width as u8);
+    let mut compiled = CompiledGraph::compile(&graph);
+    println!(
+        "{width} bit accumulator: {} nodes, {} cells in {} levels",
+        graph.nodes.len(),
+        compiled.cell_count(),
+        compiled.level_count()
+    );
+
+    let start = Instant::now();
+    for cycle in 0..cycles {
+        for (&id, bit) in addend.iter().zip(addend_bits(cycle, width)) {
+            if graph[id].state != bit {
+                graph[id].state = bit;
+                graph.propagate_from(id);
+            }
+        }
+        for state in [true, false] {
+            graph[clock].state = state;
+            graph.propagate_from(clock);
+        }
+    }
+    let graph_time = start.elapsed();
+
+    let start = Instant::now();
+    for cycle in 0..cycles {
+        for (&id, bit) in addend.iter().zip(addend_bits(cycle, width)) {
+            if compiled.constant(id) != bit {
+                compiled.set_constant(id, bit);
+            }
+        }
+        for state in [true, false] {
+            compiled.set_constant(clock, state);
+        }
+    }
+    let compiled_time = start.elapsed();
+
+    let same = outputs
+        .iter()
+        .all(|&id| graph[id].state == compiled.output(id));
+    println!("graph:    {graph_time:?} for {cycles} cycles");
+    println!("compiled: {compiled_time:?} for {cycles} cycles");
+    println!(
+        "speedup {:.1}x, outputs {}",
+        graph_time.as_secs_f64() / compiled_time.as_secs_f64(),
+        if same { "match" } else { "DIFFER" }
+    );
+}
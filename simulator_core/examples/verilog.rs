@@ -0,0 +1,68 @@
+use simulator_core::{
+    components::{
+        gates::{And, Or, Xor},
+        simple::{Constant, DebugOutput, Fork},
+        subcircuit::Subcircuit,
+    },
+    export::verilog::to_verilog,
+    graph::Graph,
+};
+
+fn half_adder() -> Subcircuit {
+    let mut graph = Graph::new();
+
+    let a = graph.add_comp(Constant::default());
+    let b = graph.add_comp(Constant::default());
+    let a_fork = graph.add_comp(Fork::new(1, 2));
+    let b_fork = graph.add_comp(Fork::new(1, 2));
+    let xor = graph.add_comp(Xor);
+    let and = graph.add_comp(And);
+    let sum = graph.add_comp(DebugOutput::default());
+    let carry = graph.add_comp(DebugOutput::default());
+
+    graph.set_name(a, "a");
+    graph.set_name(b, "b");
+    graph.set_name(sum, "sum");
+    graph.set_name(carry, "carry");
+
+    graph.add_conn(a, 0, a_fork, 0);
+    graph.add_conn(b, 0, b_fork, 0);
+    graph.add_conn(a_fork, 0, and, 0);
+    graph.add_conn(a_fork, 1, xor, 0);
+    graph.add_conn(b_fork, 0, and, 1);
+    graph.add_conn(b_fork, 1, xor, 1);
+    graph.add_conn(xor, 0, sum, 0);
+    graph.add_conn(and, 0, carry, 0);
+
+    Subcircuit::new("half_adder", graph, vec![a, b], vec![sum, carry])
+}
+
+pub fn main() {
+    let mut graph = Graph::new();
+
+    let a = graph.add_comp(Constant::default());
+    let b = graph.add_comp(Constant::default());
+    let c_in = graph.add_comp(Constant::default());
+    let first = graph.add_comp(half_adder());
+    let second = graph.add_comp(half_adder());
+    let or = graph.add_comp(Or);
+    let sum = graph.add_comp(DebugOutput::default());
+    let c_out = graph.add_comp(DebugOutput::default());
+
+    graph.set_name(a, "a");
+    graph.set_name(b, "b");
+    graph.set_name(c_in, "c_in");
+    graph.set_name(sum, "sum");
+    graph.set_name(c_out, "c_out");
+
+    graph.add_conn(a, 0, first, 0);
+    graph.add_conn(b, 0, first, 1);
+    graph.add_conn(first, 0, second, 0);
+    graph.add_conn(c_in, 0, second, 1);
+    graph.add_conn(second, 0, sum, 0);
+    graph.add_conn(first, 1, or, 0);
+    graph.add_conn(second, 1, or, 1);
+    graph.add_conn(or, 0, c_out, 0);
+
+    print!("{}", to_verilog(&graph, "full_adder"));
+}
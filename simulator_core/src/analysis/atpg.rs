@@ -0,0 +1,386 @@
+//! Automatic test pattern generation for single stuck-at faults.
+//!
+//! The combinational cone of the outputs is flattened into simple gates, then
+//! PODEM looks for input values activating each fault and carrying its effect to an
+//! output. Every new vector drops the faults it already detects, and a final set
+//! cover leaves out the vectors other ones make unnecessary.
+
+use std::collections::{HashMap, HashSet};
+
+use bitvec::prelude::*;
+
+use crate::{
+    components::simple::{Constant, DebugOutput},
+    graph::{fault::Fault, id::TypedId, Graph},
+};
+
+use super::{
+    fault_simulation::stuck_at_faults,
+    parallel::{pack, Gate, Net, NetFault, Netlist},
+    test_vectors::TestVectors,
+    truth_table::{check_combinational, TruthTableError},
+};
+
+/// Decisions PODEM may take back for one fault before giving up on it.
+pub const BACKTRACK_LIMIT: usize = 10_000;
+
+/// Vectors detecting every detectable stuck-at fault of the graph,
+/// with the faults sorted by what became of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestSet {
+    pub vectors: TestVectors,
+    pub detected: Vec<Fault>,
+    /// Faults no input can tell apart from the good circuit on the outputs.
+    pub undetectable: Vec<Fault>,
+    /// Faults left undecided after [`BACKTRACK_LIMIT`] backtracks.
+    pub aborted: Vec<Fault>,
+}
+
+/// Generates a small set of vectors for the stuck-at faults of the graph,
+/// none of which can be left out without losing a detected fault.
+///
+/// The `inputs` are set by the vectors, other `Constant`s keep their state.
+pub fn generate_tests(
+    graph: &Graph,
+    inputs: &[TypedId<Constant>],
+    outputs: &[TypedId<DebugOutput>],
+) -> Result<TestSet, TruthTableError> {
+    check_combinational(graph, outputs.iter().map(|&id| id.into()))?;
+    let netlist = Netlist::build(graph, inputs, outputs);
+
+    let faults = stuck_at_faults(graph);
+    let sites = faults
+        .iter()
+        .map(|fault| netlist.site(graph, fault))
+        .collect::<Vec<_>>();
+    let mut targets = sites.iter().flatten().copied().collect::<Vec<_>>();
+    targets.sort_by_key(|fault| (fault.net, fault.stuck));
+    targets.dedup();
+
+    let mut vectors = Vec::new();
+    let mut detected = HashSet::new();
+    let mut undecided = HashMap::new();
+    for &target in &targets {
+        if detected.contains(&target) || undecided.contains_key(&target) {
+            continue;
+        }
+        match netlist.podem(target) {
+            Podem::Detected(cube) => {
+                let vector = cube
+                    .into_iter()
+                    .map(|v| v == Some(true))
+                    .collect::<BitVec>();
+                let packed = pack(std::slice::from_ref(&vector), netlist.inputs);
+                let good = [netlist.eval(&packed[0], None)];
+                let detects = |fault| netlist.detections(&packed, &good, fault, 1)[0];
+                for &fault in &targets {
+                    if !detected.contains(&fault) && detects(fault) {
+                        detected.insert(fault);
+                    }
+                }
+                vectors.push(vector);
+            }
+            outcome => {
+                undecided.insert(target, outcome == Podem::Aborted);
+            }
+        }
+    }
+    let vectors = compact(&netlist, vectors, &targets);
+
+    let mut set = TestSet {
+        vectors: TestVectors {
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+            vectors,
+        },
+        detected: Vec::new(),
+        undetectable: Vec::new(),
+        aborted: Vec::new(),
+    };
+    for (fault, site) in faults.into_iter().zip(sites) {
+        match site.map(|site| (detected.contains(&site), undecided.get(&site))) {
+            Some((true, _)) => set.detected.push(fault),
+            Some((false, Some(true))) => set.aborted.push(fault),
+            _ => set.undetectable.push(fault),
+        }
+    }
+    Ok(set)
+}
+
+/// Picks vectors greedily by the faults they detect which no picked vector does,
+/// then drops vectors whose faults the others cover.
+fn compact(netlist: &Netlist, vectors: Vec<BitVec>, faults: &[NetFault]) -> Vec<BitVec> {
+    let packed = pack(&vectors, netlist.inputs);
+    let good = packed
+        .iter()
+        .map(|words| netlist.eval(words, None))
+        .collect::<Vec<_>>();
+    let by_fault = faults
+        .iter()
+        .map(|&fault| netlist.detections(&packed, &good, fault, vectors.len()))
+        .collect::<Vec<_>>();
+    let detects = (0..vectors.len())
+        .map(|v| {
+            by_fault
+                .iter()
+                .map(|detected| detected[v])
+                .collect::<BitVec>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut covered = bitvec![0; faults.len()];
+    let mut chosen = Vec::new();
+    loop {
+        let gain = |v: usize| (detects[v].clone() & !covered.clone()).count_ones();
+        let Some(best) = (0..vectors.len())
+            .filter(|&v| gain(v) > 0)
+            .max_by_key(|&v| gain(v))
+        else {
+            break;
+        };
+        covered |= detects[best].clone();
+        chosen.push(best);
+    }
+
+    let mut i = chosen.len();
+    while i > 0 {
+        i -= 1;
+        let mut others = bitvec![0; faults.len()];
+        for (j, &v) in chosen.iter().enumerate() {
+            if j != i {
+                others |= detects[v].clone();
+            }
+        }
+        if others == covered {
+            chosen.remove(i);
+        }
+    }
+    chosen.into_iter().map(|v| vectors[v].clone()).collect()
+}
+
+/// Value of a net in the good and in the faulty circuit, `None` while unknown.
+type Value = (Option<bool>, Option<bool>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Podem {
+    /// Input values detecting the fault, `None` for inputs which do not matter.
+    Detected(Vec<Option<bool>>),
+    Undetectable,
+    Aborted,
+}
+
+impl Netlist {
+    /// Values of all nets for the given input values, with the fault injected.
+    fn simulate(&self, inputs: &[Option<bool>], fault: NetFault) -> Vec<Value> {
+        let mut values: Vec<Value> = Vec::with_capacity(self.nets.len());
+        for (index, net) in self.nets.iter().enumerate() {
+            let good = net.inputs.iter().map(|&i| values[i].0);
+            let good = eval(net.gate, inputs, good);
+            let faulty = net.inputs.iter().map(|&i| values[i].1);
+            let faulty = match index == fault.net {
+                true => Some(fault.stuck),
+                false => eval(net.gate, inputs, faulty),
+            };
+            values.push((good, faulty));
+        }
+        values
+    }
+
+    fn detected(&self, values: &[Value]) -> bool {
+        self.outputs.iter().any(|&net| {
+            let (good, faulty) = values[net];
+            good.is_some() && faulty.is_some() && good != faulty
+        })
+    }
+
+    /// Path oriented decision making: inputs are assigned one at a time,
+    /// each one found by tracing an objective back from the fault or the D-frontier.
+    fn podem(&self, fault: NetFault) -> Podem {
+        let mut inputs = vec![None; self.inputs];
+        // Assigned inputs, with whether the other value was tried already.
+        let mut decisions: Vec<(usize, bool)> = Vec::new();
+        let mut backtracks = 0;
+
+        loop {
+            let values = self.simulate(&inputs, fault);
+            if self.detected(&values) {
+                return Podem::Detected(inputs);
+            }
+            if let Some((net, value)) = self.objective(&values, fault) {
+                let (input, value) = self.backtrace(&values, net, value);
+                inputs[input] = Some(value);
+                decisions.push((input, false));
+                continue;
+            }
+
+            loop {
+                let Some((input, flipped)) = decisions.pop() else {
+                    return Podem::Undetectable;
+                };
+                if !flipped {
+                    inputs[input] = inputs[input].map(|value| !value);
+                    decisions.push((input, true));
+                    break;
+                }
+                inputs[input] = None;
+            }
+            backtracks += 1;
+            if backtracks > BACKTRACK_LIMIT {
+                return Podem::Aborted;
+            }
+        }
+    }
+
+    /// Net and value to aim for next, `None` when the fault can no longer be detected.
+    fn objective(&self, values: &[Value], fault: NetFault) -> Option<(usize, bool)> {
+        match values[fault.net].0 {
+            None => return Some((fault.net, !fault.stuck)),
+            Some(good) if good == fault.stuck => return None,
+            Some(_) => {}
+        }
+
+        // The gate of the D-frontier nearest to the outputs.
+        let differs = |net: usize| {
+            let (good, faulty) = values[net];
+            good.is_some() && faulty.is_some() && good != faulty
+        };
+        let unknown = |net: &Net| net.inputs.iter().copied().find(|&i| values[i].0.is_none());
+        let frontier = self.nets.iter().enumerate().rev().find(|(index, net)| {
+            let (good, faulty) = values[*index];
+            (good.is_none() || faulty.is_none())
+                && net.inputs.iter().any(|&i| differs(i))
+                && unknown(net).is_some()
+        });
+        let (_, net) = frontier?;
+        let non_controlling = matches!(net.gate, Gate::And);
+        Some((unknown(net)?, non_controlling))
+    }
+
+    /// Follows unknown nets from `net` back to an input which helps setting it to `value`.
+    fn backtrace(&self, values: &[Value], mut net: usize, mut value: bool) -> (usize, bool) {
+        loop {
+            let gate = &self.nets[net];
+            match gate.gate {
+                Gate::Input(input) => return (input, value),
+                Gate::Const(_) => unreachable!("objectives are unknown nets"),
+                Gate::Not => {
+                    net = gate.inputs[0];
+                    value = !value;
+                }
+                Gate::Buf | Gate::And | Gate::Or => {
+                    net = *gate
+                        .inputs
+                        .iter()
+                        .find(|&&i| values[i].0.is_none())
+                        .unwrap();
+                }
+                Gate::Xor => {
+                    let (unknown, other) = match values[gate.inputs[0]].0 {
+                        None => (gate.inputs[0], gate.inputs[1]),
+                        Some(_) => (gate.inputs[1], gate.inputs[0]),
+                    };
+                    value ^= values[other].0.unwrap_or(false);
+                    net = unknown;
+                }
+            }
+        }
+    }
+}
+
+fn eval(
+    gate: Gate,
+    inputs: &[Option<bool>],
+    mut values: impl Iterator<Item = Option<bool>>,
+) -> Option<bool> {
+    let all = |values: &mut dyn Iterator<Item = Option<bool>>, dominant: bool| {
+        let mut unknown = false;
+        for value in values {
+            match value {
+                Some(value) if value == dominant => return Some(dominant),
+                None => unknown = true,
+                Some(_) => {}
+            }
+        }
+        if unknown {
+            None
+        } else {
+            Some(!dominant)
+        }
+    };
+    match gate {
+        Gate::Input(input) => inputs[input],
+        Gate::Const(value) => Some(value),
+        Gate::And => all(&mut values, false),
+        Gate::Or => all(&mut values, true),
+        Gate::Xor => {
+            let (a, b) = (values.next().unwrap(), values.next().unwrap());
+            Some(a? ^ b?)
+        }
+        Gate::Not => values.next().unwrap().map(|value| !value),
+        Gate::Buf => values.next().unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{
+        fault_simulation::fault_simulation,
+        synthesis::{parse_equations, synthesize, GateStyle},
+    };
+
+    fn build(
+        source: &str,
+        style: GateStyle,
+    ) -> (Graph, Vec<TypedId<Constant>>, Vec<TypedId<DebugOutput>>) {
+        let equations = parse_equations(source).unwrap();
+        let graph = synthesize(&equations, style);
+        let id = |name: &String| graph.find_by_name(name).unwrap();
+        let inputs = equations
+            .inputs
+            .iter()
+            .map(|name| id(name).into())
+            .collect();
+        let outputs = equations
+            .outputs
+            .iter()
+            .map(|(name, _)| id(name).into())
+            .collect();
+        (graph, inputs, outputs)
+    }
+
+    #[test]
+    fn full_adders() {
+        for style in [GateStyle::Mixed, GateStyle::NandOnly] {
+            let (graph, inputs, outputs) = build("S = A ^ B ^ C; K = A & B | C & (A ^ B)", style);
+            let set = generate_tests(&graph, &inputs, &outputs).unwrap();
+            assert!(set.undetectable.is_empty() && set.aborted.is_empty());
+            assert!(set.vectors.vectors.len() < 8);
+
+            let faults = stuck_at_faults(&graph);
+            let report = fault_simulation(&graph, &set.vectors, &faults);
+            assert_eq!(report.coverage(), 1.0);
+        }
+    }
+
+    #[test]
+    fn redundant_and_wide_circuits() {
+        // `A & B | A & !B` is just `A`, no fault on `B` shows.
+        let (graph, inputs, outputs) = build("Q = A & B | A & !B", GateStyle::Mixed);
+        let set = generate_tests(&graph, &inputs, &outputs).unwrap();
+        let b = graph.find_by_name("B").unwrap();
+        assert!(set.undetectable.iter().any(|fault| fault.node == b));
+        let report = fault_simulation(&graph, &set.vectors, &stuck_at_faults(&graph));
+        assert_eq!(report.undetected, set.undetectable);
+
+        // An and of 20 inputs needs all ones and each single zero.
+        let names = (0..20).map(|i| format!("x{i}")).collect::<Vec<_>>();
+        let source = format!("Q = {}", names.join(" & "));
+        let (graph, inputs, outputs) = build(&source, GateStyle::Mixed);
+        let set = generate_tests(&graph, &inputs, &outputs).unwrap();
+        assert_eq!(set.vectors.vectors.len(), 21);
+        assert_eq!(set.undetectable, []);
+        let report = fault_simulation(&graph, &set.vectors, &stuck_at_faults(&graph));
+        assert_eq!(report.coverage(), 1.0);
+    }
+}
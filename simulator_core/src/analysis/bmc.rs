@@ -0,0 +1,1032 @@
+//! Bounded model checking of safety properties on sequential graphs.
+//!
+//! The graph is unrolled from its current state for a number of clock cycles and
+//! turned into one SAT problem, a model of it is an input sequence breaking the property.
+//!
+//! Every cycle the free inputs, the named `Constant`s, take new values with the clock low,
+//! then the clock goes high, and the property is checked after both steps.
+//! Without a clock every cycle is a single step. Memory elements update once per step
+//! from the values settled in the previous one, so they must be edge triggered, and an
+//! asynchronous reset driven by the circuit itself only shows in the following step.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+    fmt::{Display, Write},
+};
+
+use bitvec::prelude::*;
+use slotmap::SecondaryMap;
+
+use crate::{
+    components::{
+        memory::{FlipFlopKind, Trigger},
+        simple::Constant,
+        Component, ComponentBehaviour,
+    },
+    graph::{
+        id::{ComponentId, TypedId},
+        Graph,
+    },
+};
+
+use super::{
+    expression::Expr,
+    sat::{self, Lit, Solver},
+    synthesis::{parse_expression, SynthesisError},
+    truth_table::describe,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BmcOptions {
+    pub cycles: usize,
+    /// Input toggled low and high every cycle, left out of the free inputs.
+    pub clock: Option<TypedId<Constant>>,
+}
+
+/// Condition over named signals, written like the expressions of
+/// [`parse_equations`](super::synthesis::parse_equations).
+///
+/// A name stands for the output of the node, or the input of a `DebugOutput`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Property {
+    /// The condition holds after every step.
+    Always(String),
+    /// The condition never holds.
+    Never(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BmcError {
+    Parse(SynthesisError),
+    UnknownSignal(String),
+    DuplicateName(String),
+    /// A feedback loop not broken by a memory element, listed in signal order.
+    CombinationalLoop {
+        path: Vec<String>,
+    },
+    /// Latches are transparent while the clock is at their level, which steps cannot capture.
+    LevelTriggered {
+        component: String,
+    },
+}
+
+impl Display for BmcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BmcError::Parse(err) => write!(f, "property {err}"),
+            BmcError::UnknownSignal(name) => write!(f, "no signal is named `{name}`"),
+            BmcError::DuplicateName(name) => {
+                write!(f, "more than one signal is named `{name}`")
+            }
+            BmcError::CombinationalLoop { path } => {
+                write!(f, "combinational loop: {}", path.join(" -> "))
+            }
+            BmcError::LevelTriggered { component } => {
+                write!(
+                    f,
+                    "{component} is level triggered, only edges are supported"
+                )
+            }
+        }
+    }
+}
+
+impl Error for BmcError {}
+
+impl From<SynthesisError> for BmcError {
+    fn from(err: SynthesisError) -> Self {
+        BmcError::Parse(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BmcResult {
+    /// No input sequence of at most the given number of cycles breaks the property.
+    Holds,
+    Violated(Trace),
+}
+
+/// Steps from the state of the graph to a violation of the property, the last step breaking it.
+///
+/// With a clock, step `2 * k` is cycle `k` with the clock low and step `2 * k + 1` with it high.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    /// Free inputs, with their values in every step.
+    pub inputs: Vec<TypedId<Constant>>,
+    pub input_values: Vec<BitVec>,
+    pub clock: Option<TypedId<Constant>>,
+    /// Named signals, sorted, with their values after every step.
+    pub signals: Vec<String>,
+    pub values: Vec<BitVec>,
+}
+
+impl Trace {
+    pub fn steps(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn clock_value(&self, step: usize) -> Option<bool> {
+        self.clock.map(|_| step % 2 == 1)
+    }
+
+    /// Sets the inputs of `step` in the graph, the steps have to be applied in order
+    /// starting from the state the graph was checked in.
+    pub fn apply(&self, graph: &mut Graph, step: usize) {
+        let inputs = self
+            .inputs
+            .iter()
+            .zip(self.input_values[step].iter().by_vals());
+        let clock = self.clock.zip(self.clock_value(step));
+        for (&id, value) in inputs.chain(clock.as_ref().map(|(id, value)| (id, *value))) {
+            if graph[id].state != value {
+                graph[id].state = value;
+                graph.propagate_from(id);
+            }
+        }
+    }
+
+    /// Value change dump of the signals, one time unit per step.
+    pub fn to_vcd(&self) -> String {
+        let code = |mut index: usize| {
+            let mut code = String::new();
+            loop {
+                code.push((b'!' + (index % 94) as u8) as char);
+                index /= 94;
+                if index == 0 {
+                    break code;
+                }
+                index -= 1;
+            }
+        };
+
+        let mut vcd = String::from("$timescale 1 ns $end\n$scope module trace $end\n");
+        for (i, name) in self.signals.iter().enumerate() {
+            let name = name.replace(char::is_whitespace, "_");
+            writeln!(vcd, "$var wire 1 {} {name} $end", code(i)).unwrap();
+        }
+        vcd.push_str("$upscope $end\n$enddefinitions $end\n");
+
+        for (step, values) in self.values.iter().enumerate() {
+            writeln!(vcd, "#{step}").unwrap();
+            if step == 0 {
+                vcd.push_str("$dumpvars\n");
+            }
+            for (i, value) in values.iter().by_vals().enumerate() {
+                if step == 0 || self.values[step - 1][i] != value {
+                    writeln!(vcd, "{}{}", value as u8, code(i)).unwrap();
+                }
+            }
+            if step == 0 {
+                vcd.push_str("$end\n");
+            }
+        }
+        writeln!(vcd, "#{}", self.steps()).unwrap();
+        vcd
+    }
+}
+
+/// Looks for inputs breaking the property within `options.cycles` cycles.
+pub fn check_property(
+    graph: &Graph,
+    property: &Property,
+    options: &BmcOptions,
+) -> Result<BmcResult, BmcError> {
+    let (source, negated) = match property {
+        Property::Always(source) => (source, true),
+        Property::Never(source) => (source, false),
+    };
+    let (names, expr) = parse_expression(source)?;
+
+    let mut cells = Vec::new();
+    let top = flatten(&mut cells, graph, &SecondaryMap::new())?;
+
+    let mut named = BTreeMap::new();
+    let mut duplicates = HashSet::new();
+    for (id, node) in &graph.nodes {
+        if let Some(name) = &node.name {
+            if named.insert(name.clone(), id).is_some() {
+                duplicates.insert(name.clone());
+            }
+        }
+    }
+    if let Some(clock) = options.clock.filter(|&clock| graph.name(clock).is_none()) {
+        named.entry("clock".to_string()).or_insert(clock.into());
+    }
+    let vars = names
+        .iter()
+        .map(|name| match named.get(name) {
+            _ if duplicates.contains(name) => Err(BmcError::DuplicateName(name.clone())),
+            Some(&id) => Ok(top[id]),
+            None => Err(BmcError::UnknownSignal(name.clone())),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let clock = options.clock.map(|id| top[id.into()]);
+    let inputs = graph
+        .nodes
+        .iter()
+        .filter(|(id, node)| {
+            node.name.is_some()
+                && matches!(node.component, Component::Constant(_))
+                && Some(top[*id]) != clock
+        })
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+
+    let mut unrolling = Unrolling::new(&cells);
+    let phases = if clock.is_some() { 2 } else { 1 };
+    let mut input_lits = Vec::new();
+    let mut value_lits = Vec::new();
+    let mut bad = Vec::new();
+    for _ in 0..options.cycles {
+        let cycle_inputs = inputs
+            .iter()
+            .map(|_| unrolling.solver.new_lit())
+            .collect::<Vec<_>>();
+        for phase in 0..phases {
+            let mut leaves = inputs
+                .iter()
+                .zip(&cycle_inputs)
+                .map(|(&id, &lit)| (top[id], lit))
+                .collect::<HashMap<_, _>>();
+            if let Some(clock) = clock {
+                let truth = unrolling.truth;
+                leaves.insert(clock, if phase == 1 { truth } else { !truth });
+            }
+
+            let mut settled = unrolling.step(leaves)?;
+            let var_lits = vars
+                .iter()
+                .map(|&cell| unrolling.value(&mut settled, cell))
+                .collect::<Result<Vec<_>, _>>()?;
+            let holds = unrolling.expr(&expr, &var_lits);
+            bad.push(if negated { !holds } else { holds });
+            let values = named
+                .values()
+                .map(|&id| unrolling.value(&mut settled, top[id]))
+                .collect::<Result<Vec<_>, _>>()?;
+            value_lits.push(values);
+            input_lits.push(cycle_inputs.clone());
+        }
+    }
+
+    let solver = &mut unrolling.solver;
+    solver.add_clause(&bad);
+    if !solver.solve() {
+        return Ok(BmcResult::Holds);
+    }
+    let model = |lits: &[Lit]| -> BitVec {
+        lits.iter()
+            .map(|&lit| solver.value(lit) == Some(true))
+            .collect()
+    };
+    let violation = bad
+        .iter()
+        .position(|&lit| solver.value(lit) == Some(true))
+        .unwrap();
+    Ok(BmcResult::Violated(Trace {
+        inputs: inputs.into_iter().map(TypedId::from).collect(),
+        input_values: input_lits[..=violation].iter().map(|l| model(l)).collect(),
+        clock: options.clock,
+        signals: named.into_keys().collect(),
+        values: value_lits[..=violation].iter().map(|l| model(l)).collect(),
+    }))
+}
+
+/// Output of a cell, `None` for a disconnected input, which floats.
+type Signal = Option<(usize, usize)>;
+
+enum CellKind<'g> {
+    Component(&'g Component),
+    /// Inner input of a subcircuit, driven by the input slot of the subcircuit.
+    Port,
+    /// Subcircuit, its outputs are the signals driving the inner `DebugOutput`s.
+    Subcircuit(Vec<Signal>),
+}
+
+/// Node of the graph with subcircuits flattened, one per subcircuit instance.
+struct Cell<'g> {
+    graph: &'g Graph,
+    id: ComponentId,
+    inputs: Vec<Signal>,
+    kind: CellKind<'g>,
+}
+
+/// Adds the cells of `graph` and returns their indices,
+/// `ports` are the signals driving the inner inputs of a subcircuit.
+fn flatten<'g>(
+    cells: &mut Vec<Cell<'g>>,
+    graph: &'g Graph,
+    ports: &SecondaryMap<ComponentId, Signal>,
+) -> Result<SecondaryMap<ComponentId, usize>, BmcError> {
+    let base = cells.len();
+    let index = graph
+        .nodes
+        .keys()
+        .enumerate()
+        .map(|(i, id)| (id, base + i))
+        .collect::<SecondaryMap<_, _>>();
+    let signal = |slot: &Option<_>| {
+        let source: &crate::graph::node::Slot = slot.as_ref()?;
+        Some((index[source.target_node], source.target_slot))
+    };
+
+    for (id, node) in &graph.nodes {
+        let (inputs, kind) = match (&node.component, ports.get(id)) {
+            (Component::Constant(_), Some(&port)) => (vec![port], CellKind::Port),
+            (Component::Subcircuit(_), _) => {
+                let inputs = node.input_slots.iter().map(signal).collect();
+                (inputs, CellKind::Subcircuit(Vec::new()))
+            }
+            (component, _) => {
+                let trigger = match component {
+                    Component::FlipFlop(ff) => Some(ff.trigger),
+                    Component::Ram(ram) => Some(ram.trigger),
+                    _ => None,
+                };
+                if matches!(trigger, Some(Trigger::HighLevel | Trigger::LowLevel)) {
+                    return Err(BmcError::LevelTriggered {
+                        component: describe(graph, id),
+                    });
+                }
+                let inputs = node.input_slots.iter().map(signal).collect();
+                (inputs, CellKind::Component(component))
+            }
+        };
+        cells.push(Cell {
+            graph,
+            id,
+            inputs,
+            kind,
+        });
+    }
+
+    for (id, node) in &graph.nodes {
+        let Component::Subcircuit(sub) = &node.component else {
+            continue;
+        };
+        let cell = index[id];
+        let ports = sub
+            .input_ids()
+            .zip(cells[cell].inputs.clone())
+            .collect::<SecondaryMap<_, _>>();
+        let inner = flatten(cells, &sub.graph, &ports)?;
+        let outputs = sub
+            .output_ids()
+            .map(|id| {
+                let slot = &sub.graph.nodes[id].input_slots[0];
+                slot.as_ref()
+                    .map(|source| (inner[source.target_node], source.target_slot))
+            })
+            .collect();
+        cells[cell].kind = CellKind::Subcircuit(outputs);
+    }
+    Ok(index)
+}
+
+/// Memory element with the literals of its state before the next step.
+struct Memory {
+    cell: usize,
+    clock_slot: usize,
+    state: Vec<Lit>,
+    /// Clock input as seen after the previous step.
+    prev_clock: Lit,
+}
+
+/// Signals of the cells for one set of free inputs and memory states.
+struct Evaluation {
+    leaves: HashMap<usize, Lit>,
+    states: Vec<Vec<Lit>>,
+    outputs: Vec<Option<Vec<Lit>>>,
+    /// Whether each output is released, `None` when it never is.
+    released: Vec<Option<Vec<Option<Lit>>>>,
+    /// Cells being encoded, each one reading an output of the next.
+    stack: Vec<usize>,
+}
+
+struct Unrolling<'c, 'g> {
+    cells: &'c [Cell<'g>],
+    solver: Solver,
+    /// Literal fixed to true.
+    truth: Lit,
+    memories: Vec<Memory>,
+    memory_of: HashMap<usize, usize>,
+}
+
+impl<'c, 'g> Unrolling<'c, 'g> {
+    /// Fixes the memories to the state of their graphs.
+    fn new(cells: &'c [Cell<'g>]) -> Self {
+        let mut solver = Solver::new();
+        let truth = solver.new_lit();
+        solver.add_clause(&[truth]);
+        let constant = |value: bool| if value { truth } else { !truth };
+
+        let mut memories = Vec::new();
+        let mut memory_of = HashMap::new();
+        for (index, cell) in cells.iter().enumerate() {
+            let (state, clock_slot) = match cell.kind {
+                CellKind::Component(Component::FlipFlop(ff)) => {
+                    (vec![constant(ff.state)], ff.kind.data_inputs())
+                }
+                CellKind::Component(Component::Ram(ram)) => {
+                    let width = ram.data_width as usize;
+                    let bits = ram.contents.iter().flat_map(|&word| {
+                        (0..width).map(move |bit| constant(word >> bit & 1 == 1))
+                    });
+                    (bits.collect(), ram.address_width as usize + width + 1)
+                }
+                _ => continue,
+            };
+            memory_of.insert(index, memories.len());
+            memories.push(Memory {
+                cell: index,
+                clock_slot,
+                state,
+                prev_clock: constant(cell.graph.inputs[cell.id][clock_slot]),
+            });
+        }
+
+        Self {
+            cells,
+            solver,
+            truth,
+            memories,
+            memory_of,
+        }
+    }
+
+    fn constant(&self, value: bool) -> Lit {
+        if value {
+            self.truth
+        } else {
+            !self.truth
+        }
+    }
+
+    fn evaluation(&self, leaves: HashMap<usize, Lit>, states: Vec<Vec<Lit>>) -> Evaluation {
+        Evaluation {
+            leaves,
+            states,
+            outputs: vec![None; self.cells.len()],
+            released: vec![None; self.cells.len()],
+            stack: Vec::new(),
+        }
+    }
+
+    /// Updates the memories from the given free inputs, returning the settled signals.
+    fn step(&mut self, leaves: HashMap<usize, Lit>) -> Result<Evaluation, BmcError> {
+        let cells = self.cells;
+        let states = self.memories.iter().map(|m| m.state.clone()).collect();
+        let mut before = self.evaluation(leaves.clone(), states);
+        let mut next = Vec::with_capacity(self.memories.len());
+        for m in 0..self.memories.len() {
+            let cell = self.memories[m].cell;
+            let inputs = (0..cells[cell].inputs.len())
+                .map(|slot| self.input(&mut before, cell, slot))
+                .collect::<Result<Vec<_>, _>>()?;
+            next.push(self.update(m, &inputs));
+        }
+
+        let mut settled = self.evaluation(leaves, next.clone());
+        for (m, state) in next.into_iter().enumerate() {
+            let memory = &self.memories[m];
+            let prev_clock = self.input(&mut settled, memory.cell, memory.clock_slot)?;
+            let memory = &mut self.memories[m];
+            memory.state = state;
+            memory.prev_clock = prev_clock;
+        }
+        Ok(settled)
+    }
+
+    /// State of memory `m` after a step with the given inputs.
+    fn update(&mut self, m: usize, inputs: &[Lit]) -> Vec<Lit> {
+        let Memory {
+            cell,
+            clock_slot,
+            ref state,
+            prev_clock,
+        } = self.memories[m];
+        let state = state.clone();
+        let (f, t) = (self.constant(false), self.constant(true));
+        let solver = &mut self.solver;
+        let clock = inputs[clock_slot];
+
+        match self.cells[cell].kind {
+            CellKind::Component(Component::FlipFlop(ff)) => {
+                let fires = fires(solver, ff.trigger, prev_clock, clock);
+                let (s, a, b) = (state[0], inputs[0], inputs[1]);
+                let next = match ff.kind {
+                    FlipFlopKind::D => a,
+                    FlipFlopKind::T => sat::xor(solver, s, a),
+                    FlipFlopKind::JK => {
+                        let j = sat::ite(solver, b, !s, t);
+                        let not_j = sat::ite(solver, b, f, s);
+                        sat::ite(solver, a, j, not_j)
+                    }
+                    FlipFlopKind::SR => {
+                        let set = sat::ite(solver, b, s, t);
+                        let not_set = sat::ite(solver, b, f, s);
+                        sat::ite(solver, a, set, not_set)
+                    }
+                };
+                let (reset, preset) = (inputs[clock_slot + 1], inputs[clock_slot + 2]);
+                let clocked = sat::ite(solver, fires, next, s);
+                let preset = sat::ite(solver, preset, t, clocked);
+                vec![sat::ite(solver, reset, f, preset)]
+            }
+            CellKind::Component(Component::Ram(ram)) => {
+                let fires = fires(solver, ram.trigger, prev_clock, clock);
+                let address_width = ram.address_width as usize;
+                let width = ram.data_width as usize;
+                let data = &inputs[address_width..address_width + width];
+                let (store, clear) = (inputs[clock_slot - 1], inputs[clock_slot + 1]);
+                let write = sat::and(solver, &[store, fires]);
+                let selected = decode(solver, &inputs[..address_width]);
+                let words = state.chunks(width).zip(selected);
+                let words = words.flat_map(|(word, selected)| {
+                    let write = sat::and(solver, &[write, selected]);
+                    let bits = word.iter().zip(data).map(|(&old, &new)| {
+                        let written = sat::ite(solver, write, new, old);
+                        sat::ite(solver, clear, f, written)
+                    });
+                    bits.collect::<Vec<_>>()
+                });
+                words.collect()
+            }
+            _ => unreachable!("only flip flops and RAMs are memories"),
+        }
+    }
+
+    /// Value of a signal read by an input slot reading `floating` while nothing drives it.
+    fn signal(
+        &mut self,
+        eval: &mut Evaluation,
+        signal: Signal,
+        floating: bool,
+    ) -> Result<Lit, BmcError> {
+        let Some((cell, slot)) = signal else {
+            return Ok(self.constant(floating));
+        };
+        let source = &self.cells[cell];
+        let (value, released) = match source.graph.terminal_input(source.id, slot) {
+            Some(except) => self.line(eval, cell, except)?,
+            None => {
+                let value = self.outputs(eval, cell)?[slot];
+                (value, self.released(eval, cell)?[slot])
+            }
+        };
+        Ok(match released {
+            None => value,
+            Some(released) if floating => sat::or(&mut self.solver, &[value, released]),
+            Some(released) => sat::and(&mut self.solver, &[value, !released]),
+        })
+    }
+
+    /// Value coming into `slot` of `cell`.
+    fn input(&mut self, eval: &mut Evaluation, cell: usize, slot: usize) -> Result<Lit, BmcError> {
+        let floating = match self.cells[cell].kind {
+            CellKind::Component(component) => component.floating_input(slot),
+            _ => false,
+        };
+        self.signal(eval, self.cells[cell].inputs[slot], floating)
+    }
+
+    /// Value of the line of the `Fork` `cell` left without input `except`, and whether
+    /// it is released, as the bidirectional pin driving that input reads it.
+    fn line(
+        &mut self,
+        eval: &mut Evaluation,
+        cell: usize,
+        except: usize,
+    ) -> Result<(Lit, Option<Lit>), BmcError> {
+        let (mut values, mut undriven) = (Vec::new(), Vec::new());
+        for slot in (0..self.cells[cell].inputs.len()).filter(|&slot| slot != except) {
+            values.push(self.input(eval, cell, slot)?);
+            undriven.push(match self.cells[cell].inputs[slot] {
+                Some((source, slot)) => self.released(eval, source)?[slot],
+                None => Some(self.truth),
+            });
+        }
+        let value = sat::or(&mut self.solver, &values);
+        let undriven = undriven.into_iter().collect::<Option<Vec<_>>>();
+        let released = undriven.map(|lits| sat::and(&mut self.solver, &lits));
+        Ok((value, released))
+    }
+
+    /// Literals of whether the outputs of `cell` are released, encoded once per
+    /// evaluation after its outputs.
+    fn released(
+        &mut self,
+        eval: &mut Evaluation,
+        cell: usize,
+    ) -> Result<Vec<Option<Lit>>, BmcError> {
+        if let Some(lits) = &eval.released[cell] {
+            return Ok(lits.clone());
+        }
+        let slots = self.outputs(eval, cell)?.len();
+        let lits = match self.cells[cell].kind {
+            CellKind::Component(Component::OpenCollector(_)) => {
+                vec![Some(self.input(eval, cell, 0)?)]
+            }
+            CellKind::Component(Component::Transceiver(transceiver)) => {
+                let width = transceiver.width as usize;
+                let dir = self.input(eval, cell, 2 * width)?;
+                let enabled = !self.input(eval, cell, 2 * width + 1)?;
+                let to_a = sat::and(&mut self.solver, &[enabled, !dir]);
+                let to_b = sat::and(&mut self.solver, &[enabled, dir]);
+                let mut lits = vec![Some(!to_a); width];
+                lits.extend(vec![Some(!to_b); width]);
+                lits
+            }
+            CellKind::Component(Component::Fork(_)) => {
+                let mut undriven = Vec::new();
+                for &signal in &self.cells[cell].inputs {
+                    undriven.push(match signal {
+                        Some((source, slot)) => self.released(eval, source)?[slot],
+                        None => Some(self.truth),
+                    });
+                }
+                let undriven = undriven.into_iter().collect::<Option<Vec<_>>>();
+                vec![undriven.map(|lits| sat::and(&mut self.solver, &lits)); slots]
+            }
+            _ => vec![None; slots],
+        };
+        eval.released[cell] = Some(lits.clone());
+        Ok(lits)
+    }
+
+    /// Value of a named cell, its input for a `DebugOutput`.
+    fn value(&mut self, eval: &mut Evaluation, cell: usize) -> Result<Lit, BmcError> {
+        let cells = self.cells;
+        match cells[cell].kind {
+            CellKind::Component(Component::DebugOutput(_)) => self.input(eval, cell, 0),
+            _ => {
+                let outputs = self.outputs(eval, cell)?;
+                Ok(outputs.first().copied().unwrap_or(self.constant(false)))
+            }
+        }
+    }
+
+    /// Literals of all outputs of `cell`, encoded once per evaluation.
+    fn outputs(&mut self, eval: &mut Evaluation, cell: usize) -> Result<Vec<Lit>, BmcError> {
+        if let Some(lits) = &eval.outputs[cell] {
+            return Ok(lits.clone());
+        }
+        let cells = self.cells;
+        if let Some(start) = eval.stack.iter().position(|&other| other == cell) {
+            let path = eval.stack[start..].iter().rev();
+            return Err(BmcError::CombinationalLoop {
+                path: path
+                    .map(|&c| describe(cells[c].graph, cells[c].id))
+                    .collect(),
+            });
+        }
+        eval.stack.push(cell);
+        let mut inputs = Vec::new();
+        if let CellKind::Component(_) | CellKind::Port = cells[cell].kind {
+            if !self.memory_of.contains_key(&cell) {
+                for slot in 0..cells[cell].inputs.len() {
+                    inputs.push(self.input(eval, cell, slot)?);
+                }
+            }
+        }
+
+        let lits = match &cells[cell].kind {
+            CellKind::Port => inputs,
+            CellKind::Subcircuit(outputs) => outputs
+                .iter()
+                .map(|&signal| self.signal(eval, signal, false))
+                .collect::<Result<_, _>>()?,
+            CellKind::Component(component) => match component {
+                Component::Constant(constant) => {
+                    vec![match eval.leaves.get(&cell) {
+                        Some(&lit) => lit,
+                        None => self.constant(constant.state),
+                    }]
+                }
+                Component::DebugOutput(_) => Vec::new(),
+                Component::And(_) => vec![sat::and(&mut self.solver, &inputs)],
+                Component::Or(_) => vec![sat::or(&mut self.solver, &inputs)],
+                Component::Xor(_) => vec![sat::xor(&mut self.solver, inputs[0], inputs[1])],
+                Component::Not(_) => vec![!inputs[0]],
+                Component::PullResistor(_) => vec![inputs[0]],
+                // Pulls its line low unless released, see `Unrolling::released`.
+                Component::OpenCollector(_) => vec![self.constant(false)],
+                Component::Transceiver(transceiver) => {
+                    let width = transceiver.width as usize;
+                    let (dir, enabled) = (inputs[2 * width], !inputs[2 * width + 1]);
+                    let to_a = sat::and(&mut self.solver, &[enabled, !dir]);
+                    let to_b = sat::and(&mut self.solver, &[enabled, dir]);
+                    let solver = &mut self.solver;
+                    let a = (0..width).map(|i| sat::and(solver, &[to_a, inputs[width + i]]));
+                    let a = a.collect::<Vec<_>>();
+                    let b = (0..width).map(|i| sat::and(solver, &[to_b, inputs[i]]));
+                    a.into_iter().chain(b.collect::<Vec<_>>()).collect()
+                }
+                Component::Fork(_) => {
+                    let lit = match inputs[..] {
+                        [single] => single,
+                        _ => sat::or(&mut self.solver, &inputs),
+                    };
+                    vec![lit; cells[cell].graph.nodes[cells[cell].id].output_slots.len()]
+                }
+                Component::Rom(rom) => {
+                    let selected = decode(&mut self.solver, &inputs);
+                    (0..rom.data_width as usize)
+                        .map(|bit| {
+                            let words = rom.contents.iter().zip(&selected);
+                            let ones = words
+                                .filter(|(word, _)| *word >> bit & 1 == 1)
+                                .map(|(_, &lit)| lit)
+                                .collect::<Vec<_>>();
+                            sat::or(&mut self.solver, &ones)
+                        })
+                        .collect()
+                }
+                Component::FlipFlop(_) => {
+                    let state = eval.states[self.memory_of[&cell]][0];
+                    vec![state, !state]
+                }
+                Component::Ram(ram) => {
+                    let address = (0..ram.address_width as usize)
+                        .map(|slot| self.input(eval, cell, slot))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let selected = decode(&mut self.solver, &address);
+                    let width = ram.data_width as usize;
+                    let state = eval.states[self.memory_of[&cell]].clone();
+                    (0..width)
+                        .map(|bit| {
+                            let bits = state
+                                .chunks(width)
+                                .zip(&selected)
+                                .map(|(word, &lit)| sat::and(&mut self.solver, &[lit, word[bit]]));
+                            let bits = bits.collect::<Vec<_>>();
+                            sat::or(&mut self.solver, &bits)
+                        })
+                        .collect()
+                }
+                Component::Subcircuit(_) => unreachable!("flattened"),
+            },
+        };
+        eval.stack.pop();
+        eval.outputs[cell] = Some(lits.clone());
+        Ok(lits)
+    }
+
+    fn expr(&mut self, expr: &Expr, vars: &[Lit]) -> Lit {
+        let mut terms = |terms: &[Expr]| {
+            terms
+                .iter()
+                .map(|term| self.expr(term, vars))
+                .collect::<Vec<_>>()
+        };
+        match expr {
+            Expr::Const(value) => self.constant(*value),
+            Expr::Var(var) => vars[*var],
+            Expr::Not(inner) => !self.expr(inner, vars),
+            Expr::And(inner) => {
+                let lits = terms(inner);
+                sat::and(&mut self.solver, &lits)
+            }
+            Expr::Or(inner) => {
+                let lits = terms(inner);
+                sat::or(&mut self.solver, &lits)
+            }
+            Expr::Xor(inner) => {
+                let lits = terms(inner);
+                let (first, rest) = lits.split_first().unwrap();
+                rest.iter()
+                    .fold(*first, |acc, &lit| sat::xor(&mut self.solver, acc, lit))
+            }
+        }
+    }
+}
+
+fn fires(solver: &mut Solver, trigger: Trigger, prev_clock: Lit, clock: Lit) -> Lit {
+    match trigger {
+        Trigger::RisingEdge => sat::and(solver, &[!prev_clock, clock]),
+        Trigger::FallingEdge => sat::and(solver, &[prev_clock, !clock]),
+        Trigger::HighLevel | Trigger::LowLevel => unreachable!("rejected when flattening"),
+    }
+}
+
+/// One literal per address, true when the address bits, least significant first, select it.
+fn decode(solver: &mut Solver, address: &[Lit]) -> Vec<Lit> {
+    (0..1usize << address.len())
+        .map(|a| {
+            let bits = address.iter().enumerate();
+            let lits = bits
+                .map(|(bit, &lit)| if a >> bit & 1 == 1 { lit } else { !lit })
+                .collect::<Vec<_>>();
+            sat::and(solver, &lits)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{
+        gates::{And, Or},
+        memory::FlipFlop,
+        simple::{DebugOutput, Fork},
+        wired::{OpenCollector, PullResistor, Transceiver},
+    };
+
+    /// Two bit counter counting the cycles `en` is high.
+    fn counter() -> (Graph, TypedId<Constant>, [TypedId<DebugOutput>; 2]) {
+        let mut graph = Graph::new();
+        let en = graph.add_comp(Constant::default());
+        let clk = graph.add_comp(Constant::default());
+        let fork_en = graph.add_comp(Fork::new(1, 2));
+        let fork_clk = graph.add_comp(Fork::new(1, 2));
+        let ff0 = graph.add_comp(FlipFlop::new(FlipFlopKind::T));
+        let ff1 = graph.add_comp(FlipFlop::new(FlipFlopKind::T));
+        let fork_q0 = graph.add_comp(Fork::new(1, 2));
+        let and = graph.add_comp(And);
+        let q0 = graph.add_comp(DebugOutput::default());
+        let q1 = graph.add_comp(DebugOutput::default());
+        graph.add_conn(en, 0, fork_en, 0);
+        graph.add_conn(clk, 0, fork_clk, 0);
+        graph.add_conn(fork_en, 0, ff0, 0);
+        graph.add_conn(fork_en, 1, and, 0);
+        graph.add_conn(fork_clk, 0, ff0, 1);
+        graph.add_conn(fork_clk, 1, ff1, 1);
+        graph.add_conn(ff0, 0, fork_q0, 0);
+        graph.add_conn(fork_q0, 0, q0, 0);
+        graph.add_conn(fork_q0, 1, and, 1);
+        graph.add_conn(and, 0, ff1, 0);
+        graph.add_conn(ff1, 0, q1, 0);
+        graph.set_name(en, "en");
+        graph.set_name(clk, "clk");
+        graph.set_name(q0, "q0");
+        graph.set_name(q1, "q1");
+        graph.propagate_all();
+        (graph, clk, [q0, q1])
+    }
+
+    #[test]
+    fn counter_reaches_three() {
+        let (mut graph, clk, [q0, q1]) = counter();
+        let property = Property::Never("q0 & q1".to_string());
+        let options = |cycles| BmcOptions {
+            cycles,
+            clock: Some(clk),
+        };
+        assert_eq!(
+            check_property(&graph, &property, &options(2)),
+            Ok(BmcResult::Holds)
+        );
+        let Ok(BmcResult::Violated(trace)) = check_property(&graph, &property, &options(4)) else {
+            panic!("three is reached in three cycles");
+        };
+        assert_eq!(trace.steps(), 6);
+        assert_eq!(trace.signals, ["clk", "en", "q0", "q1"]);
+        assert!(trace.input_values.iter().all(|values| values[0]));
+
+        // Replaying the trace goes through the same values.
+        let position = |name| trace.signals.iter().position(|s| s == name).unwrap();
+        for step in 0..trace.steps() {
+            trace.apply(&mut graph, step);
+            let values = &trace.values[step];
+            assert_eq!(values[position("clk")], trace.clock_value(step).unwrap());
+            assert_eq!(values[position("q0")], graph[q0].state);
+            assert_eq!(values[position("q1")], graph[q1].state);
+        }
+        assert!(graph[q0].state && graph[q1].state);
+
+        let vcd = trace.to_vcd();
+        assert!(vcd.contains("$var wire 1 ! clk $end\n$var wire 1 \" en $end\n"));
+        assert!(vcd.contains("#0\n$dumpvars\n0!\n1\"\n0#\n0$\n$end\n#1\n1!\n1#\n#2\n0!\n"));
+        assert!(vcd.ends_with("#5\n1!\n1#\n#6\n"));
+
+        let always = Property::Always("!q1 | en".to_string());
+        assert!(matches!(
+            check_property(&graph, &always, &options(3)),
+            Ok(BmcResult::Violated(_))
+        ));
+    }
+
+    #[test]
+    fn open_collectors_make_wired_and() {
+        let mut graph = Graph::new();
+        let [a, b] = [(); 2].map(|_| graph.add_comp(Constant::default()));
+        let [oc_a, oc_b] = [(); 2].map(|_| graph.add_comp(OpenCollector));
+        let line = graph.add_comp(Fork::new(2, 1));
+        let pull_up = graph.add_comp(PullResistor::up());
+        let y = graph.add_comp(DebugOutput::default());
+        graph.add_conn(a, 0, oc_a, 0);
+        graph.add_conn(b, 0, oc_b, 0);
+        graph.add_conn(oc_a, 0, line, 0);
+        graph.add_conn(oc_b, 0, line, 1);
+        graph.add_conn(line, 0, pull_up, 0);
+        graph.add_conn(pull_up, 0, y, 0);
+        graph.set_name(a, "a");
+        graph.set_name(b, "b");
+        graph.set_name(y, "y");
+        graph.propagate_all();
+        let options = BmcOptions {
+            cycles: 1,
+            clock: None,
+        };
+
+        let wired_and = Property::Never("y ^ a & b".to_string());
+        assert_eq!(
+            check_property(&graph, &wired_and, &options),
+            Ok(BmcResult::Holds)
+        );
+        let Ok(BmcResult::Violated(trace)) =
+            check_property(&graph, &Property::Never("y".to_string()), &options)
+        else {
+            panic!("the pull-up raises the line");
+        };
+        trace.apply(&mut graph, trace.steps() - 1);
+        assert!(graph[y].state);
+    }
+
+    #[test]
+    fn transceiver_pins_read_their_lines() {
+        let mut graph = Graph::new();
+        let [d, e, dir, oe] = [(); 4].map(|_| graph.add_comp(Constant::default()));
+        let transceiver = graph.add_comp(Transceiver::new(1));
+        let [bus_a, bus_b] = [(); 2].map(|_| graph.add_comp(Fork::new(2, 2)));
+        let [p, q] = [(); 2].map(|_| graph.add_comp(DebugOutput::default()));
+        graph.add_conn(d, 0, bus_a, 0);
+        graph.add_conn(transceiver, 0, bus_a, 1);
+        graph.add_conn(bus_a, 0, transceiver, 0);
+        graph.add_conn(bus_a, 1, p, 0);
+        graph.add_conn(transceiver, 1, bus_b, 0);
+        graph.add_conn(e, 0, bus_b, 1);
+        graph.add_conn(bus_b, 0, transceiver, 1);
+        graph.add_conn(bus_b, 1, q, 0);
+        graph.add_conn(dir, 0, transceiver, 2);
+        graph.add_conn(oe, 0, transceiver, 3);
+        for (id, name) in [(d, "d"), (e, "e"), (dir, "dir"), (oe, "oe")] {
+            graph.set_name(id, name);
+        }
+        graph.set_name(p, "p");
+        graph.set_name(q, "q");
+        graph.propagate_all();
+        let options = BmcOptions {
+            cycles: 1,
+            clock: None,
+        };
+
+        // Each side reads the other one without its own drive, the lines join both.
+        let side_b = Property::Never("q ^ (e | dir & !oe & d)".to_string());
+        assert_eq!(
+            check_property(&graph, &side_b, &options),
+            Ok(BmcResult::Holds)
+        );
+        let side_a = Property::Never("p ^ (d | !dir & !oe & e)".to_string());
+        assert_eq!(
+            check_property(&graph, &side_a, &options),
+            Ok(BmcResult::Holds)
+        );
+        assert!(matches!(
+            check_property(&graph, &Property::Never("p ^ d".to_string()), &options),
+            Ok(BmcResult::Violated(_))
+        ));
+    }
+
+    #[test]
+    fn unsupported_graphs() {
+        let (graph, clk, _) = counter();
+        let options = BmcOptions {
+            cycles: 1,
+            clock: Some(clk),
+        };
+        assert_eq!(
+            check_property(&graph, &Property::Never("q2".to_string()), &options),
+            Err(BmcError::UnknownSignal("q2".to_string()))
+        );
+
+        let mut graph = Graph::new();
+        let or = graph.add_comp(Or);
+        let fork = graph.add_comp(Fork::new(1, 2));
+        let q = graph.add_comp(DebugOutput::default());
+        graph.add_conn(or, 0, fork, 0);
+        graph.add_conn(fork, 0, or, 0);
+        graph.add_conn(fork, 1, q, 0);
+        graph.set_name(q, "q");
+        assert_eq!(
+            check_property(&graph, &Property::Never("q".to_string()), &options),
+            Err(BmcError::CombinationalLoop {
+                path: ["Or", "Fork"].map(String::from).to_vec()
+            })
+        );
+
+        let mut latch = FlipFlop::new(FlipFlopKind::D);
+        latch.trigger = Trigger::HighLevel;
+        let mut graph = Graph::new();
+        let latch = graph.add_comp(latch);
+        graph.set_name(latch, "l");
+        assert_eq!(
+            check_property(&graph, &Property::Never("l".to_string()), &options),
+            Err(BmcError::LevelTriggered {
+                component: "FlipFlop `l`".to_string()
+            })
+        );
+    }
+}
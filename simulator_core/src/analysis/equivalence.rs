@@ -0,0 +1,709 @@
+//! Combinational equivalence of two graphs.
+
+use std::{collections::BTreeMap, error::Error, fmt::Display};
+
+use slotmap::SecondaryMap;
+
+use crate::{
+    components::{
+        simple::{Constant, DebugOutput},
+        Component, ComponentBehaviour,
+    },
+    graph::{
+        id::{ComponentId, TypedId},
+        Graph,
+    },
+};
+
+use super::{
+    sat::{self, Lit, Solver},
+    truth_table::{check_combinational, truth_table, TruthTableError, MAX_INPUTS},
+};
+
+/// Up to this many inputs the graphs are compared on every input combination.
+pub const EXHAUSTIVE_INPUTS: usize = 12;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Method {
+    /// Simulation for small graphs, SAT otherwise.
+    #[default]
+    Auto,
+    /// Simulation of every input combination, for at most [`MAX_INPUTS`] inputs.
+    Exhaustive,
+    Sat,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EquivalenceError {
+    /// The named inputs or outputs of the graphs differ.
+    Interface(String),
+    DuplicateName(String),
+    /// [`Method::Exhaustive`] was asked for more than [`MAX_INPUTS`] inputs.
+    TooManyInputs {
+        count: usize,
+    },
+    NotCombinational(TruthTableError),
+}
+
+impl Display for EquivalenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EquivalenceError::Interface(message) => write!(f, "{message}"),
+            EquivalenceError::DuplicateName(name) => {
+                write!(f, "more than one input or output is named `{name}`")
+            }
+            EquivalenceError::TooManyInputs { count } => write!(
+                f,
+                "{count} inputs are too many to compare on every combination, \
+                 at most {MAX_INPUTS} are allowed"
+            ),
+            EquivalenceError::NotCombinational(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for EquivalenceError {}
+
+impl From<TruthTableError> for EquivalenceError {
+    fn from(err: TruthTableError) -> Self {
+        EquivalenceError::NotCombinational(err)
+    }
+}
+
+/// Input values on which the graphs differ, with the outputs of both, sorted by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterexample {
+    pub inputs: Vec<(String, bool)>,
+    pub left: Vec<(String, bool)>,
+    pub right: Vec<(String, bool)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Equivalence {
+    Equivalent,
+    Different(Counterexample),
+}
+
+/// Named `Constant`s and `DebugOutput`s of a graph, by name.
+struct Interface {
+    inputs: BTreeMap<String, TypedId<Constant>>,
+    outputs: BTreeMap<String, TypedId<DebugOutput>>,
+}
+
+impl Interface {
+    fn of(graph: &Graph) -> Result<Self, EquivalenceError> {
+        let mut interface = Interface {
+            inputs: BTreeMap::new(),
+            outputs: BTreeMap::new(),
+        };
+        for (id, node) in &graph.nodes {
+            let Some(name) = node.name.clone() else {
+                continue;
+            };
+            let duplicate = match node.component {
+                Component::Constant(_) => {
+                    interface.inputs.insert(name.clone(), id.into()).is_some()
+                }
+                Component::DebugOutput(_) => {
+                    interface.outputs.insert(name.clone(), id.into()).is_some()
+                }
+                _ => false,
+            };
+            if duplicate {
+                return Err(EquivalenceError::DuplicateName(name));
+            }
+        }
+        Ok(interface)
+    }
+
+    fn input_ids(&self) -> Vec<TypedId<Constant>> {
+        self.inputs.values().copied().collect()
+    }
+
+    fn output_ids(&self) -> Vec<TypedId<DebugOutput>> {
+        self.outputs.values().copied().collect()
+    }
+}
+
+/// Checks whether two graphs compute the same outputs for all inputs.
+///
+/// Inputs are the named `Constant`s and outputs the named `DebugOutput`s,
+/// both graphs need the same names. Unnamed `Constant`s keep their state.
+pub fn check_equivalence(
+    left: &mut Graph,
+    right: &mut Graph,
+    method: Method,
+) -> Result<Equivalence, EquivalenceError> {
+    let left_interface = Interface::of(left)?;
+    let right_interface = Interface::of(right)?;
+    same_names(
+        "input",
+        left_interface.inputs.keys(),
+        right_interface.inputs.keys(),
+    )?;
+    same_names(
+        "output",
+        left_interface.outputs.keys(),
+        right_interface.outputs.keys(),
+    )?;
+
+    let exhaustive = match method {
+        Method::Auto => left_interface.inputs.len() <= EXHAUSTIVE_INPUTS,
+        Method::Exhaustive if left_interface.inputs.len() > MAX_INPUTS => {
+            return Err(EquivalenceError::TooManyInputs {
+                count: left_interface.inputs.len(),
+            })
+        }
+        Method::Exhaustive => true,
+        Method::Sat => false,
+    };
+    let inputs = if exhaustive {
+        compare_exhaustively(left, &left_interface, right, &right_interface)?
+    } else {
+        compare_with_sat(left, &left_interface, right, &right_interface)?
+    };
+
+    let Some(inputs) = inputs else {
+        return Ok(Equivalence::Equivalent);
+    };
+    let names = left_interface.inputs.keys().cloned();
+    Ok(Equivalence::Different(Counterexample {
+        left: evaluate(left, &left_interface, &inputs),
+        right: evaluate(right, &right_interface, &inputs),
+        inputs: names.zip(inputs).collect(),
+    }))
+}
+
+fn same_names<'a>(
+    kind: &str,
+    left: impl Iterator<Item = &'a String>,
+    right: impl Iterator<Item = &'a String>,
+) -> Result<(), EquivalenceError> {
+    let (left, right) = (left.collect::<Vec<_>>(), right.collect::<Vec<_>>());
+    let missing = |from: &[&String], of: &[&String], graph: &str| match of
+        .iter()
+        .find(|name| !from.contains(name))
+    {
+        Some(name) => Err(EquivalenceError::Interface(format!(
+            "{kind} `{name}` is missing from the {graph} graph"
+        ))),
+        None => Ok(()),
+    };
+    missing(&right, &left, "second")?;
+    missing(&left, &right, "first")
+}
+
+/// Returns the first input combination with different outputs.
+fn compare_exhaustively(
+    left: &mut Graph,
+    left_interface: &Interface,
+    right: &mut Graph,
+    right_interface: &Interface,
+) -> Result<Option<Vec<bool>>, EquivalenceError> {
+    let left_table = truth_table(
+        left,
+        &left_interface.input_ids(),
+        &left_interface.output_ids(),
+    )?;
+    let right_table = truth_table(
+        right,
+        &right_interface.input_ids(),
+        &right_interface.output_ids(),
+    )?;
+    let row = (0..left_table.rows.len()).find(|&row| left_table.rows[row] != right_table.rows[row]);
+    Ok(row.map(|row| {
+        (0..left_table.inputs.len())
+            .map(|input| left_table.input(row, input))
+            .collect()
+    }))
+}
+
+/// Solves the miter of the graphs, returning the inputs of a model.
+fn compare_with_sat(
+    left: &Graph,
+    left_interface: &Interface,
+    right: &Graph,
+    right_interface: &Interface,
+) -> Result<Option<Vec<bool>>, EquivalenceError> {
+    let left_outputs = left_interface.outputs.values().map(|&id| id.into());
+    check_combinational(left, left_outputs)?;
+    let right_outputs = right_interface.outputs.values().map(|&id| id.into());
+    check_combinational(right, right_outputs)?;
+
+    let mut solver = Solver::new();
+    let truth = solver.new_lit();
+    solver.add_clause(&[truth]);
+    let inputs = left_interface
+        .inputs
+        .keys()
+        .map(|_| solver.new_lit())
+        .collect::<Vec<_>>();
+
+    let mut encode = |graph: &Graph, interface: &Interface| {
+        let mut leaves = SecondaryMap::new();
+        for (&id, &lit) in interface.inputs.values().zip(&inputs) {
+            leaves.insert(id.into(), lit);
+        }
+        let mut encoder = Encoder {
+            solver: &mut solver,
+            truth,
+        };
+        let mut encoded = Encoded::default();
+        interface
+            .outputs
+            .values()
+            .map(|&id| encoder.read(graph, id.into(), 0, &leaves, &mut encoded))
+            .collect::<Vec<_>>()
+    };
+    let left_lits = encode(left, left_interface);
+    let right_lits = encode(right, right_interface);
+
+    let differences = left_lits
+        .into_iter()
+        .zip(right_lits)
+        .map(|(l, r)| sat::xor(&mut solver, l, r))
+        .collect::<Vec<_>>();
+    solver.add_clause(&differences);
+
+    if !solver.solve() {
+        return Ok(None);
+    }
+    Ok(Some(
+        inputs
+            .iter()
+            .map(|&lit| solver.value(lit) == Some(true))
+            .collect(),
+    ))
+}
+
+/// Tseitin encoding of graph signals.
+struct Encoder<'a> {
+    solver: &'a mut Solver,
+    /// Literal fixed to true.
+    truth: Lit,
+}
+
+/// Literals of the nodes of one graph instance, encoded once.
+#[derive(Default)]
+struct Encoded {
+    outputs: SecondaryMap<ComponentId, Vec<Lit>>,
+    /// Whether each output is released, `None` when it never is.
+    released: SecondaryMap<ComponentId, Vec<Option<Lit>>>,
+}
+
+impl Encoder<'_> {
+    /// Literal of the signal coming into `slot` of `node`,
+    /// which reads as it floats while nothing drives it.
+    fn read(
+        &mut self,
+        graph: &Graph,
+        node: ComponentId,
+        slot: usize,
+        leaves: &SecondaryMap<ComponentId, Lit>,
+        encoded: &mut Encoded,
+    ) -> Lit {
+        if let Some(source) = &graph.nodes[node].input_slots[slot] {
+            self.encode(graph, source.target_node, leaves, encoded);
+        }
+        self.input(graph, node, slot, encoded)
+    }
+
+    /// Encodes `root` and the nodes driving it, each once per graph instance.
+    ///
+    /// Nodes are encoded after their drivers, in the order of a depth first search
+    /// against the signal flow kept on a stack, so long paths do not overflow
+    /// the call stack. The stack holds a node, the input slot it is read without
+    /// and its next input slot. A `Fork` read by a bidirectional pin without the
+    /// input the pin drives is left to [`Encoder::line`], only its drivers are encoded.
+    fn encode(
+        &mut self,
+        graph: &Graph,
+        root: ComponentId,
+        leaves: &SecondaryMap<ComponentId, Lit>,
+        encoded: &mut Encoded,
+    ) {
+        let mut stack = vec![(root, None, 0)];
+        while let Some(&(node, except, slot)) = stack.last() {
+            if encoded.outputs.contains_key(node) {
+                stack.pop();
+                continue;
+            }
+            match graph.nodes[node].input_slots.get(slot) {
+                Some(source) => {
+                    stack.last_mut().unwrap().2 += 1;
+                    match source {
+                        Some(source) if except != Some(slot) => {
+                            let (node, slot) = (source.target_node, source.target_slot);
+                            stack.push((node, graph.terminal_input(node, slot), 0));
+                        }
+                        _ => {}
+                    }
+                }
+                None => {
+                    if except.is_none() {
+                        self.node(graph, node, leaves, encoded);
+                    }
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    /// Literal of the signal coming into `slot` of `node`, whose driver is encoded.
+    fn input(&mut self, graph: &Graph, node: ComponentId, slot: usize, encoded: &Encoded) -> Lit {
+        let floating = graph[node].floating_input(slot);
+        let Some(source) = &graph.nodes[node].input_slots[slot] else {
+            return if floating { self.truth } else { !self.truth };
+        };
+        let (driver, slot) = (source.target_node, source.target_slot);
+        let (value, released) = match graph.terminal_input(driver, slot) {
+            Some(except) => self.line(graph, driver, except, encoded),
+            None => {
+                let released = encoded.released[driver][slot];
+                (encoded.outputs[driver][slot], released)
+            }
+        };
+        match released {
+            None => value,
+            Some(released) if floating => sat::or(self.solver, &[value, released]),
+            Some(released) => sat::and(self.solver, &[value, !released]),
+        }
+    }
+
+    /// Literal of the line of the `Fork` `node` left without input `except`, and
+    /// whether it is released, as the bidirectional pin driving that input reads it.
+    fn line(
+        &mut self,
+        graph: &Graph,
+        node: ComponentId,
+        except: usize,
+        encoded: &Encoded,
+    ) -> (Lit, Option<Lit>) {
+        let input_slots = graph.nodes[node].input_slots.iter().enumerate();
+        let (mut values, mut undriven) = (Vec::new(), Vec::new());
+        for (slot, source) in input_slots.filter(|&(slot, _)| slot != except) {
+            values.push(self.input(graph, node, slot, encoded));
+            undriven.push(match source {
+                Some(source) => encoded.released[source.target_node][source.target_slot],
+                None => Some(self.truth),
+            });
+        }
+        let value = sat::or(self.solver, &values);
+        let undriven = undriven.into_iter().collect::<Option<Vec<_>>>();
+        (value, undriven.map(|lits| sat::and(self.solver, &lits)))
+    }
+
+    /// Encodes the outputs of `node` and whether they are released, its drivers
+    /// being encoded.
+    fn node(
+        &mut self,
+        graph: &Graph,
+        node: ComponentId,
+        leaves: &SecondaryMap<ComponentId, Lit>,
+        encoded: &mut Encoded,
+    ) {
+        let input_slots = &graph.nodes[node].input_slots;
+        let inputs = (0..input_slots.len())
+            .map(|slot| self.input(graph, node, slot, encoded))
+            .collect::<Vec<_>>();
+        let slots = graph.nodes[node].output_slots.len();
+        let mut released = vec![None; slots];
+
+        let lits = match &graph[node] {
+            Component::Constant(constant) => {
+                vec![leaves.get(node).copied().unwrap_or(if constant.state {
+                    self.truth
+                } else {
+                    !self.truth
+                })]
+            }
+            Component::And(_) => vec![sat::and(self.solver, &inputs)],
+            Component::Or(_) => vec![sat::or(self.solver, &inputs)],
+            Component::Xor(_) => vec![sat::xor(self.solver, inputs[0], inputs[1])],
+            Component::Not(_) => vec![!inputs[0]],
+            Component::PullResistor(_) => vec![inputs[0]],
+            // Pulls its line low unless released by a high input.
+            Component::OpenCollector(_) => {
+                released = vec![Some(inputs[0])];
+                vec![!self.truth]
+            }
+            Component::Transceiver(transceiver) => {
+                let width = transceiver.width as usize;
+                let (dir, enabled) = (inputs[2 * width], !inputs[2 * width + 1]);
+                let to_a = sat::and(self.solver, &[enabled, !dir]);
+                let to_b = sat::and(self.solver, &[enabled, dir]);
+                released = vec![Some(!to_a); width];
+                released.extend(vec![Some(!to_b); width]);
+                let a = (0..width).map(|i| sat::and(self.solver, &[to_a, inputs[width + i]]));
+                let a = a.collect::<Vec<_>>();
+                let b = (0..width).map(|i| sat::and(self.solver, &[to_b, inputs[i]]));
+                a.into_iter().chain(b.collect::<Vec<_>>()).collect()
+            }
+            Component::Fork(_) => {
+                // Released when none of the inputs is driven.
+                let undriven = input_slots.iter().map(|source| match source {
+                    Some(source) => encoded.released[source.target_node][source.target_slot],
+                    None => Some(self.truth),
+                });
+                let undriven = undriven.collect::<Option<Vec<_>>>();
+                released = vec![undriven.map(|lits| sat::and(self.solver, &lits)); slots];
+                let lit = match inputs[..] {
+                    [single] => single,
+                    _ => sat::or(self.solver, &inputs),
+                };
+                vec![lit; slots]
+            }
+            Component::Subcircuit(sub) => {
+                let mut inner_leaves = SecondaryMap::new();
+                for (id, &lit) in sub.input_ids().zip(&inputs) {
+                    inner_leaves.insert(id, lit);
+                }
+                let mut inner_encoded = Encoded::default();
+                sub.output_ids()
+                    .map(|id| self.read(&sub.graph, id, 0, &inner_leaves, &mut inner_encoded))
+                    .collect()
+            }
+            Component::Rom(rom) => {
+                let words = rom.contents.iter().enumerate().map(|(a, &word)| {
+                    let address = inputs.iter().enumerate();
+                    let lits = address
+                        .map(|(bit, &lit)| if a >> bit & 1 == 1 { lit } else { !lit })
+                        .collect::<Vec<_>>();
+                    (word, sat::and(self.solver, &lits))
+                });
+                let words = words.collect::<Vec<_>>();
+                (0..rom.data_width as usize)
+                    .map(|bit| {
+                        let selected = words
+                            .iter()
+                            .filter(|(word, _)| word >> bit & 1 == 1)
+                            .map(|&(_, lit)| lit)
+                            .collect::<Vec<_>>();
+                        sat::or(self.solver, &selected)
+                    })
+                    .collect()
+            }
+            Component::DebugOutput(_) | Component::FlipFlop(_) | Component::Ram(_) => {
+                unreachable!("checked by check_combinational")
+            }
+        };
+        encoded.outputs.insert(node, lits);
+        encoded.released.insert(node, released);
+    }
+}
+
+/// Outputs of the graph for the given inputs, the graph is left as it was.
+fn evaluate(graph: &mut Graph, interface: &Interface, values: &[bool]) -> Vec<(String, bool)> {
+    let set = |graph: &mut Graph, values: &mut dyn Iterator<Item = bool>| {
+        for (&id, value) in interface.inputs.values().zip(values) {
+            graph[id].state = value;
+            graph.propagate_from(id);
+        }
+    };
+    let original = interface
+        .inputs
+        .values()
+        .map(|&id| graph[id].state)
+        .collect::<Vec<_>>();
+
+    set(graph, &mut values.iter().copied());
+    let outputs = interface
+        .outputs
+        .iter()
+        .map(|(name, &id)| (name.clone(), graph[id].state))
+        .collect();
+    set(graph, &mut original.into_iter());
+    outputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::synthesis::{parse_equations, synthesize, GateStyle},
+        components::{
+            gates::Xor,
+            simple::Fork,
+            wired::{OpenCollector, PullResistor, Transceiver},
+        },
+    };
+
+    fn build(source: &str, style: GateStyle) -> Graph {
+        synthesize(&parse_equations(source).unwrap(), style)
+    }
+
+    #[test]
+    fn full_adders() {
+        let xor_form = "S = A ^ B ^ C; K = A & B | C & (A ^ B)";
+        let sum_of_products = "S = !A & !B & C | !A & B & !C | A & !B & !C | A & B & C
+                               K = A & B | A & C | B & C";
+        let wrong_carry = "S = A ^ B ^ C; K = A & B | A & C";
+
+        for method in [Method::Exhaustive, Method::Sat] {
+            let mut left = build(xor_form, GateStyle::Mixed);
+            let mut right = build(sum_of_products, GateStyle::NandOnly);
+            assert_eq!(
+                check_equivalence(&mut left, &mut right, method),
+                Ok(Equivalence::Equivalent)
+            );
+
+            let mut wrong = build(wrong_carry, GateStyle::NorOnly);
+            let Ok(Equivalence::Different(counterexample)) =
+                check_equivalence(&mut left, &mut wrong, method)
+            else {
+                panic!("the carries differ");
+            };
+            let value = |name| {
+                counterexample
+                    .inputs
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .unwrap()
+                    .1
+            };
+            // Only `B & C` without `A` tells the carries apart.
+            assert!(!value("A") && value("B") && value("C"));
+            assert_eq!(counterexample.left[0], ("K".to_string(), true));
+            assert_eq!(counterexample.right[0], ("K".to_string(), false));
+        }
+    }
+
+    #[test]
+    fn open_collectors_make_wired_and() {
+        let mut wired = Graph::new();
+        let [a, b] = [(); 2].map(|_| wired.add_comp(Constant::default()));
+        let [oc_a, oc_b] = [(); 2].map(|_| wired.add_comp(OpenCollector));
+        let line = wired.add_comp(Fork::new(2, 1));
+        let pull_up = wired.add_comp(PullResistor::up());
+        let y = wired.add_comp(DebugOutput::default());
+        wired.add_conn(a, 0, oc_a, 0);
+        wired.add_conn(b, 0, oc_b, 0);
+        wired.add_conn(oc_a, 0, line, 0);
+        wired.add_conn(oc_b, 0, line, 1);
+        wired.add_conn(line, 0, pull_up, 0);
+        wired.add_conn(pull_up, 0, y, 0);
+        wired.set_name(a, "A");
+        wired.set_name(b, "B");
+        wired.set_name(y, "Y");
+        wired.propagate_all();
+
+        for method in [Method::Exhaustive, Method::Sat] {
+            let mut and = build("Y = A & B", GateStyle::Mixed);
+            assert_eq!(
+                check_equivalence(&mut wired, &mut and, method),
+                Ok(Equivalence::Equivalent)
+            );
+            let mut or = build("Y = A | B", GateStyle::Mixed);
+            assert!(matches!(
+                check_equivalence(&mut wired, &mut or, method),
+                Ok(Equivalence::Different(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn transceiver_pins_read_their_lines() {
+        let mut bus = Graph::new();
+        let [d, e, dir, oe] = [(); 4].map(|_| bus.add_comp(Constant::default()));
+        let transceiver = bus.add_comp(Transceiver::new(1));
+        let [bus_a, bus_b] = [(); 2].map(|_| bus.add_comp(Fork::new(2, 2)));
+        let [p, q] = [(); 2].map(|_| bus.add_comp(DebugOutput::default()));
+        let sides = [(d, bus_a, p), (e, bus_b, q)];
+        for (side, (input, line, output)) in sides.into_iter().enumerate() {
+            bus.add_conn(input, 0, line, 0);
+            bus.add_conn(transceiver, side, line, 1);
+            bus.add_conn(line, 0, transceiver, side);
+            bus.add_conn(line, 1, output, 0);
+        }
+        bus.add_conn(dir, 0, transceiver, 2);
+        bus.add_conn(oe, 0, transceiver, 3);
+        for (id, name) in [(d, "D"), (e, "E"), (dir, "DIR"), (oe, "OE")] {
+            bus.set_name(id, name);
+        }
+        bus.set_name(p, "P");
+        bus.set_name(q, "Q");
+        bus.propagate_all();
+
+        for method in [Method::Exhaustive, Method::Sat] {
+            let mut joined = build(
+                "P = D | !DIR & !OE & E; Q = E | DIR & !OE & D",
+                GateStyle::Mixed,
+            );
+            assert_eq!(
+                check_equivalence(&mut bus, &mut joined, method),
+                Ok(Equivalence::Equivalent)
+            );
+            let mut one_way = build("P = D; Q = E | DIR & !OE & D", GateStyle::Mixed);
+            assert!(matches!(
+                check_equivalence(&mut bus, &mut one_way, method),
+                Ok(Equivalence::Different(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn wide_circuits_use_sat() {
+        let names = (0..24).map(|i| format!("x{i}")).collect::<Vec<_>>();
+        let parity = format!("P = {}", names.join(" ^ "));
+        let all = format!("Q = {}", names.join(" & "));
+        let mut left = build(&format!("{parity}; {all}"), GateStyle::Mixed);
+        let mut right = build(&format!("{parity}; {all}"), GateStyle::NandOnly);
+        assert_eq!(
+            check_equivalence(&mut left, &mut right, Method::Auto),
+            Ok(Equivalence::Equivalent)
+        );
+
+        let almost_all = format!("Q = {} & !x23", names[..23].join(" & "));
+        let mut wrong = build(&format!("{parity}; {almost_all}"), GateStyle::Mixed);
+        let Ok(Equivalence::Different(counterexample)) =
+            check_equivalence(&mut left, &mut wrong, Method::Auto)
+        else {
+            panic!("the ands differ");
+        };
+        assert_ne!(counterexample.left, counterexample.right);
+        assert_eq!(
+            check_equivalence(&mut left, &mut right, Method::Exhaustive),
+            Err(EquivalenceError::TooManyInputs { count: 24 })
+        );
+
+        let mut other = build("P = x0", GateStyle::Mixed);
+        assert_eq!(
+            check_equivalence(&mut left, &mut other, Method::Auto),
+            Err(EquivalenceError::Interface(
+                "input `x1` is missing from the second graph".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn long_chains_are_encoded_without_recursion() {
+        // `Y = A ^ B ^ B ^ ...` with an odd number of `B`s, gates deep.
+        let mut chain = Graph::new();
+        let [a, b] = [(); 2].map(|_| chain.add_comp(Constant::default()));
+        let y = chain.add_comp(DebugOutput::default());
+        chain.set_name(a, "A");
+        chain.set_name(b, "B");
+        chain.set_name(y, "Y");
+        let (mut line, mut b) = (ComponentId::from(a), (ComponentId::from(b), 0));
+        for _ in 0..20_001 {
+            let xor = chain.add_comp(Xor);
+            let fork = chain.add_comp(Fork::new(1, 2));
+            chain.add_conn(b.0, b.1, fork, 0);
+            chain.add_conn(line, 0, xor, 0);
+            chain.add_conn(fork, 0, xor, 1);
+            (line, b) = (xor.into(), (fork.into(), 1));
+        }
+        chain.add_conn(line, 0, y, 0);
+
+        let mut xor = build("Y = A ^ B", GateStyle::Mixed);
+        assert_eq!(
+            check_equivalence(&mut chain, &mut xor, Method::Sat),
+            Ok(Equivalence::Equivalent)
+        );
+        let mut or = build("Y = A | B", GateStyle::Mixed);
+        assert!(matches!(
+            check_equivalence(&mut chain, &mut or, Method::Sat),
+            Ok(Equivalence::Different(_))
+        ));
+    }
+}
@@ -0,0 +1,532 @@
+//! Boolean expressions of the outputs of combinational graphs.
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    ops::Not,
+};
+
+use slotmap::{SecondaryMap, SparseSecondaryMap};
+
+use crate::{
+    components::{
+        simple::{Constant, DebugOutput},
+        Component, ComponentBehaviour,
+    },
+    graph::{
+        id::{ComponentId, TypedId},
+        Graph,
+    },
+};
+
+use super::truth_table::{check_combinational, TruthTableError};
+
+/// Boolean expression over numbered variables.
+///
+/// Build it with [`Expr::and`], [`Expr::or`], [`Expr::xor`] and `!`,
+/// which fold constants and flatten nested operators of the same kind.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expr {
+    Const(bool),
+    Var(usize),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Xor(Vec<Expr>),
+}
+
+impl Not for Expr {
+    type Output = Expr;
+
+    fn not(self) -> Expr {
+        match self {
+            Expr::Const(value) => Expr::Const(!value),
+            Expr::Not(inner) => *inner,
+            expr => Expr::Not(Box::new(expr)),
+        }
+    }
+}
+
+impl Expr {
+    pub fn and(terms: impl IntoIterator<Item = Expr>) -> Expr {
+        let mut flat = Vec::new();
+        for term in terms {
+            match term {
+                Expr::Const(true) => {}
+                Expr::Const(false) => return Expr::Const(false),
+                Expr::And(inner) => flat.extend(inner),
+                term => flat.push(term),
+            }
+        }
+        match flat.len() {
+            0 => Expr::Const(true),
+            1 => flat.pop().unwrap(),
+            _ => Expr::And(flat),
+        }
+    }
+
+    pub fn or(terms: impl IntoIterator<Item = Expr>) -> Expr {
+        let mut flat = Vec::new();
+        for term in terms {
+            match term {
+                Expr::Const(false) => {}
+                Expr::Const(true) => return Expr::Const(true),
+                Expr::Or(inner) => flat.extend(inner),
+                term => flat.push(term),
+            }
+        }
+        match flat.len() {
+            0 => Expr::Const(false),
+            1 => flat.pop().unwrap(),
+            _ => Expr::Or(flat),
+        }
+    }
+
+    pub fn xor(terms: impl IntoIterator<Item = Expr>) -> Expr {
+        let mut flat = Vec::new();
+        let mut inverted = false;
+        for term in terms {
+            match term {
+                Expr::Const(value) => inverted ^= value,
+                Expr::Xor(inner) => flat.extend(inner),
+                term => flat.push(term),
+            }
+        }
+        let expr = match flat.len() {
+            0 => Expr::Const(false),
+            1 => flat.pop().unwrap(),
+            _ => Expr::Xor(flat),
+        };
+        if inverted {
+            !expr
+        } else {
+            expr
+        }
+    }
+
+    /// Value of the expression, variable `i` being `vars[i]`.
+    pub fn eval(&self, vars: &[bool]) -> bool {
+        match self {
+            Expr::Const(value) => *value,
+            Expr::Var(i) => vars[*i],
+            Expr::Not(inner) => !inner.eval(vars),
+            Expr::And(terms) => terms.iter().all(|term| term.eval(vars)),
+            Expr::Or(terms) => terms.iter().any(|term| term.eval(vars)),
+            Expr::Xor(terms) => terms.iter().fold(false, |acc, term| acc ^ term.eval(vars)),
+        }
+    }
+
+    /// Number of two input gates and inverters needed to build the expression,
+    /// identical subexpressions being built once.
+    pub fn gate_count(&self) -> usize {
+        fn collect<'a>(expr: &'a Expr, seen: &mut Vec<&'a Expr>) {
+            let terms = match expr {
+                Expr::Const(_) | Expr::Var(_) => return,
+                Expr::Not(inner) => std::slice::from_ref(&**inner),
+                Expr::And(terms) | Expr::Or(terms) | Expr::Xor(terms) => terms,
+            };
+            if !seen.contains(&expr) {
+                seen.push(expr);
+                terms.iter().for_each(|term| collect(term, seen));
+            }
+        }
+
+        let mut seen = Vec::new();
+        collect(self, &mut seen);
+        seen.iter()
+            .map(|expr| match expr {
+                Expr::And(terms) | Expr::Or(terms) | Expr::Xor(terms) => terms.len() - 1,
+                _ => 1,
+            })
+            .sum()
+    }
+
+    /// Displays the expression with `!`, `&`, `^` and `|`, binding in that order,
+    /// variable `i` being called `names[i]`.
+    pub fn display<'a>(&'a self, names: &'a [String]) -> impl Display + 'a {
+        Named { expr: self, names }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Or(_) => 0,
+            Expr::Xor(_) => 1,
+            Expr::And(_) => 2,
+            Expr::Const(_) | Expr::Var(_) | Expr::Not(_) => 3,
+        }
+    }
+}
+
+struct Named<'a> {
+    expr: &'a Expr,
+    names: &'a [String],
+}
+
+impl Named<'_> {
+    fn child<'b>(&'b self, expr: &'b Expr) -> Named<'b> {
+        Named {
+            expr,
+            names: self.names,
+        }
+    }
+
+    fn operand(&self, f: &mut Formatter<'_>, expr: &Expr) -> std::fmt::Result {
+        if expr.precedence() < self.expr.precedence() {
+            write!(f, "({})", self.child(expr))
+        } else {
+            write!(f, "{}", self.child(expr))
+        }
+    }
+}
+
+impl Display for Named<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (terms, operator) = match self.expr {
+            Expr::Const(value) => return write!(f, "{}", *value as u8),
+            Expr::Var(i) => return write!(f, "{}", self.names[*i]),
+            Expr::Not(inner) => {
+                write!(f, "!")?;
+                return self.operand(f, inner);
+            }
+            Expr::And(terms) => (terms, " & "),
+            Expr::Or(terms) => (terms, " | "),
+            Expr::Xor(terms) => (terms, " ^ "),
+        };
+        for (i, term) in terms.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{operator}")?;
+            }
+            self.operand(f, term)?;
+        }
+        Ok(())
+    }
+}
+
+/// Derives the expression of every output from the structure of the graph,
+/// `inputs[i]` being variable `i`.
+///
+/// Other `Constant`s are taken with their current state, undriven input slots read as
+/// they float in two-valued simulation.
+/// Fails like [`truth_table`](super::truth_table::truth_table) on sequential graphs.
+pub fn extract(
+    graph: &Graph,
+    inputs: &[TypedId<Constant>],
+    outputs: &[TypedId<DebugOutput>],
+) -> Result<Vec<Expr>, TruthTableError> {
+    check_combinational(graph, outputs.iter().map(|&id| id.into()))?;
+
+    let mut leaves = SecondaryMap::new();
+    for (i, &id) in inputs.iter().enumerate() {
+        leaves.insert(id.into(), Expr::Var(i));
+    }
+    let mut extractor = Extractor::new(graph, leaves);
+    Ok(outputs
+        .iter()
+        .map(|&id| extractor.input_expr(id.into(), 0))
+        .collect())
+}
+
+/// Expressions of the signals of a graph, each output slot being derived once
+/// so that reconvergent fan-out does not repeat the work.
+struct Extractor<'a> {
+    graph: &'a Graph,
+    leaves: SecondaryMap<ComponentId, Expr>,
+    cache: HashMap<(ComponentId, usize), Expr>,
+    /// Expressions of whether output slots are released.
+    released: HashMap<(ComponentId, usize), Expr>,
+}
+
+impl<'a> Extractor<'a> {
+    fn new(graph: &'a Graph, leaves: SecondaryMap<ComponentId, Expr>) -> Self {
+        Self {
+            graph,
+            leaves,
+            cache: HashMap::new(),
+            released: HashMap::new(),
+        }
+    }
+
+    /// Expression of the signal coming into `slot` of `node`, which reads as it
+    /// floats while nothing drives it.
+    fn input_expr(&mut self, node: ComponentId, slot: usize) -> Expr {
+        let graph = self.graph;
+        let floating = graph[node].floating_input(slot);
+        let Some(source) = &graph.nodes[node].input_slots[slot] else {
+            return Expr::Const(floating);
+        };
+        let value = self.output_expr(source.target_node, source.target_slot);
+        match self.released_expr(source.target_node, source.target_slot) {
+            Expr::Const(false) => value,
+            released if floating => Expr::or([value, released]),
+            released => Expr::and([value, !released]),
+        }
+    }
+
+    /// Expression of whether nothing drives `slot` of `node`.
+    fn undriven_expr(&mut self, node: ComponentId, slot: usize) -> Expr {
+        match &self.graph.nodes[node].input_slots[slot] {
+            Some(source) => self.released_expr(source.target_node, source.target_slot),
+            None => Expr::Const(true),
+        }
+    }
+
+    /// Expression of whether `slot` of `node` is released, leaving its line to
+    /// the other drivers.
+    fn released_expr(&mut self, node: ComponentId, slot: usize) -> Expr {
+        if let Some(expr) = self.released.get(&(node, slot)) {
+            return expr.clone();
+        }
+        let graph = self.graph;
+        let expr = match &graph[node] {
+            Component::OpenCollector(_) => self.input_expr(node, 0),
+            Component::Transceiver(transceiver) => {
+                let width = transceiver.width as usize;
+                let dir = self.input_expr(node, 2 * width);
+                let enabled = !self.input_expr(node, 2 * width + 1);
+                let towards = if slot < width { !dir } else { dir };
+                !Expr::and([enabled, towards])
+            }
+            Component::Fork(_) => {
+                let inputs = self.line_inputs(node, slot);
+                Expr::and(inputs.into_iter().map(|i| self.undriven_expr(node, i)))
+            }
+            _ => Expr::Const(false),
+        };
+        self.released.insert((node, slot), expr.clone());
+        expr
+    }
+
+    /// Expression of the signal going out of `slot` of `node`.
+    fn output_expr(&mut self, node: ComponentId, slot: usize) -> Expr {
+        if let Some(expr) = self.cache.get(&(node, slot)) {
+            return expr.clone();
+        }
+        let expr = self.derive(node, slot);
+        self.cache.insert((node, slot), expr.clone());
+        expr
+    }
+
+    fn inputs(&mut self, node: ComponentId) -> Vec<Expr> {
+        (0..self.graph.nodes[node].input_slots.len())
+            .map(|i| self.input_expr(node, i))
+            .collect()
+    }
+
+    /// Input slots of the `Fork` `node` making the line of its output `slot`, the one
+    /// driven by a bidirectional pin reading the line left out.
+    fn line_inputs(&self, node: ComponentId, slot: usize) -> Vec<usize> {
+        let except = self.graph.terminal_input(node, slot);
+        (0..self.graph.nodes[node].input_slots.len())
+            .filter(|&i| Some(i) != except)
+            .collect()
+    }
+
+    fn derive(&mut self, node: ComponentId, slot: usize) -> Expr {
+        let graph = self.graph;
+        match &graph[node] {
+            Component::Constant(constant) => self
+                .leaves
+                .get(node)
+                .cloned()
+                .unwrap_or(Expr::Const(constant.state)),
+            Component::And(_) => Expr::and(self.inputs(node)),
+            Component::Or(_) => Expr::or(self.inputs(node)),
+            Component::Fork(_) => {
+                let inputs = self.line_inputs(node, slot);
+                Expr::or(inputs.into_iter().map(|i| self.input_expr(node, i)))
+            }
+            Component::Xor(_) => Expr::xor(self.inputs(node)),
+            Component::Not(_) => !self.input_expr(node, 0),
+            Component::PullResistor(_) => self.input_expr(node, 0),
+            // Pulls its line low unless released, see `released_expr`.
+            Component::OpenCollector(_) => Expr::Const(false),
+            Component::Transceiver(transceiver) => {
+                let width = transceiver.width as usize;
+                let dir = self.input_expr(node, 2 * width);
+                let enabled = !self.input_expr(node, 2 * width + 1);
+                match slot.checked_sub(width) {
+                    Some(b) => Expr::and([enabled, dir, self.input_expr(node, b)]),
+                    None => Expr::and([enabled, !dir, self.input_expr(node, width + slot)]),
+                }
+            }
+            Component::Subcircuit(sub) => {
+                let mut inner_leaves = SecondaryMap::new();
+                for (i, id) in sub.input_ids().enumerate() {
+                    inner_leaves.insert(id, self.input_expr(node, i));
+                }
+                Extractor::new(&sub.graph, inner_leaves).input_expr(sub.outputs[slot].into(), 0)
+            }
+            Component::Rom(rom) => {
+                let address = self.inputs(node);
+                let words = rom.contents.iter().enumerate();
+                Expr::or(
+                    words
+                        .filter(|(_, word)| *word >> slot & 1 == 1)
+                        .map(|(a, _)| {
+                            Expr::and(address.iter().enumerate().map(|(bit, expr)| {
+                                if a >> bit & 1 == 1 {
+                                    expr.clone()
+                                } else {
+                                    !expr.clone()
+                                }
+                            }))
+                        }),
+                )
+            }
+            Component::DebugOutput(_) | Component::FlipFlop(_) | Component::Ram(_) => {
+                unreachable!("checked by check_combinational")
+            }
+        }
+    }
+}
+
+/// Number of gates, `Not`s included, driving the given nodes, subcircuits counted by their contents.
+pub fn gate_count(graph: &Graph, roots: impl Iterator<Item = ComponentId>) -> usize {
+    let mut seen = SparseSecondaryMap::new();
+    let mut stack = roots.collect::<Vec<_>>();
+    let mut count = 0;
+    while let Some(id) = stack.pop() {
+        if seen.insert(id, ()).is_some() {
+            continue;
+        }
+        count += match &graph[id] {
+            Component::And(_) | Component::Or(_) | Component::Xor(_) | Component::Not(_) => 1,
+            Component::Subcircuit(sub) => gate_count(&sub.graph, sub.output_ids()),
+            _ => 0,
+        };
+        stack.extend(
+            graph.nodes[id]
+                .input_slots
+                .iter()
+                .flatten()
+                .map(|slot| slot.target_node),
+        );
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{
+        gates::{And, Not, Or},
+        memory::Rom,
+        simple::Fork,
+        subcircuit::Subcircuit,
+        wired::{OpenCollector, PullResistor},
+    };
+
+    #[test]
+    fn folding_and_display() {
+        let names = ["A", "B", "C"].map(String::from);
+        let (a, b, c) = (Expr::Var(0), Expr::Var(1), Expr::Var(2));
+
+        assert_eq!(Expr::and([a.clone(), Expr::Const(true)]), a);
+        assert_eq!(Expr::or([a.clone(), Expr::Const(true)]), Expr::Const(true));
+        assert_eq!(Expr::xor([a.clone(), Expr::Const(true)]), !a.clone());
+        assert_eq!(!!a.clone(), a);
+
+        let expr = Expr::or([
+            Expr::and([a.clone(), !b.clone()]),
+            !Expr::or([b.clone(), c.clone()]),
+            Expr::xor([a.clone(), Expr::and([b.clone(), c.clone()])]),
+        ]);
+        assert_eq!(
+            expr.display(&names).to_string(),
+            "A & !B | !(B | C) | A ^ B & C"
+        );
+        assert!(expr.eval(&[true, false, true]));
+        assert!(!expr.eval(&[false, true, false]));
+        assert_eq!(expr.gate_count(), 8);
+        // The shared `!A` is counted once.
+        let shared = Expr::or([Expr::and([!a.clone(), b.clone()]), Expr::and([!a, c])]);
+        assert_eq!(shared.gate_count(), 4);
+    }
+
+    #[test]
+    fn extraction_through_forks_and_subcircuits() {
+        // Inner: y = !(p & q)
+        let mut inner = Graph::new();
+        let p = inner.add_comp(Constant::default());
+        let q = inner.add_comp(Constant::default());
+        let and = inner.add_comp(And);
+        let not = inner.add_comp(Not);
+        let y = inner.add_comp(DebugOutput::default());
+        inner.add_conn(p, 0, and, 0);
+        inner.add_conn(q, 0, and, 1);
+        inner.add_conn(and, 0, not, 0);
+        inner.add_conn(not, 0, y, 0);
+
+        // Outer: out = nand(a, b) | a, second output from a ROM of a, b.
+        let mut graph = Graph::new();
+        let a = graph.add_comp(Constant::default());
+        let b = graph.add_comp(Constant::default());
+        let fork = graph.add_comp(Fork::new(1, 3));
+        let nand = graph.add_comp(Subcircuit::new("nand", inner, vec![p, q], vec![y]));
+        let or = graph.add_comp(Or);
+        let rom = graph.add_comp(Rom::new(2, 1, vec![0, 1, 1, 0]));
+        let out = graph.add_comp(DebugOutput::default());
+        let rom_out = graph.add_comp(DebugOutput::default());
+        graph.add_conn(a, 0, fork, 0);
+        graph.add_conn(fork, 0, nand, 0);
+        graph.add_conn(b, 0, nand, 1);
+        graph.add_conn(nand, 0, or, 0);
+        graph.add_conn(fork, 1, or, 1);
+        graph.add_conn(or, 0, out, 0);
+        graph.add_conn(fork, 2, rom, 0);
+        graph.add_conn(rom, 0, rom_out, 0);
+
+        let names = ["a", "b"].map(String::from);
+        let exprs = extract(&graph, &[a, b], &[out, rom_out]).unwrap();
+        assert_eq!(exprs[0].display(&names).to_string(), "!(a & b) | a");
+        // The ROM address bit 1 is unconnected, so only word 1 counts.
+        assert_eq!(exprs[1].display(&names).to_string(), "a");
+        assert_eq!(gate_count(&graph, [out.into()].into_iter()), 3);
+    }
+
+    #[test]
+    fn open_collectors_make_wired_and() {
+        let mut graph = Graph::new();
+        let [a, b] = [(); 2].map(|_| graph.add_comp(Constant::default()));
+        let [oc_a, oc_b] = [(); 2].map(|_| graph.add_comp(OpenCollector));
+        let line = graph.add_comp(Fork::new(2, 1));
+        let pull_up = graph.add_comp(PullResistor::up());
+        let [out, released] = [(); 2].map(|_| graph.add_comp(DebugOutput::default()));
+        let oc = graph.add_comp(OpenCollector);
+        graph.add_conn(a, 0, oc_a, 0);
+        graph.add_conn(b, 0, oc_b, 0);
+        graph.add_conn(oc_a, 0, line, 0);
+        graph.add_conn(oc_b, 0, line, 1);
+        graph.add_conn(line, 0, pull_up, 0);
+        graph.add_conn(pull_up, 0, out, 0);
+        graph.add_conn(oc, 0, released, 0);
+
+        let names = ["a", "b"].map(String::from);
+        let exprs = extract(&graph, &[a, b], &[out, released]).unwrap();
+        assert_eq!(exprs[0].display(&names).to_string(), "a & b");
+        // Without a pull-up a released line reads low.
+        assert_eq!(exprs[1], Expr::Const(false));
+    }
+
+    #[test]
+    fn reconvergent_fan_out_is_derived_once() {
+        // Each stage is `s | s & 0`, both paths of a stage leading back to the previous one.
+        let mut graph = Graph::new();
+        let a = graph.add_comp(Constant::default());
+        let mut signal: ComponentId = a.into();
+        for _ in 0..40 {
+            let fork = graph.add_comp(Fork::new(1, 2));
+            let and = graph.add_comp(And);
+            let or = graph.add_comp(Or);
+            graph.add_conn(signal, 0, fork, 0);
+            graph.add_conn(fork, 0, and, 0);
+            graph.add_conn(and, 0, or, 0);
+            graph.add_conn(fork, 1, or, 1);
+            signal = or.into();
+        }
+        let out = graph.add_comp(DebugOutput::default());
+        graph.add_conn(signal, 0, out, 0);
+
+        assert_eq!(extract(&graph, &[a], &[out]).unwrap(), [Expr::Var(0)]);
+    }
+}
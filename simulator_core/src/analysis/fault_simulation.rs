@@ -0,0 +1,226 @@
+//! Single stuck-at fault simulation of a set of test vectors.
+
+use crate::graph::{
+    fault::{Fault, FaultValue, Pin},
+    Graph,
+};
+
+use super::{
+    parallel::{pack, Netlist},
+    test_vectors::TestVectors,
+    truth_table::{check_combinational, describe},
+};
+
+/// Faults told apart from the good circuit by the vectors, and the ones which are not.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FaultReport {
+    pub detected: Vec<Fault>,
+    pub undetected: Vec<Fault>,
+}
+
+impl FaultReport {
+    /// Share of the faults detected, `1` without any faults.
+    pub fn coverage(&self) -> f64 {
+        let total = self.detected.len() + self.undetected.len();
+        if total == 0 {
+            1.0
+        } else {
+            self.detected.len() as f64 / total as f64
+        }
+    }
+}
+
+/// Stuck-at-0 and stuck-at-1 faults on every connected pin of the graph,
+/// subcircuits count as a single node.
+pub fn stuck_at_faults(graph: &Graph) -> Vec<Fault> {
+    let mut faults = Vec::new();
+    for (node, data) in &graph.nodes {
+        let inputs = data.input_slots.iter().enumerate();
+        let inputs = inputs.filter(|(_, slot)| slot.is_some()).map(|(i, _)| Pin::Input(i));
+        let outputs = data.output_slots.iter().enumerate();
+        let outputs = outputs.filter(|(_, slot)| slot.is_some()).map(|(o, _)| Pin::Output(o));
+        for pin in outputs.chain(inputs) {
+            for value in [false, true] {
+                faults.push(Fault {
+                    node,
+                    pin,
+                    value: FaultValue::StuckAt(value),
+                });
+            }
+        }
+    }
+    faults
+}
+
+/// Runs the vectors for each fault, a fault is detected when an output differs
+/// from the good circuit after any vector.
+/// Faults already injected in the graph are left out of the good circuit.
+///
+/// Combinational outputs are evaluated 64 vectors at a time, otherwise the
+/// vectors run in order on a copy of the graph.
+pub fn fault_simulation(graph: &Graph, vectors: &TestVectors, faults: &[Fault]) -> FaultReport {
+    let outputs = vectors.outputs.iter().map(|&id| id.into());
+    if check_combinational(graph, outputs).is_ok() {
+        return parallel_fault_simulation(graph, vectors, faults);
+    }
+
+    let mut good = graph.clone();
+    good.clear_faults();
+    let expected = vectors.run(&mut good.clone());
+
+    let mut report = FaultReport::default();
+    for &fault in faults {
+        let mut faulty = good.clone();
+        faulty.inject_fault(fault);
+        if vectors.run(&mut faulty) != expected {
+            report.detected.push(fault);
+        } else {
+            report.undetected.push(fault);
+        }
+    }
+    report
+}
+
+fn parallel_fault_simulation(
+    graph: &Graph,
+    vectors: &TestVectors,
+    faults: &[Fault],
+) -> FaultReport {
+    let netlist = Netlist::build(graph, &vectors.inputs, &vectors.outputs);
+    let packed = pack(&vectors.vectors, vectors.inputs.len());
+    let good = packed
+        .iter()
+        .map(|words| netlist.eval(words, None))
+        .collect::<Vec<_>>();
+
+    let mut report = FaultReport::default();
+    for &fault in faults {
+        // Faults outside the cone of the outputs cannot change them.
+        let detected = netlist.site(graph, &fault).is_some_and(|site| {
+            let count = vectors.vectors.len();
+            netlist.detections(&packed, &good, site, count).any()
+        });
+        if detected {
+            report.detected.push(fault);
+        } else {
+            report.undetected.push(fault);
+        }
+    }
+    report
+}
+
+/// Fault like "And `x` input 1 stuck at 0".
+pub fn describe_fault(graph: &Graph, fault: &Fault) -> String {
+    let pin = match fault.pin {
+        Pin::Input(slot) => format!("input {slot}"),
+        Pin::Output(slot) => format!("output {slot}"),
+    };
+    let value = match fault.value {
+        FaultValue::StuckAt(value) => format!("stuck at {}", value as u8),
+        FaultValue::Floating => "floating".to_string(),
+    };
+    format!("{} {pin} {value}", describe(graph, fault.node))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::{
+            synthesis::{parse_equations, synthesize, GateStyle},
+            test_vectors::parse_vectors,
+        },
+        components::{
+            gates::Not,
+            simple::{Constant, DebugOutput},
+        },
+        graph::id::TypedId,
+    };
+
+    #[test]
+    fn injected_faults() {
+        let mut graph = Graph::new();
+        let a = graph.add_comp(Constant::default());
+        let not = graph.add_comp(Not);
+        let q = graph.add_comp(DebugOutput::default());
+        graph.add_conn(a, 0, not, 0);
+        graph.add_conn(not, 0, q, 0);
+        graph.propagate_all();
+        assert!(graph[q].state);
+
+        let fault = |pin, value| Fault {
+            node: not.into(),
+            pin,
+            value,
+        };
+        graph.inject_fault(fault(Pin::Output(0), FaultValue::StuckAt(false)));
+        assert!(!graph[q].state);
+        graph.inject_fault(fault(Pin::Input(0), FaultValue::StuckAt(true)));
+        graph.clear_fault(not, Pin::Output(0));
+        assert!(!graph[q].state);
+        assert_eq!(
+            describe_fault(&graph, &graph.faults().next().unwrap()),
+            "Not input 0 stuck at 1"
+        );
+
+        graph.clear_faults();
+        assert!(graph[q].state);
+        graph[a].state = true;
+        graph.propagate_from(a);
+        assert!(!graph[q].state);
+        graph.inject_fault(fault(Pin::Input(0), FaultValue::Floating));
+        assert!(graph[q].state);
+        graph.clear_fault(not, Pin::Input(0));
+        assert!(!graph[q].state);
+        assert_eq!(graph.faults().count(), 0);
+    }
+
+    fn vectors(graph: &Graph, inputs: &[&str], outputs: &[&str], source: &str) -> TestVectors {
+        let id = |name: &&str| graph.find_by_name(name).unwrap();
+        TestVectors {
+            inputs: inputs.iter().map(|name| TypedId::from(id(name))).collect(),
+            outputs: outputs.iter().map(|name| TypedId::from(id(name))).collect(),
+            vectors: parse_vectors(source, inputs.len()).unwrap(),
+        }
+    }
+
+    #[test]
+    fn coverage() {
+        let equations = parse_equations("S = A ^ B ^ C; K = A & B | C & (A ^ B)").unwrap();
+        let graph = synthesize(&equations, GateStyle::Mixed);
+        let faults = stuck_at_faults(&graph);
+
+        let all = "000\n001\n010\n011\n100\n101\n110\n111";
+        let exhaustive = vectors(&graph, &["A", "B", "C"], &["S", "K"], all);
+        let report = fault_simulation(&graph, &exhaustive, &faults);
+        assert_eq!(report.detected.len(), faults.len());
+        assert_eq!(report.coverage(), 1.0);
+
+        let few = vectors(&graph, &["A", "B", "C"], &["S", "K"], "000 # all low\n1 1 1");
+        let report = fault_simulation(&graph, &few, &faults);
+        assert!(!report.undetected.is_empty());
+        assert!(report.coverage() < 1.0);
+
+        // `a & b | a & !b` is just `a`, faults on `b` change nothing.
+        let equations = parse_equations("Q = A & B | A & !B").unwrap();
+        let mut graph = synthesize(&equations, GateStyle::Mixed);
+        let redundant = vectors(&graph, &["A", "B"], &["Q"], "00\n01\n10\n11");
+        let b = graph.find_by_name("B").unwrap();
+        let stuck_b = Fault {
+            node: b,
+            pin: Pin::Output(0),
+            value: FaultValue::StuckAt(true),
+        };
+        graph.inject_fault(stuck_b);
+        let report = fault_simulation(&graph, &redundant, &[stuck_b]);
+        assert_eq!(report.undetected, [stuck_b]);
+
+        assert_eq!(
+            parse_vectors("01\n1x", 2),
+            Err(crate::analysis::test_vectors::VectorError {
+                line: 2,
+                message: "`x` is not a value".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Nets changing more than once while a single input change settles.
+//!
+//! The propagation queue evaluates a node again when its inputs change after it
+//! was evaluated, so a net fed by paths of different lengths can pulse before it
+//! settles. Such pulses are the static and dynamic hazards real gates would show.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    components::simple::Constant,
+    graph::{
+        id::{ComponentId, TypedId},
+        Graph,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HazardKind {
+    /// The net pulses and settles back to its old value.
+    Static,
+    /// The net changes more than once on the way to its new value.
+    Dynamic,
+}
+
+/// Output slot of a node which changed more than once in a settle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Glitch {
+    pub node: ComponentId,
+    pub slot: usize,
+    pub transitions: usize,
+}
+
+impl Glitch {
+    pub fn kind(&self) -> HazardKind {
+        if self.transitions % 2 == 0 {
+            HazardKind::Static
+        } else {
+            HazardKind::Dynamic
+        }
+    }
+}
+
+/// Glitches caused by setting an input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HazardReport {
+    pub input: TypedId<Constant>,
+    /// New state of the input.
+    pub state: bool,
+    pub glitches: Vec<Glitch>,
+}
+
+/// Propagates from `node` like [`Graph::propagate_from`], returning the output slots
+/// which changed more than once.
+pub fn settle_counting_transitions(graph: &mut Graph, node: ComponentId) -> Vec<Glitch> {
+    let mut transitions = BTreeMap::<(ComponentId, usize), usize>::new();
+    for step in graph.steps_from(node) {
+        for slot in step.changed {
+            *transitions.entry((step.node, slot)).or_default() += 1;
+        }
+    }
+    transitions
+        .into_iter()
+        .filter(|&(_, count)| count > 1)
+        .map(|((node, slot), transitions)| Glitch {
+            node,
+            slot,
+            transitions,
+        })
+        .collect()
+}
+
+/// Toggles each input and back on a copy of the graph, reporting the changes
+/// which make some net glitch.
+pub fn find_hazards(graph: &Graph, inputs: &[TypedId<Constant>]) -> Vec<HazardReport> {
+    let mut graph = graph.clone();
+    let mut reports = Vec::new();
+    for &input in inputs {
+        for _ in 0..2 {
+            let state = !graph[input].state;
+            graph[input].state = state;
+            let glitches = settle_counting_transitions(&mut graph, input.into());
+            if !glitches.is_empty() {
+                reports.push(HazardReport {
+                    input,
+                    state,
+                    glitches,
+                });
+            }
+        }
+    }
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{
+        gates::{And, Not, Or},
+        simple::{DebugOutput, Fork},
+    };
+
+    /// `a & !a` and `a | !a` with the inverted path three gates long.
+    #[test]
+    fn finds_static_hazards() {
+        let mut graph = Graph::new();
+        let a = graph.add_comp(Constant::default());
+        let fork = graph.add_comp(Fork::new(1, 3));
+        let [not_1, not_2, not_3] = [(); 3].map(|_| graph.add_comp(Not));
+        let not_fork = graph.add_comp(Fork::new(1, 2));
+        let and = graph.add_comp(And);
+        let or = graph.add_comp(Or);
+        let [and_out, or_out] = [(); 2].map(|_| graph.add_comp(DebugOutput::default()));
+        graph.add_conn(a, 0, fork, 0);
+        graph.add_conn(fork, 0, not_1, 0);
+        graph.add_conn(not_1, 0, not_2, 0);
+        graph.add_conn(not_2, 0, not_3, 0);
+        graph.add_conn(not_3, 0, not_fork, 0);
+        graph.add_conn(fork, 1, and, 0);
+        graph.add_conn(not_fork, 0, and, 1);
+        graph.add_conn(fork, 2, or, 0);
+        graph.add_conn(not_fork, 1, or, 1);
+        graph.add_conn(and, 0, and_out, 0);
+        graph.add_conn(or, 0, or_out, 0);
+        graph.propagate_all();
+
+        let reports = find_hazards(&graph, &[a]);
+        let summary = reports
+            .iter()
+            .map(|report| {
+                let nodes = report.glitches.iter().map(|glitch| glitch.node);
+                (report.state, nodes.collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+        // The and gate pulses on a rising edge, the or gate on a falling one.
+        assert_eq!(
+            summary,
+            [(true, vec![and.into()]), (false, vec![or.into()])]
+        );
+        let glitch = reports[0].glitches[0];
+        assert_eq!((glitch.slot, glitch.transitions), (0, 2));
+        assert_eq!(glitch.kind(), HazardKind::Static);
+        assert!(!graph[a].state);
+    }
+}
@@ -0,0 +1,272 @@
+//! Two level minimization with the Quine–McCluskey method.
+
+use std::collections::HashSet;
+
+use crate::{
+    components::simple::{Constant, DebugOutput},
+    graph::{id::TypedId, Graph},
+};
+
+use super::{
+    expression::{extract, gate_count, Expr},
+    truth_table::{truth_table, TruthTable, TruthTableError},
+};
+
+/// Product term over `n` variables, numbered like the rows of a [`TruthTable`]:
+/// variable `0` is the most significant bit.
+///
+/// Bits set in `mask` are variables left out of the product,
+/// the other bits of `value` tell whether the variable appears plain or negated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Implicant {
+    pub value: u32,
+    pub mask: u32,
+}
+
+impl Implicant {
+    pub fn minterm(value: u32) -> Self {
+        Self { value, mask: 0 }
+    }
+
+    pub fn covers(&self, minterm: u32) -> bool {
+        minterm & !self.mask == self.value
+    }
+
+    pub fn literals(&self, vars: usize) -> usize {
+        vars - self.mask.count_ones() as usize
+    }
+
+    pub fn to_expr(&self, vars: usize) -> Expr {
+        Expr::and((0..vars).filter_map(|var| {
+            let bit = 1 << (vars - 1 - var);
+            match (self.mask & bit != 0, self.value & bit != 0) {
+                (true, _) => None,
+                (false, true) => Some(Expr::Var(var)),
+                (false, false) => Some(!Expr::Var(var)),
+            }
+        }))
+    }
+}
+
+/// All prime implicants of the function over `vars` variables
+/// which is `1` on `ones` and may be anything on `dont_cares`.
+pub fn prime_implicants(vars: usize, ones: &[u32], dont_cares: &[u32]) -> Vec<Implicant> {
+    let mut current = ones
+        .iter()
+        .chain(dont_cares)
+        .map(|&m| Implicant::minterm(m))
+        .collect::<HashSet<_>>();
+    let mut primes = Vec::new();
+
+    while !current.is_empty() {
+        let mut merged = HashSet::new();
+        let mut used = HashSet::new();
+        for implicant in &current {
+            for var in 0..vars {
+                let bit = 1 << var;
+                if implicant.mask & bit != 0 || implicant.value & bit != 0 {
+                    continue;
+                }
+                let pair = Implicant {
+                    value: implicant.value | bit,
+                    mask: implicant.mask,
+                };
+                if current.contains(&pair) {
+                    merged.insert(Implicant {
+                        value: implicant.value,
+                        mask: implicant.mask | bit,
+                    });
+                    used.insert(*implicant);
+                    used.insert(pair);
+                }
+            }
+        }
+        primes.extend(current.difference(&used));
+        current = merged;
+    }
+
+    primes.sort();
+    primes
+}
+
+/// Cheapest set of `primes` covering all `ones`,
+/// with as few implicants as possible and then as few literals as possible.
+pub fn minimal_cover(vars: usize, primes: &[Implicant], ones: &[u32]) -> Vec<Implicant> {
+    struct Search<'a> {
+        vars: usize,
+        primes: &'a [Implicant],
+        best: Option<(usize, usize, Vec<Implicant>)>,
+    }
+
+    impl Search<'_> {
+        fn run(&mut self, uncovered: &[u32], chosen: &mut Vec<Implicant>, literals: usize) {
+            if let Some((count, best_literals, _)) = &self.best {
+                if (chosen.len(), literals) >= (*count, *best_literals) {
+                    return;
+                }
+            }
+            // Branch on the minterm with the fewest candidates, essential primes come first.
+            let candidates = |&m: &u32| self.primes.iter().filter(move |p| p.covers(m));
+            let Some(minterm) = uncovered.iter().min_by_key(|m| candidates(m).count()) else {
+                self.best = Some((chosen.len(), literals, chosen.clone()));
+                return;
+            };
+            for &prime in candidates(minterm).collect::<Vec<_>>() {
+                let rest = uncovered
+                    .iter()
+                    .copied()
+                    .filter(|&m| !prime.covers(m))
+                    .collect::<Vec<_>>();
+                chosen.push(prime);
+                self.run(&rest, chosen, literals + prime.literals(self.vars));
+                chosen.pop();
+            }
+        }
+    }
+
+    let mut search = Search {
+        vars,
+        primes,
+        best: None,
+    };
+    search.run(ones, &mut Vec::new(), 0);
+    let mut cover = search.best.map(|(_, _, cover)| cover).unwrap_or_default();
+    cover.sort();
+    cover
+}
+
+/// Sum of the given products.
+pub fn sum_of_products(vars: usize, implicants: &[Implicant]) -> Expr {
+    Expr::or(implicants.iter().map(|implicant| implicant.to_expr(vars)))
+}
+
+/// Minimal sum of products of output `output` of the table.
+pub fn minimize(table: &TruthTable, output: usize) -> Vec<Implicant> {
+    let vars = table.inputs.len();
+    let ones = (0..table.rows.len() as u32)
+        .filter(|&row| table.rows[row as usize][output])
+        .collect::<Vec<_>>();
+    minimal_cover(vars, &prime_implicants(vars, &ones, &[]), &ones)
+}
+
+/// An output as built in the graph next to its minimal sum of products.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputReport {
+    pub name: String,
+    pub expression: Expr,
+    pub minimized: Expr,
+    /// Gates driving the output in the graph.
+    pub circuit_gates: usize,
+    /// Two input gates and inverters needed by the minimized form.
+    pub minimized_gates: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinimizationReport {
+    /// Variable names used by the expressions.
+    pub inputs: Vec<String>,
+    pub outputs: Vec<OutputReport>,
+}
+
+/// Extracts the expression of every output and minimizes it.
+pub fn minimize_outputs(
+    graph: &mut Graph,
+    inputs: &[TypedId<Constant>],
+    outputs: &[TypedId<DebugOutput>],
+) -> Result<MinimizationReport, TruthTableError> {
+    let expressions = extract(graph, inputs, outputs)?;
+    let table = truth_table(graph, inputs, outputs)?;
+    let vars = table.inputs.len();
+
+    let reports = expressions.into_iter().enumerate().map(|(o, expression)| {
+        let minimized = sum_of_products(vars, &minimize(&table, o));
+        OutputReport {
+            name: table.outputs[o].clone(),
+            circuit_gates: gate_count(graph, [outputs[o].into()].into_iter()),
+            minimized_gates: minimized.gate_count(),
+            expression,
+            minimized,
+        }
+    });
+    Ok(MinimizationReport {
+        outputs: reports.collect(),
+        inputs: table.inputs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{
+        gates::{And, Not, Or},
+        simple::Fork,
+    };
+
+    #[test]
+    fn quine_mccluskey() {
+        // f(a, b, c, d) = sum m(4, 8, 10, 11, 12, 15) + d(9, 14)
+        let ones = [4, 8, 10, 11, 12, 15];
+        let primes = prime_implicants(4, &ones, &[9, 14]);
+        let names = ["a", "b", "c", "d"].map(String::from);
+        let shown = |implicants: &[Implicant]| {
+            implicants
+                .iter()
+                .map(|i| i.to_expr(4).display(&names).to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(shown(&primes), ["b & !c & !d", "a & !b", "a & !d", "a & c"]);
+
+        let cover = minimal_cover(4, &primes, &ones);
+        assert_eq!(shown(&cover), ["b & !c & !d", "a & !b", "a & c"]);
+        assert_eq!(
+            sum_of_products(4, &cover).display(&names).to_string(),
+            "b & !c & !d | a & !b | a & c"
+        );
+
+        assert_eq!(minimal_cover(2, &prime_implicants(2, &[], &[]), &[]), []);
+        assert_eq!(sum_of_products(2, &[]), Expr::Const(false));
+        let all = prime_implicants(2, &[0, 1, 2, 3], &[]);
+        assert_eq!(
+            sum_of_products(2, &minimal_cover(2, &all, &[0, 1, 2, 3])),
+            Expr::Const(true)
+        );
+    }
+
+    #[test]
+    fn redundant_circuit() {
+        // q = a & b | a & !b, which is just a.
+        let mut graph = Graph::new();
+        let a = graph.add_comp(Constant::default());
+        let b = graph.add_comp(Constant::default());
+        let fork_a = graph.add_comp(Fork::new(1, 2));
+        let fork_b = graph.add_comp(Fork::new(1, 2));
+        let not = graph.add_comp(Not);
+        let and_1 = graph.add_comp(And);
+        let and_2 = graph.add_comp(And);
+        let or = graph.add_comp(Or);
+        let q = graph.add_comp(DebugOutput::default());
+        graph.add_conn(a, 0, fork_a, 0);
+        graph.add_conn(b, 0, fork_b, 0);
+        graph.add_conn(fork_a, 0, and_1, 0);
+        graph.add_conn(fork_b, 0, and_1, 1);
+        graph.add_conn(fork_a, 1, and_2, 0);
+        graph.add_conn(fork_b, 1, not, 0);
+        graph.add_conn(not, 0, and_2, 1);
+        graph.add_conn(and_1, 0, or, 0);
+        graph.add_conn(and_2, 0, or, 1);
+        graph.add_conn(or, 0, q, 0);
+        graph.set_name(a, "a");
+        graph.set_name(b, "b");
+        graph.set_name(q, "q");
+
+        let report = minimize_outputs(&mut graph, &[a, b], &[q]).unwrap();
+        let output = &report.outputs[0];
+        assert_eq!(output.name, "q");
+        assert_eq!(
+            output.expression.display(&report.inputs).to_string(),
+            "a & b | a & !b"
+        );
+        assert_eq!(output.minimized.display(&report.inputs).to_string(), "a");
+        assert_eq!((output.circuit_gates, output.minimized_gates), (4, 0));
+    }
+}
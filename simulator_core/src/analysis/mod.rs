@@ -0,0 +1,14 @@
+
+pub mod atpg;
+pub mod bmc;
+pub mod equivalence;
+pub mod expression;
+pub mod fault_simulation;
+pub mod hazards;
+pub mod minimize;
+pub mod parallel;
+pub mod races;
+pub mod sat;
+pub mod synthesis;
+pub mod test_vectors;
+pub mod truth_table;
\ No newline at end of file
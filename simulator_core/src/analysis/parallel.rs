@@ -0,0 +1,583 @@
+//! Bit-parallel evaluation of combinational circuits.
+//!
+//! The cone of the outputs is compiled into simple gates in signal order, and
+//! every net holds a `u64` word with one bit for each of 64 independent input
+//! vectors, so a gate is evaluated for all of them with a single word operation.
+
+use std::collections::HashMap;
+
+use bitvec::prelude::*;
+use slotmap::SecondaryMap;
+
+use crate::{
+    components::{
+        simple::{Constant, DebugOutput},
+        Component, ComponentBehaviour,
+    },
+    graph::{
+        fault::{Fault, Pin},
+        id::{ComponentId, TypedId},
+        Graph,
+    },
+};
+
+use super::truth_table::{check_combinational, TruthTableError};
+
+/// Vectors evaluated by one pass over the gates.
+pub const LANES: usize = u64::BITS as usize;
+
+/// Combinational cone of some outputs compiled for bit-parallel evaluation.
+pub struct ParallelSimulator {
+    netlist: Netlist,
+}
+
+impl ParallelSimulator {
+    /// Compiles the cone of `outputs`, the `inputs` become the vector values
+    /// and other `Constant`s keep their current state.
+    ///
+    /// Fails when the outputs are not a function of the inputs alone.
+    pub fn new(
+        graph: &Graph,
+        inputs: &[TypedId<Constant>],
+        outputs: &[TypedId<DebugOutput>],
+    ) -> Result<Self, TruthTableError> {
+        check_combinational(graph, outputs.iter().map(|&id| id.into()))?;
+        Ok(ParallelSimulator {
+            netlist: Netlist::build(graph, inputs, outputs),
+        })
+    }
+
+    /// Number of gates the cone was compiled into.
+    pub fn gate_count(&self) -> usize {
+        self.netlist.nets.len()
+    }
+
+    /// Evaluates 64 vectors at once: bit `k` of `inputs[i]` is input `i` of vector `k`,
+    /// and bit `k` of each returned word is the matching output of vector `k`.
+    pub fn eval(&self, inputs: &[u64]) -> Vec<u64> {
+        self.netlist.eval(inputs, None)
+    }
+
+    /// Outputs for each vector, evaluated 64 at a time.
+    pub fn run(&self, vectors: &[BitVec]) -> Vec<BitVec> {
+        let mut outputs = Vec::with_capacity(vectors.len());
+        let packed = pack(vectors, self.netlist.inputs);
+        for (chunk, words) in vectors.chunks(LANES).zip(packed) {
+            let words = self.eval(&words);
+            outputs.extend((0..chunk.len()).map(|lane| unpack(&words, lane)));
+        }
+        outputs
+    }
+}
+
+/// Input words of the vectors, 64 vectors per entry.
+pub(super) fn pack(vectors: &[BitVec], inputs: usize) -> Vec<Vec<u64>> {
+    vectors
+        .chunks(LANES)
+        .map(|chunk| {
+            let mut words = vec![0; inputs];
+            for (lane, vector) in chunk.iter().enumerate() {
+                for (word, bit) in words.iter_mut().zip(vector.iter().by_vals()) {
+                    *word |= (bit as u64) << lane;
+                }
+            }
+            words
+        })
+        .collect()
+}
+
+/// Values of one vector out of evaluated words.
+pub(super) fn unpack(words: &[u64], lane: usize) -> BitVec {
+    words.iter().map(|word| word >> lane & 1 == 1).collect()
+}
+
+/// A net forced to a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(super) struct NetFault {
+    pub(super) net: usize,
+    pub(super) stuck: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Gate {
+    Input(usize),
+    Const(bool),
+    And,
+    Or,
+    Xor,
+    Not,
+    Buf,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Net {
+    pub(super) gate: Gate,
+    pub(super) inputs: Vec<usize>,
+}
+
+/// Gates in signal order, every net driven by one gate.
+pub(super) struct Netlist {
+    pub(super) nets: Vec<Net>,
+    pub(super) inputs: usize,
+    pub(super) outputs: Vec<usize>,
+    /// Net of each output slot of the top graph in the cone of the outputs.
+    pub(super) pins: HashMap<(ComponentId, usize), usize>,
+}
+
+impl Netlist {
+    /// Flattens the cone of `outputs`, which must be combinational.
+    pub(super) fn build(
+        graph: &Graph,
+        inputs: &[TypedId<Constant>],
+        outputs: &[TypedId<DebugOutput>],
+    ) -> Self {
+        let mut builder = Builder {
+            nets: Vec::new(),
+            memo: HashMap::new(),
+            released: HashMap::new(),
+            instances: HashMap::new(),
+            pins: HashMap::new(),
+        };
+        let mut ports = SecondaryMap::new();
+        for (i, &id) in inputs.iter().enumerate() {
+            let net = builder.push(Gate::Input(i), Vec::new());
+            ports.insert(id.into(), net);
+        }
+        let outputs = outputs
+            .iter()
+            .map(|&id| builder.input(graph, 0, &ports, id.into(), 0))
+            .collect();
+        Netlist {
+            nets: builder.nets,
+            inputs: inputs.len(),
+            outputs,
+            pins: builder.pins,
+        }
+    }
+
+    /// Net fault equivalent to a fault of the graph, `None` outside the cone.
+    ///
+    /// Faults on an input slot are the same as on the output slot driving it,
+    /// a floating slot is stuck at the level its reader floats to.
+    pub(super) fn site(&self, graph: &Graph, fault: &Fault) -> Option<NetFault> {
+        let (node, slot) = match fault.pin {
+            Pin::Output(slot) => (fault.node, slot),
+            Pin::Input(slot) => {
+                let source = graph.nodes[fault.node].input_slots[slot].as_ref()?;
+                (source.target_node, source.target_slot)
+            }
+        };
+        let stuck = match fault.value.value() {
+            Some(value) => value,
+            None => {
+                let reader = graph.nodes[node].output_slots[slot].as_ref()?;
+                graph[reader.target_node].floating_input(reader.target_slot)
+            }
+        };
+        self.pins
+            .get(&(node, slot))
+            .map(|&net| NetFault { net, stuck })
+    }
+
+    /// Output words for 64 vectors of input words, with the fault injected.
+    pub(super) fn eval(&self, inputs: &[u64], fault: Option<NetFault>) -> Vec<u64> {
+        let mut values: Vec<u64> = Vec::with_capacity(self.nets.len());
+        for (index, net) in self.nets.iter().enumerate() {
+            let mut operands = net.inputs.iter().map(|&i| values[i]);
+            let value = match net.gate {
+                Gate::Input(input) => inputs[input],
+                Gate::Const(value) => 0u64.wrapping_sub(value as u64),
+                Gate::And => operands.fold(!0, |a, b| a & b),
+                Gate::Or => operands.fold(0, |a, b| a | b),
+                Gate::Xor => operands.fold(0, |a, b| a ^ b),
+                Gate::Not => !operands.next().unwrap(),
+                Gate::Buf => operands.next().unwrap(),
+            };
+            values.push(match fault {
+                Some(fault) if fault.net == index => 0u64.wrapping_sub(fault.stuck as u64),
+                _ => value,
+            });
+        }
+        self.outputs.iter().map(|&net| values[net]).collect()
+    }
+
+    /// Bits of the `count` packed vectors whose outputs the fault changes,
+    /// `good` being the outputs without it.
+    pub(super) fn detections(
+        &self,
+        packed: &[Vec<u64>],
+        good: &[Vec<u64>],
+        fault: NetFault,
+        count: usize,
+    ) -> BitVec {
+        let mut detected = BitVec::with_capacity(count);
+        for (words, good) in packed.iter().zip(good) {
+            let faulty = self.eval(words, Some(fault));
+            let differs = good.iter().zip(faulty).fold(0, |acc, (g, f)| acc | (g ^ f));
+            let lanes = (count - detected.len()).min(LANES);
+            detected.extend((0..lanes).map(|lane| differs >> lane & 1 == 1));
+        }
+        detected
+    }
+}
+
+struct Builder {
+    nets: Vec<Net>,
+    /// Net of an output slot, by graph instance, node and slot.
+    memo: HashMap<(usize, ComponentId, usize), usize>,
+    /// Net telling whether an output slot is released, `None` when it never is.
+    released: HashMap<(usize, ComponentId, usize), Option<usize>>,
+    /// Instance number and input nets of each subcircuit, by its instance and node.
+    instances: HashMap<(usize, ComponentId), (usize, SecondaryMap<ComponentId, usize>)>,
+    pins: HashMap<(ComponentId, usize), usize>,
+}
+
+impl Builder {
+    fn push(&mut self, gate: Gate, inputs: Vec<usize>) -> usize {
+        self.nets.push(Net { gate, inputs });
+        self.nets.len() - 1
+    }
+
+    /// Net coming into `slot` of `node`, `ports` are the nets of the graph's inputs.
+    /// The slot reads as it floats while nothing drives it.
+    fn input(
+        &mut self,
+        graph: &Graph,
+        instance: usize,
+        ports: &SecondaryMap<ComponentId, usize>,
+        node: ComponentId,
+        slot: usize,
+    ) -> usize {
+        let floating = graph[node].floating_input(slot);
+        let Some(source) = &graph.nodes[node].input_slots[slot] else {
+            return self.push(Gate::Const(floating), Vec::new());
+        };
+        let (node, slot) = (source.target_node, source.target_slot);
+        let value = self.output(graph, instance, ports, node, slot);
+        match self.released(graph, instance, ports, node, slot) {
+            None => value,
+            Some(released) if floating => self.push(Gate::Or, vec![value, released]),
+            Some(released) => {
+                let driven = self.push(Gate::Not, vec![released]);
+                self.push(Gate::And, vec![value, driven])
+            }
+        }
+    }
+
+    /// Net telling whether `slot` of `node` is released, `None` when it never is.
+    fn released(
+        &mut self,
+        graph: &Graph,
+        instance: usize,
+        ports: &SecondaryMap<ComponentId, usize>,
+        node: ComponentId,
+        slot: usize,
+    ) -> Option<usize> {
+        if let Some(&net) = self.released.get(&(instance, node, slot)) {
+            return net;
+        }
+        let net = match &graph[node] {
+            Component::OpenCollector(_) => Some(self.input(graph, instance, ports, node, 0)),
+            Component::Transceiver(transceiver) => {
+                let width = transceiver.width as usize;
+                let dir = self.input(graph, instance, ports, node, 2 * width);
+                let output_enable = self.input(graph, instance, ports, node, 2 * width + 1);
+                let enabled = self.push(Gate::Not, vec![output_enable]);
+                let towards = match slot < width {
+                    true => self.push(Gate::Not, vec![dir]),
+                    false => dir,
+                };
+                let driving = self.push(Gate::And, vec![enabled, towards]);
+                Some(self.push(Gate::Not, vec![driving]))
+            }
+            Component::Fork(_) => {
+                let except = graph.terminal_input(node, slot);
+                let mut undriven = Vec::new();
+                for (i, source) in graph.nodes[node].input_slots.iter().enumerate() {
+                    if Some(i) == except {
+                        continue;
+                    }
+                    undriven.push(match source {
+                        Some(source) => {
+                            let (node, slot) = (source.target_node, source.target_slot);
+                            self.released(graph, instance, ports, node, slot)
+                        }
+                        None => Some(self.push(Gate::Const(true), Vec::new())),
+                    });
+                }
+                let undriven = undriven.into_iter().collect::<Option<Vec<_>>>();
+                undriven.map(|nets| self.push(Gate::And, nets))
+            }
+            _ => None,
+        };
+        self.released.insert((instance, node, slot), net);
+        net
+    }
+
+    fn output(
+        &mut self,
+        graph: &Graph,
+        instance: usize,
+        ports: &SecondaryMap<ComponentId, usize>,
+        node: ComponentId,
+        slot: usize,
+    ) -> usize {
+        if let Some(&net) = self.memo.get(&(instance, node, slot)) {
+            return net;
+        }
+        let inputs = |builder: &mut Self| {
+            (0..graph.nodes[node].input_slots.len())
+                .map(|i| builder.input(graph, instance, ports, node, i))
+                .collect::<Vec<_>>()
+        };
+
+        let driver = match &graph[node] {
+            Component::Constant(constant) => match ports.get(node) {
+                Some(&net) => net,
+                None => self.push(Gate::Const(constant.state), Vec::new()),
+            },
+            Component::And(_) => {
+                let inputs = inputs(self);
+                self.push(Gate::And, inputs)
+            }
+            Component::Or(_) => {
+                let inputs = inputs(self);
+                self.push(Gate::Or, inputs)
+            }
+            Component::Xor(_) => {
+                let inputs = inputs(self);
+                self.push(Gate::Xor, inputs)
+            }
+            Component::Not(_) => {
+                let inputs = inputs(self);
+                self.push(Gate::Not, inputs)
+            }
+            Component::PullResistor(_) => inputs(self)[0],
+            // Pulls its line low unless released, see `Builder::released`.
+            Component::OpenCollector(_) => self.push(Gate::Const(false), Vec::new()),
+            Component::Transceiver(transceiver) => {
+                let width = transceiver.width as usize;
+                let inputs = inputs(self);
+                let (dir, output_enable) = (inputs[2 * width], inputs[2 * width + 1]);
+                let enabled = self.push(Gate::Not, vec![output_enable]);
+                let (direction, data) = match slot.checked_sub(width) {
+                    Some(b) => (dir, inputs[b]),
+                    None => (self.push(Gate::Not, vec![dir]), inputs[width + slot]),
+                };
+                self.push(Gate::And, vec![enabled, direction, data])
+            }
+            Component::Fork(_) => {
+                // Every output is a branch of its own, they share the stem
+                // but for bidirectional pins, which read the line without their drive.
+                let stem = self.memo.get(&(instance, node, usize::MAX)).copied();
+                match (graph.terminal_input(node, slot), stem) {
+                    (Some(except), _) => {
+                        let inputs = (0..graph.nodes[node].input_slots.len())
+                            .filter(|&i| i != except)
+                            .map(|i| self.input(graph, instance, ports, node, i))
+                            .collect();
+                        self.push(Gate::Or, inputs)
+                    }
+                    (None, Some(stem)) => stem,
+                    (None, None) => {
+                        let inputs = inputs(self);
+                        let stem = self.push(Gate::Or, inputs);
+                        self.memo.insert((instance, node, usize::MAX), stem);
+                        stem
+                    }
+                }
+            }
+            Component::Subcircuit(sub) => {
+                let key = (instance, node);
+                if !self.instances.contains_key(&key) {
+                    let inputs = inputs(self);
+                    let ports = sub.input_ids().zip(inputs).collect();
+                    let inner = self.instances.len() + 1;
+                    self.instances.insert(key, (inner, ports));
+                }
+                let (inner, ports) = self.instances[&key].clone();
+                self.input(&sub.graph, inner, &ports, sub.outputs[slot].into(), 0)
+            }
+            Component::Rom(rom) => {
+                let address = inputs(self);
+                let negated = address
+                    .iter()
+                    .map(|&net| self.push(Gate::Not, vec![net]))
+                    .collect::<Vec<_>>();
+                let words = rom.contents.iter().enumerate();
+                let words = words.filter(|(_, &word)| word >> slot & 1 == 1);
+                let terms = words
+                    .map(|(a, _)| {
+                        let bits = (0..address.len())
+                            .map(|bit| {
+                                if a >> bit & 1 == 1 {
+                                    address[bit]
+                                } else {
+                                    negated[bit]
+                                }
+                            })
+                            .collect();
+                        self.push(Gate::And, bits)
+                    })
+                    .collect::<Vec<_>>();
+                self.push(Gate::Or, terms)
+            }
+            Component::DebugOutput(_) | Component::FlipFlop(_) | Component::Ram(_) => {
+                unreachable!("checked by check_combinational")
+            }
+        };
+
+        // Each slot gets a net of its own to be a fault site.
+        let net = self.push(Gate::Buf, vec![driver]);
+        self.memo.insert((instance, node, slot), net);
+        if instance == 0 {
+            self.pins.insert((node, slot), net);
+        }
+        net
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::{
+            expression::extract,
+            synthesis::{parse_equations, synthesize, GateStyle},
+            test_vectors::TestVectors,
+        },
+        components::{
+            simple::Fork,
+            wired::{OpenCollector, PullResistor, Transceiver},
+        },
+    };
+
+    #[test]
+    fn matches_propagation() {
+        let equations = parse_equations(
+            "S = A ^ B ^ C ^ D; K = A & B | C & (A ^ B);
+             P = !(E & F) | G & !A; Q = D ^ E & !(F | G)",
+        )
+        .unwrap();
+        let mut graph = synthesize(&equations, GateStyle::NandOnly);
+        let id = |name: &String| graph.find_by_name(name).unwrap();
+        let inputs = equations
+            .inputs
+            .iter()
+            .map(|name| id(name).into())
+            .collect::<Vec<_>>();
+        let outputs = equations
+            .outputs
+            .iter()
+            .map(|(name, _)| id(name).into())
+            .collect::<Vec<_>>();
+
+        // More than one word of vectors, the last one partly filled.
+        let vectors = (0..100u32)
+            .map(|v| {
+                let v = v.wrapping_mul(37) % (1 << inputs.len());
+                (0..inputs.len()).map(|i| v >> i & 1 == 1).collect()
+            })
+            .collect::<Vec<BitVec>>();
+        let simulator = ParallelSimulator::new(&graph, &inputs, &outputs).unwrap();
+        let parallel = simulator.run(&vectors);
+        assert_eq!(parallel.len(), vectors.len());
+
+        let vectors = TestVectors {
+            inputs,
+            outputs,
+            vectors,
+        };
+        assert_eq!(parallel, vectors.run(&mut graph));
+    }
+
+    #[test]
+    fn resolves_released_lines_like_propagation() {
+        let mut graph = Graph::new();
+        let inputs = [(); 5].map(|_| graph.add_comp(Constant::default()));
+        let [a, b, x, dir, output_enable] = inputs;
+        let [oc_a, oc_b] = [(); 2].map(|_| graph.add_comp(OpenCollector));
+        let line = graph.add_comp(Fork::new(2, 1));
+        let transceiver = graph.add_comp(Transceiver::new(1));
+        let [pull_up, bus_b] = [(); 2].map(|_| graph.add_comp(PullResistor::up()));
+        let bus_a = graph.add_comp(PullResistor::down());
+        let outputs = [(); 3].map(|_| graph.add_comp(DebugOutput::default()));
+        graph.add_conn(a, 0, oc_a, 0);
+        graph.add_conn(b, 0, oc_b, 0);
+        graph.add_conn(oc_a, 0, line, 0);
+        graph.add_conn(oc_b, 0, line, 1);
+        graph.add_conn(line, 0, pull_up, 0);
+        graph.add_conn(pull_up, 0, outputs[0], 0);
+        graph.add_conn(x, 0, transceiver, 0);
+        graph.add_conn(dir, 0, transceiver, 2);
+        graph.add_conn(output_enable, 0, transceiver, 3);
+        graph.add_conn(transceiver, 0, bus_a, 0);
+        graph.add_conn(transceiver, 1, bus_b, 0);
+        graph.add_conn(bus_a, 0, outputs[1], 0);
+        graph.add_conn(bus_b, 0, outputs[2], 0);
+        graph.propagate_all();
+
+        let vectors = (0..32u32)
+            .map(|v| (0..5).map(|i| v >> i & 1 == 1).collect())
+            .collect::<Vec<BitVec>>();
+        let simulator = ParallelSimulator::new(&graph, &inputs, &outputs).unwrap();
+        let parallel = simulator.run(&vectors);
+        let vectors = TestVectors {
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+            vectors,
+        };
+        assert_eq!(parallel, vectors.run(&mut graph));
+        // Both released, the pull-up wins.
+        assert!(parallel[0b11][0]);
+        assert!(!parallel[0b01][0]);
+    }
+
+    #[test]
+    fn bidirectional_pins_are_terminals_of_their_line() {
+        let mut graph = Graph::new();
+        let inputs = [(); 4].map(|_| graph.add_comp(Constant::default()));
+        let [a, b, dir, output_enable] = inputs;
+        let [oc_a, oc_b] = [(); 2].map(|_| graph.add_comp(OpenCollector));
+        let transceiver = graph.add_comp(Transceiver::new(1));
+        // Each line joins an open collector and a pin of the transceiver, which reads it back.
+        let [bus_a, bus_b] = [(); 2].map(|_| graph.add_comp(Fork::new(2, 2)));
+        let [pull_a, pull_b] = [(); 2].map(|_| graph.add_comp(PullResistor::up()));
+        let outputs = [(); 2].map(|_| graph.add_comp(DebugOutput::default()));
+        let sides = [(a, oc_a, bus_a, pull_a), (b, oc_b, bus_b, pull_b)];
+        for (slot, (input, oc, bus, pull)) in sides.into_iter().enumerate() {
+            graph.add_conn(input, 0, oc, 0);
+            graph.add_conn(oc, 0, bus, 0);
+            graph.add_conn(transceiver, slot, bus, 1);
+            graph.add_conn(bus, 0, transceiver, slot);
+            graph.add_conn(bus, 1, pull, 0);
+            graph.add_conn(pull, 0, outputs[slot], 0);
+        }
+        graph.add_conn(dir, 0, transceiver, 2);
+        graph.add_conn(output_enable, 0, transceiver, 3);
+        graph.propagate_all();
+
+        let vectors = (0..16u32)
+            .map(|v| (0..4).map(|i| v >> i & 1 == 1).collect())
+            .collect::<Vec<BitVec>>();
+        let simulator = ParallelSimulator::new(&graph, &inputs, &outputs).unwrap();
+        let parallel = simulator.run(&vectors);
+        let expressions = extract(&graph, &inputs, &outputs).unwrap();
+        for (vector, outputs) in vectors.iter().zip(&parallel) {
+            let vars = vector.iter().by_vals().collect::<Vec<_>>();
+            let derived = expressions.iter().map(|expr| expr.eval(&vars));
+            assert_eq!(derived.collect::<BitVec>(), *outputs);
+        }
+        let vectors = TestVectors {
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+            vectors,
+        };
+        assert_eq!(parallel, vectors.run(&mut graph));
+        // Driving `B`, the low `A` side pulls the high `B` side low.
+        assert_eq!(parallel[0b0110], bits![0, 0]);
+        // Driving `A` the other way round.
+        assert_eq!(parallel[0b0001], bits![0, 0]);
+        assert_eq!(parallel[0b1011], bits![1, 1]);
+    }
+}
@@ -0,0 +1,116 @@
+//! Outputs whose settled value depends on the order nodes are evaluated in.
+//!
+//! Propagation has no gate delays, so when changes race around a loop the value
+//! a circuit settles to is decided by the order of the queue. Settling the same
+//! change in shuffled orders and comparing the outputs finds such races.
+
+use crate::{
+    components::{
+        simple::{Constant, DebugOutput},
+        Component,
+    },
+    graph::{id::TypedId, Graph},
+};
+
+/// Output taking both values over the orders tried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Race {
+    pub output: TypedId<DebugOutput>,
+    /// Orders it settled to one in, out of the orders tried.
+    pub ones: usize,
+    pub orders: usize,
+}
+
+/// Toggles `input` on copies of the graph and settles the change in queue order
+/// and in `orders` shuffled ones, returning the outputs of the top graph which
+/// did not always settle to the same value.
+pub fn find_races(graph: &Graph, input: TypedId<Constant>, orders: usize, seed: u64) -> Vec<Race> {
+    let outputs = graph
+        .nodes
+        .iter()
+        .filter(|(_, node)| matches!(node.component, Component::DebugOutput(_)))
+        .map(|(id, _)| TypedId::<DebugOutput>::from(id))
+        .collect::<Vec<_>>();
+
+    let mut ones = vec![0; outputs.len()];
+    for order in 0..=orders {
+        let mut graph = graph.clone();
+        graph[input].state = !graph[input].state;
+        let propagation = graph.start_propagation(input);
+        let mut propagation = match order {
+            0 => propagation,
+            _ => propagation.shuffled(seed.wrapping_add(order as u64)),
+        };
+        propagation.finish(&mut graph);
+        for (ones, &output) in ones.iter_mut().zip(&outputs) {
+            *ones += graph[output].state as usize;
+        }
+    }
+
+    outputs
+        .into_iter()
+        .zip(ones)
+        .filter(|&(_, ones)| ones != 0 && ones != orders + 1)
+        .map(|(output, ones)| Race {
+            output,
+            ones,
+            orders: orders + 1,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{
+        gates::{And, Not, Or},
+        simple::Fork,
+    };
+
+    /// Latch of two nor gates, with `release` driving both set and reset.
+    fn sr_latch(graph: &mut Graph) -> TypedId<Constant> {
+        let release = graph.add_comp(Constant { state: true });
+        let fork = graph.add_comp(Fork::new(1, 2));
+        let [or_q, or_nq] = [(); 2].map(|_| graph.add_comp(Or));
+        let [not_q, not_nq] = [(); 2].map(|_| graph.add_comp(Not));
+        let [fork_q, fork_nq] = [(); 2].map(|_| graph.add_comp(Fork::new(1, 2)));
+        let [q, nq] = [(); 2].map(|_| graph.add_comp(DebugOutput::default()));
+        graph.add_conn(release, 0, fork, 0);
+        graph.add_conn(fork, 0, or_q, 0);
+        graph.add_conn(fork, 1, or_nq, 0);
+        graph.add_conn(or_q, 0, not_q, 0);
+        graph.add_conn(or_nq, 0, not_nq, 0);
+        graph.add_conn(not_q, 0, fork_q, 0);
+        graph.add_conn(not_nq, 0, fork_nq, 0);
+        graph.add_conn(fork_q, 0, q, 0);
+        graph.add_conn(fork_nq, 0, nq, 0);
+        graph.add_conn(fork_q, 1, or_nq, 1);
+        graph.add_conn(fork_nq, 1, or_q, 1);
+        release
+    }
+
+    #[test]
+    fn flags_released_latch() {
+        let mut graph = Graph::new();
+        let release = sr_latch(&mut graph);
+        graph.propagate_all();
+        let races = find_races(&graph, release, 16, 1);
+        assert_eq!(races.len(), 2);
+        assert!(races.iter().all(|race| race.orders == 17));
+        // Queue order oscillates until the depth limit, shuffled ones settle either way.
+        assert!(races.iter().all(|race| race.ones > 1));
+    }
+
+    #[test]
+    fn ignores_combinational_logic() {
+        let mut graph = Graph::new();
+        let [a, b] = [(); 2].map(|_| graph.add_comp(Constant::default()));
+        let and = graph.add_comp(And);
+        let output = graph.add_comp(DebugOutput::default());
+        graph.add_conn(a, 0, and, 0);
+        graph.add_conn(b, 0, and, 1);
+        graph.add_conn(and, 0, output, 0);
+        graph.propagate_all();
+        assert!(find_races(&graph, a, 8, 3).is_empty());
+    }
+}
@@ -0,0 +1,388 @@
+//! Small conflict driven clause learning SAT solver.
+//!
+//! Two watched literals, first UIP learning, activity based branching with phase saving,
+//! and geometric restarts. Learnt clauses are never dropped, which is fine for the
+//! size of the problems built from circuits here.
+
+use std::ops::Not;
+
+/// Variable or its negation, `var * 2 + negated`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub fn new(var: usize, positive: bool) -> Self {
+        Self((var as u32) << 1 | !positive as u32)
+    }
+
+    pub fn var(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 & 1 == 0
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    /// Clauses watching each literal, visited when the literal becomes false.
+    watches: Vec<Vec<usize>>,
+    assigns: Vec<Option<bool>>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    phase: Vec<bool>,
+    activity: Vec<f64>,
+    activity_increment: f64,
+    trail: Vec<Lit>,
+    trail_limits: Vec<usize>,
+    propagated: usize,
+    /// False once the clauses are known to be unsatisfiable.
+    consistent: bool,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self {
+            activity_increment: 1.0,
+            consistent: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn new_var(&mut self) -> usize {
+        self.assigns.push(None);
+        self.level.push(0);
+        self.reason.push(None);
+        self.phase.push(false);
+        self.activity.push(0.0);
+        self.watches.extend([Vec::new(), Vec::new()]);
+        self.assigns.len() - 1
+    }
+
+    pub fn new_lit(&mut self) -> Lit {
+        Lit::new(self.new_var(), true)
+    }
+
+    pub fn var_count(&self) -> usize {
+        self.assigns.len()
+    }
+
+    /// Adds a clause, must be called before [`Solver::solve`].
+    pub fn add_clause(&mut self, lits: &[Lit]) {
+        debug_assert!(self.trail_limits.is_empty());
+        let mut clause = Vec::with_capacity(lits.len());
+        for &lit in lits {
+            match self.value(lit) {
+                Some(true) => return,
+                Some(false) => {}
+                None if clause.contains(&!lit) => return,
+                None if !clause.contains(&lit) => clause.push(lit),
+                None => {}
+            }
+        }
+
+        match clause[..] {
+            [] => self.consistent = false,
+            [lit] => {
+                self.assign(lit, None);
+                if self.propagate().is_some() {
+                    self.consistent = false;
+                }
+            }
+            _ => {
+                self.watch(self.clauses.len(), &clause);
+                self.clauses.push(clause);
+            }
+        }
+    }
+
+    /// Looks for an assignment satisfying all clauses, read it with [`Solver::value`] afterwards.
+    pub fn solve(&mut self) -> bool {
+        if !self.consistent {
+            return false;
+        }
+        let mut conflicts = 0;
+        let mut restart_limit = 100.0;
+
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.trail_limits.is_empty() {
+                    self.consistent = false;
+                    return false;
+                }
+                let (learnt, backtrack_level) = self.analyze(conflict);
+                self.cancel_until(backtrack_level);
+                if let [lit] = learnt[..] {
+                    self.assign(lit, None);
+                } else {
+                    let index = self.clauses.len();
+                    self.watch(index, &learnt);
+                    self.assign(learnt[0], Some(index));
+                    self.clauses.push(learnt);
+                }
+                self.activity_increment /= 0.95;
+
+                conflicts += 1;
+                if conflicts as f64 >= restart_limit {
+                    conflicts = 0;
+                    restart_limit *= 1.5;
+                    self.cancel_until(0);
+                }
+            } else {
+                let unassigned = (0..self.var_count()).filter(|&var| self.assigns[var].is_none());
+                let Some(var) =
+                    unassigned.max_by(|&a, &b| self.activity[a].total_cmp(&self.activity[b]))
+                else {
+                    return true;
+                };
+                self.trail_limits.push(self.trail.len());
+                self.assign(Lit::new(var, self.phase[var]), None);
+            }
+        }
+    }
+
+    pub fn value(&self, lit: Lit) -> Option<bool> {
+        self.assigns[lit.var()].map(|value| value == lit.is_positive())
+    }
+
+    fn watch(&mut self, index: usize, clause: &[Lit]) {
+        self.watches[clause[0].index()].push(index);
+        self.watches[clause[1].index()].push(index);
+    }
+
+    fn assign(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit.var();
+        self.assigns[var] = Some(lit.is_positive());
+        self.level[var] = self.trail_limits.len();
+        self.reason[var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Propagates unit clauses, returning a conflicting clause if there is one.
+    /// A clause implying a literal keeps it first.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.propagated < self.trail.len() {
+            let false_lit = !self.trail[self.propagated];
+            self.propagated += 1;
+
+            let mut watchers = std::mem::take(&mut self.watches[false_lit.index()]);
+            let mut kept = 0;
+            let mut conflict = None;
+            let mut i = 0;
+            while i < watchers.len() {
+                let index = watchers[i];
+                i += 1;
+                if conflict.is_some() {
+                    watchers[kept] = index;
+                    kept += 1;
+                    continue;
+                }
+
+                let clause = &mut self.clauses[index];
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                let assigns = &self.assigns;
+                let value = |lit: Lit| assigns[lit.var()].map(|v| v == lit.is_positive());
+                if value(first) == Some(true) {
+                    watchers[kept] = index;
+                    kept += 1;
+                    continue;
+                }
+                if let Some(k) = (2..clause.len()).find(|&k| value(clause[k]) != Some(false)) {
+                    clause.swap(1, k);
+                    let new_watch = clause[1];
+                    self.watches[new_watch.index()].push(index);
+                    continue;
+                }
+
+                watchers[kept] = index;
+                kept += 1;
+                if value(first) == Some(false) {
+                    conflict = Some(index);
+                } else {
+                    self.assign(first, Some(index));
+                }
+            }
+            watchers.truncate(kept);
+            self.watches[false_lit.index()] = watchers;
+
+            if conflict.is_some() {
+                self.propagated = self.trail.len();
+                return conflict;
+            }
+        }
+        None
+    }
+
+    /// Derives the first UIP clause of a conflict and the level to go back to.
+    fn analyze(&mut self, mut conflict: usize) -> (Vec<Lit>, usize) {
+        let current_level = self.trail_limits.len();
+        let mut seen = vec![false; self.var_count()];
+        let mut learnt = vec![Lit(0)];
+        let mut pending = 0;
+        let mut index = self.trail.len();
+        let mut implied = None;
+
+        loop {
+            let skip = implied.is_some() as usize;
+            for k in skip..self.clauses[conflict].len() {
+                let lit = self.clauses[conflict][k];
+                let var = lit.var();
+                if seen[var] || self.level[var] == 0 {
+                    continue;
+                }
+                seen[var] = true;
+                self.bump(var);
+                if self.level[var] == current_level {
+                    pending += 1;
+                } else {
+                    learnt.push(lit);
+                }
+            }
+
+            let lit = loop {
+                index -= 1;
+                if seen[self.trail[index].var()] {
+                    break self.trail[index];
+                }
+            };
+            seen[lit.var()] = false;
+            implied = Some(lit);
+            pending -= 1;
+            if pending == 0 {
+                break;
+            }
+            conflict = self.reason[lit.var()].expect("implied literals have a reason");
+        }
+        learnt[0] = !implied.unwrap();
+
+        let backtrack_level = match learnt.len() {
+            1 => 0,
+            _ => {
+                let highest = (1..learnt.len())
+                    .max_by_key(|&k| self.level[learnt[k].var()])
+                    .unwrap();
+                learnt.swap(1, highest);
+                self.level[learnt[1].var()]
+            }
+        };
+        (learnt, backtrack_level)
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.activity_increment;
+        if self.activity[var] > 1e100 {
+            self.activity.iter_mut().for_each(|a| *a *= 1e-100);
+            self.activity_increment *= 1e-100;
+        }
+    }
+
+    fn cancel_until(&mut self, level: usize) {
+        if self.trail_limits.len() <= level {
+            return;
+        }
+        let start = self.trail_limits[level];
+        for lit in self.trail.drain(start..) {
+            let var = lit.var();
+            self.phase[var] = lit.is_positive();
+            self.assigns[var] = None;
+            self.reason[var] = None;
+        }
+        self.trail_limits.truncate(level);
+        self.propagated = self.trail.len();
+    }
+}
+
+/// Clauses making `out` the and of `inputs`.
+pub fn and(solver: &mut Solver, inputs: &[Lit]) -> Lit {
+    let out = solver.new_lit();
+    for &input in inputs {
+        solver.add_clause(&[!out, input]);
+    }
+    let mut clause = inputs.iter().map(|&input| !input).collect::<Vec<_>>();
+    clause.push(out);
+    solver.add_clause(&clause);
+    out
+}
+
+/// Clauses making `out` the or of `inputs`.
+pub fn or(solver: &mut Solver, inputs: &[Lit]) -> Lit {
+    let negated = inputs.iter().map(|&input| !input).collect::<Vec<_>>();
+    !and(solver, &negated)
+}
+
+/// Clauses making `out` the xor of `a` and `b`.
+pub fn xor(solver: &mut Solver, a: Lit, b: Lit) -> Lit {
+    let out = solver.new_lit();
+    solver.add_clause(&[!out, a, b]);
+    solver.add_clause(&[!out, !a, !b]);
+    solver.add_clause(&[out, !a, b]);
+    solver.add_clause(&[out, a, !b]);
+    out
+}
+
+/// Clauses making `out` equal to `then` when `condition` holds and to `otherwise` if not.
+pub fn ite(solver: &mut Solver, condition: Lit, then: Lit, otherwise: Lit) -> Lit {
+    let out = solver.new_lit();
+    solver.add_clause(&[!condition, !then, out]);
+    solver.add_clause(&[!condition, then, !out]);
+    solver.add_clause(&[condition, !otherwise, out]);
+    solver.add_clause(&[condition, otherwise, !out]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pigeons_and_models() {
+        // Four pigeons do not fit in three holes.
+        let mut solver = Solver::new();
+        let holes = 3;
+        let pigeon = (0..=holes)
+            .map(|_| (0..holes).map(|_| solver.new_lit()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        for lits in &pigeon {
+            solver.add_clause(lits);
+        }
+        for hole in 0..holes {
+            for (a, first) in pigeon.iter().enumerate() {
+                for second in &pigeon[a + 1..] {
+                    solver.add_clause(&[!first[hole], !second[hole]]);
+                }
+            }
+        }
+        assert!(!solver.solve());
+
+        // x ^ y, y ^ z, x | z has the models x = z = 1, y = 0.
+        let mut solver = Solver::new();
+        let [x, y, z] = [0; 3].map(|_| solver.new_lit());
+        let xy = xor(&mut solver, x, y);
+        let yz = xor(&mut solver, y, z);
+        let xz = or(&mut solver, &[x, z]);
+        let all = and(&mut solver, &[xy, yz, xz]);
+        solver.add_clause(&[all]);
+        assert!(solver.solve());
+        assert_eq!(
+            [x, y, z].map(|lit| solver.value(lit)),
+            [Some(true), Some(false), Some(true)]
+        );
+    }
+}
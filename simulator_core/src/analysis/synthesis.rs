@@ -0,0 +1,641 @@
+//! Gate networks built from boolean equations or truth tables.
+//!
+//! Equations are written one per line or separated by `;`, like `Q = (A & !B) | C`.
+//! Operators, from the loosest: `|` or `+`, `^`, `&` or `*`, then `!` or `~` in front
+//! and `'` behind a term. `0` and `1` are constants, `//` and `#` start comments.
+//! Names assigned earlier may be used in later equations.
+//!
+//! Truth tables list the input names, a `|`, and the output names on the first line,
+//! then one row of `0`s and `1`s per combination in the same layout.
+//! Outputs may be `x` or `-` for don't care, missing rows are `0`.
+//! Markdown tables, as written by [`TruthTable::to_markdown`](super::truth_table::TruthTable::to_markdown),
+//! are read too, an empty column splitting the inputs from the outputs.
+
+use std::{collections::HashMap, error::Error, fmt::Display};
+
+use crate::{
+    graph::Graph,
+    import::netlist::{composite, Bit, Netlist},
+};
+
+use super::{
+    expression::Expr,
+    minimize::{minimal_cover, prime_implicants, sum_of_products},
+    truth_table::MAX_INPUTS,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SynthesisError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for SynthesisError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for SynthesisError {}
+
+fn error<T>(line: usize, message: impl Into<String>) -> Result<T, SynthesisError> {
+    Err(SynthesisError {
+        line,
+        message: message.into(),
+    })
+}
+
+/// Named outputs as expressions over named inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Equations {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<(String, Expr)>,
+}
+
+/// Gates the synthesized network is made of.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GateStyle {
+    /// `And`, `Or`, `Xor` and `Not`.
+    #[default]
+    Mixed,
+    /// Two input `NAND` subcircuits only.
+    NandOnly,
+    /// Two input `NOR` subcircuits only.
+    NorOnly,
+}
+
+pub fn parse_equations(source: &str) -> Result<Equations, SynthesisError> {
+    let mut equations = Equations {
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+    };
+
+    for (line, text) in source.lines().enumerate() {
+        let line = line + 1;
+        let text = strip_comment(text);
+        for statement in text.split(';').filter(|s| !s.trim().is_empty()) {
+            let Some((name, expr)) = statement.split_once('=') else {
+                return error(line, "expected `name = expression`");
+            };
+            let name = name.trim();
+            if !is_identifier(name) {
+                return error(line, format!("`{name}` is not a valid name"));
+            }
+            if equations.inputs.iter().any(|input| input == name) {
+                return error(line, format!("`{name}` is already used as an input"));
+            }
+            if equations.outputs.iter().any(|(output, _)| output == name) {
+                return error(line, format!("`{name}` is assigned twice"));
+            }
+
+            let expr = parse_expr(line, expr, &mut equations)?;
+            equations.outputs.push((name.to_string(), expr));
+        }
+    }
+
+    if equations.outputs.is_empty() {
+        return error(1, "no equations");
+    }
+    Ok(equations)
+}
+
+/// Parses a single expression, returning it with the names of its variables.
+pub fn parse_expression(source: &str) -> Result<(Vec<String>, Expr), SynthesisError> {
+    let mut equations = Equations {
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+    };
+    let expr = parse_expr(1, source, &mut equations)?;
+    Ok((equations.inputs, expr))
+}
+
+/// Parses the right hand side of an equation, adding new names to the inputs.
+fn parse_expr(line: usize, text: &str, equations: &mut Equations) -> Result<Expr, SynthesisError> {
+    let mut parser = ExprParser {
+        tokens: tokenize(text).map_err(|message| SynthesisError { line, message })?,
+        position: 0,
+        equations,
+    };
+    parser
+        .or()
+        .and_then(|expr| match parser.tokens.get(parser.position) {
+            Some(token) => Err(format!("unexpected `{token}`")),
+            None => Ok(expr),
+        })
+        .map_err(|message| SynthesisError { line, message })
+}
+
+/// Reads a truth table and turns every output into its minimal sum of products.
+pub fn parse_truth_table(source: &str) -> Result<Equations, SynthesisError> {
+    let mut lines = source
+        .lines()
+        .enumerate()
+        .map(|(line, text)| (line + 1, strip_comment(text).trim()))
+        .filter(|(_, text)| !text.is_empty() && !is_rule(text));
+
+    let Some((line, header)) = lines.next() else {
+        return error(1, "empty truth table");
+    };
+    let (inputs, outputs) = split_row(line, header)?;
+    if let Some(name) = inputs
+        .iter()
+        .chain(&outputs)
+        .find(|name| !is_identifier(name))
+    {
+        return error(line, format!("`{name}` is not a valid name"));
+    }
+    if inputs.len() > MAX_INPUTS {
+        return error(line, format!("at most {MAX_INPUTS} inputs are allowed"));
+    }
+    let inputs = inputs.into_iter().map(str::to_string).collect::<Vec<_>>();
+
+    let mut ones = vec![Vec::new(); outputs.len()];
+    let mut dont_cares = vec![Vec::new(); outputs.len()];
+    for (line, text) in lines {
+        let (row_inputs, row_outputs) = split_row(line, text)?;
+        if row_inputs.len() != inputs.len() || row_outputs.len() != outputs.len() {
+            return error(line, "the row does not match the header");
+        }
+        let mut row = 0;
+        for value in row_inputs {
+            row = row << 1
+                | match value {
+                    "0" => 0,
+                    "1" => 1,
+                    _ => return error(line, format!("`{value}` is not an input value")),
+                };
+        }
+        for (o, value) in row_outputs.into_iter().enumerate() {
+            match value {
+                "0" => {}
+                "1" => ones[o].push(row),
+                "x" | "X" | "-" => dont_cares[o].push(row),
+                _ => return error(line, format!("`{value}` is not an output value")),
+            }
+        }
+    }
+
+    let vars = inputs.len();
+    let outputs = outputs.into_iter().zip(ones.iter().zip(&dont_cares));
+    let outputs = outputs.map(|(name, (ones, dont_cares))| {
+        let primes = prime_implicants(vars, ones, dont_cares);
+        let cover = minimal_cover(vars, &primes, ones);
+        (name.to_string(), sum_of_products(vars, &cover))
+    });
+    Ok(Equations {
+        outputs: outputs.collect(),
+        inputs,
+    })
+}
+
+/// Builds the gate network of the equations, with a named `Constant` per input
+/// and a named `DebugOutput` per output.
+pub fn synthesize(equations: &Equations, style: GateStyle) -> Graph {
+    let mut netlist = Netlist::default();
+    let inputs = equations
+        .inputs
+        .iter()
+        .map(|name| {
+            let net = netlist.new_net(Some(name.clone()));
+            netlist.add_input(name.clone(), net);
+            Bit::Net(net)
+        })
+        .collect();
+
+    let mut lowering = Lowering {
+        netlist,
+        inputs,
+        style,
+        built: HashMap::new(),
+    };
+    for (name, expr) in &equations.outputs {
+        let bit = lowering.emit(expr, true);
+        lowering.netlist.add_output(name.clone(), bit);
+    }
+    lowering.netlist.build().graph
+}
+
+struct Lowering {
+    netlist: Netlist,
+    inputs: Vec<Bit>,
+    style: GateStyle,
+    /// Already built subexpressions, by polarity.
+    built: HashMap<(Expr, bool), Bit>,
+}
+
+impl Lowering {
+    /// Builds `expr`, or its negation when not `positive`.
+    fn emit(&mut self, expr: &Expr, positive: bool) -> Bit {
+        if let Some(&bit) = self.built.get(&(expr.clone(), positive)) {
+            return bit;
+        }
+        let bit = match self.style {
+            GateStyle::Mixed => self.mixed(expr, positive),
+            GateStyle::NandOnly | GateStyle::NorOnly => self.universal(expr, positive),
+        };
+        self.built.insert((expr.clone(), positive), bit);
+        bit
+    }
+
+    fn mixed(&mut self, expr: &Expr, positive: bool) -> Bit {
+        if !positive {
+            let bit = self.emit(expr, true);
+            return self.netlist.not(bit);
+        }
+        let mut fold = |terms: &[Expr], op: fn(&mut Netlist, Bit, Bit) -> Bit| {
+            let bits = terms
+                .iter()
+                .map(|term| self.emit(term, true))
+                .collect::<Vec<_>>();
+            let (first, rest) = bits.split_first().unwrap();
+            rest.iter()
+                .fold(*first, |acc, &bit| op(&mut self.netlist, acc, bit))
+        };
+        match expr {
+            Expr::Const(value) => Bit::Const(*value),
+            Expr::Var(var) => self.inputs[*var],
+            Expr::Not(inner) => self.emit(inner, false),
+            Expr::And(terms) => fold(terms, Netlist::and),
+            Expr::Or(terms) => fold(terms, Netlist::or),
+            Expr::Xor(terms) => fold(terms, Netlist::xor),
+        }
+    }
+
+    /// Lowering to `NAND`s, which negate an `And`, or `NOR`s, which negate an `Or`.
+    fn universal(&mut self, expr: &Expr, positive: bool) -> Bit {
+        let nand = self.style == GateStyle::NandOnly;
+        match expr {
+            Expr::Const(value) => Bit::Const(*value == positive),
+            Expr::Var(var) if positive => self.inputs[*var],
+            Expr::Var(var) => self.invert(self.inputs[*var]),
+            Expr::Not(inner) => self.emit(inner, !positive),
+            Expr::And(terms) | Expr::Or(terms) if matches!(expr, Expr::And(_)) == nand => {
+                let (a, b) = self.split(expr, terms);
+                let negated = self.gate(a, b);
+                if positive {
+                    self.invert(negated)
+                } else {
+                    negated
+                }
+            }
+            Expr::And(terms) | Expr::Or(terms) => {
+                // De Morgan's law turns it into the negation of the other operator.
+                let negated = terms.iter().map(|term| !term.clone()).collect();
+                let dual = if nand {
+                    Expr::And(negated)
+                } else {
+                    Expr::Or(negated)
+                };
+                self.emit(&dual, !positive)
+            }
+            Expr::Xor(terms) => {
+                let (a, b) = self.split(expr, terms);
+                let middle = self.gate(a, b);
+                let left = self.gate(a, middle);
+                let right = self.gate(b, middle);
+                // Four `NAND`s make a xor, four `NOR`s a xnor.
+                let result = self.gate(left, right);
+                if positive == nand {
+                    result
+                } else {
+                    self.invert(result)
+                }
+            }
+        }
+    }
+
+    /// Builds all terms but the last as one operand and the last one as the other.
+    fn split(&mut self, expr: &Expr, terms: &[Expr]) -> (Bit, Bit) {
+        let (last, rest) = terms.split_last().unwrap();
+        let rest = match (rest, expr) {
+            ([single], _) => single.clone(),
+            (_, Expr::And(_)) => Expr::And(rest.to_vec()),
+            (_, Expr::Or(_)) => Expr::Or(rest.to_vec()),
+            _ => Expr::Xor(rest.to_vec()),
+        };
+        (self.emit(&rest, true), self.emit(last, true))
+    }
+
+    fn invert(&mut self, bit: Bit) -> Bit {
+        self.gate(bit, bit)
+    }
+
+    fn gate(&mut self, a: Bit, b: Bit) -> Bit {
+        let nand = self.style == GateStyle::NandOnly;
+        // The value forcing the output, `0` for `NAND` and `1` for `NOR`.
+        let dominant = !nand;
+        match (a, b) {
+            (Bit::Const(value), _) | (_, Bit::Const(value)) if value == dominant => {
+                Bit::Const(nand)
+            }
+            (Bit::Const(_), Bit::Const(_)) => Bit::Const(!nand),
+            (Bit::Const(_), other) | (other, Bit::Const(_)) => self.invert(other),
+            (a, b) => {
+                let (name, gate) = if nand {
+                    ("NAND", Netlist::and as fn(&mut Netlist, Bit, Bit) -> Bit)
+                } else {
+                    ("NOR", Netlist::or as fn(&mut Netlist, Bit, Bit) -> Bit)
+                };
+                let component = composite(name, 2, 1, |netlist, bits| {
+                    let bit = gate(netlist, bits[0], bits[1]);
+                    vec![netlist.not(bit)]
+                });
+                self.netlist.gate(component, vec![a, b])
+            }
+        }
+    }
+}
+
+fn strip_comment(text: &str) -> &str {
+    let end = [text.find("//"), text.find('#')]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Markdown rule under the header.
+fn is_rule(text: &str) -> bool {
+    text.contains('-') && text.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+/// Splits a truth table row into the input and the output cells.
+fn split_row(line: usize, text: &str) -> Result<(Vec<&str>, Vec<&str>), SynthesisError> {
+    let cells = text
+        .trim_start_matches('|')
+        .trim_end_matches('|')
+        .split('|')
+        .map(str::trim)
+        .collect::<Vec<_>>();
+    fn words<'a>(cells: &[&'a str]) -> Vec<&'a str> {
+        cells
+            .iter()
+            .flat_map(|cell| cell.split(|c: char| c == ',' || c.is_whitespace()))
+            .filter(|word| !word.is_empty())
+            .collect()
+    }
+    if let Some(split) = cells.iter().position(|cell| cell.is_empty()) {
+        Ok((words(&cells[..split]), words(&cells[split + 1..])))
+    } else if let [inputs, outputs] = cells[..] {
+        Ok((words(&[inputs]), words(&[outputs])))
+    } else {
+        error(line, "inputs and outputs must be separated by `|`")
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' {
+            let mut end = start + c.len_utf8();
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(text[start..end].to_string());
+        } else if "|+^&*!~'()".contains(c) {
+            tokens.push(c.to_string());
+        } else {
+            return Err(format!("unexpected `{c}`"));
+        }
+    }
+    Ok(tokens)
+}
+
+struct ExprParser<'a> {
+    tokens: Vec<String>,
+    position: usize,
+    equations: &'a mut Equations,
+}
+
+impl ExprParser<'_> {
+    fn eat(&mut self, options: &[&str]) -> bool {
+        let found = self
+            .tokens
+            .get(self.position)
+            .is_some_and(|token| options.contains(&token.as_str()));
+        self.position += found as usize;
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut terms = vec![self.xor()?];
+        while self.eat(&["|", "+"]) {
+            terms.push(self.xor()?);
+        }
+        Ok(Expr::or(terms))
+    }
+
+    fn xor(&mut self) -> Result<Expr, String> {
+        let mut terms = vec![self.and()?];
+        while self.eat(&["^"]) {
+            terms.push(self.and()?);
+        }
+        Ok(Expr::xor(terms))
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut terms = vec![self.unary()?];
+        while self.eat(&["&", "*"]) {
+            terms.push(self.unary()?);
+        }
+        Ok(Expr::and(terms))
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat(&["!", "~"]) {
+            return Ok(!self.unary()?);
+        }
+        let mut expr = self.atom()?;
+        while self.eat(&["'"]) {
+            expr = !expr;
+        }
+        Ok(expr)
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let Some(token) = self.tokens.get(self.position).cloned() else {
+            return Err("unexpected end of the expression".to_string());
+        };
+        self.position += 1;
+        match token.as_str() {
+            "(" => {
+                let expr = self.or()?;
+                if !self.eat(&[")"]) {
+                    return Err("expected `)`".to_string());
+                }
+                Ok(expr)
+            }
+            "0" => Ok(Expr::Const(false)),
+            "1" => Ok(Expr::Const(true)),
+            name if is_identifier(name) => Ok(self.variable(name)),
+            token => Err(format!("unexpected `{token}`")),
+        }
+    }
+
+    fn variable(&mut self, name: &str) -> Expr {
+        let Equations { inputs, outputs } = &mut *self.equations;
+        if let Some((_, expr)) = outputs.iter().find(|(output, _)| output == name) {
+            return expr.clone();
+        }
+        let var = inputs
+            .iter()
+            .position(|input| input == name)
+            .unwrap_or_else(|| {
+                inputs.push(name.to_string());
+                inputs.len() - 1
+            });
+        Expr::Var(var)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::truth_table::truth_table,
+        components::{
+            simple::{Constant, DebugOutput},
+            Component,
+        },
+        graph::id::TypedId,
+    };
+
+    /// Truth table of the synthesized graph, outputs in equation order.
+    fn simulate(graph: &mut Graph, equations: &Equations) -> Vec<Vec<bool>> {
+        let id = |name: &String| graph.find_by_name(name).unwrap();
+        let inputs: Vec<TypedId<Constant>> = equations
+            .inputs
+            .iter()
+            .map(|name| id(name).into())
+            .collect();
+        let outputs: Vec<TypedId<DebugOutput>> = equations
+            .outputs
+            .iter()
+            .map(|(name, _)| id(name).into())
+            .collect();
+        let table = truth_table(graph, &inputs, &outputs).unwrap();
+        table
+            .rows
+            .iter()
+            .map(|row| row.iter().by_vals().collect())
+            .collect()
+    }
+
+    fn expected(equations: &Equations) -> Vec<Vec<bool>> {
+        let vars = equations.inputs.len();
+        (0..1 << vars)
+            .map(|row: usize| {
+                let values = (0..vars)
+                    .map(|var| row >> (vars - 1 - var) & 1 == 1)
+                    .collect::<Vec<_>>();
+                equations
+                    .outputs
+                    .iter()
+                    .map(|(_, expr)| expr.eval(&values))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn equations_in_every_style() {
+        let equations =
+            parse_equations("Q = (A & !B) | C  // the example\nT = A ^ B ^ C; R = (T + A)' * 1")
+                .unwrap();
+        assert_eq!(equations.inputs, ["A", "B", "C"]);
+        assert_eq!(
+            equations.outputs[2]
+                .1
+                .display(&equations.inputs)
+                .to_string(),
+            "!(A ^ B ^ C | A)"
+        );
+
+        for style in [GateStyle::Mixed, GateStyle::NandOnly, GateStyle::NorOnly] {
+            let mut graph = synthesize(&equations, style);
+            assert_eq!(simulate(&mut graph, &equations), expected(&equations));
+
+            let allowed = match style {
+                GateStyle::Mixed => None,
+                GateStyle::NandOnly => Some("NAND"),
+                GateStyle::NorOnly => Some("NOR"),
+            };
+            for (_, node) in &graph.nodes {
+                match (&node.component, allowed) {
+                    (Component::Subcircuit(sub), Some(name)) => assert_eq!(sub.name, name),
+                    (
+                        Component::Constant(_) | Component::DebugOutput(_) | Component::Fork(_),
+                        _,
+                    ) => {}
+                    (component, None) => assert!(!matches!(component, Component::Subcircuit(_))),
+                    (component, Some(_)) => panic!("{component:?} in a universal gate network"),
+                }
+            }
+        }
+
+        let errors = [
+            ("Q = A &", "line 1: unexpected end of the expression"),
+            ("Q = A\n\nQ = B", "line 3: `Q` is assigned twice"),
+            ("Q = (A | B", "line 1: expected `)`"),
+            ("Q = A B", "line 1: unexpected `B`"),
+            ("Q = A\nA = B", "line 2: `A` is already used as an input"),
+            ("A & B", "line 1: expected `name = expression`"),
+        ];
+        for (source, message) in errors {
+            assert_eq!(parse_equations(source).unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn truth_tables() {
+        let markdown = "\
+| A | B | | S | C |
+| :-: | :-: | - | :-: | :-: |
+| 0 | 0 | | 0 | 0 |
+| 0 | 1 | | 1 | 0 |
+| 1 | 0 | | 1 | 0 |
+| 1 | 1 | | 0 | 1 |
+";
+        let equations = parse_truth_table(markdown).unwrap();
+        let shown = |equations: &Equations| {
+            equations
+                .outputs
+                .iter()
+                .map(|(name, expr)| format!("{name} = {}", expr.display(&equations.inputs)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(shown(&equations), ["S = !A & B | A & !B", "C = A & B"]);
+        let mut graph = synthesize(&equations, GateStyle::NandOnly);
+        assert_eq!(simulate(&mut graph, &equations), expected(&equations));
+
+        // Don't cares and missing rows.
+        let plain = "a b c | y\n0 0 1 | 1\n0 1 1 | x\n1 1 1 | 1\n1 0 1 | -";
+        assert_eq!(shown(&parse_truth_table(plain).unwrap()), ["y = c"]);
+
+        assert_eq!(
+            parse_truth_table("a b | y\n0 1 1").unwrap_err().to_string(),
+            "line 2: inputs and outputs must be separated by `|`"
+        );
+        assert_eq!(
+            parse_truth_table("a b | y\n0 1 | 2")
+                .unwrap_err()
+                .to_string(),
+            "line 2: `2` is not an output value"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Sequences of input values applied to a graph.
+//!
+//! Vectors are written one per line as `0`s and `1`s, one per input in order,
+//! optionally separated by spaces. `//` and `#` start comments.
+
+use std::{error::Error, fmt::Display};
+
+use bitvec::prelude::*;
+
+use crate::{
+    components::simple::{Constant, DebugOutput},
+    graph::{id::TypedId, Graph},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VectorError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for VectorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for VectorError {}
+
+/// Input values applied in order, with the outputs read after each vector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestVectors {
+    pub inputs: Vec<TypedId<Constant>>,
+    pub outputs: Vec<TypedId<DebugOutput>>,
+    pub vectors: Vec<BitVec>,
+}
+
+impl TestVectors {
+    /// Applies the vectors one after another, returning the outputs after each one.
+    pub fn run(&self, graph: &mut Graph) -> Vec<BitVec> {
+        self.vectors
+            .iter()
+            .map(|vector| {
+                for (&id, value) in self.inputs.iter().zip(vector.iter().by_vals()) {
+                    if graph[id].state != value {
+                        graph[id].state = value;
+                        graph.propagate_from(id);
+                    }
+                }
+                self.outputs.iter().map(|&id| graph[id].state).collect()
+            })
+            .collect()
+    }
+
+    /// Vectors in the text format, one per line.
+    pub fn to_text(&self) -> String {
+        self.vectors
+            .iter()
+            .map(|vector| {
+                let bits = vector.iter().by_vals().map(|bit| if bit { "1" } else { "0" });
+                bits.collect::<Vec<_>>().join(" ") + "\n"
+            })
+            .collect()
+    }
+}
+
+/// Reads vectors of `width` values.
+pub fn parse_vectors(source: &str, width: usize) -> Result<Vec<BitVec>, VectorError> {
+    let mut vectors = Vec::new();
+    for (line, text) in source.lines().enumerate() {
+        let error = |message: String| VectorError {
+            line: line + 1,
+            message,
+        };
+        let end = [text.find("//"), text.find('#')]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(text.len());
+        let vector = text[..end]
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| match c {
+                '0' => Ok(false),
+                '1' => Ok(true),
+                c => Err(error(format!("`{c}` is not a value"))),
+            })
+            .collect::<Result<BitVec, _>>()?;
+        if vector.is_empty() {
+            continue;
+        }
+        if vector.len() != width {
+            return Err(error(format!(
+                "{} values given for {width} inputs",
+                vector.len()
+            )));
+        }
+        vectors.push(vector);
+    }
+    Ok(vectors)
+}
@@ -0,0 +1,403 @@
+//! Truth tables of combinational parts of a graph.
+
+use std::{collections::HashMap, error::Error, fmt::Display};
+
+use bitvec::prelude::*;
+
+use crate::{
+    components::{
+        simple::{Constant, DebugOutput},
+        Component,
+    },
+    graph::{
+        id::{ComponentId, TypedId},
+        Graph,
+    },
+};
+
+use super::parallel::{unpack, ParallelSimulator, LANES};
+
+/// Tables over more inputs get too large to be of any use.
+pub const MAX_INPUTS: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TruthTableError {
+    TooManyInputs {
+        count: usize,
+    },
+    /// The outputs depend on a feedback loop, listed in signal order.
+    SequentialLoop {
+        path: Vec<String>,
+    },
+    /// The outputs depend on a memory element or a subcircuit holding one.
+    Stateful {
+        component: String,
+    },
+}
+
+impl Display for TruthTableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TruthTableError::TooManyInputs { count } => {
+                write!(
+                    f,
+                    "{count} inputs selected, at most {MAX_INPUTS} are allowed"
+                )
+            }
+            TruthTableError::SequentialLoop { path } => {
+                write!(
+                    f,
+                    "the outputs depend on a sequential loop: {}",
+                    path.join(" -> ")
+                )
+            }
+            TruthTableError::Stateful { component } => {
+                write!(f, "the outputs depend on the state of {component}")
+            }
+        }
+    }
+}
+
+impl Error for TruthTableError {}
+
+/// Outputs for every combination of the inputs.
+///
+/// Row `r` holds the outputs for the inputs set to the bits of `r`,
+/// the first input being the most significant bit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TruthTable {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub rows: Vec<BitVec>,
+}
+
+impl TruthTable {
+    /// Value of input `input` in row `row`.
+    pub fn input(&self, row: usize, input: usize) -> bool {
+        row >> (self.inputs.len() - 1 - input) & 1 == 1
+    }
+
+    pub fn to_csv(&self) -> String {
+        let header = self
+            .inputs
+            .iter()
+            .chain(&self.outputs)
+            .map(|name| csv_field(name));
+        let mut csv = header.collect::<Vec<_>>().join(",");
+        csv.push('\n');
+        for row in 0..self.rows.len() {
+            csv.push_str(&self.row_values(row).join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// GitHub flavoured Markdown table, inputs and outputs are split by an empty column.
+    pub fn to_markdown(&self) -> String {
+        let names = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| name.replace('|', "\\|"))
+                .collect::<Vec<_>>()
+        };
+        let mut markdown = format!(
+            "| {} | | {} |\n",
+            names(&self.inputs).join(" | "),
+            names(&self.outputs).join(" | ")
+        );
+        let rule = |count| vec![":-:"; count].join(" | ");
+        markdown.push_str(&format!(
+            "| {} | - | {} |\n",
+            rule(self.inputs.len()),
+            rule(self.outputs.len())
+        ));
+        for row in 0..self.rows.len() {
+            let values = self.row_values(row);
+            let (inputs, outputs) = values.split_at(self.inputs.len());
+            markdown.push_str(&format!(
+                "| {} | | {} |\n",
+                inputs.join(" | "),
+                outputs.join(" | ")
+            ));
+        }
+        markdown
+    }
+
+    fn row_values(&self, row: usize) -> Vec<&'static str> {
+        let inputs = (0..self.inputs.len()).map(|i| self.input(row, i));
+        let outputs = self.rows[row].iter().by_vals();
+        inputs
+            .chain(outputs)
+            .map(|bit| if bit { "1" } else { "0" })
+            .collect()
+    }
+}
+
+/// Enumerates all combinations of `inputs` and reads `outputs` for each one,
+/// evaluating 64 rows at a time.
+///
+/// Fails when the outputs are not a function of the inputs alone,
+/// that is they depend on a loop or on a memory element.
+pub fn truth_table(
+    graph: &Graph,
+    inputs: &[TypedId<Constant>],
+    outputs: &[TypedId<DebugOutput>],
+) -> Result<TruthTable, TruthTableError> {
+    if inputs.len() > MAX_INPUTS {
+        return Err(TruthTableError::TooManyInputs {
+            count: inputs.len(),
+        });
+    }
+    let simulator = ParallelSimulator::new(graph, inputs, outputs)?;
+
+    let column = |graph: &Graph, id: ComponentId, prefix: &str, i: usize| {
+        graph
+            .name(id)
+            .map_or_else(|| format!("{prefix}{i}"), str::to_string)
+    };
+    let table_inputs = inputs.iter().enumerate();
+    let table_outputs = outputs.iter().enumerate();
+    let mut table = TruthTable {
+        inputs: table_inputs
+            .map(|(i, &id)| column(graph, id.into(), "in", i))
+            .collect(),
+        outputs: table_outputs
+            .map(|(o, &id)| column(graph, id.into(), "out", o))
+            .collect(),
+        rows: Vec::with_capacity(1 << inputs.len()),
+    };
+
+    let rows = 1 << inputs.len();
+    for first in (0..rows).step_by(LANES) {
+        let lanes = first..rows.min(first + LANES);
+        let words = (0..inputs.len())
+            .map(|i| {
+                let bits = lanes.clone().map(|row| table.input(row, i) as u64);
+                let bits = bits.enumerate();
+                bits.fold(0, |word, (lane, bit)| word | bit << lane)
+            })
+            .collect::<Vec<_>>();
+        let words = simulator.eval(&words);
+        let lanes = 0..lanes.len();
+        table.rows.extend(lanes.map(|lane| unpack(&words, lane)));
+    }
+
+    Ok(table)
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    Open,
+    Done,
+}
+
+/// Checks that no loop or memory element drives the given nodes.
+///
+/// A bidirectional pin reads its line without its own drive, see
+/// [`Graph::terminal_input`], so the line and the pin make no loop. Components are
+/// taken as a whole, so a line joining bidirectional pins of several components
+/// still makes one.
+pub(super) fn check_combinational(
+    graph: &Graph,
+    roots: impl Iterator<Item = ComponentId>,
+) -> Result<(), TruthTableError> {
+    let mut visits = HashMap::new();
+    // Depth first search against the signal flow, the stack holds a node, the input
+    // slot it is read without and its next input slot.
+    for root in roots {
+        let mut stack = vec![(root, None, 0)];
+        while let Some(&(id, except, slot)) = stack.last() {
+            if slot == 0 {
+                match visits.get(&(id, except)) {
+                    Some(Visit::Done) => {
+                        stack.pop();
+                        continue;
+                    }
+                    Some(Visit::Open) => {
+                        let open = |&(other, without, _): &_| (other, without) == (id, except);
+                        let start = stack.iter().position(open).unwrap();
+                        let path = stack[start..stack.len() - 1].iter().rev();
+                        return Err(TruthTableError::SequentialLoop {
+                            path: path.map(|&(id, _, _)| describe(graph, id)).collect(),
+                        });
+                    }
+                    None => {}
+                }
+                visits.insert((id, except), Visit::Open);
+                check_component(graph, id)?;
+            }
+
+            match graph.nodes[id].input_slots.get(slot) {
+                Some(input) => {
+                    stack.last_mut().unwrap().2 += 1;
+                    match input {
+                        Some(input) if except != Some(slot) => {
+                            let (node, slot) = (input.target_node, input.target_slot);
+                            stack.push((node, graph.terminal_input(node, slot), 0));
+                        }
+                        _ => {}
+                    }
+                }
+                None => {
+                    visits.insert((id, except), Visit::Done);
+                    stack.pop();
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_component(graph: &Graph, id: ComponentId) -> Result<(), TruthTableError> {
+    let stateful = match &graph[id] {
+        Component::FlipFlop(_) | Component::Ram(_) => true,
+        Component::Subcircuit(sub) => check_combinational(&sub.graph, sub.output_ids()).is_err(),
+        _ => false,
+    };
+    if stateful {
+        Err(TruthTableError::Stateful {
+            component: describe(graph, id),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Kind of the node, with its name if it has one.
+pub fn describe(graph: &Graph, id: ComponentId) -> String {
+    let kind = match &graph[id] {
+        Component::And(_) => "And",
+        Component::Or(_) => "Or",
+        Component::Xor(_) => "Xor",
+        Component::Not(_) => "Not",
+        Component::Fork(_) => "Fork",
+        Component::DebugOutput(_) => "DebugOutput",
+        Component::Constant(_) => "Constant",
+        Component::Subcircuit(sub) => &sub.name,
+        Component::FlipFlop(_) => "FlipFlop",
+        Component::Rom(_) => "Rom",
+        Component::Ram(_) => "Ram",
+        Component::PullResistor(_) => "PullResistor",
+        Component::OpenCollector(_) => "OpenCollector",
+        Component::Transceiver(_) => "Transceiver",
+    };
+    match graph.name(id) {
+        Some(name) => format!("{kind} `{name}`"),
+        None => kind.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{
+        gates::{And, Not, Or, Xor},
+        memory::{FlipFlop, FlipFlopKind},
+        simple::Fork,
+    };
+
+    #[test]
+    fn half_adder() {
+        let mut graph = Graph::new();
+        let a = graph.add_comp(Constant::default());
+        let b = graph.add_comp(Constant { state: true });
+        let fork_a = graph.add_comp(Fork::new(1, 2));
+        let fork_b = graph.add_comp(Fork::new(1, 2));
+        let xor = graph.add_comp(Xor);
+        let and = graph.add_comp(And);
+        let sum = graph.add_comp(DebugOutput::default());
+        let carry = graph.add_comp(DebugOutput::default());
+        graph.add_conn(a, 0, fork_a, 0);
+        graph.add_conn(b, 0, fork_b, 0);
+        graph.add_conn(fork_a, 0, xor, 0);
+        graph.add_conn(fork_b, 0, xor, 1);
+        graph.add_conn(fork_a, 1, and, 0);
+        graph.add_conn(fork_b, 1, and, 1);
+        graph.add_conn(xor, 0, sum, 0);
+        graph.add_conn(and, 0, carry, 0);
+        graph.set_name(a, "A");
+        graph.set_name(b, "B");
+        graph.set_name(sum, "S");
+        graph.propagate_all();
+
+        let table = truth_table(&graph, &[a, b], &[sum, carry]).unwrap();
+        assert_eq!(table.inputs, ["A", "B"]);
+        assert_eq!(table.outputs, ["S", "out1"]);
+        assert_eq!(
+            table.rows,
+            [bitvec![0, 0], bitvec![1, 0], bitvec![1, 0], bitvec![0, 1]]
+        );
+        assert_eq!(
+            table.to_csv(),
+            "A,B,S,out1\n0,0,0,0\n0,1,1,0\n1,0,1,0\n1,1,0,1\n"
+        );
+        assert!(table.to_markdown().starts_with(
+            "| A | B | | S | out1 |\n| :-: | :-: | - | :-: | :-: |\n| 0 | 0 | | 0 | 0 |\n"
+        ));
+
+        // The graph is not touched.
+        assert!(!graph[a].state && graph[b].state);
+        assert!(graph[sum].state && !graph[carry].state);
+    }
+
+    #[test]
+    fn sequential_outputs_are_rejected() {
+        // Cross coupled NOR latch.
+        let mut graph = Graph::new();
+        let set = graph.add_comp(Constant::default());
+        let reset = graph.add_comp(Constant::default());
+        let or_q = graph.add_comp(Or);
+        let or_nq = graph.add_comp(Or);
+        let not_q = graph.add_comp(Not);
+        let not_nq = graph.add_comp(Not);
+        let fork = graph.add_comp(Fork::new(1, 2));
+        let q = graph.add_comp(DebugOutput::default());
+        graph.add_conn(reset, 0, or_q, 0);
+        graph.add_conn(set, 0, or_nq, 0);
+        graph.add_conn(or_q, 0, not_q, 0);
+        graph.add_conn(or_nq, 0, not_nq, 0);
+        graph.add_conn(not_q, 0, fork, 0);
+        graph.add_conn(fork, 0, q, 0);
+        graph.add_conn(fork, 1, or_nq, 1);
+        graph.add_conn(not_nq, 0, or_q, 1);
+        graph.set_name(not_q, "Q");
+
+        assert_eq!(
+            truth_table(&graph, &[set, reset], &[q]),
+            Err(TruthTableError::SequentialLoop {
+                path: ["Or", "Not", "Or", "Not `Q`", "Fork"]
+                    .map(String::from)
+                    .to_vec()
+            })
+        );
+
+        let mut graph = Graph::new();
+        let d = graph.add_comp(Constant::default());
+        let ff = graph.add_comp(FlipFlop::new(FlipFlopKind::D));
+        let q = graph.add_comp(DebugOutput::default());
+        graph.add_conn(d, 0, ff, 0);
+        graph.add_conn(ff, 0, q, 0);
+        assert_eq!(
+            truth_table(&graph, &[d], &[q]),
+            Err(TruthTableError::Stateful {
+                component: "FlipFlop".to_string()
+            })
+        );
+
+        let inputs = vec![d; MAX_INPUTS + 1];
+        assert_eq!(
+            truth_table(&graph, &inputs, &[q]),
+            Err(TruthTableError::TooManyInputs {
+                count: MAX_INPUTS + 1
+            })
+        );
+    }
+}
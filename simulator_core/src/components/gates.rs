@@ -2,6 +2,7 @@ use bitvec::slice::BitSlice;
 use serde::{Deserialize, Serialize};
 
 use super::ComponentBehaviour;
+use crate::graph::logic::Logic;
 
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct And;
@@ -22,6 +23,9 @@ impl ComponentBehaviour for And {
     fn output_size(&self) -> usize {
         1
     }
+    fn propagate_logic(&mut self, _prev_input: &[Logic], input: &[Logic], output: &mut [Logic]) {
+        output[0] = input[0].and(input[1]);
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
@@ -43,6 +47,9 @@ impl ComponentBehaviour for Or {
     fn output_size(&self) -> usize {
         1
     }
+    fn propagate_logic(&mut self, _prev_input: &[Logic], input: &[Logic], output: &mut [Logic]) {
+        output[0] = input[0].or(input[1]);
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
@@ -64,6 +71,9 @@ impl ComponentBehaviour for Not {
     fn output_size(&self) -> usize {
         1
     }
+    fn propagate_logic(&mut self, _prev_input: &[Logic], input: &[Logic], output: &mut [Logic]) {
+        output[0] = !input[0];
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
@@ -85,4 +95,7 @@ impl ComponentBehaviour for Xor {
     fn output_size(&self) -> usize {
         1
     }
+    fn propagate_logic(&mut self, _prev_input: &[Logic], input: &[Logic], output: &mut [Logic]) {
+        output[0] = input[0].xor(input[1]);
+    }
 }
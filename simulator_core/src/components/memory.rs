@@ -0,0 +1,390 @@
+use bitvec::{field::BitField, slice::BitSlice};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::ComponentBehaviour;
+use crate::graph::logic::{propagate_known, Logic};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FlipFlopKind {
+    #[default]
+    D,
+    T,
+    JK,
+    SR,
+}
+
+impl FlipFlopKind {
+    pub fn data_inputs(&self) -> usize {
+        match self {
+            FlipFlopKind::D | FlipFlopKind::T => 1,
+            FlipFlopKind::JK | FlipFlopKind::SR => 2,
+        }
+    }
+}
+
+/// When the clock input lets a memory element take new data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Trigger {
+    #[default]
+    RisingEdge,
+    FallingEdge,
+    HighLevel,
+    LowLevel,
+}
+
+impl Trigger {
+    fn fires(&self, prev_clock: bool, clock: bool) -> bool {
+        match self {
+            Trigger::RisingEdge => !prev_clock && clock,
+            Trigger::FallingEdge => prev_clock && !clock,
+            Trigger::HighLevel => clock,
+            Trigger::LowLevel => !clock,
+        }
+    }
+
+    /// Whether the trigger fires, unknown when it depends on an unknown clock level.
+    fn fires_logic(&self, prev_clock: Logic, clock: Logic) -> Logic {
+        match self {
+            Trigger::RisingEdge => (!prev_clock).and(clock),
+            Trigger::FallingEdge => prev_clock.and(!clock),
+            Trigger::HighLevel => clock.read(),
+            Trigger::LowLevel => !clock,
+        }
+    }
+}
+
+/// Level of an asynchronous control input, which is inactive when left floating.
+fn control(level: Logic) -> Logic {
+    match level {
+        Logic::Z => Logic::Zero,
+        level => level,
+    }
+}
+
+/// Single bit memory.
+///
+/// Inputs are the data inputs of the kind (`D`, `T`, `J K` or `S R`), clock,
+/// asynchronous reset and asynchronous preset. Outputs are `Q` and `!Q`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct FlipFlop {
+    pub kind: FlipFlopKind,
+    #[serde(default)]
+    pub trigger: Trigger,
+    #[serde(default)]
+    pub state: bool,
+    /// Whether `state` is unknown in four-valued simulation.
+    #[serde(skip)]
+    pub unknown: bool,
+}
+
+impl FlipFlop {
+    pub fn new(kind: FlipFlopKind) -> Self {
+        Self {
+            kind,
+            ..Default::default()
+        }
+    }
+}
+
+impl ComponentBehaviour for FlipFlop {
+    fn propagate(
+        &mut self,
+        prev_input: &BitSlice,
+        input: &BitSlice,
+        output: &mut BitSlice,
+        _mask: &mut BitSlice,
+    ) {
+        let clock = self.kind.data_inputs();
+        let (reset, preset) = (input[clock + 1], input[clock + 2]);
+
+        if reset {
+            self.state = false;
+        } else if preset {
+            self.state = true;
+        } else if self.trigger.fires(prev_input[clock], input[clock]) {
+            self.state = match (self.kind, input[0], input[1]) {
+                (FlipFlopKind::D, d, _) => d,
+                (FlipFlopKind::T, t, _) => self.state ^ t,
+                (FlipFlopKind::JK, true, true) => !self.state,
+                (FlipFlopKind::JK | FlipFlopKind::SR, true, false) => true,
+                (FlipFlopKind::JK | FlipFlopKind::SR, false, true) => false,
+                _ => self.state,
+            };
+        }
+
+        output.set(0, self.state);
+        output.set(1, !self.state);
+    }
+
+    fn input_size(&self) -> usize {
+        self.kind.data_inputs() + 3
+    }
+
+    fn output_size(&self) -> usize {
+        2
+    }
+
+    fn propagate_logic(&mut self, prev_input: &[Logic], input: &[Logic], output: &mut [Logic]) {
+        let clock = self.kind.data_inputs();
+        let (reset, preset) = (control(input[clock + 1]), control(input[clock + 2]));
+        let state = if self.unknown {
+            Logic::X
+        } else {
+            self.state.into()
+        };
+
+        let state = if reset == Logic::One {
+            Logic::Zero
+        } else if preset == Logic::One {
+            Logic::One
+        } else if !reset.is_known() || !preset.is_known() {
+            Logic::X
+        } else {
+            let next = match (self.kind, input[0].read(), input[1].read()) {
+                (FlipFlopKind::D, d, _) => d,
+                (FlipFlopKind::T, t, _) => state.xor(t),
+                (FlipFlopKind::JK, Logic::One, Logic::One) => !state,
+                (FlipFlopKind::JK | FlipFlopKind::SR, Logic::One, Logic::Zero) => Logic::One,
+                (FlipFlopKind::JK | FlipFlopKind::SR, Logic::Zero, Logic::One) => Logic::Zero,
+                (FlipFlopKind::JK | FlipFlopKind::SR, j, k) if j.is_known() && k.is_known() => {
+                    state
+                }
+                _ => Logic::X,
+            };
+            match self.trigger.fires_logic(prev_input[clock], input[clock]) {
+                Logic::One => next,
+                Logic::Zero => state,
+                _ if next == state => state,
+                _ => Logic::X,
+            }
+        };
+
+        self.state = state.bit();
+        self.unknown = !state.is_known();
+        output[0] = state;
+        output[1] = !state;
+    }
+
+    fn forget_state(&mut self) {
+        self.unknown = true;
+    }
+}
+
+/// Widest address of a memory, wider ones are capped to it.
+pub const MAX_ADDRESS_WIDTH: u8 = 16;
+/// Widest word of a memory, which must fit in a `u64`.
+pub const MAX_DATA_WIDTH: u8 = 64;
+
+/// Read only memory, inputs are the address bits and outputs the data bits, least significant first.
+///
+/// Words past the end of `contents` read as zero.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Rom {
+    #[serde(deserialize_with = "address_width")]
+    pub address_width: u8,
+    #[serde(deserialize_with = "data_width")]
+    pub data_width: u8,
+    pub contents: Vec<u64>,
+}
+
+impl Rom {
+    pub fn new(address_width: u8, data_width: u8, mut contents: Vec<u64>) -> Self {
+        let address_width = address_width.min(MAX_ADDRESS_WIDTH);
+        let data_width = data_width.min(MAX_DATA_WIDTH);
+        contents.resize(1 << address_width, 0);
+        Self {
+            address_width,
+            data_width,
+            contents,
+        }
+    }
+}
+
+impl ComponentBehaviour for Rom {
+    fn propagate(
+        &mut self,
+        _prev_input: &BitSlice,
+        input: &BitSlice,
+        output: &mut BitSlice,
+        _mask: &mut BitSlice,
+    ) {
+        let address = read_bits(input) as usize;
+        write_bits(output, self.contents.get(address).copied().unwrap_or(0));
+    }
+
+    fn input_size(&self) -> usize {
+        self.address_width as usize
+    }
+
+    fn output_size(&self) -> usize {
+        self.data_width as usize
+    }
+}
+
+/// Random access memory with separate data input and output.
+///
+/// Inputs are the address bits, data bits, store, clock and clear,
+/// outputs the data bits at the current address.
+/// The data is written when store is set and the clock fires, clear empties the whole memory.
+/// Words past the end of `contents` read as zero.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Ram {
+    #[serde(deserialize_with = "address_width")]
+    pub address_width: u8,
+    #[serde(deserialize_with = "data_width")]
+    pub data_width: u8,
+    #[serde(default)]
+    pub trigger: Trigger,
+    pub contents: Vec<u64>,
+}
+
+impl Ram {
+    pub fn new(address_width: u8, data_width: u8) -> Self {
+        let address_width = address_width.min(MAX_ADDRESS_WIDTH);
+        let data_width = data_width.min(MAX_DATA_WIDTH);
+        Self {
+            address_width,
+            data_width,
+            trigger: Trigger::default(),
+            contents: vec![0; 1 << address_width],
+        }
+    }
+}
+
+impl ComponentBehaviour for Ram {
+    fn propagate(
+        &mut self,
+        prev_input: &BitSlice,
+        input: &BitSlice,
+        output: &mut BitSlice,
+        _mask: &mut BitSlice,
+    ) {
+        let address_width = self.address_width as usize;
+        let data_end = address_width + self.data_width as usize;
+        let address = read_bits(&input[..address_width]) as usize;
+        let [store, clock, clear] = [data_end, data_end + 1, data_end + 2];
+
+        if input[clear] {
+            self.contents.iter_mut().for_each(|word| *word = 0);
+        } else if input[store] && self.trigger.fires(prev_input[clock], input[clock]) {
+            if address >= self.contents.len() {
+                self.contents.resize(address + 1, 0);
+            }
+            self.contents[address] = read_bits(&input[address_width..data_end]);
+        }
+
+        write_bits(output, self.contents.get(address).copied().unwrap_or(0));
+    }
+
+    fn input_size(&self) -> usize {
+        self.address_width as usize + self.data_width as usize + 3
+    }
+
+    fn output_size(&self) -> usize {
+        self.data_width as usize
+    }
+
+    fn propagate_logic(&mut self, prev_input: &[Logic], input: &[Logic], output: &mut [Logic]) {
+        let clear = input.len() - 1;
+        let mut input = input.to_vec();
+        input[clear] = control(input[clear]);
+        propagate_known(self, prev_input, &input, output);
+    }
+}
+
+fn address_width<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+    u8::deserialize(deserializer).map(|width| width.min(MAX_ADDRESS_WIDTH))
+}
+
+fn data_width<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+    u8::deserialize(deserializer).map(|width| width.min(MAX_DATA_WIDTH))
+}
+
+fn read_bits(bits: &BitSlice) -> u64 {
+    if bits.is_empty() {
+        0
+    } else {
+        bits[..bits.len().min(64)].load_le()
+    }
+}
+
+fn write_bits(bits: &mut BitSlice, value: u64) {
+    if !bits.is_empty() {
+        let low = bits.len().min(64);
+        bits[..low].store_le(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitvec::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn flip_flops() {
+        let mut ff = FlipFlop::new(FlipFlopKind::JK);
+        let mut output = bitvec![0; 2];
+        let mut mask = bitvec![1; 2];
+        let mut clock = |ff: &mut FlipFlop, j: bool, k: bool| {
+            let low = [j, k, false, false, false].into_iter().collect::<BitVec>();
+            let mut high = low.clone();
+            high.set(2, true);
+            ff.propagate(&low, &high, &mut output, &mut mask);
+            ff.propagate(&high, &low, &mut output, &mut mask);
+            (output[0], output[1])
+        };
+
+        assert_eq!(clock(&mut ff, true, false), (true, false));
+        assert_eq!(clock(&mut ff, false, false), (true, false));
+        assert_eq!(clock(&mut ff, true, true), (false, true));
+        assert_eq!(clock(&mut ff, true, true), (true, false));
+        assert_eq!(clock(&mut ff, false, true), (false, true));
+    }
+
+    #[test]
+    fn ram_stores_on_clock() {
+        let mut ram = Ram::new(2, 4);
+        let mut output = bitvec![0; 4];
+        let mut mask = bitvec![1; 4];
+        // address 2, data 0b1011, store
+        let low = bitvec![0, 1, 1, 1, 0, 1, 1, 0, 0];
+        let high = bitvec![0, 1, 1, 1, 0, 1, 1, 1, 0];
+
+        ram.propagate(&low, &low, &mut output, &mut mask);
+        assert_eq!(output.load_le::<u64>(), 0);
+        ram.propagate(&low, &high, &mut output, &mut mask);
+        assert_eq!(output.load_le::<u64>(), 0b1011);
+        assert_eq!(ram.contents, vec![0, 0, 0b1011, 0]);
+
+        // Contents not sized by `new`, like ones deserialized from an older file.
+        let mut ram = Ram {
+            contents: vec![7],
+            ..ram
+        };
+        ram.propagate(&low, &low, &mut output, &mut mask);
+        assert_eq!(output.load_le::<u64>(), 0);
+        ram.propagate(&low, &high, &mut output, &mut mask);
+        assert_eq!(ram.contents, vec![7, 0, 0b1011]);
+    }
+
+    #[test]
+    fn rom_reads_missing_words_as_zero() {
+        let mut rom = Rom {
+            address_width: 2,
+            data_width: 2,
+            contents: vec![0b11],
+        };
+        let mut output = bitvec![1; 2];
+        let mut mask = bitvec![1; 2];
+        rom.propagate(&bitvec![1, 1], &bitvec![1, 1], &mut output, &mut mask);
+        assert_eq!(output.load_le::<u64>(), 0);
+
+        let rom = Rom::new(80, 80, Vec::new());
+        assert_eq!(
+            (rom.address_width, rom.data_width),
+            (MAX_ADDRESS_WIDTH, MAX_DATA_WIDTH)
+        );
+        assert_eq!(rom.contents.len(), 1 << MAX_ADDRESS_WIDTH);
+    }
+}
@@ -1,5 +1,8 @@
 pub mod gates;
+pub mod memory;
 pub mod simple;
+pub mod subcircuit;
+pub mod wired;
 
 use std::fmt::Debug;
 
@@ -7,30 +10,84 @@ use bitvec::slice::BitSlice;
 use enum_dispatch::enum_dispatch;
 use serde::{Deserialize, Serialize};
 
-use crate::impl_comp_as_ref;
+use crate::{
+    graph::logic::{propagate_known, Logic},
+    impl_comp_as_ref,
+};
 
 use self::{
     gates::{And, Not, Or, Xor},
+    memory::{FlipFlop, Ram, Rom},
     simple::{Constant, DebugOutput, Fork},
+    subcircuit::Subcircuit,
+    wired::{OpenCollector, PullResistor, Transceiver},
 };
 
+/// Input slot and output slot of a component making one pin, which the component
+/// turns into an input or an output on every evaluation.
+///
+/// The circuit joins both slots to the line of the pin, the `Fork` of its cable
+/// group. The pin is one terminal of that net: the component drives the line through
+/// the output slot and releases it to read the line through the input slot, which
+/// gets the line as the other drivers leave it, see
+/// [`crate::graph::Graph::terminal_input`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BidirectionalPin {
+    pub input: usize,
+    pub output: usize,
+}
+
+/// Direction of a bidirectional pin, see [`crate::graph::Graph::pin_direction`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PinDirection {
+    Input,
+    Output,
+}
+
 #[enum_dispatch(Component)]
 pub trait ComponentBehaviour: Debug {
     fn propagate(&mut self, prev_input: &BitSlice, input: &BitSlice, output: &mut BitSlice, mask: &mut BitSlice);
     fn input_size(&self) -> usize;
     fn output_size(&self) -> usize;
+
+    /// Evaluates the component on four-valued levels, see [`crate::graph::logic`].
+    fn propagate_logic(&mut self, prev_input: &[Logic], input: &[Logic], output: &mut [Logic]) {
+        propagate_known(self, prev_input, input, output);
+    }
+
+    /// Makes the state kept by the component unknown, as after power-on.
+    fn forget_state(&mut self) {}
+
+    /// Value an undriven input slot reads in two-valued simulation.
+    fn floating_input(&self, _slot: usize) -> bool {
+        false
+    }
+
+    /// Masks out the outputs left undriven in two-valued simulation when nothing
+    /// drives the input slots set in `undriven`, none by default.
+    fn release(&self, _undriven: &BitSlice, _mask: &mut BitSlice) {}
+
+    fn bidirectional_pins(&self) -> Vec<BidirectionalPin> {
+        Vec::new()
+    }
 }
 
 #[enum_dispatch]
 #[rustfmt::skip]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum Component {
     And, Or, Xor, Not,
     Fork, DebugOutput, Constant,
+    Subcircuit,
+    FlipFlop, Rom, Ram,
+    PullResistor, OpenCollector, Transceiver,
 }
 
 impl_comp_as_ref![
     And, Or, Xor, Not,
-    Fork, DebugOutput, Constant
+    Fork, DebugOutput, Constant,
+    Subcircuit,
+    FlipFlop, Rom, Ram,
+    PullResistor, OpenCollector, Transceiver
 ];
\ No newline at end of file
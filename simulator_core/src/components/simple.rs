@@ -1,7 +1,8 @@
-use bitvec::{slice::BitSlice, bits};
+use bitvec::slice::BitSlice;
 use serde::{Deserialize, Serialize};
 
 use super::ComponentBehaviour;
+use crate::graph::logic::Logic;
 
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Constant {
@@ -61,6 +62,33 @@ impl Fork {
     pub fn new(input_size: u8, output_size: u8) -> Self {
         Self { input_size, output_size }
     }
+
+    /// Value of the line left without input `except`, and whether it is released,
+    /// as it reaches the bidirectional pin driving that input.
+    pub fn line_without(
+        &self,
+        input: &BitSlice,
+        undriven: &BitSlice,
+        except: usize,
+    ) -> (bool, bool) {
+        let mut others = (0..self.input_size as usize).filter(|&slot| slot != except);
+        let bit = others.clone().any(|slot| input[slot]);
+        (bit, others.all(|slot| undriven[slot]))
+    }
+
+    /// Level of the line left without input `except`, see [`Fork::line_without`].
+    pub fn level_without(&self, input: &[Logic], except: usize) -> Logic {
+        let others = input[..self.input_size as usize].iter().enumerate();
+        resolve(others.filter(|&(slot, _)| slot != except).map(|(_, &level)| level))
+    }
+}
+
+/// Level of a line driven by `levels`, leaving out undriven ones.
+fn resolve(levels: impl Iterator<Item = Logic>) -> Logic {
+    levels
+        .filter(|&level| level != Logic::Z)
+        .reduce(|a, b| if a == b { a } else { Logic::X })
+        .unwrap_or(Logic::Z)
 }
 
 impl ComponentBehaviour for Fork {
@@ -84,4 +112,18 @@ impl ComponentBehaviour for Fork {
     fn output_size(&self) -> usize {
         self.output_size as usize
     }
+
+    /// Leaves the line joining the inputs floating when none of them is driven,
+    /// released inputs read as `0` and are left out of the others.
+    fn release(&self, undriven: &BitSlice, mask: &mut BitSlice) {
+        if undriven[..self.input_size as usize].all() {
+            mask.fill(false);
+        }
+    }
+
+    /// Resolves the line joining the inputs: undriven inputs are left out, the line
+    /// floats when none is driven and is unknown when the drivers disagree.
+    fn propagate_logic(&mut self, _prev_input: &[Logic], input: &[Logic], output: &mut [Logic]) {
+        output.fill(resolve(input[..self.input_size as usize].iter().copied()));
+    }
 }
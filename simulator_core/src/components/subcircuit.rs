@@ -0,0 +1,79 @@
+use bitvec::slice::BitSlice;
+use serde::{Deserialize, Serialize};
+
+use crate::graph::{
+    id::{ComponentId, TypedId},
+    Graph,
+};
+
+use super::{
+    simple::{Constant, DebugOutput},
+    ComponentBehaviour,
+};
+
+/// Whole graph packed into a single component.
+///
+/// `inputs` are `Constant` nodes of the inner graph driven by the input slots,
+/// `outputs` are `DebugOutput` nodes of the inner graph read into the output slots.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subcircuit {
+    pub name: String,
+    pub graph: Box<Graph>,
+    pub inputs: Vec<TypedId<Constant>>,
+    pub outputs: Vec<TypedId<DebugOutput>>,
+}
+
+impl Subcircuit {
+    pub fn new(
+        name: impl Into<String>,
+        mut graph: Graph,
+        inputs: Vec<TypedId<Constant>>,
+        outputs: Vec<TypedId<DebugOutput>>,
+    ) -> Self {
+        graph.propagate_all();
+
+        Self {
+            name: name.into(),
+            graph: Box::new(graph),
+            inputs,
+            outputs,
+        }
+    }
+
+    pub fn input_ids(&self) -> impl Iterator<Item = ComponentId> + '_ {
+        self.inputs.iter().map(|&id| id.into())
+    }
+
+    pub fn output_ids(&self) -> impl Iterator<Item = ComponentId> + '_ {
+        self.outputs.iter().map(|&id| id.into())
+    }
+}
+
+impl ComponentBehaviour for Subcircuit {
+    fn propagate(
+        &mut self,
+        _prev_input: &BitSlice,
+        input: &BitSlice,
+        output: &mut BitSlice,
+        _mask: &mut BitSlice,
+    ) {
+        for (i, &id) in self.inputs.iter().enumerate() {
+            if self.graph[id].state != input[i] {
+                self.graph[id].state = input[i];
+                self.graph.propagate_from(id);
+            }
+        }
+
+        for (o, &id) in self.outputs.iter().enumerate() {
+            output.set(o, self.graph[id].state);
+        }
+    }
+
+    fn input_size(&self) -> usize {
+        self.inputs.len()
+    }
+
+    fn output_size(&self) -> usize {
+        self.outputs.len()
+    }
+}
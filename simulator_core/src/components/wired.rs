@@ -0,0 +1,307 @@
+//! Pull resistors, open-collector outputs and bus transceivers, for lines shared
+//! by several drivers.
+//!
+//! An open-collector output only ever pulls its line low and leaves it floating
+//! otherwise, so joining several of them makes a wired-AND once a pull-up gives the
+//! line its high level. The line of joined outputs is resolved by the [`Fork`] of
+//! its cable group: released outputs are left out, so one output pulling the line low
+//! wins, and the line floats to the level of its pull resistor when none drives it.
+//! Four-valued levels also tell drivers fighting over a line, see
+//! [`crate::graph::Graph::set_four_valued`].
+//!
+//! [`Fork`]: super::simple::Fork
+
+use bitvec::slice::BitSlice;
+use serde::{Deserialize, Serialize};
+
+use super::{BidirectionalPin, ComponentBehaviour};
+use crate::graph::logic::Logic;
+
+/// Resistor pulling a line to `level` while nothing drives it.
+///
+/// It sits between the line and its readers, its input is the line.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PullResistor {
+    /// `true` for a pull-up, `false` for a pull-down.
+    pub level: bool,
+}
+
+impl PullResistor {
+    pub fn up() -> Self {
+        Self { level: true }
+    }
+
+    pub fn down() -> Self {
+        Self { level: false }
+    }
+}
+
+impl ComponentBehaviour for PullResistor {
+    fn propagate(
+        &mut self,
+        _prev_input: &BitSlice,
+        input: &BitSlice,
+        output: &mut BitSlice,
+        _mask: &mut BitSlice,
+    ) {
+        output.set(0, input[0]);
+    }
+
+    fn input_size(&self) -> usize {
+        1
+    }
+
+    fn output_size(&self) -> usize {
+        1
+    }
+
+    fn propagate_logic(&mut self, _prev_input: &[Logic], input: &[Logic], output: &mut [Logic]) {
+        output[0] = match input[0] {
+            Logic::Z => self.level.into(),
+            level => level,
+        };
+    }
+
+    fn floating_input(&self, _slot: usize) -> bool {
+        self.level
+    }
+}
+
+/// Buffer with an open-collector (open-drain) output, which drives the line low
+/// for a low input and leaves it floating for a high one.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct OpenCollector;
+
+impl ComponentBehaviour for OpenCollector {
+    fn propagate(
+        &mut self,
+        _prev_input: &BitSlice,
+        input: &BitSlice,
+        output: &mut BitSlice,
+        mask: &mut BitSlice,
+    ) {
+        output.set(0, false);
+        mask.set(0, !input[0]);
+    }
+
+    fn input_size(&self) -> usize {
+        1
+    }
+
+    fn output_size(&self) -> usize {
+        1
+    }
+
+    fn propagate_logic(&mut self, _prev_input: &[Logic], input: &[Logic], output: &mut [Logic]) {
+        output[0] = match input[0].read() {
+            Logic::Zero => Logic::Zero,
+            Logic::One => Logic::Z,
+            _ => Logic::X,
+        };
+    }
+}
+
+/// Bus transceiver like the 74LS245, passing data between two sides of
+/// bidirectional pins.
+///
+/// Inputs are the `A` pins, the `B` pins, `DIR` and active low `!OE`, outputs the
+/// `A` pins and the `B` pins, each pin being a [`BidirectionalPin`] of an input slot
+/// and an output slot joined to its line. A high `DIR` makes the `B` pins outputs
+/// driven by the `A` pins, a low one the other way round, while a high `!OE` makes
+/// them all inputs, leaving both sides floating.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Transceiver {
+    pub width: u8,
+}
+
+impl Transceiver {
+    pub fn new(width: u8) -> Self {
+        Self { width }
+    }
+}
+
+impl Default for Transceiver {
+    fn default() -> Self {
+        Self::new(8)
+    }
+}
+
+impl ComponentBehaviour for Transceiver {
+    fn propagate(
+        &mut self,
+        _prev_input: &BitSlice,
+        input: &BitSlice,
+        output: &mut BitSlice,
+        mask: &mut BitSlice,
+    ) {
+        let width = self.width as usize;
+        let (dir, enabled) = (input[2 * width], !input[2 * width + 1]);
+        for (a, b) in (0..width).map(|i| (i, width + i)) {
+            let (drive_a, drive_b) = (enabled && !dir, enabled && dir);
+            output.set(a, drive_a && input[b]);
+            output.set(b, drive_b && input[a]);
+            mask.set(a, drive_a);
+            mask.set(b, drive_b);
+        }
+    }
+
+    fn input_size(&self) -> usize {
+        2 * self.width as usize + 2
+    }
+
+    fn output_size(&self) -> usize {
+        2 * self.width as usize
+    }
+
+    fn propagate_logic(&mut self, _prev_input: &[Logic], input: &[Logic], output: &mut [Logic]) {
+        let width = self.width as usize;
+        let (dir, enabled) = (input[2 * width], !input[2 * width + 1]);
+        let drive = |drives: Logic, level: Logic| match drives {
+            Logic::One => level.read(),
+            Logic::Zero => Logic::Z,
+            _ => Logic::X,
+        };
+        for (a, b) in (0..width).map(|i| (i, width + i)) {
+            output[a] = drive(enabled.and(!dir), input[b]);
+            output[b] = drive(enabled.and(dir), input[a]);
+        }
+    }
+
+    fn bidirectional_pins(&self) -> Vec<BidirectionalPin> {
+        (0..self.output_size())
+            .map(|slot| BidirectionalPin {
+                input: slot,
+                output: slot,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        components::{
+            simple::{Constant, DebugOutput, Fork},
+            PinDirection,
+        },
+        graph::Graph,
+    };
+
+    #[test]
+    fn transceiver_drives_one_side() {
+        let mut graph = Graph::new();
+        let a = graph.add_comp(Constant { state: true });
+        let dir = graph.add_comp(Constant { state: true });
+        let output_enable = graph.add_comp(Constant::default());
+        let transceiver = graph.add_comp(Transceiver::new(1));
+        let bus_a = graph.add_comp(Fork::new(2, 2));
+        let bus_b = graph.add_comp(Fork::new(1, 2));
+        let [out_a, out_b] = [(); 2].map(|_| graph.add_comp(DebugOutput::default()));
+        graph.add_conn(a, 0, bus_a, 0);
+        graph.add_conn(transceiver, 0, bus_a, 1);
+        graph.add_conn(bus_a, 0, transceiver, 0);
+        graph.add_conn(bus_a, 1, out_a, 0);
+        graph.add_conn(transceiver, 1, bus_b, 0);
+        graph.add_conn(bus_b, 0, transceiver, 1);
+        graph.add_conn(bus_b, 1, out_b, 0);
+        graph.add_conn(dir, 0, transceiver, 2);
+        graph.add_conn(output_enable, 0, transceiver, 3);
+        graph.propagate_all();
+        let pins = graph[transceiver].bidirectional_pins();
+        assert_eq!(
+            graph.pin_direction(transceiver, pins[0]),
+            PinDirection::Input
+        );
+        assert_eq!(
+            graph.pin_direction(transceiver, pins[1]),
+            PinDirection::Output
+        );
+        assert!(graph[out_b].state);
+        // The `B` pin reads its line as the other drivers leave it, and there are none.
+        assert!(!graph.inputs[transceiver.into()][1]);
+
+        graph.set_four_valued(true);
+        assert_eq!(graph.output_level(transceiver, 0), Logic::Z);
+        assert_eq!(graph.input_level(out_b, 0), Logic::One);
+        assert_eq!(graph.input_level(transceiver, 1), Logic::Z);
+
+        // Turned around nothing drives the `B` side, so the `A` side gets unknown.
+        graph[dir].state = false;
+        graph.propagate_from(dir);
+        assert_eq!(graph.output_level(transceiver, 1), Logic::Z);
+        assert_eq!(graph.input_level(out_b, 0), Logic::Z);
+        assert_eq!(graph.input_level(out_a, 0), Logic::X);
+        assert_eq!(
+            graph.pin_direction(transceiver, pins[0]),
+            PinDirection::Output
+        );
+        assert_eq!(graph.input_level(transceiver, 0), Logic::One);
+
+        graph[output_enable].state = true;
+        graph.propagate_from(output_enable);
+        assert_eq!(graph.input_level(out_a, 0), Logic::One);
+    }
+
+    #[test]
+    fn wired_and_with_pull_up() {
+        let mut graph = Graph::new();
+        let [a, b] = [(); 2].map(|_| graph.add_comp(Constant { state: true }));
+        let [oc_a, oc_b] = [(); 2].map(|_| graph.add_comp(OpenCollector));
+        let line = graph.add_comp(Fork::new(2, 1));
+        let pull_up = graph.add_comp(PullResistor::up());
+        let output = graph.add_comp(DebugOutput::default());
+        graph.add_conn(a, 0, oc_a, 0);
+        graph.add_conn(b, 0, oc_b, 0);
+        graph.add_conn(oc_a, 0, line, 0);
+        graph.add_conn(oc_b, 0, line, 1);
+        graph.add_conn(line, 0, pull_up, 0);
+        graph.add_conn(pull_up, 0, output, 0);
+        let unused = graph.add_comp(PullResistor::up());
+        let unused_output = graph.add_comp(DebugOutput::default());
+        graph.add_conn(unused, 0, unused_output, 0);
+        graph.propagate_all();
+        let set = |graph: &mut Graph, state: bool| {
+            graph[b].state = state;
+            graph.propagate_from(b);
+            graph.input_level(output, 0)
+        };
+
+        assert!(graph[output].state);
+        assert!(graph[unused_output].state);
+        assert_eq!(set(&mut graph, false), Logic::Zero);
+        assert_eq!(set(&mut graph, true), Logic::One);
+
+        graph.set_four_valued(true);
+        assert_eq!(graph.input_level(pull_up, 0), Logic::Z);
+        assert!(graph[output].state);
+        assert_eq!(set(&mut graph, false), Logic::Zero);
+        assert_eq!(set(&mut graph, true), Logic::One);
+        assert_eq!(graph.input_level(unused_output, 0), Logic::One);
+
+        graph.set_four_valued(false);
+        assert_eq!(set(&mut graph, false), Logic::Zero);
+        assert_eq!(set(&mut graph, true), Logic::One);
+    }
+
+    #[test]
+    fn contention_is_unknown() {
+        let mut graph = Graph::new();
+        let zero = graph.add_comp(Constant::default());
+        let one = graph.add_comp(Constant { state: true });
+        let line = graph.add_comp(Fork::new(2, 1));
+        let pull_down = graph.add_comp(PullResistor::down());
+        let output = graph.add_comp(DebugOutput::default());
+        graph.add_conn(zero, 0, line, 0);
+        graph.add_conn(one, 0, line, 1);
+        graph.add_conn(line, 0, pull_down, 0);
+        graph.add_conn(pull_down, 0, output, 0);
+        graph.set_four_valued(true);
+        assert_eq!(graph.input_level(output, 0), Logic::X);
+
+        graph.remove_conn(one, 0, line, 1);
+        graph.remove_conn(zero, 0, line, 0);
+        graph.propagate_from(line);
+        assert_eq!(graph.input_level(output, 0), Logic::Zero);
+    }
+}
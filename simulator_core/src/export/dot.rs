@@ -0,0 +1,273 @@
+use std::fmt::Write;
+
+use slotmap::SecondaryMap;
+
+use crate::{
+    components::{Component, ComponentBehaviour},
+    graph::{id::ComponentId, logic::Logic, node::Node, Graph},
+};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DotOptions {
+    /// Draw the inner graphs of subcircuits as clusters instead of single nodes.
+    pub cluster_subcircuits: bool,
+}
+
+/// Exports the graph in the Graphviz DOT format.
+///
+/// Every node, `Fork`s included, is labeled with its component type and name.
+/// Edges are labeled `output slot -> input slot = current level`, `X` or `Z` for
+/// unknown and undriven lines.
+pub fn to_dot(graph: &Graph, options: DotOptions) -> String {
+    let mut writer = Writer {
+        out: String::new(),
+        options,
+        next_node: 0,
+        next_cluster: 0,
+    };
+    writeln!(writer.out, "digraph {{").unwrap();
+    writeln!(writer.out, "    node [fontname=\"monospace\"];").unwrap();
+    writeln!(
+        writer.out,
+        "    edge [fontname=\"monospace\", fontsize=10];"
+    )
+    .unwrap();
+    writer.graph(graph, 1);
+    writeln!(writer.out, "}}").unwrap();
+    writer.out
+}
+
+/// DOT nodes standing for the slots of a component.
+enum Endpoints {
+    Node(String),
+    Cluster {
+        inputs: Vec<String>,
+        outputs: Vec<String>,
+    },
+}
+
+impl Endpoints {
+    fn input(&self, slot: usize) -> Option<&str> {
+        match self {
+            Endpoints::Node(node) => Some(node),
+            Endpoints::Cluster { inputs, .. } => inputs.get(slot).map(String::as_str),
+        }
+    }
+
+    fn output(&self, slot: usize) -> Option<&str> {
+        match self {
+            Endpoints::Node(node) => Some(node),
+            Endpoints::Cluster { outputs, .. } => outputs.get(slot).map(String::as_str),
+        }
+    }
+}
+
+struct Writer {
+    out: String,
+    options: DotOptions,
+    next_node: usize,
+    next_cluster: usize,
+}
+
+impl Writer {
+    fn graph(&mut self, graph: &Graph, depth: usize) -> SecondaryMap<ComponentId, Endpoints> {
+        let indent = "    ".repeat(depth);
+        let mut endpoints = SecondaryMap::new();
+
+        for (id, node) in graph.nodes.iter() {
+            match &node.component {
+                Component::Subcircuit(sub) if self.options.cluster_subcircuits => {
+                    let cluster = self.next_cluster;
+                    self.next_cluster += 1;
+                    writeln!(self.out, "{indent}subgraph cluster_{cluster} {{").unwrap();
+                    writeln!(self.out, "{indent}    label=\"{}\";", escape(&label(node))).unwrap();
+                    let inner = self.graph(&sub.graph, depth + 1);
+                    writeln!(self.out, "{indent}}}").unwrap();
+
+                    let names = |ids: &mut dyn Iterator<Item = ComponentId>| {
+                        ids.filter_map(|id| inner.get(id)?.output(0).map(str::to_string))
+                            .collect()
+                    };
+                    endpoints.insert(
+                        id,
+                        Endpoints::Cluster {
+                            inputs: names(&mut sub.input_ids()),
+                            outputs: names(&mut sub.output_ids()),
+                        },
+                    );
+                }
+                component => {
+                    let name = format!("n{}", self.next_node);
+                    self.next_node += 1;
+                    writeln!(
+                        self.out,
+                        "{indent}{name} [label=\"{}\", shape={}];",
+                        escape(&label(node)),
+                        shape(component)
+                    )
+                    .unwrap();
+                    endpoints.insert(id, Endpoints::Node(name));
+                }
+            }
+        }
+
+        for (id, node) in graph.nodes.iter() {
+            for (o, slot) in node.output_slots.iter().enumerate() {
+                let Some(slot) = slot else { continue };
+                let from = endpoints[id].output(o);
+                let to = endpoints[slot.target_node].input(slot.target_slot);
+                let (Some(from), Some(to)) = (from, to) else {
+                    continue;
+                };
+                let level = graph.output_level(id, o);
+                let style = match level {
+                    Logic::One => ", color=green",
+                    Logic::Zero => "",
+                    Logic::X => ", color=red",
+                    Logic::Z => ", style=dashed",
+                };
+                writeln!(
+                    self.out,
+                    "{indent}{from} -> {to} [label=\"{o} -> {} = {level}\"{style}];",
+                    slot.target_slot,
+                )
+                .unwrap();
+            }
+        }
+
+        endpoints
+    }
+}
+
+fn label(node: &Node) -> String {
+    let component = &node.component;
+    let kind = match component {
+        Component::And(_) => "And".to_string(),
+        Component::Or(_) => "Or".to_string(),
+        Component::Xor(_) => "Xor".to_string(),
+        Component::Not(_) => "Not".to_string(),
+        Component::Fork(_) => format!(
+            "Fork {}->{}",
+            component.input_size(),
+            component.output_size()
+        ),
+        Component::DebugOutput(d) => format!("DebugOutput = {}", d.state as u8),
+        Component::Constant(c) => format!("Constant = {}", c.state as u8),
+        Component::Subcircuit(s) => format!("Subcircuit {}", s.name),
+        Component::FlipFlop(f) => format!("FlipFlop {:?} = {}", f.kind, f.state as u8),
+        Component::Rom(_) => "Rom".to_string(),
+        Component::Ram(_) => "Ram".to_string(),
+        Component::PullResistor(p) => format!("PullResistor to {}", p.level as u8),
+        Component::OpenCollector(_) => "OpenCollector".to_string(),
+        Component::Transceiver(t) => format!("Transceiver {}", t.width),
+    };
+    match &node.name {
+        Some(name) => format!("{kind}\n{name}"),
+        None => kind,
+    }
+}
+
+fn shape(component: &Component) -> &'static str {
+    match component {
+        Component::Fork(_) => "diamond",
+        Component::Constant(_) | Component::DebugOutput(_) => "ellipse",
+        _ => "box",
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{
+        gates::Not,
+        simple::{Constant, DebugOutput, Fork},
+        subcircuit::Subcircuit,
+        wired::{OpenCollector, PullResistor},
+    };
+
+    fn inverter() -> Subcircuit {
+        let mut graph = Graph::new();
+        let input = graph.add_comp(Constant::default());
+        let not = graph.add_comp(Not);
+        let output = graph.add_comp(DebugOutput::default());
+        graph.add_conn(input, 0, not, 0);
+        graph.add_conn(not, 0, output, 0);
+        Subcircuit::new("inverter", graph, vec![input], vec![output])
+    }
+
+    #[test]
+    fn forks_and_clusters() {
+        let mut graph = Graph::new();
+        let a = graph.add_comp(Constant::default());
+        let fork = graph.add_comp(Fork::new(1, 2));
+        let sub = graph.add_comp(inverter());
+        let x = graph.add_comp(DebugOutput::default());
+        let y = graph.add_comp(DebugOutput::default());
+        graph.set_name(a, "a \"in\"");
+        graph.add_conn(a, 0, fork, 0);
+        graph.add_conn(fork, 0, x, 0);
+        graph.add_conn(fork, 1, sub, 0);
+        graph.add_conn(sub, 0, y, 0);
+        graph.propagate_all();
+
+        let flat = to_dot(&graph, DotOptions::default());
+        assert!(flat.contains(r#"n0 [label="Constant = 0\na \"in\"", shape=ellipse];"#));
+        assert!(flat.contains(r#"n1 [label="Fork 1->2", shape=diamond];"#));
+        assert!(flat.contains(r#"n1 -> n2 [label="1 -> 0 = 0"];"#));
+        assert!(flat.contains(r#"n2 -> n4 [label="0 -> 0 = 1", color=green];"#));
+
+        let clustered = to_dot(
+            &graph,
+            DotOptions {
+                cluster_subcircuits: true,
+            },
+        );
+        assert!(clustered.contains("subgraph cluster_0 {"));
+        assert!(clustered.contains(r#"label="Subcircuit inverter";"#));
+        // Edges go straight to the inner input and out of the inner output.
+        assert!(clustered.contains(r#"n1 -> n2 [label="1 -> 0 = 0"];"#));
+        assert!(clustered.contains(r#"n4 -> n6 [label="0 -> 0 = 1", color=green];"#));
+    }
+
+    #[test]
+    fn released_and_unknown_levels() {
+        let mut graph = Graph::new();
+        let a = graph.add_comp(Constant { state: true });
+        let oc = graph.add_comp(OpenCollector);
+        let line = graph.add_comp(Fork::new(1, 1));
+        let pull_up = graph.add_comp(PullResistor::up());
+        let y = graph.add_comp(DebugOutput::default());
+        graph.add_conn(a, 0, oc, 0);
+        graph.add_conn(oc, 0, line, 0);
+        graph.add_conn(line, 0, pull_up, 0);
+        graph.add_conn(pull_up, 0, y, 0);
+        graph.propagate_all();
+
+        let dot = to_dot(&graph, DotOptions::default());
+        assert!(dot.contains(r#"n1 -> n2 [label="0 -> 0 = Z", style=dashed];"#));
+        assert!(dot.contains(r#"n2 -> n3 [label="0 -> 0 = Z", style=dashed];"#));
+        assert!(dot.contains(r#"n3 -> n4 [label="0 -> 0 = 1", color=green];"#));
+
+        graph[a].state = false;
+        graph.propagate_from(a);
+        let dot = to_dot(&graph, DotOptions::default());
+        assert!(dot.contains(r#"n1 -> n2 [label="0 -> 0 = 0"];"#));
+
+        graph.set_four_valued(true);
+        let sources = graph.add_comp(Fork::new(2, 1));
+        let [b, c] = [false, true].map(|state| graph.add_comp(Constant { state }));
+        let z = graph.add_comp(DebugOutput::default());
+        graph.add_conn(b, 0, sources, 0);
+        graph.add_conn(c, 0, sources, 1);
+        graph.add_conn(sources, 0, z, 0);
+        graph.propagate_all();
+        let dot = to_dot(&graph, DotOptions::default());
+        assert!(dot.contains(r#"n5 -> n8 [label="0 -> 0 = X", color=red];"#));
+    }
+}
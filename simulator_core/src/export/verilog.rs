@@ -0,0 +1,560 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use slotmap::SecondaryMap;
+
+use crate::{
+    components::{
+        memory::{FlipFlop, FlipFlopKind, Ram, Rom, Trigger},
+        Component, ComponentBehaviour,
+    },
+    graph::{id::ComponentId, node::Slot, Graph},
+};
+
+/// Reserved words of IEEE 1364-2005.
+#[rustfmt::skip]
+const KEYWORDS: &[&str] = &[
+    "always", "and", "assign", "automatic", "begin", "buf", "bufif0", "bufif1", "case", "casex",
+    "casez", "cell", "cmos", "config", "deassign", "default", "defparam", "design", "disable",
+    "edge", "else", "end", "endcase", "endconfig", "endfunction", "endgenerate", "endmodule",
+    "endprimitive", "endspecify", "endtable", "endtask", "event", "for", "force", "forever", "fork",
+    "function", "generate", "genvar", "highz0", "highz1", "if", "ifnone", "incdir", "include",
+    "initial", "inout", "input", "instance", "integer", "join", "large", "liblist", "library",
+    "localparam", "macromodule", "medium", "module", "nand", "negedge", "nmos", "nor",
+    "noshowcancelled", "not", "notif0", "notif1", "or", "output", "parameter", "pmos", "posedge",
+    "primitive", "pull0", "pull1", "pulldown", "pullup", "pulsestyle_ondetect",
+    "pulsestyle_onevent", "rcmos", "real", "realtime", "reg", "release", "repeat", "rnmos", "rpmos",
+    "rtran", "rtranif0", "rtranif1", "scalared", "showcancelled", "signed", "small", "specify",
+    "specparam", "strong0", "strong1", "supply0", "supply1", "table", "task", "time", "tran",
+    "tranif0", "tranif1", "tri", "tri0", "tri1", "triand", "trior", "trireg", "unsigned", "use",
+    "uwire", "vectored", "wait", "wand", "weak0", "weak1", "while", "wire", "wor", "xnor", "xor",
+];
+
+/// Exports the graph as a structural Verilog netlist, `top` being the name of the main module.
+///
+/// Every `Constant` becomes an input port and every `DebugOutput` an output port of the top module.
+/// Gates are mapped to Verilog primitives, `Fork`s, `Subcircuit`s and memories to generated modules,
+/// which are written before the modules using them. Subcircuits sharing a name are assumed to be identical.
+pub fn to_verilog(graph: &Graph, top: &str) -> String {
+    let inputs = graph
+        .nodes
+        .iter()
+        .filter(|(_, node)| matches!(node.component, Component::Constant(_)))
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    let outputs = graph
+        .nodes
+        .iter()
+        .filter(|(_, node)| matches!(node.component, Component::DebugOutput(_)))
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+
+    let mut exporter = Exporter::default();
+    exporter.module(graph, top, &inputs, &outputs);
+    exporter.modules.join("\n")
+}
+
+struct Ports {
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+}
+
+#[derive(Default)]
+struct Exporter {
+    modules: Vec<String>,
+    defined: HashMap<String, Ports>,
+}
+
+impl Exporter {
+    fn module(
+        &mut self,
+        graph: &Graph,
+        name: &str,
+        inputs: &[ComponentId],
+        outputs: &[ComponentId],
+    ) -> String {
+        let module_name = sanitize(name);
+        if self.defined.contains_key(&module_name) {
+            return module_name;
+        }
+
+        let mut names = Namer::default();
+        let mut nets: SecondaryMap<ComponentId, Vec<String>> = SecondaryMap::new();
+
+        let input_ports = inputs
+            .iter()
+            .enumerate()
+            .map(|(i, &id)| {
+                let port = names.claim(graph.name(id).unwrap_or(&format!("in{i}")));
+                nets.insert(id, vec![port.clone()]);
+                port
+            })
+            .collect::<Vec<_>>();
+        let output_ports = outputs
+            .iter()
+            .enumerate()
+            .map(|(o, &id)| names.claim(graph.name(id).unwrap_or(&format!("out{o}"))))
+            .collect::<Vec<_>>();
+
+        let mut instances = Vec::new();
+        for (idx, (id, node)) in graph.nodes.iter().enumerate() {
+            if nets.contains_key(id) || outputs.contains(&id) {
+                continue;
+            }
+            if let Component::DebugOutput(_) = node.component {
+                continue;
+            }
+
+            let instance = names.claim(
+                node.name
+                    .as_deref()
+                    .unwrap_or(&format!("{}{idx}", kind(&node.component))),
+            );
+            let output_size = node.component.output_size();
+            let wires = (0..output_size)
+                .map(|o| match output_size {
+                    1 => names.claim(&format!("{instance}_out")),
+                    _ => names.claim(&format!("{instance}_out{o}")),
+                })
+                .collect();
+            nets.insert(id, wires);
+            instances.push((id, instance));
+        }
+
+        let source = |slot: &Option<Slot>| {
+            slot.as_ref()
+                .and_then(|slot| nets.get(slot.target_node)?.get(slot.target_slot).cloned())
+                .unwrap_or_else(|| "1'b0".to_string())
+        };
+
+        let mut body = String::new();
+        for (id, _) in &instances {
+            for wire in &nets[*id] {
+                writeln!(body, "    wire {wire};").unwrap();
+            }
+        }
+        if !body.is_empty() {
+            body.push('\n');
+        }
+
+        for (id, instance) in &instances {
+            let node = &graph.nodes[*id];
+            let ins = node.input_slots.iter().map(source).collect::<Vec<_>>();
+            let outs = &nets[*id];
+
+            match &node.component {
+                Component::And(_) | Component::Or(_) | Component::Xor(_) | Component::Not(_) => {
+                    let terminals = outs.iter().chain(ins.iter()).cloned().collect::<Vec<_>>();
+                    writeln!(
+                        body,
+                        "    {} {instance} ({});",
+                        kind(&node.component),
+                        terminals.join(", ")
+                    )
+                    .unwrap();
+                }
+                Component::Constant(c) => {
+                    writeln!(body, "    assign {} = 1'b{};", outs[0], c.state as u8).unwrap();
+                }
+                Component::PullResistor(pull) => match node.input_slots[0] {
+                    Some(_) => {
+                        let primitive = if pull.level { "pullup" } else { "pulldown" };
+                        writeln!(body, "    {primitive} ({});", ins[0]).unwrap();
+                        writeln!(body, "    assign {} = {};", outs[0], ins[0]).unwrap();
+                    }
+                    None => {
+                        writeln!(body, "    assign {} = 1'b{};", outs[0], pull.level as u8)
+                            .unwrap();
+                    }
+                },
+                Component::OpenCollector(_) => {
+                    writeln!(body, "    bufif0 {instance} ({}, 1'b0, {});", outs[0], ins[0])
+                        .unwrap();
+                }
+                Component::Transceiver(t) => {
+                    // The halves of each pin are separate nets, only the driven ones are assigned.
+                    let width = t.width as usize;
+                    let (dir, oe) = (&ins[2 * width], &ins[2 * width + 1]);
+                    for (a, b) in (0..width).map(|i| (i, width + i)) {
+                        for (to, from, direction) in [(a, b, "~"), (b, a, "")] {
+                            writeln!(
+                                body,
+                                "    assign {} = ~{oe} & {direction}{dir} ? {} : 1'bz;",
+                                outs[to], ins[from]
+                            )
+                            .unwrap();
+                        }
+                    }
+                }
+                Component::Fork(_) => {
+                    let fork_name = self.fork_module(ins.len(), outs.len());
+                    let ports = &self.defined[&fork_name];
+                    write_instance(&mut body, &fork_name, instance, ports, &ins, outs);
+                }
+                Component::Subcircuit(sub) => {
+                    let sub_inputs = sub.input_ids().collect::<Vec<_>>();
+                    let sub_outputs = sub.output_ids().collect::<Vec<_>>();
+                    let sub_name = self.module(&sub.graph, &sub.name, &sub_inputs, &sub_outputs);
+                    let ports = &self.defined[&sub_name];
+                    write_instance(&mut body, &sub_name, instance, ports, &ins, outs);
+                }
+                Component::FlipFlop(ff) => {
+                    let ff_name = self.flip_flop_module(ff);
+                    let ports = &self.defined[&ff_name];
+                    write_instance(&mut body, &ff_name, instance, ports, &ins, outs);
+                }
+                Component::Rom(rom) => {
+                    let rom_name = self.rom_module(&format!("{module_name}_{instance}"), rom);
+                    let ports = &self.defined[&rom_name];
+                    write_instance(&mut body, &rom_name, instance, ports, &ins, outs);
+                }
+                Component::Ram(ram) => {
+                    let ram_name = self.ram_module(ram);
+                    let ports = &self.defined[&ram_name];
+                    write_instance(&mut body, &ram_name, instance, ports, &ins, outs);
+                }
+                Component::DebugOutput(_) => unreachable!(),
+            }
+        }
+
+        if !output_ports.is_empty() {
+            body.push('\n');
+        }
+        for (port, &id) in output_ports.iter().zip(outputs) {
+            writeln!(
+                body,
+                "    assign {port} = {};",
+                source(&graph.nodes[id].input_slots[0])
+            )
+            .unwrap();
+        }
+
+        let ports = Ports {
+            inputs: input_ports,
+            outputs: output_ports,
+        };
+        self.modules
+            .push(format_module(&module_name, &ports, &body));
+        self.defined.insert(module_name.clone(), ports);
+
+        module_name
+    }
+
+    fn fork_module(&mut self, input_size: usize, output_size: usize) -> String {
+        let name = format!("fork_{input_size}_{output_size}");
+        if self.defined.contains_key(&name) {
+            return name;
+        }
+
+        let ports = Ports {
+            inputs: (0..input_size).map(|i| format!("in{i}")).collect(),
+            outputs: (0..output_size).map(|o| format!("out{o}")).collect(),
+        };
+
+        let value = if ports.inputs.is_empty() {
+            "1'b0".to_string()
+        } else {
+            ports.inputs.join(" | ")
+        };
+        let mut body = String::new();
+        for output in &ports.outputs {
+            writeln!(body, "    assign {output} = {value};").unwrap();
+        }
+
+        self.modules.push(format_module(&name, &ports, &body));
+        self.defined.insert(name.clone(), ports);
+
+        name
+    }
+
+    fn flip_flop_module(&mut self, ff: &FlipFlop) -> String {
+        let (kind, data, next) = match ff.kind {
+            FlipFlopKind::D => ("d", vec!["d"], "d"),
+            FlipFlopKind::T => ("t", vec!["t"], "state ^ t"),
+            FlipFlopKind::JK => ("jk", vec!["j", "k"], "j & k ? ~state : j ? 1'b1 : k ? 1'b0 : state"),
+            FlipFlopKind::SR => ("sr", vec!["s", "r"], "s & ~r ? 1'b1 : r & ~s ? 1'b0 : state"),
+        };
+        let (trigger, sensitivity, enable) = match ff.trigger {
+            Trigger::RisingEdge => ("rising", "posedge clk or ", None),
+            Trigger::FallingEdge => ("falling", "negedge clk or ", None),
+            Trigger::HighLevel => ("high", "", Some("clk")),
+            Trigger::LowLevel => ("low", "", Some("~clk")),
+        };
+        let name = format!("{kind}_flip_flop_{trigger}");
+        if self.defined.contains_key(&name) {
+            return name;
+        }
+
+        let ports = Ports {
+            inputs: data
+                .iter()
+                .chain(&["clk", "reset", "preset"])
+                .map(|p| p.to_string())
+                .collect(),
+            outputs: vec!["q".to_string(), "q_n".to_string()],
+        };
+
+        let mut body = String::new();
+        writeln!(body, "    reg state = 1'b0;\n").unwrap();
+        match enable {
+            None => writeln!(body, "    always @({sensitivity}posedge reset or posedge preset)").unwrap(),
+            Some(_) => writeln!(body, "    always @*").unwrap(),
+        }
+        writeln!(body, "        if (reset) state <= 1'b0;").unwrap();
+        writeln!(body, "        else if (preset) state <= 1'b1;").unwrap();
+        match enable {
+            None => writeln!(body, "        else state <= {next};").unwrap(),
+            Some(enable) => writeln!(body, "        else if ({enable}) state <= {next};").unwrap(),
+        }
+        writeln!(body, "\n    assign q = state;\n    assign q_n = ~state;").unwrap();
+
+        self.modules.push(format_module(&name, &ports, &body));
+        self.defined.insert(name.clone(), ports);
+
+        name
+    }
+
+    fn rom_module(&mut self, name: &str, rom: &Rom) -> String {
+        let name = sanitize(&format!("{name}_rom"));
+        let ports = Ports {
+            inputs: (0..rom.address_width).map(|i| format!("a{i}")).collect(),
+            outputs: (0..rom.data_width).map(|o| format!("d{o}")).collect(),
+        };
+
+        let mut body = String::new();
+        writeln!(body, "    reg [{}:0] data;\n", rom.data_width.max(1) - 1).unwrap();
+        writeln!(body, "    always @*").unwrap();
+        writeln!(body, "        case ({})", bus(&ports.inputs)).unwrap();
+        for (address, word) in rom.contents.iter().enumerate().filter(|(_, &w)| w != 0) {
+            writeln!(
+                body,
+                "            {}'d{address}: data = {}'d{word};",
+                rom.address_width, rom.data_width
+            )
+            .unwrap();
+        }
+        writeln!(body, "            default: data = 0;").unwrap();
+        writeln!(body, "        endcase\n").unwrap();
+        writeln!(body, "    assign {} = data;", bus(&ports.outputs)).unwrap();
+
+        self.modules.push(format_module(&name, &ports, &body));
+        self.defined.insert(name.clone(), ports);
+
+        name
+    }
+
+    fn ram_module(&mut self, ram: &Ram) -> String {
+        let edge = match ram.trigger {
+            Trigger::RisingEdge | Trigger::HighLevel => "posedge",
+            Trigger::FallingEdge | Trigger::LowLevel => "negedge",
+        };
+        let name = format!("ram_{}x{}_{edge}", ram.address_width, ram.data_width);
+        if self.defined.contains_key(&name) {
+            return name;
+        }
+
+        let address = (0..ram.address_width).map(|i| format!("a{i}")).collect::<Vec<_>>();
+        let data = (0..ram.data_width).map(|i| format!("d{i}")).collect::<Vec<_>>();
+        let ports = Ports {
+            inputs: address
+                .iter()
+                .chain(&data)
+                .cloned()
+                .chain(["store", "clk", "clear"].map(String::from))
+                .collect(),
+            outputs: (0..ram.data_width).map(|o| format!("q{o}")).collect(),
+        };
+
+        let mut body = String::new();
+        writeln!(
+            body,
+            "    reg [{}:0] memory [0:{}];",
+            ram.data_width.max(1) - 1,
+            (1u64 << ram.address_width) - 1
+        )
+        .unwrap();
+        writeln!(body, "    integer i;\n").unwrap();
+        writeln!(body, "    always @({edge} clk or posedge clear)").unwrap();
+        writeln!(body, "        if (clear) for (i = 0; i < {}; i = i + 1) memory[i] <= 0;", 1u64 << ram.address_width).unwrap();
+        writeln!(body, "        else if (store) memory[{}] <= {};\n", bus(&address), bus(&data)).unwrap();
+        writeln!(body, "    assign {} = memory[{}];", bus(&ports.outputs), bus(&address)).unwrap();
+
+        self.modules.push(format_module(&name, &ports, &body));
+        self.defined.insert(name.clone(), ports);
+
+        name
+    }
+}
+
+/// Concatenation of scalar nets given least significant first.
+fn bus(bits: &[String]) -> String {
+    match bits {
+        [bit] => bit.clone(),
+        bits => format!(
+            "{{{}}}",
+            bits.iter().rev().cloned().collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+fn kind(component: &Component) -> &'static str {
+    match component {
+        Component::And(_) => "and",
+        Component::Or(_) => "or",
+        Component::Xor(_) => "xor",
+        Component::Not(_) => "not",
+        Component::Fork(_) => "fork",
+        Component::DebugOutput(_) => "debug",
+        Component::Constant(_) => "const",
+        Component::Subcircuit(_) => "sub",
+        Component::FlipFlop(_) => "ff",
+        Component::Rom(_) => "rom",
+        Component::Ram(_) => "ram",
+        Component::PullResistor(_) => "pull",
+        Component::OpenCollector(_) => "oc",
+        Component::Transceiver(_) => "xcvr",
+    }
+}
+
+fn format_module(name: &str, ports: &Ports, body: &str) -> String {
+    let declarations = ports
+        .inputs
+        .iter()
+        .map(|port| format!("    input {port}"))
+        .chain(
+            ports
+                .outputs
+                .iter()
+                .map(|port| format!("    output {port}")),
+        )
+        .collect::<Vec<_>>();
+
+    let mut module = String::new();
+    if declarations.is_empty() {
+        writeln!(module, "module {name};").unwrap();
+    } else {
+        writeln!(module, "module {name} (\n{}\n);", declarations.join(",\n")).unwrap();
+    }
+    module.push_str(body);
+    module.push_str("endmodule\n");
+    module
+}
+
+fn write_instance(
+    body: &mut String,
+    module: &str,
+    instance: &str,
+    ports: &Ports,
+    ins: &[String],
+    outs: &[String],
+) {
+    let connections = ports
+        .inputs
+        .iter()
+        .zip(ins)
+        .chain(ports.outputs.iter().zip(outs))
+        .map(|(port, net)| format!(".{port}({net})"))
+        .collect::<Vec<_>>();
+    writeln!(
+        body,
+        "    {module} {instance} ({});",
+        connections.join(", ")
+    )
+    .unwrap();
+}
+
+fn sanitize(name: &str) -> String {
+    let mut ident = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+#[derive(Default)]
+struct Namer {
+    used: HashSet<String>,
+}
+
+impl Namer {
+    fn claim(&mut self, base: &str) -> String {
+        let base = sanitize(base);
+        let mut name = base.clone();
+        let mut n = 1;
+        while self.used.contains(&name) {
+            name = format!("{base}_{n}");
+            n += 1;
+        }
+        self.used.insert(name.clone());
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{
+        gates::{And, Xor},
+        simple::{Constant, DebugOutput, Fork},
+    };
+
+    #[test]
+    fn half_adder_export() {
+        let mut graph = Graph::new();
+        let a = graph.add_comp(Constant::default());
+        let b = graph.add_comp(Constant::default());
+        let a_fork = graph.add_comp(Fork::new(1, 2));
+        let b_fork = graph.add_comp(Fork::new(1, 2));
+        let xor = graph.add_comp(Xor);
+        let and = graph.add_comp(And);
+        let sum = graph.add_comp(DebugOutput::default());
+        let carry = graph.add_comp(DebugOutput::default());
+        graph.set_name(a, "a");
+        graph.set_name(b, "b");
+        graph.set_name(sum, "sum");
+        graph.set_name(carry, "carry");
+
+        graph.add_conn(a, 0, a_fork, 0);
+        graph.add_conn(b, 0, b_fork, 0);
+        graph.add_conn(a_fork, 0, and, 0);
+        graph.add_conn(a_fork, 1, xor, 0);
+        graph.add_conn(b_fork, 0, and, 1);
+        graph.add_conn(b_fork, 1, xor, 1);
+        graph.add_conn(and, 0, carry, 0);
+        graph.add_conn(xor, 0, sum, 0);
+
+        let verilog = to_verilog(&graph, "half adder");
+
+        assert!(verilog.starts_with("module fork_1_2 ("));
+        assert!(verilog.contains("module half_adder (\n    input a,\n    input b,\n    output sum,\n    output carry\n);"));
+        assert!(
+            verilog.contains("    fork_1_2 fork2 (.in0(a), .out0(fork2_out0), .out1(fork2_out1));")
+        );
+        assert!(verilog.contains("    xor xor4 (xor4_out, fork2_out1, fork3_out1);"));
+        assert!(verilog.contains("    assign carry = and5_out;"));
+        assert_eq!(verilog, to_verilog(&graph, "half adder"));
+    }
+
+    #[test]
+    fn reserved_names_are_escaped() {
+        assert_eq!(sanitize("fork"), "fork_");
+        assert_eq!(sanitize("supply1"), "supply1_");
+        assert_eq!(sanitize("pullup"), "pullup_");
+        assert_eq!(sanitize("2 bit"), "_2_bit");
+        assert_eq!(sanitize("data"), "data");
+    }
+}
@@ -0,0 +1,985 @@
+//! Graphs compiled into flat arrays for fast simulation.
+//!
+//! Subcircuits are flattened, every other node becomes a cell with dense indices
+//! into contiguous input and output bits, and cells are levelized so changes are
+//! evaluated in signal order: a cell in an acyclic cone is evaluated once per settle.
+//! Wires going back to a lower level, which close loops, schedule their target
+//! again like [`Graph::propagate_from`] does.
+//!
+//! Edge triggered flip-flops and memories are not levelized: a settle goes in rounds
+//! like the one of [`partition::PartitionedGraph`], the other cells settle, then the
+//! clocked cells whose inputs changed are all evaluated before any of their outputs
+//! is driven, so a clock edge samples the data from before the edge.
+//!
+//! Settled values are the ones of [`Graph::propagate_from`] unless they depend on
+//! the order of evaluation, short glitches within a settle may not show up.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, BinaryHeap},
+    ops::Range,
+};
+
+use bitvec::prelude::*;
+use slotmap::SecondaryMap;
+
+use crate::components::{
+    memory::Trigger,
+    simple::{Constant, DebugOutput},
+    Component, ComponentBehaviour,
+};
+
+use super::{
+    fault::{FaultValue, Pin},
+    id::{ComponentId, TypedId},
+    Graph, MAX_PROPAGATION_DEPTH,
+};
+
+pub mod partition;
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Constant,
+    /// `Constant` driven by an input slot of its subcircuit.
+    InputPort,
+    /// `DebugOutput`, driving an output slot when it is an output of its subcircuit.
+    Output,
+    And,
+    Or,
+    Xor,
+    Not,
+    Fork,
+    /// Component evaluated by its own behaviour, like one keeping state, by index.
+    Stateful(usize),
+}
+
+#[derive(Debug, Clone)]
+struct Cell {
+    op: Op,
+    inputs: Range<usize>,
+    outputs: Range<usize>,
+    faults: Range<usize>,
+    /// Output slots of a `Fork` leading to bidirectional pins, see [`Graph::terminals`].
+    terminals: Range<usize>,
+    level: usize,
+}
+
+/// Cells of one graph, the top one or the graph of a subcircuit.
+#[derive(Debug, Clone, Default)]
+struct Instance {
+    /// Subcircuit nodes leading to the graph from the top one.
+    path: Vec<ComponentId>,
+    cells: SecondaryMap<ComponentId, usize>,
+    /// Instance of each subcircuit node.
+    subcircuits: SecondaryMap<ComponentId, usize>,
+    /// Cells of the input and output ports of a subcircuit, in slot order.
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+}
+
+/// A graph turned into flat arrays, simulated apart from the graph it was compiled from.
+///
+/// Faults injected in the graph are compiled in.
+/// [`CompiledGraph::write_back`] copies the state back into the graph.
+#[derive(Debug, Clone, Default)]
+pub struct CompiledGraph {
+    cells: Vec<Cell>,
+    /// Input bit driven by each output bit.
+    wires: Vec<Option<usize>>,
+    /// Cell of each input bit.
+    owners: Vec<usize>,
+    faults: Vec<(Pin, FaultValue)>,
+    /// Output slot of a cell and the input slot it is resolved without.
+    terminals: Vec<(usize, usize)>,
+    instances: Vec<Instance>,
+
+    inputs: Vec<bool>,
+    /// Level each input bit reads while nothing drives it.
+    floating: BitVec,
+    /// Input bits left unconnected or driven by a released output.
+    undriven: BitVec,
+    /// Inputs of the cells when they were last evaluated, for edge triggering.
+    last_inputs: BitVec,
+    outputs: Vec<bool>,
+    /// Output bits the last evaluation of their cell left undriven.
+    released: BitVec,
+    /// State of each `Constant` cell.
+    constants: BitVec,
+    stateful: Vec<Component>,
+    /// Slots of the stateful component being evaluated, kept to save allocations.
+    scratch: [BitVec; 3],
+
+    /// Cells to evaluate, by level.
+    queue: Vec<Vec<usize>>,
+    queued: Vec<bool>,
+    /// Levels with cells to evaluate, lowest first.
+    levels: BinaryHeap<Reverse<usize>>,
+    /// Edge triggered cells, evaluated together once the other cells settled.
+    clocked: BitVec,
+    /// Clocked cells to evaluate in the next round.
+    pending: BTreeSet<usize>,
+    /// Whether the last settle was cut short.
+    overflowed: bool,
+}
+
+impl CompiledGraph {
+    /// Compiles the graph, starting from its current state.
+    pub fn compile(graph: &Graph) -> Self {
+        let mut compiled = CompiledGraph::default();
+        compiled.add_instance(graph, Vec::new(), None);
+        compiled.last_inputs = compiled.inputs.iter().collect();
+        compiled.levelize();
+        compiled
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Whether the last settle was cut short after too many evaluations,
+    /// as happens to a loop which never settles.
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// State of a `Constant` of the top graph.
+    pub fn constant(&self, id: TypedId<Constant>) -> bool {
+        self.constants[self.instances[0].cells[id.into()]]
+    }
+
+    /// Sets a `Constant` of the top graph and propagates its new state.
+    pub fn set_constant(&mut self, id: TypedId<Constant>, state: bool) {
+        let cell = self.instances[0].cells[id.into()];
+        self.constants.set(cell, state);
+        self.schedule(cell);
+        self.settle();
+    }
+
+    /// State of a `DebugOutput` of the top graph.
+    pub fn output(&self, id: TypedId<DebugOutput>) -> bool {
+        let cell = self.instances[0].cells[id.into()];
+        self.inputs[self.cells[cell].inputs.start]
+    }
+
+    /// Evaluates a node of the top graph again and propagates the changes.
+    pub fn propagate_from(&mut self, node: impl Into<ComponentId>) {
+        let node = node.into();
+        let top = &self.instances[0];
+        let cells = match top.subcircuits.get(node) {
+            Some(&child) => self.instances[child].inputs.clone(),
+            None => vec![top.cells[node]],
+        };
+        for cell in cells {
+            self.schedule(cell);
+        }
+        self.settle();
+    }
+
+    pub fn propagate_all(&mut self) {
+        for cell in 0..self.cells.len() {
+            self.schedule(cell);
+        }
+        self.settle();
+    }
+
+    /// Copies the simulation state into the graph the compiled one was made from.
+    pub fn write_back(&self, graph: &mut Graph) {
+        for instance in &self.instances {
+            let mut graph = &mut *graph;
+            for &node in &instance.path {
+                let Component::Subcircuit(sub) = &mut graph.nodes[node].component else {
+                    unreachable!("paths go through subcircuits");
+                };
+                graph = sub.graph.as_mut();
+            }
+
+            for (node, &index) in &instance.cells {
+                let cell = &self.cells[index];
+                let inputs = &self.inputs[cell.inputs.clone()];
+                let outputs = &self.outputs[cell.outputs.clone()];
+                // Ports have a slot more than their node.
+                let len = graph.inputs[node].len();
+                graph.inputs[node] = inputs[..len].iter().collect();
+                let len = graph.outputs[node].len();
+                graph.outputs[node] = outputs[..len].iter().collect();
+                graph
+                    .released
+                    .insert(node, self.released[cell.outputs.clone()][..len].into());
+
+                match (cell.op, &mut graph.nodes[node].component) {
+                    (Op::Constant, Component::Constant(constant)) => {
+                        constant.state = self.constants[index];
+                    }
+                    (Op::InputPort, Component::Constant(constant)) => constant.state = outputs[0],
+                    (Op::Output, Component::DebugOutput(output)) => output.state = inputs[0],
+                    (Op::Stateful(stateful), component) => {
+                        *component = self.stateful[stateful].clone();
+                    }
+                    _ => {}
+                }
+            }
+
+            for (node, &child) in &instance.subcircuits {
+                let child = &self.instances[child];
+                let value = |&cell: &usize| self.inputs[self.cells[cell].inputs.start];
+                graph.inputs[node] = child.inputs.iter().map(value).collect();
+                graph.outputs[node] = child.outputs.iter().map(value).collect();
+            }
+        }
+    }
+
+    /// Adds cells for the nodes of the graph, `parent` being the graph
+    /// and node of the subcircuit it belongs to.
+    fn add_instance(
+        &mut self,
+        graph: &Graph,
+        path: Vec<ComponentId>,
+        parent: Option<(&Graph, ComponentId)>,
+    ) -> usize {
+        let index = self.instances.len();
+        self.instances.push(Instance {
+            path: path.clone(),
+            ..Default::default()
+        });
+        let subcircuit = parent.map(|(parent, node)| match &parent[node] {
+            Component::Subcircuit(sub) => (parent, node, sub),
+            _ => unreachable!("instances are made for subcircuits"),
+        });
+
+        for (node, data) in &graph.nodes {
+            let mut faults = graph.faults.get(node).cloned().unwrap_or_default();
+            let mut inputs = graph.inputs[node].clone();
+            let mut outputs = graph.outputs[node].clone();
+            let mut floating = (0..inputs.len())
+                .map(|slot| data.component.floating_input(slot))
+                .collect::<BitVec>();
+            let mut state = false;
+            // Slot of the parent node and the value on it, for ports.
+            let port = |ports: &[ComponentId], values: &BitSlice| {
+                let (parent, id, _) = subcircuit?;
+                let slot = ports.iter().position(|&port| port == node)?;
+                let faults = parent.faults.get(id).cloned().unwrap_or_default();
+                Some((slot, values[slot], faults))
+            };
+
+            let op = match &data.component {
+                Component::Constant(constant) => {
+                    let ports = subcircuit.map(|(parent, id, sub)| {
+                        let ids = sub.input_ids().collect::<Vec<_>>();
+                        port(&ids, &parent.inputs[id])
+                    });
+                    match ports.flatten() {
+                        Some((slot, value, parent_faults)) => {
+                            inputs = bitvec![value as usize; 1];
+                            let (parent, id, _) = subcircuit.unwrap();
+                            floating = bitvec![parent[id].floating_input(slot) as usize; 1];
+                            let pins = parent_faults.into_iter().filter_map(|(pin, value)| {
+                                (pin == Pin::Input(slot)).then_some((Pin::Input(0), value))
+                            });
+                            faults.extend(pins);
+                            Op::InputPort
+                        }
+                        None => {
+                            state = constant.state;
+                            Op::Constant
+                        }
+                    }
+                }
+                Component::DebugOutput(_) => {
+                    let ports = subcircuit.map(|(parent, id, sub)| {
+                        let ids = sub.output_ids().collect::<Vec<_>>();
+                        port(&ids, &parent.outputs[id])
+                    });
+                    if let Some((slot, value, parent_faults)) = ports.flatten() {
+                        outputs = bitvec![value as usize; 1];
+                        let pins = parent_faults.into_iter().filter_map(|(pin, value)| {
+                            (pin == Pin::Output(slot)).then_some((Pin::Output(0), value))
+                        });
+                        faults.extend(pins);
+                    }
+                    Op::Output
+                }
+                Component::And(_) => Op::And,
+                Component::Or(_) => Op::Or,
+                Component::Xor(_) => Op::Xor,
+                Component::Not(_) => Op::Not,
+                Component::Fork(_) => Op::Fork,
+                Component::FlipFlop(_)
+                | Component::Rom(_)
+                | Component::Ram(_)
+                | Component::PullResistor(_)
+                | Component::OpenCollector(_)
+                | Component::Transceiver(_) => {
+                    self.stateful.push(data.component.clone());
+                    Op::Stateful(self.stateful.len() - 1)
+                }
+                Component::Subcircuit(sub) => {
+                    let mut inner = path.clone();
+                    inner.push(node);
+                    let child = self.add_instance(&sub.graph, inner, Some((graph, node)));
+                    self.instances[index].subcircuits.insert(node, child);
+                    continue;
+                }
+            };
+            let terminals = graph.terminals(node);
+            let cell = self.push_cell(op, inputs, outputs, &faults, &terminals, state);
+            let (input_bits, output_bits) = (&self.cells[cell].inputs, &self.cells[cell].outputs);
+            self.floating[input_bits.clone()].copy_from_bitslice(&floating);
+            if let Some(released) = graph.released.get(node) {
+                // Output ports have a slot more than their node.
+                let len = released.len().min(output_bits.len());
+                self.released[output_bits.clone()][..len].copy_from_bitslice(&released[..len]);
+            }
+            self.instances[index].cells.insert(node, cell);
+        }
+
+        if let Some((_, _, sub)) = subcircuit {
+            let cells = &self.instances[index].cells;
+            let inputs = sub.input_ids().map(|id| cells[id]).collect();
+            let outputs = sub.output_ids().map(|id| cells[id]).collect();
+            self.instances[index].inputs = inputs;
+            self.instances[index].outputs = outputs;
+        }
+
+        for (node, data) in &graph.nodes {
+            for (slot, target) in data.output_slots.iter().enumerate() {
+                let Some(target) = target else { continue };
+                let from = self.output_bit(index, graph, node, slot);
+                let to = self.input_bit(index, graph, target.target_node, target.target_slot);
+                self.wires[from] = Some(to);
+                let released = self.released[from];
+                self.undriven.set(to, released);
+            }
+        }
+        index
+    }
+
+    /// Adds a cell with the given slot values, unconnected inputs reading `0` while
+    /// undriven and unconnected outputs.
+    fn push_cell(
+        &mut self,
+        op: Op,
+        inputs: impl IntoIterator<Item = bool>,
+        outputs: impl IntoIterator<Item = bool>,
+        faults: &[(Pin, FaultValue)],
+        terminals: &[(usize, usize)],
+        constant: bool,
+    ) -> usize {
+        let cell = self.cells.len();
+        let input_start = self.inputs.len();
+        self.inputs.extend(inputs);
+        self.owners.resize(self.inputs.len(), cell);
+        self.floating.resize(self.inputs.len(), false);
+        self.undriven.resize(self.inputs.len(), true);
+        let output_start = self.outputs.len();
+        self.outputs.extend(outputs);
+        self.wires.resize(self.outputs.len(), None);
+        self.released.resize(self.outputs.len(), false);
+        let fault_start = self.faults.len();
+        self.faults.extend_from_slice(faults);
+        let terminal_start = self.terminals.len();
+        self.terminals.extend_from_slice(terminals);
+        self.constants.push(constant);
+        self.cells.push(Cell {
+            op,
+            inputs: input_start..self.inputs.len(),
+            outputs: output_start..self.outputs.len(),
+            faults: fault_start..self.faults.len(),
+            terminals: terminal_start..self.terminals.len(),
+            level: 0,
+        });
+        cell
+    }
+
+    fn output_bit(&self, instance: usize, graph: &Graph, node: ComponentId, slot: usize) -> usize {
+        let instance = &self.instances[instance];
+        match &graph[node] {
+            Component::Subcircuit(_) => {
+                let child = &self.instances[instance.subcircuits[node]];
+                self.cells[child.outputs[slot]].outputs.start
+            }
+            _ => self.cells[instance.cells[node]].outputs.start + slot,
+        }
+    }
+
+    fn input_bit(&self, instance: usize, graph: &Graph, node: ComponentId, slot: usize) -> usize {
+        let instance = &self.instances[instance];
+        match &graph[node] {
+            Component::Subcircuit(_) => {
+                let child = &self.instances[instance.subcircuits[node]];
+                self.cells[child.inputs[slot]].inputs.start
+            }
+            _ => self.cells[instance.cells[node]].inputs.start + slot,
+        }
+    }
+
+    /// Cells driving other cells of this one.
+    fn successors(&self, cell: usize) -> impl Iterator<Item = usize> + '_ {
+        let wires = self.cells[cell]
+            .outputs
+            .clone()
+            .filter_map(|bit| self.wires[bit]);
+        wires.map(|input| self.owners[input])
+    }
+
+    /// Gives each cell a level above the cells driving it, leaving out the wires
+    /// which close loops, found by a depth first search from cells without drivers.
+    fn levelize(&mut self) {
+        let count = self.cells.len();
+        self.clocked = (0..count).map(|cell| self.is_clocked(cell)).collect();
+        let mut driven = bitvec![0; count];
+        for cell in 0..count {
+            for next in self.successors(cell) {
+                driven.set(next, true);
+            }
+        }
+        let roots = driven.iter_zeros().chain(driven.iter_ones());
+
+        let mut visited = bitvec![0; count];
+        let mut finished = Vec::with_capacity(count);
+        for root in roots {
+            if visited[root] {
+                continue;
+            }
+            visited.set(root, true);
+            let mut stack = vec![(root, self.successors(root).collect::<Vec<_>>())];
+            while let Some((cell, next)) = stack.last_mut() {
+                match next.pop() {
+                    Some(next) if !visited[next] => {
+                        visited.set(next, true);
+                        stack.push((next, self.successors(next).collect()));
+                    }
+                    Some(_) => {}
+                    None => {
+                        finished.push(*cell);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        // Reversed finishing order is topological for all but the loop closing wires.
+        let mut rank = vec![0; count];
+        for (position, &cell) in finished.iter().rev().enumerate() {
+            rank[cell] = position;
+        }
+        let mut levels = vec![0; count];
+        for &cell in finished.iter().rev() {
+            for next in self.successors(cell) {
+                if rank[next] > rank[cell] {
+                    levels[next] = levels[next].max(levels[cell] + 1);
+                }
+            }
+        }
+        for (cell, level) in self.cells.iter_mut().zip(levels) {
+            cell.level = level;
+        }
+
+        let level_count = self
+            .cells
+            .iter()
+            .map(|cell| cell.level + 1)
+            .max()
+            .unwrap_or(0);
+        self.queue = vec![Vec::new(); level_count];
+        self.queued = vec![false; count];
+    }
+
+    /// Whether a cell only changes its state on a clock edge.
+    fn is_clocked(&self, cell: usize) -> bool {
+        let Op::Stateful(index) = self.cells[cell].op else {
+            return false;
+        };
+        let trigger = match &self.stateful[index] {
+            Component::FlipFlop(flip_flop) => flip_flop.trigger,
+            Component::Ram(ram) => ram.trigger,
+            _ => return false,
+        };
+        matches!(trigger, Trigger::RisingEdge | Trigger::FallingEdge)
+    }
+
+    fn schedule(&mut self, cell: usize) {
+        if self.clocked[cell] {
+            self.pending.insert(cell);
+        } else if !self.queued[cell] {
+            self.queued[cell] = true;
+            let level = self.cells[cell].level;
+            if self.queue[level].is_empty() {
+                self.levels.push(Reverse(level));
+            }
+            self.queue[level].push(cell);
+        }
+    }
+
+    /// Evaluates the scheduled cells, lowest level first, until nothing changes,
+    /// then the clocked cells, in rounds.
+    fn settle(&mut self) {
+        // Every cell once, and as many evaluations as a graph propagation on top.
+        let limit = self.cells.len() + MAX_PROPAGATION_DEPTH;
+        let mut evaluations = 0;
+        self.overflowed = false;
+        loop {
+            while let Some(&Reverse(level)) = self.levels.peek() {
+                let cell = self.queue[level].pop().unwrap();
+                if self.queue[level].is_empty() {
+                    self.levels.pop();
+                }
+                self.queued[cell] = false;
+
+                evaluations += 1;
+                if evaluations > limit {
+                    self.overflow();
+                    return;
+                }
+                self.compute(cell);
+                self.drive(cell);
+            }
+            if self.pending.is_empty() {
+                return;
+            }
+
+            // All clocked cells see the inputs of the round before any of them changes.
+            let pending = std::mem::take(&mut self.pending);
+            evaluations += pending.len();
+            if evaluations > limit {
+                self.overflow();
+                return;
+            }
+            for &cell in &pending {
+                self.compute(cell);
+            }
+            for &cell in &pending {
+                self.drive(cell);
+            }
+        }
+    }
+
+    /// Gives up the settle, dropping the cells left to evaluate.
+    fn overflow(&mut self) {
+        self.overflowed = true;
+        self.clear_queue();
+    }
+
+    fn clear_queue(&mut self) {
+        self.queue.iter_mut().for_each(Vec::clear);
+        self.queued.fill(false);
+        self.levels.clear();
+        self.pending.clear();
+    }
+
+    /// Drives the wires from the outputs of a cell, scheduling the cells they change.
+    fn drive(&mut self, index: usize) {
+        for bit in self.cells[index].outputs.clone() {
+            let Some(target) = self.wires[bit] else {
+                continue;
+            };
+            if self.receive(target, self.outputs[bit], self.released[bit]) {
+                self.schedule(self.owners[target]);
+            }
+        }
+    }
+
+    /// Sets an input bit from the output driving it, the input floating when the
+    /// output is released, returns whether it changed.
+    fn receive(&mut self, input: usize, value: bool, released: bool) -> bool {
+        let value = if released {
+            self.floating[input]
+        } else {
+            value
+        };
+        let changed = self.inputs[input] != value || self.undriven[input] != released;
+        self.inputs[input] = value;
+        self.undriven.set(input, released);
+        changed
+    }
+
+    /// Sets the outputs of a cell from its inputs, without driving the wires.
+    fn compute(&mut self, index: usize) {
+        let Cell {
+            op,
+            inputs,
+            outputs,
+            faults,
+            terminals,
+            ..
+        } = self.cells[index].clone();
+        let faults = &self.faults[faults];
+        for &(pin, value) in faults {
+            if let Pin::Input(slot) = pin {
+                let bit = inputs.start + slot;
+                if let Some(value) = value.value() {
+                    self.inputs[bit] = value;
+                }
+                self.undriven.set(bit, value.value().is_none());
+            }
+        }
+        // Unconnected inputs float, whatever they were left with when disconnected.
+        for bit in self.undriven[inputs.clone()].iter_ones() {
+            self.inputs[inputs.start + bit] = self.floating[inputs.start + bit];
+        }
+
+        let input = &self.inputs[inputs.clone()];
+        let undriven = &self.undriven[inputs.clone()];
+        let output = &mut self.outputs[outputs.clone()];
+        let released = &mut self.released[outputs];
+        released.fill(false);
+        match op {
+            Op::Constant => output[0] = self.constants[index],
+            Op::InputPort => output[0] = input[0],
+            Op::Output => {
+                if let Some(output) = output.first_mut() {
+                    *output = input[0];
+                }
+            }
+            Op::And => output[0] = input[0] && input[1],
+            Op::Or => output[0] = input[0] || input[1],
+            Op::Xor => output[0] = input[0] ^ input[1],
+            Op::Not => output[0] = !input[0],
+            Op::Fork => {
+                output.fill(input.contains(&true));
+                released.fill(undriven.all());
+                // Bidirectional pins read the line as the other inputs leave it.
+                for &(slot, except) in &self.terminals[terminals] {
+                    let mut others = (0..input.len()).filter(|&bit| bit != except);
+                    output[slot] = others.clone().any(|bit| input[bit]);
+                    released.set(slot, others.all(|bit| undriven[bit]));
+                }
+            }
+            Op::Stateful(stateful) => {
+                let last_input = &mut self.last_inputs[inputs];
+                let [bits, result, mask] = &mut self.scratch;
+                bits.clear();
+                bits.extend(input);
+                result.resize(output.len(), false);
+                mask.clear();
+                mask.resize(output.len(), true);
+                let component = &mut self.stateful[stateful];
+                component.propagate(last_input, bits, result, mask);
+                component.release(undriven, mask);
+                last_input.copy_from_bitslice(bits);
+                for (output, bit) in output.iter_mut().zip(result.iter().by_vals()) {
+                    *output = bit;
+                }
+                for (mut released, masked) in released.iter_mut().zip(mask.iter().by_vals()) {
+                    *released = !masked;
+                }
+            }
+        }
+        for &(pin, value) in faults {
+            if let Pin::Output(slot) = pin {
+                output[slot] = value.value().unwrap_or(false);
+                released.set(slot, value.value().is_none());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        components::{
+            gates::{And, Not, Or, Xor},
+            memory::{FlipFlop, FlipFlopKind, Rom},
+            simple::Fork,
+            subcircuit::Subcircuit,
+            wired::{OpenCollector, PullResistor, Transceiver},
+        },
+        graph::fault::Fault,
+    };
+
+    fn full_adder() -> Subcircuit {
+        let mut graph = Graph::new();
+        let [a, b, c] = [(); 3].map(|_| graph.add_comp(Constant::default()));
+        let [fork_a, fork_b, fork_c, fork_x] = [(); 4].map(|_| graph.add_comp(Fork::new(1, 2)));
+        let [xor_ab, xor_c] = [(); 2].map(|_| graph.add_comp(Xor));
+        let [and_ab, and_c] = [(); 2].map(|_| graph.add_comp(And));
+        let or = graph.add_comp(Or);
+        let [sum, carry] = [(); 2].map(|_| graph.add_comp(DebugOutput::default()));
+        graph.add_conn(a, 0, fork_a, 0);
+        graph.add_conn(b, 0, fork_b, 0);
+        graph.add_conn(c, 0, fork_c, 0);
+        graph.add_conn(fork_a, 0, xor_ab, 0);
+        graph.add_conn(fork_b, 0, xor_ab, 1);
+        graph.add_conn(fork_a, 1, and_ab, 0);
+        graph.add_conn(fork_b, 1, and_ab, 1);
+        graph.add_conn(xor_ab, 0, fork_x, 0);
+        graph.add_conn(fork_x, 0, xor_c, 0);
+        graph.add_conn(fork_c, 0, xor_c, 1);
+        graph.add_conn(fork_x, 1, and_c, 0);
+        graph.add_conn(fork_c, 1, and_c, 1);
+        graph.add_conn(and_ab, 0, or, 0);
+        graph.add_conn(and_c, 0, or, 1);
+        graph.add_conn(xor_c, 0, sum, 0);
+        graph.add_conn(or, 0, carry, 0);
+        Subcircuit::new("full_adder", graph, vec![a, b, c], vec![sum, carry])
+    }
+
+    /// Adds a register `q` adding `a` on every rising edge of `clock`,
+    /// with names starting with `prefix`.
+    pub(super) fn add_accumulator(graph: &mut Graph, width: usize, prefix: &str) {
+        let clock = graph.add_comp(Constant::default());
+        let clock_fork = graph.add_comp(Fork::new(1, width as u8));
+        graph.add_conn(clock, 0, clock_fork, 0);
+        graph.set_name(clock, format!("{prefix}clock"));
+        let mut carry = None;
+        for bit in 0..width {
+            let a = graph.add_comp(Constant::default());
+            let adder = graph.add_comp(full_adder());
+            let register = graph.add_comp(FlipFlop::new(FlipFlopKind::D));
+            let fork = graph.add_comp(Fork::new(1, 2));
+            let q = graph.add_comp(DebugOutput::default());
+            graph.add_conn(a, 0, adder, 0);
+            graph.add_conn(fork, 0, adder, 1);
+            if let Some(carry) = carry {
+                graph.add_conn(carry, 1, adder, 2);
+            }
+            graph.add_conn(adder, 0, register, 0);
+            graph.add_conn(clock_fork, bit, register, 1);
+            graph.add_conn(register, 0, fork, 0);
+            graph.add_conn(fork, 1, q, 0);
+            graph.set_name(a, format!("{prefix}a{bit}"));
+            graph.set_name(q, format!("{prefix}q{bit}"));
+            carry = Some(adder);
+        }
+    }
+
+    /// Adds a wired-AND of open collectors `a` and `b` read through a pull-up as `y`,
+    /// and a transceiver between a bus driven by `d` and read as `p` and a pulled up
+    /// bus also driven by an open collector `e` and read as `q`, with `dir` and `oe`,
+    /// with names starting with `prefix`.
+    pub(super) fn add_wired(graph: &mut Graph, prefix: &str) {
+        let [a, b, d, e, dir, oe] = [(); 6].map(|_| graph.add_comp(Constant::default()));
+        let [oc_a, oc_b, oc_e] = [(); 3].map(|_| graph.add_comp(OpenCollector));
+        let line = graph.add_comp(Fork::new(2, 1));
+        let [pull_y, pull_q] = [(); 2].map(|_| graph.add_comp(PullResistor::up()));
+        let transceiver = graph.add_comp(Transceiver::new(1));
+        let [bus_a, bus_b] = [(); 2].map(|_| graph.add_comp(Fork::new(2, 2)));
+        let [y, p, q] = [(); 3].map(|_| graph.add_comp(DebugOutput::default()));
+        graph.add_conn(a, 0, oc_a, 0);
+        graph.add_conn(b, 0, oc_b, 0);
+        graph.add_conn(oc_a, 0, line, 0);
+        graph.add_conn(oc_b, 0, line, 1);
+        graph.add_conn(line, 0, pull_y, 0);
+        graph.add_conn(pull_y, 0, y, 0);
+        graph.add_conn(d, 0, bus_a, 0);
+        graph.add_conn(transceiver, 0, bus_a, 1);
+        graph.add_conn(bus_a, 0, transceiver, 0);
+        graph.add_conn(bus_a, 1, p, 0);
+        graph.add_conn(e, 0, oc_e, 0);
+        graph.add_conn(transceiver, 1, bus_b, 0);
+        graph.add_conn(oc_e, 0, bus_b, 1);
+        graph.add_conn(bus_b, 0, transceiver, 1);
+        graph.add_conn(bus_b, 1, pull_q, 0);
+        graph.add_conn(pull_q, 0, q, 0);
+        graph.add_conn(dir, 0, transceiver, 2);
+        graph.add_conn(oe, 0, transceiver, 3);
+        for (id, name) in [
+            (a, "a"),
+            (b, "b"),
+            (d, "d"),
+            (e, "e"),
+            (dir, "dir"),
+            (oe, "oe"),
+        ] {
+            graph.set_name(id, format!("{prefix}{name}"));
+        }
+        for (id, name) in [(y, "y"), (p, "p"), (q, "q")] {
+            graph.set_name(id, format!("{prefix}{name}"));
+        }
+    }
+
+    #[test]
+    fn matches_graph_propagation() {
+        let mut graph = Graph::new();
+        add_accumulator(&mut graph, 6, "");
+        add_wired(&mut graph, "w");
+        graph.propagate_all();
+        let id = |name: String| graph.find_by_name(&name).unwrap();
+        let clock: TypedId<Constant> = id("clock".to_string()).into();
+        let wired = ["a", "b", "d", "e", "dir", "oe"].map(|name| id(format!("w{name}")));
+        let addend = (0..6)
+            .map(|bit| id(format!("a{bit}")))
+            .chain(wired)
+            .map(TypedId::from)
+            .collect::<Vec<_>>();
+        let outputs: Vec<TypedId<DebugOutput>> = (0..6)
+            .map(|bit| id(format!("q{bit}")))
+            .chain(["y", "p", "q"].map(|name| id(format!("w{name}"))))
+            .map(TypedId::from)
+            .collect();
+        let original = graph.clone();
+        let mut compiled = CompiledGraph::compile(&graph);
+        assert!(compiled.level_count() > 6);
+
+        let mut seed = 7u32;
+        for _ in 0..600 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let input = match seed >> 16 & 1 {
+                0 => clock,
+                _ => addend[(seed >> 8) as usize % addend.len()],
+            };
+            let state = !graph[input].state;
+            graph[input].state = state;
+            graph.propagate_from(input);
+            compiled.set_constant(input, state);
+            for &output in &outputs {
+                assert_eq!(compiled.output(output), graph[output].state);
+            }
+        }
+
+        let mut written = original;
+        compiled.write_back(&mut written);
+        for (id, node) in &graph.nodes {
+            assert_eq!(written.inputs[id], graph.inputs[id]);
+            assert_eq!(written.outputs[id], graph.outputs[id]);
+            assert_eq!(written.released[id], graph.released[id]);
+            let Component::Subcircuit(sub) = &node.component else {
+                continue;
+            };
+            let Component::Subcircuit(written) = &written[id] else {
+                unreachable!();
+            };
+            for (inner, _) in &sub.graph.nodes {
+                assert_eq!(written.graph.inputs[inner], sub.graph.inputs[inner]);
+                assert_eq!(written.graph.outputs[inner], sub.graph.outputs[inner]);
+            }
+        }
+    }
+
+    #[test]
+    fn clock_edges_sample_data_from_before_the_edge() {
+        let mut graph = Graph::new();
+        let clock = graph.add_comp(Constant::default());
+        let data = graph.add_comp(Constant { state: true });
+        let clock_fork = graph.add_comp(Fork::new(1, 2));
+        let [first, second] = [(); 2].map(|_| graph.add_comp(FlipFlop::new(FlipFlopKind::D)));
+        let fork = graph.add_comp(Fork::new(1, 2));
+        let [q1, q2] = [(); 2].map(|_| graph.add_comp(DebugOutput::default()));
+        graph.add_conn(clock, 0, clock_fork, 0);
+        graph.add_conn(clock_fork, 0, first, 1);
+        graph.add_conn(clock_fork, 1, second, 1);
+        graph.add_conn(data, 0, first, 0);
+        graph.add_conn(first, 0, fork, 0);
+        graph.add_conn(fork, 0, second, 0);
+        graph.add_conn(fork, 1, q1, 0);
+        graph.add_conn(second, 0, q2, 0);
+        graph.propagate_all();
+
+        let mut compiled = CompiledGraph::compile(&graph);
+        let mut partitioned = partition::PartitionedGraph::new(&graph);
+        let mut set = |input: TypedId<Constant>, state: bool| {
+            graph[input].state = state;
+            graph.propagate_from(input);
+            compiled.set_constant(input, state);
+            partitioned.set_constant(input, state);
+            let expected = [graph[q1].state, graph[q2].state];
+            assert_eq!([compiled.output(q1), compiled.output(q2)], expected);
+            assert_eq!([partitioned.output(q1), partitioned.output(q2)], expected);
+            expected
+        };
+
+        assert_eq!(set(clock, true), [true, false]);
+        set(clock, false);
+        set(data, false);
+        assert_eq!(set(clock, true), [false, true]);
+        set(clock, false);
+        assert_eq!(set(clock, true), [false, false]);
+    }
+
+    #[test]
+    fn oscillation_is_reported() {
+        let mut graph = Graph::new();
+        let enable = graph.add_comp(Constant::default());
+        let and = graph.add_comp(And);
+        let not = graph.add_comp(Not);
+        graph.add_conn(enable, 0, and, 0);
+        graph.add_conn(and, 0, not, 0);
+        graph.add_conn(not, 0, and, 1);
+        graph.propagate_all();
+
+        let mut compiled = CompiledGraph::compile(&graph);
+        let mut partitioned = partition::PartitionedGraph::new(&graph);
+        compiled.set_constant(enable, true);
+        partitioned.set_constant(enable, true);
+        assert!(compiled.overflowed() && partitioned.overflowed());
+        compiled.set_constant(enable, false);
+        partitioned.set_constant(enable, false);
+        assert!(!compiled.overflowed() && !partitioned.overflowed());
+    }
+
+    #[test]
+    fn faults_and_memories() {
+        let mut graph = Graph::new();
+        let address = [(); 2].map(|_| graph.add_comp(Constant::default()));
+        let rom = graph.add_comp(Rom::new(2, 2, vec![0b01, 0b10, 0b11, 0b00]));
+        let outputs = [(); 2].map(|_| graph.add_comp(DebugOutput::default()));
+        for bit in 0..2 {
+            graph.add_conn(address[bit], 0, rom, bit);
+            graph.add_conn(rom, bit, outputs[bit], 0);
+        }
+        graph.propagate_all();
+        graph.inject_fault(Fault {
+            node: rom.into(),
+            pin: Pin::Input(1),
+            value: FaultValue::StuckAt(true),
+        });
+
+        let mut compiled = CompiledGraph::compile(&graph);
+        for value in [3, 1, 2, 0] {
+            for bit in 0..2 {
+                let state = value >> bit & 1 == 1;
+                graph[address[bit]].state = state;
+                graph.propagate_from(address[bit]);
+                compiled.set_constant(address[bit], state);
+            }
+            for output in outputs {
+                assert_eq!(compiled.output(output), graph[output].state);
+            }
+        }
+        // Address 0 reads word 2 with the fault.
+        assert!(compiled.output(outputs[0]) && compiled.output(outputs[1]));
+    }
+
+    #[test]
+    fn floating_faults_read_as_undriven() {
+        let mut graph = Graph::new();
+        let [a, b] = [(); 2].map(|_| graph.add_comp(Constant { state: true }));
+        let not = graph.add_comp(Not);
+        let fork = graph.add_comp(Fork::new(1, 1));
+        let pull_up = graph.add_comp(PullResistor::up());
+        let pull_down = graph.add_comp(PullResistor::down());
+        let [y, z] = [(); 2].map(|_| graph.add_comp(DebugOutput::default()));
+        graph.add_conn(a, 0, not, 0);
+        graph.add_conn(not, 0, pull_up, 0);
+        graph.add_conn(pull_up, 0, y, 0);
+        graph.add_conn(b, 0, fork, 0);
+        graph.add_conn(fork, 0, pull_down, 0);
+        graph.add_conn(pull_down, 0, z, 0);
+        graph.propagate_all();
+        assert!(!graph[y].state && graph[z].state);
+
+        let floating = |node: ComponentId, pin| Fault {
+            node,
+            pin,
+            value: FaultValue::Floating,
+        };
+        graph.inject_fault(floating(not.into(), Pin::Output(0)));
+        graph.inject_fault(floating(fork.into(), Pin::Input(0)));
+        assert!(graph[y].state && !graph[z].state);
+
+        let mut compiled = CompiledGraph::compile(&graph);
+        compiled.propagate_all();
+        assert!(compiled.output(y) && !compiled.output(z));
+        for state in [false, true] {
+            graph[a].state = state;
+            graph.propagate_from(a);
+            compiled.set_constant(a, state);
+            assert!(graph[y].state && compiled.output(y));
+        }
+    }
+}
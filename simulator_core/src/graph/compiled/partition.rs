@@ -0,0 +1,413 @@
+//! Compiled graphs split into regions joined only through clocked elements,
+//! settled in parallel.
+//!
+//! Edge triggered flip-flops and memories are taken out of the graph, and the
+//! cells left fall into regions with no wire between them. A settle goes in rounds:
+//! the regions with changes settle side by side, the clocked cells whose inputs
+//! changed are evaluated in index order, then their outputs go to the regions for
+//! the next round. A region only reads its own cells while settling, so results
+//! do not depend on the number of threads and are those of a single thread.
+//!
+//! Regions settle on the rayon thread pool, one after another on wasm.
+
+use std::{collections::BTreeSet, ops::Range};
+
+use bitvec::vec::BitVec;
+
+#[cfg(not(target_arch = "wasm32"))]
+use rayon::prelude::*;
+
+use crate::components::simple::{Constant, DebugOutput};
+
+use super::{Cell, CompiledGraph, Op};
+use crate::graph::{
+    id::{ComponentId, TypedId},
+    Graph, MAX_PROPAGATION_DEPTH,
+};
+
+/// Cells connected to each other without a clocked element in between.
+#[derive(Debug, Clone)]
+struct Region {
+    graph: CompiledGraph,
+    /// Cell of the whole graph for each cell of the region.
+    cells: Vec<usize>,
+    /// Output bits of the region driving inputs of clocked cells of the whole graph.
+    exports: Vec<(usize, usize)>,
+}
+
+/// A compiled graph simulated one region per thread.
+#[derive(Debug, Clone)]
+pub struct PartitionedGraph {
+    /// The whole graph, simulating the clocked cells.
+    compiled: CompiledGraph,
+    regions: Vec<Region>,
+    /// Region and cell in it of each cell, `None` for clocked cells.
+    places: Vec<Option<(usize, usize)>>,
+    /// Clocked cells to evaluate in the next round.
+    pending: BTreeSet<usize>,
+    /// Whether the last settle was cut short.
+    overflowed: bool,
+}
+
+impl PartitionedGraph {
+    /// Compiles and splits the graph, starting from its current state.
+    pub fn new(graph: &Graph) -> Self {
+        Self::partition(CompiledGraph::compile(graph))
+    }
+
+    pub fn partition(compiled: CompiledGraph) -> Self {
+        let count = compiled.cells.len();
+        let clocked = compiled.clocked.clone();
+
+        let mut roots = (0..count).collect::<Vec<_>>();
+        for cell in (0..count).filter(|&cell| !clocked[cell]) {
+            for next in compiled.successors(cell) {
+                if !clocked[next] {
+                    let (a, b) = (find(&mut roots, cell), find(&mut roots, next));
+                    roots[a.max(b)] = a.min(b);
+                }
+            }
+        }
+
+        // Regions in order of their first cell.
+        let mut places = vec![None; count];
+        let mut members: Vec<Vec<usize>> = Vec::new();
+        for cell in (0..count).filter(|&cell| !clocked[cell]) {
+            let root = find(&mut roots, cell);
+            let region = match places[root] {
+                Some((region, _)) => region,
+                None => {
+                    members.push(Vec::new());
+                    members.len() - 1
+                }
+            };
+            places[cell] = Some((region, members[region].len()));
+            members[region].push(cell);
+        }
+
+        let regions = members
+            .into_iter()
+            .map(|cells| Region::extract(&compiled, &places, cells))
+            .collect();
+        PartitionedGraph {
+            compiled,
+            regions,
+            places,
+            pending: BTreeSet::new(),
+            overflowed: false,
+        }
+    }
+
+    pub fn region_count(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn clocked_count(&self) -> usize {
+        self.places.iter().filter(|place| place.is_none()).count()
+    }
+
+    /// Whether the last settle was cut short after too many rounds,
+    /// or a region after too many evaluations, as happens to a loop which never settles.
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// State of a `Constant` of the top graph.
+    pub fn constant(&self, id: TypedId<Constant>) -> bool {
+        let (region, cell) = self.place(id.into());
+        self.regions[region].graph.constants[cell]
+    }
+
+    /// Sets a `Constant` of the top graph and propagates its new state.
+    pub fn set_constant(&mut self, id: TypedId<Constant>, state: bool) {
+        let (region, cell) = self.place(id.into());
+        let graph = &mut self.regions[region].graph;
+        graph.constants.set(cell, state);
+        graph.schedule(cell);
+        self.settle();
+    }
+
+    /// State of a `DebugOutput` of the top graph.
+    pub fn output(&self, id: TypedId<DebugOutput>) -> bool {
+        let (region, cell) = self.place(id.into());
+        let graph = &self.regions[region].graph;
+        graph.inputs[graph.cells[cell].inputs.start]
+    }
+
+    pub fn propagate_all(&mut self) {
+        for region in &mut self.regions {
+            for cell in 0..region.cells.len() {
+                region.graph.schedule(cell);
+            }
+        }
+        let clocked = self.places.iter().enumerate();
+        self.pending
+            .extend(clocked.filter_map(|(cell, place)| place.is_none().then_some(cell)));
+        self.settle();
+    }
+
+    /// Copies the simulation state into the graph the partitioned one was made from.
+    pub fn write_back(&self, graph: &mut Graph) {
+        let mut compiled = self.compiled.clone();
+        for region in &self.regions {
+            let local = &region.graph;
+            for (index, &cell) in region.cells.iter().enumerate() {
+                let (from, to) = (&local.cells[index], &compiled.cells[cell]);
+                let inputs = to.inputs.clone();
+                compiled.inputs[inputs.clone()].copy_from_slice(&local.inputs[from.inputs.clone()]);
+                compiled.last_inputs[inputs]
+                    .copy_from_bitslice(&local.last_inputs[from.inputs.clone()]);
+                compiled.outputs[to.outputs.clone()]
+                    .copy_from_slice(&local.outputs[from.outputs.clone()]);
+                compiled.released[to.outputs.clone()]
+                    .copy_from_bitslice(&local.released[from.outputs.clone()]);
+                compiled.constants.set(cell, local.constants[index]);
+                if let (Op::Stateful(from), Op::Stateful(to)) = (from.op, to.op) {
+                    compiled.stateful[to] = local.stateful[from].clone();
+                }
+            }
+        }
+        compiled.write_back(graph);
+    }
+
+    /// Region and cell in it of a node of the top graph.
+    fn place(&self, node: ComponentId) -> (usize, usize) {
+        let cell = self.compiled.instances[0].cells[node];
+        self.places[cell].expect("constants and outputs are not clocked")
+    }
+
+    /// Settles the regions and the clocked cells in rounds until nothing changes.
+    fn settle(&mut self) {
+        self.overflowed = false;
+        for _ in 0..MAX_PROPAGATION_DEPTH {
+            self.overflowed |= settle_regions(&mut self.regions);
+
+            let compiled = &mut self.compiled;
+            for region in &self.regions {
+                for &(bit, input) in &region.exports {
+                    let (value, released) = (region.graph.outputs[bit], region.graph.released[bit]);
+                    if compiled.receive(input, value, released) {
+                        self.pending.insert(compiled.owners[input]);
+                    }
+                }
+            }
+            if self.pending.is_empty() {
+                return;
+            }
+
+            // All clocked cells see the inputs of the round before any of them changes.
+            let pending = std::mem::take(&mut self.pending);
+            for &cell in &pending {
+                self.compiled.compute(cell);
+            }
+            for &cell in &pending {
+                self.deliver(cell);
+            }
+        }
+        self.overflowed = true;
+        self.pending.clear();
+        for region in &mut self.regions {
+            region.graph.clear_queue();
+        }
+    }
+
+    /// Drives the wires from the outputs of a clocked cell.
+    fn deliver(&mut self, cell: usize) {
+        let compiled = &mut self.compiled;
+        for bit in compiled.cells[cell].outputs.clone() {
+            let Some(target) = compiled.wires[bit] else {
+                continue;
+            };
+            let (value, released) = (compiled.outputs[bit], compiled.released[bit]);
+            let owner = compiled.owners[target];
+            match self.places[owner] {
+                None => {
+                    if compiled.receive(target, value, released) {
+                        self.pending.insert(owner);
+                    }
+                }
+                Some((region, local)) => {
+                    let graph = &mut self.regions[region].graph;
+                    let slot = target - compiled.cells[owner].inputs.start;
+                    let input = graph.cells[local].inputs.start + slot;
+                    if graph.receive(input, value, released) {
+                        graph.schedule(local);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Region {
+    /// Copies the cells of a region out of the whole graph, in the same order.
+    fn extract(
+        compiled: &CompiledGraph,
+        places: &[Option<(usize, usize)>],
+        cells: Vec<usize>,
+    ) -> Self {
+        let mut graph = CompiledGraph::default();
+        for &cell in &cells {
+            let Cell {
+                op,
+                inputs,
+                outputs,
+                faults,
+                terminals,
+                ..
+            } = compiled.cells[cell].clone();
+            let op = match op {
+                Op::Stateful(index) => {
+                    graph.stateful.push(compiled.stateful[index].clone());
+                    Op::Stateful(graph.stateful.len() - 1)
+                }
+                op => op,
+            };
+            graph.push_cell(
+                op,
+                compiled.inputs[inputs].iter().copied(),
+                compiled.outputs[outputs].iter().copied(),
+                &compiled.faults[faults],
+                &compiled.terminals[terminals],
+                compiled.constants[cell],
+            );
+        }
+
+        let mut exports = Vec::new();
+        for (index, &cell) in cells.iter().enumerate() {
+            for (slot, bit) in compiled.cells[cell].outputs.clone().enumerate() {
+                let Some(target) = compiled.wires[bit] else {
+                    continue;
+                };
+                let local = graph.cells[index].outputs.start + slot;
+                let owner = compiled.owners[target];
+                match places[owner] {
+                    Some((_, next)) => {
+                        let input = target - compiled.cells[owner].inputs.start;
+                        graph.wires[local] = Some(graph.cells[next].inputs.start + input);
+                    }
+                    None => exports.push((local, target)),
+                }
+            }
+        }
+
+        let copy = |bits: &BitVec, slots: fn(&Cell) -> Range<usize>| {
+            let cells = cells.iter().map(|&cell| &compiled.cells[cell]);
+            cells
+                .flat_map(|cell| bits[slots(cell)].iter().by_vals())
+                .collect()
+        };
+        graph.last_inputs = copy(&compiled.last_inputs, |cell| cell.inputs.clone());
+        graph.floating = copy(&compiled.floating, |cell| cell.inputs.clone());
+        graph.undriven = copy(&compiled.undriven, |cell| cell.inputs.clone());
+        graph.released = copy(&compiled.released, |cell| cell.outputs.clone());
+        graph.levelize();
+        Region {
+            graph,
+            cells,
+            exports,
+        }
+    }
+}
+
+/// Root of the set of a cell, halving the paths on the way.
+fn find(roots: &mut [usize], mut cell: usize) -> usize {
+    while roots[cell] != cell {
+        roots[cell] = roots[roots[cell]];
+        cell = roots[cell];
+    }
+    cell
+}
+
+/// Settles the regions with changes, returns whether one of them overflowed.
+#[cfg(not(target_arch = "wasm32"))]
+fn settle_regions(regions: &mut [Region]) -> bool {
+    regions
+        .par_iter_mut()
+        .filter(|region| !region.graph.levels.is_empty())
+        .map(|region| {
+            region.graph.settle();
+            region.graph.overflowed
+        })
+        .reduce(|| false, |a, b| a || b)
+}
+
+/// Settles the regions with changes, returns whether one of them overflowed.
+#[cfg(target_arch = "wasm32")]
+fn settle_regions(regions: &mut [Region]) -> bool {
+    regions
+        .iter_mut()
+        .filter(|region| !region.graph.levels.is_empty())
+        .map(|region| {
+            region.graph.settle();
+            region.graph.overflowed
+        })
+        .fold(false, |a, b| a || b)
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::graph::compiled::tests::{add_accumulator, add_wired};
+
+    #[test]
+    fn matches_compiled_graph() {
+        let mut graph = Graph::new();
+        for prefix in ["x", "y", "z"] {
+            add_accumulator(&mut graph, 4, prefix);
+        }
+        add_wired(&mut graph, "w");
+        graph.propagate_all();
+        let id = |name: String| graph.find_by_name(&name).unwrap();
+        let mut constants: Vec<TypedId<Constant>> = Vec::new();
+        let mut outputs: Vec<TypedId<DebugOutput>> = Vec::new();
+        for prefix in ["x", "y", "z"] {
+            constants.push(id(format!("{prefix}clock")).into());
+            for bit in 0..4 {
+                constants.push(id(format!("{prefix}a{bit}")).into());
+                outputs.push(id(format!("{prefix}q{bit}")).into());
+            }
+        }
+        for name in ["a", "b", "d", "e", "dir", "oe"] {
+            constants.push(id(format!("w{name}")).into());
+        }
+        for name in ["y", "p", "q"] {
+            outputs.push(id(format!("w{name}")).into());
+        }
+
+        let mut compiled = CompiledGraph::compile(&graph);
+        let mut partitioned = PartitionedGraph::new(&graph);
+        assert!(partitioned.region_count() >= 6);
+        assert_eq!(partitioned.clocked_count(), 12);
+
+        let single = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let mut sequential = PartitionedGraph::new(&graph);
+
+        let mut seed = 11u32;
+        for _ in 0..400 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let input = constants[(seed >> 8) as usize % constants.len()];
+            let state = !compiled.constant(input);
+            compiled.set_constant(input, state);
+            partitioned.set_constant(input, state);
+            single.install(|| sequential.set_constant(input, state));
+            for &output in &outputs {
+                assert_eq!(partitioned.output(output), compiled.output(output));
+                assert_eq!(partitioned.output(output), sequential.output(output));
+            }
+        }
+
+        let [mut expected, mut parallel, mut single_threaded] = [(); 3].map(|_| graph.clone());
+        compiled.write_back(&mut expected);
+        partitioned.write_back(&mut parallel);
+        sequential.write_back(&mut single_threaded);
+        assert_eq!(parallel.inputs, single_threaded.inputs);
+        assert_eq!(parallel.outputs, single_threaded.outputs);
+        for &output in &outputs {
+            assert_eq!(parallel[output].state, expected[output].state);
+        }
+    }
+}
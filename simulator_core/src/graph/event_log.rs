@@ -0,0 +1,211 @@
+//! Timestamped input events saved as text, to be replayed later.
+//!
+//! Events are written one per line as the milliseconds since recording started
+//! followed by `set <node> <0|1>` for a `Constant` or `write <node> <address> <value>`
+//! for a word of a `Ram`. Clock ticks are `set` events of the clock. Named nodes are
+//! written by name so a log can be replayed on an edited circuit, the others as `@`
+//! and their id. `#` starts a comment.
+
+use std::{error::Error, fmt::Display};
+
+use super::{
+    history::InputEvent,
+    id::{ComponentId, Key, KeyData},
+    Graph,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for LogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for LogError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedEvent {
+    /// Milliseconds since the recording started.
+    pub millis: u64,
+    pub event: InputEvent,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventLog {
+    pub events: Vec<TimedEvent>,
+}
+
+impl EventLog {
+    pub fn push(&mut self, millis: u64, event: InputEvent) {
+        self.events.push(TimedEvent { millis, event });
+    }
+
+    /// Applies the events in order, whatever their timestamps.
+    pub fn replay(&self, graph: &mut Graph) {
+        for timed in &self.events {
+            timed.event.apply(graph);
+        }
+    }
+
+    /// Events in the text format, naming the nodes of `graph`.
+    pub fn to_text(&self, graph: &Graph) -> String {
+        let mut text = String::new();
+        for &TimedEvent { millis, event } in &self.events {
+            let line = match event {
+                InputEvent::SetConstant { node, state } => {
+                    format!("{millis} set {} {}", node_name(graph, node), state as u8)
+                }
+                InputEvent::WriteMemory {
+                    node,
+                    address,
+                    value,
+                } => format!(
+                    "{millis} write {} {address} {value}",
+                    node_name(graph, node)
+                ),
+            };
+            text.push_str(&line);
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Reads a log, finding its nodes in `graph`.
+    pub fn parse(source: &str, graph: &Graph) -> Result<Self, LogError> {
+        let mut log = EventLog::default();
+        for (line, text) in source.lines().enumerate() {
+            let error = |message: String| LogError {
+                line: line + 1,
+                message,
+            };
+            let text = text.split('#').next().unwrap_or_default();
+            let words = text.split_whitespace().collect::<Vec<_>>();
+            let number = |word: &str| {
+                word.parse::<u64>()
+                    .map_err(|_| error(format!("`{word}` is not a number")))
+            };
+            let node = |word: &str| {
+                find_node(graph, word).ok_or_else(|| error(format!("no node `{word}`")))
+            };
+
+            let event = match words[..] {
+                [] => continue,
+                [_, "set", name, state] => InputEvent::SetConstant {
+                    node: node(name)?,
+                    state: match state {
+                        "0" => false,
+                        "1" => true,
+                        _ => return Err(error(format!("`{state}` is not a value"))),
+                    },
+                },
+                [_, "write", name, address, value] => InputEvent::WriteMemory {
+                    node: node(name)?,
+                    address: number(address)? as usize,
+                    value: number(value)?,
+                },
+                _ => return Err(error(format!("`{}` is not an event", text.trim()))),
+            };
+            log.push(number(words[0])?, event);
+        }
+        Ok(log)
+    }
+}
+
+/// Name of a node when it identifies it in the text format, otherwise its id.
+fn node_name(graph: &Graph, node: ComponentId) -> String {
+    match graph.name(node) {
+        Some(name)
+            if !name.is_empty()
+                && !name.starts_with('@')
+                && !name.contains(|c: char| c.is_whitespace() || c == '#')
+                && graph.find_by_name(name) == Some(node) =>
+        {
+            name.to_string()
+        }
+        _ => format!("@{}", node.data().as_ffi()),
+    }
+}
+
+fn find_node(graph: &Graph, word: &str) -> Option<ComponentId> {
+    match word.strip_prefix('@') {
+        Some(id) => {
+            let id = ComponentId::from(KeyData::from_ffi(id.parse().ok()?));
+            graph.nodes.contains_key(id).then_some(id)
+        }
+        None => graph.find_by_name(word),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{
+        gates::And,
+        memory::Ram,
+        simple::{Constant, DebugOutput},
+    };
+
+    #[test]
+    fn replays_saved_log() {
+        let mut graph = Graph::new();
+        let a = graph.add_comp(Constant::default());
+        let b = graph.add_comp(Constant::default());
+        let and = graph.add_comp(And);
+        let ram = graph.add_comp(Ram::new(1, 4));
+        let output = graph.add_comp(DebugOutput::default());
+        graph.add_conn(a, 0, and, 0);
+        graph.add_conn(b, 0, and, 1);
+        graph.add_conn(and, 0, output, 0);
+        graph.set_name(a, "a");
+        graph.propagate_all();
+        let start = graph.clone();
+
+        let mut log = EventLog::default();
+        let events = [
+            InputEvent::SetConstant {
+                node: a.into(),
+                state: true,
+            },
+            InputEvent::SetConstant {
+                node: b.into(),
+                state: true,
+            },
+            InputEvent::WriteMemory {
+                node: ram.into(),
+                address: 1,
+                value: 0x1f,
+            },
+        ];
+        for (millis, event) in events.into_iter().enumerate() {
+            event.apply(&mut graph);
+            log.push(millis as u64 * 250, event);
+        }
+        assert!(graph[output].state);
+        assert_eq!(graph[ram].contents, [0, 0xf]);
+
+        let text = log.to_text(&graph);
+        assert!(text.starts_with("0 set a 1\n250 set @"));
+        let parsed = EventLog::parse(&format!("# session\n{text}\n"), &start).unwrap();
+        assert_eq!(parsed, log);
+        let mut replayed = start.clone();
+        parsed.replay(&mut replayed);
+        assert!(replayed[output].state);
+        assert_eq!(replayed[ram].contents, [0, 0xf]);
+
+        // Only names are kept by a rebuilt circuit.
+        let mut edited = Graph::new();
+        let a = edited.add_comp(Constant::default());
+        edited.set_name(a, "a");
+        let partial = EventLog::parse("0 set a 1", &edited).unwrap();
+        partial.replay(&mut edited);
+        assert!(edited[a].state);
+        let error = EventLog::parse("0 set a 1\n5 set b 1", &edited).unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(EventLog::parse("0 set a 2", &edited).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use bitvec::slice::BitSlice;
+
+use super::{id::ComponentId, logic::Logic};
+
+/// Input or output slot of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Pin {
+    Input(usize),
+    Output(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FaultValue {
+    StuckAt(bool),
+    /// Broken off, read like a disconnected slot.
+    Floating,
+}
+
+impl FaultValue {
+    /// Value the pin is stuck at, `None` for a floating pin.
+    pub fn value(self) -> Option<bool> {
+        match self {
+            FaultValue::StuckAt(value) => Some(value),
+            FaultValue::Floating => None,
+        }
+    }
+
+    /// Level forced in four-valued simulation, where a floating pin is undriven.
+    pub fn level(self) -> Logic {
+        match self {
+            FaultValue::StuckAt(value) => value.into(),
+            FaultValue::Floating => Logic::Z,
+        }
+    }
+}
+
+/// Pin of a node forced to a value whatever drives it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fault {
+    pub node: ComponentId,
+    pub pin: Pin,
+    pub value: FaultValue,
+}
+
+/// Overwrites the stuck input bits with their values, marking the floating ones
+/// undriven.
+pub(super) fn force_inputs(
+    faults: &[(Pin, FaultValue)],
+    input: &mut BitSlice,
+    undriven: &mut BitSlice,
+) {
+    for &(pin, value) in faults {
+        if let Pin::Input(slot) = pin {
+            match value.value() {
+                Some(value) => {
+                    input.set(slot, value);
+                    undriven.set(slot, false);
+                }
+                None => undriven.set(slot, true),
+            }
+        }
+    }
+}
+
+/// Overwrites the stuck output bits with their values, unmasking them, and masks
+/// out the floating ones.
+pub(super) fn force_outputs(
+    faults: &[(Pin, FaultValue)],
+    output: &mut BitSlice,
+    mask: &mut BitSlice,
+) {
+    for &(pin, value) in faults {
+        if let Pin::Output(slot) = pin {
+            output.set(slot, value.value().unwrap_or(false));
+            mask.set(slot, value.value().is_some());
+        }
+    }
+}
+
+/// Overwrites the faulty input levels with their values.
+pub(super) fn force_input_levels(faults: &[(Pin, FaultValue)], input: &mut [Logic]) {
+    for &(pin, value) in faults {
+        if let Pin::Input(slot) = pin {
+            input[slot] = value.level();
+        }
+    }
+}
+
+/// Overwrites the faulty output levels with their values.
+pub(super) fn force_output_levels(faults: &[(Pin, FaultValue)], output: &mut [Logic]) {
+    for &(pin, value) in faults {
+        if let Pin::Output(slot) = pin {
+            output[slot] = value.level();
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Recorded input events of a graph for stepping back and forth in time.
+
+use super::{id::ComponentId, snapshot::Snapshot, Graph};
+use crate::components::Component;
+
+/// A change made to a graph from outside the circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    /// Sets the state of a `Constant`, such as a switch or a clock.
+    SetConstant { node: ComponentId, state: bool },
+    /// Overwrites a word of a `Ram`.
+    WriteMemory {
+        node: ComponentId,
+        address: usize,
+        value: u64,
+    },
+}
+
+impl InputEvent {
+    /// Applies the event and propagates it, events on removed nodes
+    /// or outside of a memory are skipped.
+    pub fn apply(self, graph: &mut Graph) {
+        match self {
+            InputEvent::SetConstant { node, state } => {
+                let Some(Component::Constant(constant)) =
+                    graph.nodes.get_mut(node).map(|node| &mut node.component)
+                else {
+                    return;
+                };
+                constant.state = state;
+                graph.propagate_from(node);
+            }
+            InputEvent::WriteMemory {
+                node,
+                address,
+                value,
+            } => {
+                let Some(Component::Ram(ram)) =
+                    graph.nodes.get_mut(node).map(|node| &mut node.component)
+                else {
+                    return;
+                };
+                let Some(word) = ram.contents.get_mut(address) else {
+                    return;
+                };
+                *word = value & (u64::MAX >> (64 - ram.data_width.clamp(1, 64)));
+                graph.propagate_from(node);
+            }
+        }
+    }
+}
+
+/// Input events applied to a graph, with a snapshot every `interval` events.
+///
+/// Time counts the events applied since the history was started. Going to an
+/// earlier time restores the closest checkpoint before it and replays the
+/// events after it, recording a new event drops the ones after the current time.
+#[derive(Debug, Clone)]
+pub struct History {
+    events: Vec<InputEvent>,
+    /// State at times `0`, `interval`, `2 * interval`...
+    checkpoints: Vec<Snapshot>,
+    interval: usize,
+    time: usize,
+}
+
+impl History {
+    /// Starts recording at the current state of the graph.
+    pub fn new(graph: &Graph, interval: usize) -> Self {
+        Self {
+            events: Vec::new(),
+            checkpoints: vec![graph.snapshot()],
+            interval: interval.max(1),
+            time: 0,
+        }
+    }
+
+    pub fn time(&self) -> usize {
+        self.time
+    }
+
+    /// Time after the last recorded event.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn events(&self) -> &[InputEvent] {
+        &self.events
+    }
+
+    /// Applies the event to the graph and records it at the current time.
+    pub fn record(&mut self, graph: &mut Graph, event: InputEvent) {
+        self.events.truncate(self.time);
+        self.checkpoints.truncate(self.time / self.interval + 1);
+        event.apply(graph);
+        self.events.push(event);
+        self.time += 1;
+        if self.time % self.interval == 0 {
+            self.checkpoints.push(graph.snapshot());
+        }
+    }
+
+    /// Goes to the state after the first `time` events, at most the recorded ones.
+    pub fn go_to(&mut self, graph: &mut Graph, time: usize) {
+        let time = time.min(self.events.len());
+        if time < self.time || self.time / self.interval < time / self.interval {
+            let checkpoint = time / self.interval;
+            graph.restore(&self.checkpoints[checkpoint]);
+            self.time = checkpoint * self.interval;
+        }
+        for &event in &self.events[self.time..time] {
+            event.apply(graph);
+        }
+        self.time = time;
+    }
+
+    /// Undoes the last event, returns whether there was one.
+    pub fn step_back(&mut self, graph: &mut Graph) -> bool {
+        let Some(time) = self.time.checked_sub(1) else {
+            return false;
+        };
+        self.go_to(graph, time);
+        true
+    }
+
+    /// Applies the next recorded event again, returns whether there was one.
+    pub fn step_forward(&mut self, graph: &mut Graph) -> bool {
+        if self.time == self.events.len() {
+            return false;
+        }
+        self.go_to(graph, self.time + 1);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{
+        memory::{FlipFlop, FlipFlopKind},
+        simple::{Constant, DebugOutput},
+    };
+    use crate::graph::snapshot::ComponentState::Bit;
+
+    #[test]
+    fn goes_back_to_recorded_states() {
+        let mut graph = Graph::new();
+        let clock = graph.add_comp(Constant::default());
+        let toggle = graph.add_comp(Constant::default());
+        let flip_flop = graph.add_comp(FlipFlop::new(FlipFlopKind::T));
+        let output = graph.add_comp(DebugOutput::default());
+        graph.add_conn(toggle, 0, flip_flop, 0);
+        graph.add_conn(clock, 0, flip_flop, 1);
+        graph.add_conn(flip_flop, 0, output, 0);
+        graph.propagate_all();
+
+        let mut history = History::new(&graph, 4);
+        let mut states = vec![graph.snapshot()];
+        for step in 0..30 {
+            let node = if step % 7 == 3 { toggle } else { clock };
+            let state = !graph[node].state;
+            history.record(
+                &mut graph,
+                InputEvent::SetConstant {
+                    node: node.into(),
+                    state,
+                },
+            );
+            states.push(graph.snapshot());
+        }
+        let toggled = states
+            .iter()
+            .filter(|state| state.state(output) == Some(&Bit(true)));
+        assert!(toggled.count() > 5);
+
+        for time in [29, 13, 0, 4, 17, 30, 8, 7] {
+            history.go_to(&mut graph, time);
+            assert_eq!(history.time(), time);
+            assert_eq!(graph.snapshot(), states[time]);
+        }
+        assert!(history.step_back(&mut graph));
+        assert_eq!(graph.snapshot(), states[6]);
+        assert!(history.step_forward(&mut graph));
+        assert_eq!(graph.snapshot(), states[7]);
+
+        // A new event replaces the ones after it.
+        history.record(
+            &mut graph,
+            InputEvent::SetConstant {
+                node: toggle.into(),
+                state: true,
+            },
+        );
+        assert_eq!(history.len(), 8);
+        assert!(!history.step_forward(&mut graph));
+        history.go_to(&mut graph, 0);
+        assert!(!history.step_back(&mut graph));
+        assert_eq!(graph.snapshot(), states[0]);
+    }
+}
@@ -9,12 +9,28 @@ new_key_type! {
     pub struct ComponentId;
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct TypedId<C> {
     inner: ComponentId,
     marker: PhantomData<C>,
 }
 
+impl<C> Clone for TypedId<C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C> Copy for TypedId<C> {}
+
+impl<C> PartialEq for TypedId<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<C> Eq for TypedId<C> {}
+
 impl<C> From<TypedId<C>> for ComponentId {
     fn from(value: TypedId<C>) -> Self {
         value.inner
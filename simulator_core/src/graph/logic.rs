@@ -0,0 +1,196 @@
+//! Four-valued logic levels for simulating unknown and undriven signals.
+//!
+//! In two-valued simulation an uninitialized flip-flop or a floating input reads
+//! as `0`, which hides circuits that only work by luck. With four-valued levels
+//! enabled by [`super::Graph::set_four_valued`] such signals read `X` or `Z` and
+//! the unknown spreads through the gates it can affect.
+
+use std::{fmt::Display, ops::Not};
+
+use bitvec::prelude::*;
+use slotmap::SecondaryMap;
+
+use super::id::ComponentId;
+use crate::components::ComponentBehaviour;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Logic {
+    Zero,
+    One,
+    /// Driven, but to an unknown value.
+    #[default]
+    X,
+    /// Not driven at all.
+    Z,
+}
+
+impl Logic {
+    /// Value of a known level.
+    pub fn to_bool(self) -> Option<bool> {
+        match self {
+            Logic::Zero => Some(false),
+            Logic::One => Some(true),
+            Logic::X | Logic::Z => None,
+        }
+    }
+
+    pub fn is_known(self) -> bool {
+        self.to_bool().is_some()
+    }
+
+    /// Value seen by two-valued simulation, where unknown levels read as `0`.
+    pub fn bit(self) -> bool {
+        self == Logic::One
+    }
+
+    /// Level read by a gate input, which cannot tell `Z` from `X`.
+    pub fn read(self) -> Self {
+        match self {
+            Logic::Z => Logic::X,
+            level => level,
+        }
+    }
+
+    pub fn and(self, other: Self) -> Self {
+        match (self.read(), other.read()) {
+            (Logic::Zero, _) | (_, Logic::Zero) => Logic::Zero,
+            (Logic::One, Logic::One) => Logic::One,
+            _ => Logic::X,
+        }
+    }
+
+    pub fn or(self, other: Self) -> Self {
+        match (self.read(), other.read()) {
+            (Logic::One, _) | (_, Logic::One) => Logic::One,
+            (Logic::Zero, Logic::Zero) => Logic::Zero,
+            _ => Logic::X,
+        }
+    }
+
+    pub fn xor(self, other: Self) -> Self {
+        match (self.to_bool(), other.to_bool()) {
+            (Some(a), Some(b)) => (a ^ b).into(),
+            _ => Logic::X,
+        }
+    }
+}
+
+impl Not for Logic {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        match self.to_bool() {
+            Some(value) => (!value).into(),
+            None => Logic::X,
+        }
+    }
+}
+
+impl From<bool> for Logic {
+    fn from(value: bool) -> Self {
+        if value {
+            Logic::One
+        } else {
+            Logic::Zero
+        }
+    }
+}
+
+impl Display for Logic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Logic::Zero => '0',
+            Logic::One => '1',
+            Logic::X => 'X',
+            Logic::Z => 'Z',
+        };
+        write!(f, "{symbol}")
+    }
+}
+
+pub fn to_bits(levels: &[Logic]) -> BitVec {
+    levels.iter().map(|level| level.bit()).collect()
+}
+
+/// Evaluates a component by its two-valued behaviour, making every output
+/// unknown when an input is. Outputs the component masks out are left undriven.
+pub fn propagate_known<C: ComponentBehaviour + ?Sized>(
+    component: &mut C,
+    prev_input: &[Logic],
+    input: &[Logic],
+    output: &mut [Logic],
+) {
+    let mut bits = output.iter().map(|level| level.bit()).collect::<BitVec>();
+    let mut mask = bitvec![1; output.len()];
+    component.propagate(&to_bits(prev_input), &to_bits(input), &mut bits, &mut mask);
+    let known = input.iter().all(|level| level.is_known());
+    for (i, level) in output.iter_mut().enumerate() {
+        *level = match (mask[i], known) {
+            (false, _) => Logic::Z,
+            (true, true) => bits[i].into(),
+            (true, false) => Logic::X,
+        };
+    }
+}
+
+/// Levels of the slots of a graph simulated with four-valued logic,
+/// next to the two-valued slots which read them as bits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct Levels {
+    pub(super) inputs: SecondaryMap<ComponentId, Vec<Logic>>,
+    pub(super) outputs: SecondaryMap<ComponentId, Vec<Logic>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        components::{
+            gates::{And, Or},
+            memory::{FlipFlop, FlipFlopKind},
+            simple::{Constant, DebugOutput, Fork},
+        },
+        graph::Graph,
+    };
+
+    #[test]
+    fn unknown_levels_spread() {
+        let mut graph = Graph::new();
+        let clock = graph.add_comp(Constant::default());
+        let data = graph.add_comp(Constant { state: true });
+        let flip_flop = graph.add_comp(FlipFlop::new(FlipFlopKind::D));
+        let fork = graph.add_comp(Fork::new(1, 2));
+        let zero = graph.add_comp(Constant::default());
+        let and = graph.add_comp(And);
+        let or = graph.add_comp(Or);
+        let [and_out, or_out] = [(); 2].map(|_| graph.add_comp(DebugOutput::default()));
+        graph.add_conn(data, 0, flip_flop, 0);
+        graph.add_conn(clock, 0, flip_flop, 1);
+        graph.add_conn(flip_flop, 0, fork, 0);
+        graph.add_conn(fork, 0, and, 0);
+        graph.add_conn(zero, 0, and, 1);
+        graph.add_conn(fork, 1, or, 0);
+        graph.add_conn(and, 0, and_out, 0);
+        graph.add_conn(or, 0, or_out, 0);
+        graph.propagate_all();
+        assert!(!graph.is_four_valued());
+        assert_eq!(graph.output_level(flip_flop, 0), Logic::Zero);
+
+        graph.set_four_valued(true);
+        // The flip-flop was never clocked and the second input of the or gate floats.
+        assert_eq!(graph.output_level(flip_flop, 0), Logic::X);
+        assert_eq!(graph.output_level(flip_flop, 1), Logic::X);
+        assert_eq!(graph.input_level(or, 1), Logic::Z);
+        assert_eq!(graph.input_level(and_out, 0), Logic::Zero);
+        assert_eq!(graph.input_level(or_out, 0), Logic::X);
+
+        graph[clock].state = true;
+        graph.propagate_from(clock);
+        assert_eq!(graph.output_level(flip_flop, 0), Logic::One);
+        assert_eq!(graph.input_level(or_out, 0), Logic::One);
+        assert!(graph[or_out].state);
+
+        graph.set_four_valued(false);
+        assert_eq!(graph.input_level(or, 1), Logic::Zero);
+    }
+}
@@ -1,26 +1,50 @@
-use std::{
-    collections::VecDeque,
-    ops::{Index, IndexMut},
-};
+use std::ops::{Index, IndexMut};
 
 use bitvec::prelude::*;
 use serde::{Serialize, Deserialize};
 use slotmap::{SecondaryMap, SlotMap};
 
 use self::{
+    fault::{Fault, FaultValue, Pin},
     id::{ComponentId, TypedId},
+    logic::{Levels, Logic},
     node::{Node, Slot},
+    observer::{Observers, SignalChange, SubscriptionId},
+    propagation::{Propagation, Steps},
+    snapshot::Snapshot,
 };
-use crate::components::{Component, ComponentBehaviour};
+use crate::components::{BidirectionalPin, Component, ComponentBehaviour, PinDirection};
 
+pub mod compiled;
+pub mod event_log;
+pub mod fault;
+pub mod history;
 pub mod id;
+pub mod logic;
 pub mod node;
+pub mod observer;
+pub mod propagation;
+pub mod snapshot;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Graph {
     pub nodes: SlotMap<ComponentId, Node>,
     pub inputs: SecondaryMap<ComponentId, BitVec>,
     pub outputs: SecondaryMap<ComponentId, BitVec>,
+    /// Output slots the last evaluation of their node left undriven.
+    #[serde(skip)]
+    released: SecondaryMap<ComponentId, BitVec>,
+    /// Injected faults by node, they are not saved with the graph.
+    #[serde(skip)]
+    faults: SecondaryMap<ComponentId, Vec<(Pin, FaultValue)>>,
+    #[serde(skip)]
+    observers: Observers,
+    /// Nodes evaluated since the graph was made.
+    #[serde(skip)]
+    time: u64,
+    /// Four-valued levels of the slots, when enabled.
+    #[serde(skip)]
+    levels: Option<Levels>,
 }
 
 const MAX_PROPAGATION_DEPTH: usize = 10_000;
@@ -31,6 +55,11 @@ impl Graph {
             nodes: SlotMap::with_key(),
             inputs: SecondaryMap::new(),
             outputs: SecondaryMap::new(),
+            released: SecondaryMap::new(),
+            faults: SecondaryMap::new(),
+            observers: Observers::default(),
+            time: 0,
+            levels: None,
         }
     }
 
@@ -41,20 +70,45 @@ impl Graph {
 
         let node = Node {
             component,
+            name: None,
             input_slots: vec![None; input_size],
             output_slots: vec![None; output_size],
         };
 
         let node_ref = self.nodes.insert(node);
 
-        self.inputs.insert(node_ref, bitvec![0;input_size]);
+        let input = (0..input_size).map(|slot| self.nodes[node_ref].component.floating_input(slot));
+        self.inputs.insert(node_ref, input.collect());
         self.outputs.insert(node_ref, bitvec![0;output_size]);
+        self.released.insert(node_ref, bitvec![0; output_size]);
+        if let Some(levels) = &mut self.levels {
+            levels.inputs.insert(node_ref, vec![Logic::Z; input_size]);
+            levels.outputs.insert(node_ref, vec![Logic::X; output_size]);
+        }
 
         node_ref.into()
     }
 
+    pub fn set_name(&mut self, node: impl Into<ComponentId>, name: impl Into<String>) {
+        self.nodes[node.into()].name = Some(name.into());
+    }
+
+    pub fn name(&self, node: impl Into<ComponentId>) -> Option<&str> {
+        self.nodes[node.into()].name.as_deref()
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<ComponentId> {
+        self.nodes
+            .iter()
+            .find(|(_, node)| node.name.as_deref() == Some(name))
+            .map(|(id, _)| id)
+    }
+
     pub fn remove_comp(&mut self, node: impl Into<ComponentId>) {
-        let removed = self.nodes.remove(node.into()).unwrap();
+        let node = node.into();
+        let removed = self.nodes.remove(node).unwrap();
+        self.faults.remove(node);
+        self.observers.remove_node(node);
 
         for input in removed.input_slots {
             let Some(input) = input else {continue;};
@@ -130,69 +184,248 @@ impl Graph {
     }
 
     pub fn propagate_from(&mut self, node: impl Into<ComponentId>) {
+        let mut propagation = self.start_propagation(node);
+        propagation.finish(self);
+        if propagation.overflowed() {
+            // TODO Jakiś sensowny handling tego przypadku, logowanie
+            eprintln!("Reached max depth");
+        }
+    }
+
+    /// Propagation like [`Graph::propagate_from`] made one node at a time,
+    /// by a [`Propagation`] to keep between steps.
+    pub fn start_propagation(&self, node: impl Into<ComponentId>) -> Propagation {
         let node = node.into();
+        let new_levels = self.levels.as_ref().map(|levels| levels.inputs[node].clone());
+        Propagation::new(node, self.inputs[node].clone(), new_levels)
+    }
 
-        let mut queue = VecDeque::new();
-        queue.push_back(node);
+    /// Iterates over the nodes evaluated by [`Graph::propagate_from`].
+    pub fn steps_from(&mut self, node: impl Into<ComponentId>) -> Steps<'_> {
+        let propagation = self.start_propagation(node);
+        Steps::new(self, propagation)
+    }
 
-        struct QueueData {
-            new_input: BitVec
+    pub fn propagate_all(&mut self) {
+        let ids = self.nodes.keys().collect::<Vec<_>>();
+        for id in ids {
+            self.propagate_from(id);
         }
-        let mut in_queue = SecondaryMap::new();
-        in_queue.insert(node, QueueData {new_input: self.inputs[node].clone()});
-
-        let mut depth = 0;
-
-        while let Some(next_node_ref) = queue.pop_front() {
-            let queue_data = in_queue.remove(next_node_ref).unwrap();
+    }
 
-            depth += 1;
-            if depth > MAX_PROPAGATION_DEPTH {
-                // TODO Jakiś sensowny handling tego przypadku, logowanie
-                eprintln!("Reached max depth");
-                break;
-            }
+    /// Forces a pin to the value of the fault until it is cleared,
+    /// replacing an earlier fault on the same pin.
+    pub fn inject_fault(&mut self, fault: Fault) {
+        let faults = self.faults.entry(fault.node).unwrap().or_default();
+        faults.retain(|&(pin, _)| pin != fault.pin);
+        faults.push((fault.pin, fault.value));
+        self.propagate_from(fault.node);
+    }
 
-            let next_node = &mut self.nodes[next_node_ref];
+    /// Lets the pin follow the circuit again.
+    pub fn clear_fault(&mut self, node: impl Into<ComponentId>, pin: Pin) {
+        let node = node.into();
+        let Some(faults) = self.faults.get_mut(node) else {
+            return;
+        };
+        faults.retain(|&(other, _)| other != pin);
+
+        let mut propagation = self.start_propagation(node);
+        if let Pin::Input(slot) = pin {
+            let driven = match &self.nodes[node].input_slots[slot] {
+                Some(source) => self.output_level(source.target_node, source.target_slot),
+                None => Logic::Z,
+            };
+            propagation.set_input(slot, driven);
+        }
+        propagation.finish(self);
+    }
 
-            let prev_input = &self.inputs[next_node_ref];
-            let new_input = &queue_data.new_input;
-            let output = &mut self.outputs[next_node_ref];
+    pub fn clear_faults(&mut self) {
+        for fault in self.faults().collect::<Vec<_>>() {
+            self.clear_fault(fault.node, fault.pin);
+        }
+    }
 
-            let mut mask = bitvec![1; next_node.output_slots.len()];
+    pub fn faults(&self) -> impl Iterator<Item = Fault> + '_ {
+        self.faults.iter().flat_map(|(node, faults)| {
+            faults.iter().map(move |&(pin, value)| Fault { node, pin, value })
+        })
+    }
 
-            next_node.component.propagate(prev_input, new_input, output, &mut mask);
+    /// Watches a slot of a node, its changes during propagation are kept
+    /// until they are taken with [`Graph::take_changes`].
+    /// Returns `None` when the graph has no such node or slot.
+    ///
+    /// Changes are recorded as each node is evaluated, so stepping a propagation
+    /// from [`Graph::start_propagation`] and taking the changes after each step
+    /// lets a breakpoint halt it as soon as a watched slot changes.
+    pub fn subscribe(&mut self, node: impl Into<ComponentId>, pin: Pin) -> Option<SubscriptionId> {
+        let node = node.into();
+        let data = self.nodes.get(node)?;
+        let len = match pin {
+            Pin::Input(_) => data.input_slots.len(),
+            Pin::Output(_) => data.output_slots.len(),
+        };
+        let (Pin::Input(slot) | Pin::Output(slot)) = pin;
+        (slot < len).then(|| self.observers.subscribe(node, pin))
+    }
 
-            self.inputs[next_node_ref] = queue_data.new_input;
+    pub fn unsubscribe(&mut self, subscription: SubscriptionId) {
+        self.observers.unsubscribe(subscription);
+    }
 
-            for (i, out_slot) in next_node.output_slots.iter().enumerate() {
-                let &Some(Slot { target_node, target_slot }) = out_slot else {continue;};
-                let output_bit = output[i] & mask[i];
+    /// Changes of the watched slots since the last call, in the order they happened.
+    pub fn take_changes(&mut self) -> Vec<SignalChange> {
+        self.observers.take_changes()
+    }
 
-                let target_new_input = in_queue.get(target_node).map(|d| &d.new_input).unwrap_or(&self.inputs[target_node]);
+    /// Nodes evaluated by propagations since the graph was made or loaded.
+    pub fn time(&self) -> u64 {
+        self.time
+    }
 
-                if output_bit != target_new_input[target_slot] {
-                    if !in_queue.contains_key(target_node) {
-                        in_queue.insert(target_node, QueueData { new_input: target_new_input.clone() });
-                        queue.push_back(target_node);
+    /// Switches between two-valued simulation and four-valued levels.
+    ///
+    /// Turning the levels on forgets the state of memories and settles the graph
+    /// again, starting from floating inputs reading `Z` and the others `X`.
+    /// Turning them off keeps the slots, with unknown levels read as `0`
+    /// and floating inputs as [`ComponentBehaviour::floating_input`].
+    pub fn set_four_valued(&mut self, enabled: bool) {
+        if !enabled {
+            if let Some(levels) = self.levels.take() {
+                // Floating inputs read as they do in two-valued simulation.
+                for (id, inputs) in &levels.inputs {
+                    let Some(node) = self.nodes.get(id) else { continue };
+                    for (slot, &level) in inputs.iter().enumerate() {
+                        if level == Logic::Z {
+                            self.inputs[id].set(slot, node.component.floating_input(slot));
+                        }
                     }
-
-                    in_queue[target_node].new_input.set(target_slot, output_bit)
                 }
             }
+            return;
+        }
+        if self.levels.is_some() {
+            return;
+        }
+
+        let mut levels = Levels::default();
+        for (id, node) in &mut self.nodes {
+            node.component.forget_state();
+            let inputs = node
+                .input_slots
+                .iter()
+                .map(|slot| if slot.is_some() { Logic::X } else { Logic::Z })
+                .collect::<Vec<_>>();
+            self.inputs[id] = logic::to_bits(&inputs);
+            levels.inputs.insert(id, inputs);
+            levels.outputs.insert(id, vec![Logic::X; node.output_slots.len()]);
+            self.outputs[id].fill(false);
+            self.released.insert(id, bitvec![0; node.output_slots.len()]);
+        }
+        self.levels = Some(levels);
+        self.propagate_all();
+    }
+
+    pub fn is_four_valued(&self) -> bool {
+        self.levels.is_some()
+    }
+
+    /// Level of an input slot, only `0` or `1` in two-valued simulation.
+    pub fn input_level(&self, node: impl Into<ComponentId>, slot: usize) -> Logic {
+        let node = node.into();
+        match &self.levels {
+            Some(levels) => levels.inputs[node][slot],
+            None => self.inputs[node][slot].into(),
+        }
+    }
+
+    /// Level of an output slot, only `0`, `1` or `Z` for a released one in two-valued
+    /// simulation.
+    pub fn output_level(&self, node: impl Into<ComponentId>, slot: usize) -> Logic {
+        let node = node.into();
+        match &self.levels {
+            Some(levels) => levels.outputs[node][slot],
+            None if self.released.get(node).is_some_and(|released| released[slot]) => Logic::Z,
+            None => self.outputs[node][slot].into(),
+        }
+    }
+
+    /// Direction the last evaluation of `node` gave a bidirectional pin: an output
+    /// while the component drives it, an input while it releases it.
+    pub fn pin_direction(
+        &self,
+        node: impl Into<ComponentId>,
+        pin: BidirectionalPin,
+    ) -> PinDirection {
+        match self.output_level(node, pin.output) {
+            Logic::Z => PinDirection::Input,
+            _ => PinDirection::Output,
         }
     }
 
+    /// Input slot of the `Fork` `node` fed by the bidirectional pin its output `slot`
+    /// leads to, when the pin drives the same line.
+    ///
+    /// The line is one net the pin is a terminal of, so the pin reads it as the other
+    /// drivers leave it: the output slot is resolved without that input.
+    pub fn terminal_input(&self, node: impl Into<ComponentId>, slot: usize) -> Option<usize> {
+        let node = node.into();
+        let Component::Fork(_) = &self.nodes[node].component else {
+            return None;
+        };
+        let reader = self.nodes[node].output_slots[slot].as_ref()?;
+        let reader_node = &self.nodes[reader.target_node];
+        let pin = reader_node
+            .component
+            .bidirectional_pins()
+            .into_iter()
+            .find(|pin| pin.input == reader.target_slot)?;
+        let driver = reader_node.output_slots[pin.output].as_ref()?;
+        (driver.target_node == node).then_some(driver.target_slot)
+    }
+
+    /// Output slots of `node` leading to bidirectional pins, with the input slot
+    /// each is resolved without, see [`Graph::terminal_input`].
+    pub fn terminals(&self, node: impl Into<ComponentId>) -> Vec<(usize, usize)> {
+        let node = node.into();
+        (0..self.nodes[node].output_slots.len())
+            .filter_map(|slot| Some((slot, self.terminal_input(node, slot)?)))
+            .collect()
+    }
+
+    /// Copies the slots and component states, leaving out the topology.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::take(self)
+    }
+
+    /// Puts back the state of a snapshot taken from this graph, without propagating.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        snapshot.restore(self);
+    }
+
     pub fn add_input_slot(&mut self, node: impl Into<ComponentId>) {
         let node = node.into();
+        let slot = self.nodes[node].input_slots.len();
+        let floating = self.nodes[node].component.floating_input(slot);
         self.nodes[node].input_slots.push(None);
-        self.inputs[node].push(false);
+        self.inputs[node].push(floating);
+        if let Some(levels) = &mut self.levels {
+            levels.inputs[node].push(Logic::Z);
+        }
     }
 
     pub fn add_output_slot(&mut self, node: impl Into<ComponentId>) {
         let node = node.into();
         self.nodes[node].output_slots.push(None);
         self.outputs[node].push(false);
+        if let Some(released) = self.released.get_mut(node) {
+            released.push(false);
+        }
+        if let Some(levels) = &mut self.levels {
+            levels.outputs[node].push(Logic::X);
+        }
     }
 }
 
@@ -4,9 +4,11 @@ use crate::components::Component;
 
 use super::id::ComponentId;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Node {
     pub component: Component,
+    #[serde(default)]
+    pub name: Option<String>,
     pub input_slots: Vec<Option<Slot>>,
     pub output_slots: Vec<Option<Slot>>,
 }
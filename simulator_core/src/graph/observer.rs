@@ -0,0 +1,174 @@
+//! Notifications of changes of watched slots during propagation.
+
+use bitvec::slice::BitSlice;
+use slotmap::SecondaryMap;
+
+use super::{fault::Pin, id::ComponentId};
+
+/// Handle of a subscription, to cancel it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SubscriptionId(u64);
+
+/// A watched slot taking a new value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalChange {
+    pub subscription: SubscriptionId,
+    pub node: ComponentId,
+    pub pin: Pin,
+    pub old: bool,
+    pub new: bool,
+    /// Evaluations made by the graph when the slot changed, see [`super::Graph::time`].
+    pub time: u64,
+    /// Node the propagation started from.
+    pub cause: ComponentId,
+}
+
+/// Watched slots by node, and the changes seen since they were last taken.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Observers {
+    next: u64,
+    watched: SecondaryMap<ComponentId, Vec<(SubscriptionId, Pin)>>,
+    changes: Vec<SignalChange>,
+}
+
+impl Observers {
+    pub(super) fn subscribe(&mut self, node: ComponentId, pin: Pin) -> SubscriptionId {
+        let id = SubscriptionId(self.next);
+        self.next += 1;
+        self.watched
+            .entry(node)
+            .unwrap()
+            .or_default()
+            .push((id, pin));
+        id
+    }
+
+    pub(super) fn unsubscribe(&mut self, id: SubscriptionId) {
+        for (_, pins) in &mut self.watched {
+            pins.retain(|&(other, _)| other != id);
+        }
+        self.watched.retain(|_, pins| !pins.is_empty());
+    }
+
+    pub(super) fn remove_node(&mut self, node: ComponentId) {
+        self.watched.remove(node);
+    }
+
+    pub(super) fn is_watched(&self, node: ComponentId) -> bool {
+        self.watched.contains_key(node)
+    }
+
+    /// Records the changes of the watched slots of a node evaluated at `time`,
+    /// from the old to the new `inputs` and `outputs`.
+    pub(super) fn notify(
+        &mut self,
+        node: ComponentId,
+        [old_input, input]: [&BitSlice; 2],
+        [old_output, output]: [&BitSlice; 2],
+        time: u64,
+        cause: ComponentId,
+    ) {
+        let Some(pins) = self.watched.get(node) else {
+            return;
+        };
+        for &(subscription, pin) in pins {
+            let (old, new) = match pin {
+                Pin::Input(slot) => (old_input.get(slot), input.get(slot)),
+                Pin::Output(slot) => (old_output.get(slot), output.get(slot)),
+            };
+            let (Some(old), Some(new)) = (old, new) else {
+                continue;
+            };
+            if *old != *new {
+                self.changes.push(SignalChange {
+                    subscription,
+                    node,
+                    pin,
+                    old: *old,
+                    new: *new,
+                    time,
+                    cause,
+                });
+            }
+        }
+    }
+
+    pub(super) fn take_changes(&mut self) -> Vec<SignalChange> {
+        std::mem::take(&mut self.changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        components::{
+            gates::{And, Not},
+            simple::{Constant, DebugOutput},
+        },
+        graph::Graph,
+    };
+
+    #[test]
+    fn notifies_watched_changes() {
+        let mut graph = Graph::new();
+        let a = graph.add_comp(Constant::default());
+        let b = graph.add_comp(Constant { state: true });
+        let and = graph.add_comp(And);
+        let not = graph.add_comp(Not);
+        let output = graph.add_comp(DebugOutput::default());
+        graph.add_conn(a, 0, and, 0);
+        graph.add_conn(b, 0, and, 1);
+        graph.add_conn(and, 0, not, 0);
+        graph.add_conn(not, 0, output, 0);
+        graph.propagate_all();
+
+        let and_out = graph.subscribe(and, Pin::Output(0)).unwrap();
+        let and_b = graph.subscribe(and, Pin::Input(1)).unwrap();
+        let out_in = graph.subscribe(output, Pin::Input(0)).unwrap();
+        assert!(graph.take_changes().is_empty());
+        assert_eq!(graph.subscribe(and, Pin::Input(2)), None);
+
+        graph[a].state = true;
+        graph.propagate_from(a);
+        let changes = graph.take_changes();
+        let summary = changes
+            .iter()
+            .map(|change| (change.subscription, change.old, change.new, change.cause))
+            .collect::<Vec<_>>();
+        let cause = a.into();
+        assert_eq!(
+            summary,
+            [(and_out, false, true, cause), (out_in, true, false, cause)]
+        );
+        assert!(changes[0].time < changes[1].time);
+        assert!(changes[1].time < graph.time());
+        assert!(graph.take_changes().is_empty());
+
+        graph.unsubscribe(and_out);
+        graph[b].state = false;
+        graph.propagate_from(b);
+        let changes = graph.take_changes();
+        let subscriptions = changes.iter().map(|change| change.subscription);
+        assert_eq!(subscriptions.collect::<Vec<_>>(), [and_b, out_in]);
+        assert_eq!(changes[0].cause, b.into());
+        assert_eq!(changes[0].pin, Pin::Input(1));
+
+        // A breakpoint on the and gate halts the propagation before the output changes.
+        graph[b].state = true;
+        let mut propagation = graph.start_propagation(b);
+        while propagation.step(&mut graph).is_some() {
+            if !graph.take_changes().is_empty() {
+                break;
+            }
+        }
+        assert_eq!(propagation.queue().collect::<Vec<_>>(), [not.into()]);
+        assert!(graph[output].state);
+        propagation.finish(&mut graph);
+        assert!(!graph[output].state);
+        assert_eq!(graph.take_changes().len(), 1);
+
+        graph.remove_comp(not);
+        assert_eq!(graph.subscribe(not, Pin::Output(0)), None);
+    }
+}
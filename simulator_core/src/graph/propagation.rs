@@ -0,0 +1,419 @@
+//! Propagation of changes through a graph, one evaluated node at a time.
+
+use std::collections::VecDeque;
+
+use bitvec::prelude::*;
+use slotmap::SecondaryMap;
+
+use super::{fault, id::ComponentId, logic::Logic, node::Slot, Graph, MAX_PROPAGATION_DEPTH};
+use crate::components::{Component, ComponentBehaviour};
+
+/// Nodes left to evaluate, in order, with the inputs they get.
+#[derive(Debug, Clone)]
+pub struct Propagation {
+    queue: VecDeque<ComponentId>,
+    new_inputs: SecondaryMap<ComponentId, BitVec>,
+    /// Levels of the new inputs, when the graph is four-valued.
+    new_levels: SecondaryMap<ComponentId, Vec<Logic>>,
+    depth: usize,
+    /// Node the propagation started from.
+    cause: ComponentId,
+    /// State of the generator picking the next node, when not in queue order.
+    shuffle: Option<u64>,
+    /// Whether the propagation was cut short at [`MAX_PROPAGATION_DEPTH`].
+    overflowed: bool,
+}
+
+impl Propagation {
+    /// Starts from `node` getting `new_input` in place of its current input,
+    /// with `new_levels` as its levels in a four-valued graph.
+    pub(super) fn new(
+        node: ComponentId,
+        new_input: BitVec,
+        new_levels: Option<Vec<Logic>>,
+    ) -> Self {
+        let mut new_inputs = SecondaryMap::new();
+        new_inputs.insert(node, new_input);
+        let mut levels = SecondaryMap::new();
+        if let Some(new_levels) = new_levels {
+            levels.insert(node, new_levels);
+        }
+        Self {
+            queue: VecDeque::from([node]),
+            new_inputs,
+            new_levels: levels,
+            depth: 0,
+            cause: node,
+            shuffle: None,
+            overflowed: false,
+        }
+    }
+
+    /// Evaluates the queued nodes in a pseudo-random order given by `seed`
+    /// instead of the order they were queued in.
+    pub fn shuffled(mut self, seed: u64) -> Self {
+        self.shuffle = Some(seed);
+        self
+    }
+
+    /// Makes the node the propagation started from get `level` on an input slot.
+    pub(super) fn set_input(&mut self, slot: usize, level: Logic) {
+        if let Some(input) = self.new_inputs.get_mut(self.cause) {
+            input.set(slot, level.bit());
+        }
+        if let Some(levels) = self.new_levels.get_mut(self.cause) {
+            levels[slot] = level;
+        }
+    }
+
+    /// Whether the propagation gave up after too many steps,
+    /// as happens to a loop which never settles.
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Nodes left to evaluate, in the order they were queued.
+    pub fn queue(&self) -> impl Iterator<Item = ComponentId> + '_ {
+        self.queue.iter().copied()
+    }
+
+    /// Position in the queue of the node to evaluate next.
+    fn next_index(&self) -> usize {
+        match self.shuffle {
+            // The finalizer of splitmix64.
+            Some(state) if !self.queue.is_empty() => {
+                let mut z = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                ((z ^ (z >> 31)) % self.queue.len() as u64) as usize
+            }
+            _ => 0,
+        }
+    }
+
+    /// Evaluates the next node and queues the nodes its changes reach,
+    /// returns `None` when there is nothing left.
+    pub fn step(&mut self, graph: &mut Graph) -> Option<ComponentId> {
+        let next_node_ref = self.queue.remove(self.next_index())?;
+        if let Some(state) = &mut self.shuffle {
+            *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        }
+        let mut new_input = self.new_inputs.remove(next_node_ref).unwrap();
+        let mut new_levels = self.new_levels.remove(next_node_ref);
+        if new_levels.is_none() && graph.levels.is_some() {
+            // Queued before the graph became four-valued.
+            new_levels = Some(new_input.iter().map(|bit| Logic::from(*bit)).collect());
+        }
+        // Inputs nothing drives float, unconnected ones whatever they were left with
+        // when disconnected.
+        let node = &graph.nodes[next_node_ref];
+        let faults = graph.faults.get(next_node_ref);
+        let mut undriven = node
+            .input_slots
+            .iter()
+            .map(|source| match source {
+                Some(source) => graph
+                    .released
+                    .get(source.target_node)
+                    .is_some_and(|released| released[source.target_slot]),
+                None => true,
+            })
+            .collect::<BitVec>();
+        if let Some(faults) = faults {
+            fault::force_inputs(faults, &mut new_input, &mut undriven);
+        }
+        for slot in undriven.iter_ones() {
+            new_input.set(slot, node.component.floating_input(slot));
+            if let Some(new_levels) = &mut new_levels {
+                new_levels[slot] = Logic::Z;
+            }
+        }
+        if let (Some(faults), Some(new_levels)) = (faults, &mut new_levels) {
+            fault::force_input_levels(faults, new_levels);
+        }
+
+        self.depth += 1;
+        if self.depth > MAX_PROPAGATION_DEPTH {
+            self.overflowed = true;
+            self.queue.clear();
+            self.new_inputs.clear();
+            return None;
+        }
+
+        // Outputs reaching bidirectional pins on the line, resolved without their drive.
+        let terminals = graph.terminals(next_node_ref);
+        let next_node = &mut graph.nodes[next_node_ref];
+
+        let prev_input = &graph.inputs[next_node_ref];
+        let output = &mut graph.outputs[next_node_ref];
+        let prev_output = graph
+            .observers
+            .is_watched(next_node_ref)
+            .then(|| output.clone());
+
+        let mut mask = bitvec![1; next_node.output_slots.len()];
+
+        match (&mut graph.levels, &new_levels) {
+            (Some(levels), Some(new_levels)) => {
+                let output_levels = &mut levels.outputs[next_node_ref];
+                next_node.component.propagate_logic(
+                    &levels.inputs[next_node_ref],
+                    new_levels,
+                    output_levels,
+                );
+                if let Component::Fork(fork) = &next_node.component {
+                    for &(slot, except) in &terminals {
+                        output_levels[slot] = fork.level_without(new_levels, except);
+                    }
+                }
+                if let Some(faults) = faults {
+                    fault::force_output_levels(faults, output_levels);
+                }
+                for (i, level) in output_levels.iter().enumerate() {
+                    output.set(i, level.bit());
+                    mask.set(i, *level != Logic::Z);
+                }
+            }
+            _ => {
+                next_node
+                    .component
+                    .propagate(prev_input, &new_input, output, &mut mask);
+                next_node.component.release(&undriven, &mut mask);
+                if let Component::Fork(fork) = &next_node.component {
+                    for &(slot, except) in &terminals {
+                        let (bit, released) = fork.line_without(&new_input, &undriven, except);
+                        output.set(slot, bit);
+                        mask.set(slot, !released);
+                    }
+                }
+                if let Some(faults) = faults {
+                    fault::force_outputs(faults, output, &mut mask);
+                }
+            }
+        }
+        if let Some(prev_output) = prev_output {
+            let inputs = [prev_input.as_bitslice(), &new_input];
+            let outputs = [prev_output.as_bitslice(), output];
+            let (time, cause) = (graph.time, self.cause);
+            graph
+                .observers
+                .notify(next_node_ref, inputs, outputs, time, cause);
+        }
+        graph.time += 1;
+
+        graph.inputs[next_node_ref] = new_input;
+        let prev_released = graph.released.insert(next_node_ref, !mask.clone());
+        if let (Some(levels), Some(new_levels)) = (&mut graph.levels, new_levels) {
+            levels.inputs[next_node_ref] = new_levels;
+        }
+
+        let next_node = &graph.nodes[next_node_ref];
+        for (i, out_slot) in next_node.output_slots.iter().enumerate() {
+            let &Some(Slot {
+                target_node,
+                target_slot,
+            }) = out_slot
+            else {
+                continue;
+            };
+            let output_bit = match mask[i] {
+                true => output[i],
+                false => graph.nodes[target_node]
+                    .component
+                    .floating_input(target_slot),
+            };
+            let level = graph.levels.as_ref().map(|levels| {
+                let target_levels = self
+                    .new_levels
+                    .get(target_node)
+                    .unwrap_or(&levels.inputs[target_node]);
+                (levels.outputs[next_node_ref][i], target_levels[target_slot])
+            });
+
+            let target_new_input = self
+                .new_inputs
+                .get(target_node)
+                .unwrap_or(&graph.inputs[target_node]);
+
+            let changed = match level {
+                Some((level, target_level)) => level != target_level,
+                None => {
+                    let was_released = prev_released.as_ref().is_some_and(|released| released[i]);
+                    output_bit != target_new_input[target_slot] || was_released == mask[i]
+                }
+            };
+            if changed {
+                if !self.new_inputs.contains_key(target_node) {
+                    self.new_inputs
+                        .insert(target_node, target_new_input.clone());
+                    if let Some(levels) = &graph.levels {
+                        self.new_levels
+                            .insert(target_node, levels.inputs[target_node].clone());
+                    }
+                    self.queue.push_back(target_node);
+                }
+
+                self.new_inputs[target_node].set(target_slot, output_bit);
+                if let (Some((level, _)), Some(target_levels)) =
+                    (level, self.new_levels.get_mut(target_node))
+                {
+                    target_levels[target_slot] = level;
+                }
+            }
+        }
+        Some(next_node_ref)
+    }
+
+    /// Evaluates the next node like [`Propagation::step`], telling what it did.
+    pub fn step_details(&mut self, graph: &mut Graph) -> Option<PropagationStep> {
+        let &next = self.queue.get(self.next_index())?;
+        let prev_output = graph.outputs[next].clone();
+        let node = self.step(graph)?;
+        let outputs = graph.outputs[node].clone();
+        let changed = prev_output
+            .iter()
+            .by_vals()
+            .zip(outputs.iter().by_vals())
+            .enumerate()
+            .filter_map(|(slot, (old, new))| (old != new).then_some(slot))
+            .collect();
+        Some(PropagationStep {
+            node,
+            inputs: graph.inputs[node].clone(),
+            outputs,
+            changed,
+            queue: self.queue().collect(),
+        })
+    }
+
+    /// Makes the remaining steps at once.
+    pub fn finish(&mut self, graph: &mut Graph) {
+        while self.step(graph).is_some() {}
+    }
+}
+
+/// A node evaluated by a propagation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropagationStep {
+    pub node: ComponentId,
+    pub inputs: BitVec,
+    pub outputs: BitVec,
+    /// Output slots whose value changed.
+    pub changed: Vec<usize>,
+    /// Nodes left to evaluate, the next one first.
+    pub queue: Vec<ComponentId>,
+}
+
+/// Iterator over the nodes evaluated by a propagation, see [`Graph::steps_from`].
+pub struct Steps<'a> {
+    graph: &'a mut Graph,
+    propagation: Propagation,
+}
+
+impl<'a> Steps<'a> {
+    pub(super) fn new(graph: &'a mut Graph, propagation: Propagation) -> Self {
+        Self { graph, propagation }
+    }
+
+    pub fn graph(&self) -> &Graph {
+        self.graph
+    }
+}
+
+impl Iterator for Steps<'_> {
+    type Item = PropagationStep;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.propagation.step_details(self.graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{
+        gates::{And, Not},
+        simple::{Constant, DebugOutput, Fork},
+        wired::PullResistor,
+    };
+
+    #[test]
+    fn steps_match_propagation() {
+        let mut graph = Graph::new();
+        let a = graph.add_comp(Constant::default());
+        let fork = graph.add_comp(Fork::new(1, 2));
+        let not = graph.add_comp(Not);
+        let and = graph.add_comp(And);
+        let output = graph.add_comp(DebugOutput::default());
+        graph.add_conn(a, 0, fork, 0);
+        graph.add_conn(fork, 0, not, 0);
+        graph.add_conn(fork, 1, and, 1);
+        graph.add_conn(not, 0, and, 0);
+        graph.add_conn(and, 0, output, 0);
+        graph.propagate_all();
+
+        graph[a].state = true;
+        let mut expected = graph.clone();
+        expected.propagate_from(a);
+
+        let steps = graph.steps_from(a).collect::<Vec<_>>();
+        let nodes = steps.iter().map(|step| step.node).collect::<Vec<_>>();
+        assert_eq!(nodes, [a.into(), fork.into(), not.into(), and.into()]);
+        assert_eq!(steps[1].queue, [not.into(), and.into()]);
+        assert_eq!(steps[1].changed, [0, 1]);
+        assert_eq!(steps[2].changed, [0]);
+        // Both inputs of the and gate change before it is evaluated, so it does not glitch.
+        assert_eq!(steps[3].inputs, bits![0, 1]);
+        assert!(steps[3].changed.is_empty());
+        assert!(steps[3].queue.is_empty());
+        assert_eq!(graph.inputs, expected.inputs);
+        assert_eq!(graph.outputs, expected.outputs);
+        assert!(!graph[output].state);
+    }
+
+    #[test]
+    fn endless_loop_is_cut_short() {
+        let mut graph = Graph::new();
+        let enable = graph.add_comp(Constant::default());
+        let and = graph.add_comp(And);
+        let not = graph.add_comp(Not);
+        graph.add_conn(enable, 0, and, 0);
+        graph.add_conn(and, 0, not, 0);
+        graph.add_conn(not, 0, and, 1);
+        graph.propagate_all();
+
+        graph[enable].state = true;
+        let mut propagation = graph.start_propagation(enable);
+        propagation.finish(&mut graph);
+        assert!(propagation.overflowed());
+        assert_eq!(propagation.queue().count(), 0);
+    }
+
+    #[test]
+    fn disconnected_input_floats_when_evaluated() {
+        let mut graph = Graph::new();
+        let low = graph.add_comp(Constant::default());
+        let pull_up = graph.add_comp(PullResistor::up());
+        let output = graph.add_comp(DebugOutput::default());
+        graph.add_conn(low, 0, pull_up, 0);
+        graph.add_conn(pull_up, 0, output, 0);
+        graph.propagate_all();
+
+        // Disconnecting leaves the slots as they were until the node is evaluated.
+        graph.remove_conn(low, 0, pull_up, 0);
+        assert!(!graph.inputs[pull_up.into()][0]);
+        assert!(!graph[output].state);
+        graph.propagate_from(pull_up);
+        assert!(graph.inputs[pull_up.into()][0]);
+        assert!(graph[output].state);
+
+        graph.set_four_valued(true);
+        graph.add_conn(low, 0, pull_up, 0);
+        graph.propagate_from(low);
+        assert_eq!(graph.input_level(pull_up, 0), Logic::Zero);
+        graph.remove_conn_to(pull_up, 0);
+        graph.propagate_from(pull_up);
+        assert_eq!(graph.input_level(pull_up, 0), Logic::Z);
+        assert_eq!(graph.input_level(output, 0), Logic::One);
+    }
+}
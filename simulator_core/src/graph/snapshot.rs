@@ -0,0 +1,273 @@
+//! Dynamic state of a graph, kept apart from its topology.
+
+use bitvec::prelude::*;
+use slotmap::SecondaryMap;
+
+use super::{
+    id::ComponentId,
+    logic::{Levels, Logic},
+    Graph,
+};
+use crate::components::Component;
+
+/// State a component keeps besides its slots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComponentState {
+    /// State of a `Constant`, `DebugOutput` or `FlipFlop`.
+    Bit(bool),
+    /// State of a `FlipFlop` unknown to four-valued simulation,
+    /// with the bit two-valued simulation reads.
+    Unknown(bool),
+    /// Contents of a `Ram`.
+    Memory(Vec<u64>),
+    /// State of the graph of a `Subcircuit`.
+    Subcircuit(Box<Snapshot>),
+}
+
+impl ComponentState {
+    fn of(component: &Component) -> Option<Self> {
+        match component {
+            Component::Constant(constant) => Some(Self::Bit(constant.state)),
+            Component::DebugOutput(output) => Some(Self::Bit(output.state)),
+            Component::FlipFlop(flip_flop) if flip_flop.unknown => {
+                Some(Self::Unknown(flip_flop.state))
+            }
+            Component::FlipFlop(flip_flop) => Some(Self::Bit(flip_flop.state)),
+            Component::Ram(ram) => Some(Self::Memory(ram.contents.clone())),
+            Component::Subcircuit(sub) => Some(Self::Subcircuit(Box::new(sub.graph.snapshot()))),
+            _ => None,
+        }
+    }
+
+    fn restore(&self, component: &mut Component) {
+        match (self, component) {
+            (Self::Bit(state), Component::Constant(constant)) => constant.state = *state,
+            (Self::Bit(state), Component::DebugOutput(output)) => output.state = *state,
+            (Self::Bit(state), Component::FlipFlop(flip_flop)) => {
+                flip_flop.state = *state;
+                flip_flop.unknown = false;
+            }
+            (Self::Unknown(state), Component::FlipFlop(flip_flop)) => {
+                flip_flop.state = *state;
+                flip_flop.unknown = true;
+            }
+            (Self::Memory(contents), Component::Ram(ram))
+                if ram.contents.len() == contents.len() =>
+            {
+                ram.contents.clone_from(contents);
+            }
+            (Self::Subcircuit(snapshot), Component::Subcircuit(sub)) => {
+                sub.graph.restore(snapshot);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Slots and component states of a graph at one moment, without its topology.
+///
+/// Taken by [`Graph::snapshot`] and put back by [`Graph::restore`], e.g. to reset
+/// a circuit to its power-on state or to try two inputs on the same circuit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    inputs: SecondaryMap<ComponentId, BitVec>,
+    outputs: SecondaryMap<ComponentId, BitVec>,
+    /// Output slots left undriven.
+    released: SecondaryMap<ComponentId, BitVec>,
+    states: SecondaryMap<ComponentId, ComponentState>,
+    /// Levels of the slots, when the graph was four-valued.
+    levels: Option<Levels>,
+}
+
+impl Snapshot {
+    pub(super) fn take(graph: &Graph) -> Self {
+        let states = graph
+            .nodes
+            .iter()
+            .filter_map(|(id, node)| Some((id, ComponentState::of(&node.component)?)))
+            .collect();
+        Snapshot {
+            inputs: graph.inputs.clone(),
+            outputs: graph.outputs.clone(),
+            released: graph.released.clone(),
+            states,
+            levels: graph.levels.clone(),
+        }
+    }
+
+    /// Puts the state back into the nodes which are still in the graph
+    /// with the same number of slots.
+    ///
+    /// A four-valued graph restored from a two-valued snapshot gets the levels
+    /// of the restored bits, with its unconnected inputs floating.
+    pub(super) fn restore(&self, graph: &mut Graph) {
+        for (id, node) in &mut graph.nodes {
+            let mut restored = [false; 2];
+            if let Some(inputs) = self.inputs.get(id) {
+                if inputs.len() == graph.inputs[id].len() {
+                    graph.inputs[id].clone_from(inputs);
+                    restored[0] = true;
+                }
+            }
+            if let Some(outputs) = self.outputs.get(id) {
+                if outputs.len() == graph.outputs[id].len() {
+                    graph.outputs[id].clone_from(outputs);
+                    if let Some(released) = self.released.get(id) {
+                        graph.released.insert(id, released.clone());
+                    }
+                    restored[1] = true;
+                }
+            }
+            if let Some(state) = self.states.get(id) {
+                state.restore(&mut node.component);
+            }
+
+            let Some(levels) = &mut graph.levels else {
+                continue;
+            };
+            let taken = self.levels.as_ref();
+            let [inputs, outputs] = [&mut levels.inputs[id], &mut levels.outputs[id]];
+            match taken.and_then(|taken| taken.inputs.get(id)) {
+                Some(taken) if taken.len() == inputs.len() => inputs.clone_from(taken),
+                None if restored[0] => {
+                    let slots = node.input_slots.iter().zip(graph.inputs[id].iter());
+                    let levels = slots.map(|(slot, bit)| match slot {
+                        Some(_) => Logic::from(*bit),
+                        None => Logic::Z,
+                    });
+                    *inputs = levels.collect();
+                }
+                _ => {}
+            }
+            match taken.and_then(|taken| taken.outputs.get(id)) {
+                Some(taken) if taken.len() == outputs.len() => outputs.clone_from(taken),
+                None if restored[1] => {
+                    *outputs = graph.outputs[id]
+                        .iter()
+                        .map(|bit| Logic::from(*bit))
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn state(&self, node: impl Into<ComponentId>) -> Option<&ComponentState> {
+        self.states.get(node.into())
+    }
+
+    /// Nodes whose slots or state differ between the snapshots, or which are in only one.
+    pub fn changed_nodes(&self, other: &Snapshot) -> Vec<ComponentId> {
+        let mut nodes = self.inputs.keys().collect::<Vec<_>>();
+        nodes.extend(
+            other
+                .inputs
+                .keys()
+                .filter(|&id| !self.inputs.contains_key(id)),
+        );
+        nodes.retain(|&id| {
+            self.inputs.get(id) != other.inputs.get(id)
+                || self.outputs.get(id) != other.outputs.get(id)
+                || self.released.get(id) != other.released.get(id)
+                || self.states.get(id) != other.states.get(id)
+                || self.levels_of(id) != other.levels_of(id)
+        });
+        nodes
+    }
+
+    fn levels_of(&self, id: ComponentId) -> Option<(&Vec<Logic>, &Vec<Logic>)> {
+        let levels = self.levels.as_ref()?;
+        Some((levels.inputs.get(id)?, levels.outputs.get(id)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{
+        gates::Xor,
+        memory::{FlipFlop, FlipFlopKind},
+        simple::{Constant, DebugOutput, Fork},
+    };
+
+    #[test]
+    fn restores_toggle_flip_flop() {
+        let mut graph = Graph::new();
+        let clock = graph.add_comp(Constant::default());
+        let toggle = graph.add_comp(Constant { state: true });
+        let flip_flop = graph.add_comp(FlipFlop::new(FlipFlopKind::T));
+        let fork = graph.add_comp(Fork::new(1, 2));
+        let xor = graph.add_comp(Xor);
+        let output = graph.add_comp(DebugOutput::default());
+        graph.add_conn(toggle, 0, flip_flop, 0);
+        graph.add_conn(clock, 0, flip_flop, 1);
+        graph.add_conn(flip_flop, 0, fork, 0);
+        graph.add_conn(fork, 0, xor, 0);
+        graph.add_conn(fork, 1, output, 0);
+        graph.propagate_all();
+        let cycle = |graph: &mut Graph| {
+            for state in [true, false] {
+                graph[clock].state = state;
+                graph.propagate_from(clock);
+            }
+        };
+
+        let power_on = graph.snapshot();
+        cycle(&mut graph);
+        assert!(graph[output].state);
+        let after = graph.snapshot();
+        let changed = power_on.changed_nodes(&after);
+        for node in [flip_flop.into(), fork.into(), xor.into(), output.into()] {
+            assert!(changed.contains(&node));
+        }
+        assert!(!changed.contains(&toggle.into()));
+        assert_eq!(after.state(flip_flop), Some(&ComponentState::Bit(true)));
+
+        graph.restore(&power_on);
+        assert_eq!(graph.snapshot(), power_on);
+        assert!(!graph[output].state);
+        // The restored state is simulated like the one it was taken from.
+        cycle(&mut graph);
+        assert_eq!(graph.snapshot().changed_nodes(&after), Vec::new());
+    }
+
+    #[test]
+    fn restores_unknown_levels() {
+        let mut graph = Graph::new();
+        let data = graph.add_comp(Constant::default());
+        let clock = graph.add_comp(Constant::default());
+        let flip_flop = graph.add_comp(FlipFlop::new(FlipFlopKind::D));
+        let output = graph.add_comp(DebugOutput::default());
+        graph.add_conn(data, 0, flip_flop, 0);
+        graph.add_conn(clock, 0, flip_flop, 1);
+        graph.add_conn(flip_flop, 0, output, 0);
+        graph.set_four_valued(true);
+        let cycle = |graph: &mut Graph| {
+            for state in [true, false] {
+                graph[clock].state = state;
+                graph.propagate_from(clock);
+            }
+        };
+
+        let power_on = graph.snapshot();
+        assert_eq!(
+            power_on.state(flip_flop),
+            Some(&ComponentState::Unknown(false))
+        );
+        cycle(&mut graph);
+        assert_eq!(graph.input_level(output, 0), Logic::Zero);
+        // Only the levels tell the known low output from the unknown one.
+        let after = graph.snapshot();
+        let changed = power_on.changed_nodes(&after);
+        for node in [flip_flop.into(), output.into()] {
+            assert!(changed.contains(&node));
+        }
+
+        graph.restore(&power_on);
+        assert_eq!(graph.snapshot(), power_on);
+        assert_eq!(graph.output_level(flip_flop, 0), Logic::X);
+        assert_eq!(graph.input_level(output, 0), Logic::X);
+        cycle(&mut graph);
+        assert_eq!(graph.snapshot().changed_nodes(&after), Vec::new());
+    }
+}
@@ -0,0 +1,612 @@
+//! Logisim components: their pins, relative to the component location, and their behaviour.
+//!
+//! Pin positions follow the classic appearance of Logisim components.
+
+use std::collections::HashMap;
+
+use crate::{
+    components::{
+        gates::{And, Not, Or, Xor},
+        memory::{FlipFlop, FlipFlopKind, Ram, Rom, Trigger},
+        Component,
+    },
+    import::netlist::composite,
+};
+
+use super::{LogisimError, Point};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Facing {
+    East,
+    West,
+    North,
+    South,
+}
+
+impl Facing {
+    /// Rotates an offset given for a component facing east.
+    pub fn rotate(self, (x, y): Point) -> Point {
+        match self {
+            Facing::East => (x, y),
+            Facing::West => (-x, -y),
+            Facing::North => (y, -x),
+            Facing::South => (-y, x),
+        }
+    }
+
+    pub fn rotate_bounds(self, (min, max): (Point, Point)) -> (Point, Point) {
+        let (a, b) = (self.rotate(min), self.rotate(max));
+        ((a.0.min(b.0), a.1.min(b.1)), (a.0.max(b.0), a.1.max(b.1)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortKind {
+    Input,
+    Output,
+    /// Pin without an equivalent, it must be left unconnected.
+    Unused,
+}
+
+#[derive(Debug, Clone)]
+pub struct Port {
+    pub offset: Point,
+    pub width: usize,
+    pub kind: PortKind,
+}
+
+impl Port {
+    fn new(offset: Point, width: usize, kind: PortKind) -> Self {
+        Self {
+            offset,
+            width,
+            kind,
+        }
+    }
+}
+
+pub enum Function {
+    InputPin(String),
+    OutputPin(String),
+    /// Single component, its input slots are the bits of the input ports in order, same for outputs.
+    Cell(Component),
+    Constant(u64),
+    /// End of every bit of the combined port, if it has one. Port 0 is the combined one.
+    Splitter(Vec<Option<usize>>),
+    Tunnel(String),
+    Decoration,
+}
+
+pub struct Part {
+    pub function: Function,
+    pub ports: Vec<Port>,
+    pub bounds: (Point, Point),
+}
+
+/// Component element of a circuit, with its `<a>` attributes.
+pub struct Comp<'a> {
+    pub name: &'a str,
+    pub location: Point,
+    pub attributes: HashMap<&'a str, &'a str>,
+}
+
+impl Comp<'_> {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).copied()
+    }
+
+    pub fn number(&self, name: &str, default: u64) -> Result<u64, LogisimError> {
+        match self.attr(name) {
+            None => Ok(default),
+            Some(value) => parse_number(value).ok_or_else(|| self.invalid(name, value)),
+        }
+    }
+
+    pub fn facing(&self, default: Facing) -> Result<Facing, LogisimError> {
+        match self.attr("facing") {
+            None => Ok(default),
+            Some("east") => Ok(Facing::East),
+            Some("west") => Ok(Facing::West),
+            Some("north") => Ok(Facing::North),
+            Some("south") => Ok(Facing::South),
+            Some(value) => Err(self.invalid("facing", value)),
+        }
+    }
+
+    pub fn label(&self) -> Option<String> {
+        self.attr("label")
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+            .map(str::to_string)
+    }
+
+    fn width(&self) -> Result<usize, LogisimError> {
+        match self.number("width", 1)? {
+            width @ 1..=64 => Ok(width as usize),
+            _ => Err(self.invalid("width", self.attr("width").unwrap())),
+        }
+    }
+
+    fn invalid(&self, attribute: &str, value: &str) -> LogisimError {
+        LogisimError::InvalidAttribute {
+            component: self.name.to_string(),
+            location: self.location,
+            attribute: attribute.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    pub fn unsupported(&self, feature: &str) -> LogisimError {
+        LogisimError::Unsupported {
+            component: self.name.to_string(),
+            location: self.location,
+            feature: feature.to_string(),
+        }
+    }
+}
+
+fn parse_number(value: &str) -> Option<u64> {
+    let value = value.trim();
+    match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GateKind {
+    And,
+    Or,
+    Xor,
+}
+
+/// Builds the part of a library component, `None` if it's not a known one.
+///
+/// `default_inputs` is the number of gate inputs used when the file doesn't say,
+/// it differs between Logisim versions.
+pub fn part(comp: &Comp, default_inputs: u64) -> Result<Option<Part>, LogisimError> {
+    let part = match comp.name {
+        "AND Gate" => gate(comp, default_inputs, GateKind::And, false)?,
+        "OR Gate" => gate(comp, default_inputs, GateKind::Or, false)?,
+        "XOR Gate" => gate(comp, default_inputs, GateKind::Xor, false)?,
+        "NAND Gate" => gate(comp, default_inputs, GateKind::And, true)?,
+        "NOR Gate" => gate(comp, default_inputs, GateKind::Or, true)?,
+        "XNOR Gate" => gate(comp, default_inputs, GateKind::Xor, true)?,
+        "NOT Gate" | "Buffer" => inverter(comp)?,
+        "Pin" => pin(comp)?,
+        "Clock" | "Button" => {
+            let name = comp.label().unwrap_or_else(|| comp.name.to_lowercase());
+            single(comp, Function::InputPin(name), PortKind::Output, 1, 20)?
+        }
+        "Probe" | "LED" => {
+            let name = comp.label().unwrap_or_else(|| comp.name.to_lowercase());
+            let facing = if comp.name == "LED" {
+                Facing::West
+            } else {
+                Facing::East
+            };
+            let port = vec![Port::new((0, 0), 1, PortKind::Input)];
+            Part {
+                function: Function::OutputPin(name),
+                ports: port,
+                bounds: comp.facing(facing)?.rotate_bounds(((-20, -10), (0, 10))),
+            }
+        }
+        "Constant" => {
+            let width = comp.width()?;
+            let value = comp.number("value", 1)?;
+            single(comp, Function::Constant(value), PortKind::Output, width, 10)?
+        }
+        "Power" | "Ground" => {
+            let value = (comp.name == "Power") as u64;
+            let facing = if value == 1 {
+                Facing::North
+            } else {
+                Facing::South
+            };
+            let width = comp.width()?;
+            Part {
+                function: Function::Constant(if value == 1 { u64::MAX } else { 0 }),
+                ports: vec![Port::new((0, 0), width, PortKind::Output)],
+                bounds: comp.facing(facing)?.rotate_bounds(((-10, -10), (0, 10))),
+            }
+        }
+        "Tunnel" => {
+            let label = comp
+                .label()
+                .ok_or_else(|| comp.unsupported("tunnels without a label"))?;
+            let width = comp.width()?;
+            Part {
+                function: Function::Tunnel(label),
+                ports: vec![Port::new((0, 0), width, PortKind::Input)],
+                bounds: comp
+                    .facing(Facing::West)?
+                    .rotate_bounds(((0, -10), (20, 10))),
+            }
+        }
+        "Splitter" => splitter(comp)?,
+        "D Flip-Flop" => flip_flop(comp, FlipFlopKind::D)?,
+        "T Flip-Flop" => flip_flop(comp, FlipFlopKind::T)?,
+        "J-K Flip-Flop" => flip_flop(comp, FlipFlopKind::JK)?,
+        "S-R Flip-Flop" => flip_flop(comp, FlipFlopKind::SR)?,
+        "ROM" => rom(comp)?,
+        "RAM" => ram(comp)?,
+        "Text" | "Text Tool" => Part {
+            function: Function::Decoration,
+            ports: Vec::new(),
+            bounds: ((0, 0), (0, 0)),
+        },
+        "Controlled Buffer" | "Controlled Inverter" => {
+            return Err(comp.unsupported("three-state outputs"))
+        }
+        "Pull Resistor" => return Err(comp.unsupported("pull resistors")),
+        _ => return Ok(None),
+    };
+    Ok(Some(part))
+}
+
+/// Part with a single port at its location, drawn as a box of the given length behind it.
+fn single(
+    comp: &Comp,
+    function: Function,
+    kind: PortKind,
+    width: usize,
+    length: i32,
+) -> Result<Part, LogisimError> {
+    Ok(Part {
+        function,
+        ports: vec![Port::new((0, 0), width, kind)],
+        bounds: comp
+            .facing(Facing::East)?
+            .rotate_bounds(((-length, -10), (0, 10))),
+    })
+}
+
+fn pin(comp: &Comp) -> Result<Part, LogisimError> {
+    let output = comp.attr("output") == Some("true");
+    if comp.attr("tristate") == Some("true") && !output {
+        return Err(comp.unsupported("three-state pins"));
+    }
+    let width = comp.width()?;
+    let name = comp.label().unwrap_or_else(|| {
+        let (x, y) = comp.location;
+        format!("pin_{x}_{y}")
+    });
+
+    let (function, kind) = if output {
+        (Function::OutputPin(name), PortKind::Input)
+    } else {
+        (Function::InputPin(name), PortKind::Output)
+    };
+    single(comp, function, kind, width, 20)
+}
+
+fn gate_size(comp: &Comp) -> Result<i32, LogisimError> {
+    match comp.attr("size") {
+        None => Ok(50),
+        Some("narrow") => Ok(30),
+        Some("medium") => Ok(50),
+        Some("wide") => Ok(70),
+        Some(value) => match parse_number(value) {
+            Some(size @ (30 | 50 | 70)) => Ok(size as i32),
+            _ => Err(comp.invalid("size", value)),
+        },
+    }
+}
+
+/// Offset of a gate input, like Logisim places them.
+fn gate_input_offset(
+    facing: Facing,
+    size: i32,
+    axis_length: i32,
+    inputs: i32,
+    index: i32,
+    negated: bool,
+) -> Point {
+    let (skip_start, skip_dist, skip_lower_even) = if inputs <= 3 {
+        if size < 40 {
+            (-5, 10, 10)
+        } else if size < 60 || inputs <= 2 {
+            (-10, 20, 20)
+        } else {
+            (-15, 30, 30)
+        }
+    } else if inputs == 4 && size >= 60 {
+        (-5, 20, 0)
+    } else {
+        (-5, 10, 10)
+    };
+
+    let dy = if inputs % 2 == 1 {
+        skip_start * (inputs - 1) + skip_dist * index
+    } else {
+        let dy = skip_start * inputs + skip_dist * index;
+        if index >= inputs / 2 {
+            dy + skip_lower_even
+        } else {
+            dy
+        }
+    };
+    let dx = axis_length + if negated { 10 } else { 0 };
+
+    match facing {
+        Facing::North => (dy, dx),
+        Facing::South => (dy, -dx),
+        Facing::West => (dx, dy),
+        Facing::East => (-dx, dy),
+    }
+}
+
+fn gate(
+    comp: &Comp,
+    default_inputs: u64,
+    kind: GateKind,
+    negate_output: bool,
+) -> Result<Part, LogisimError> {
+    let facing = comp.facing(Facing::East)?;
+    let size = gate_size(comp)?;
+    let width = comp.width()?;
+    let inputs = match comp.number("inputs", default_inputs)? {
+        inputs @ 2..=64 => inputs as usize,
+        _ => return Err(comp.invalid("inputs", comp.attr("inputs").unwrap())),
+    };
+    let negated = (0..inputs)
+        .map(|i| comp.attr(&format!("negate{i}")) == Some("true"))
+        .collect::<Vec<_>>();
+
+    let bonus = if kind == GateKind::Xor { 10 } else { 0 };
+    let axis_length = size + bonus + if negate_output { 10 } else { 0 };
+
+    let mut ports = (0..inputs)
+        .map(|i| {
+            let offset = gate_input_offset(
+                facing,
+                size,
+                axis_length,
+                inputs as i32,
+                i as i32,
+                negated[i],
+            );
+            Port::new(offset, width, PortKind::Input)
+        })
+        .collect::<Vec<_>>();
+    ports.push(Port::new((0, 0), width, PortKind::Output));
+
+    let plain = inputs == 2 && width == 1 && !negate_output && !negated.contains(&true);
+    let component = if plain {
+        match kind {
+            GateKind::And => And.into(),
+            GateKind::Or => Or.into(),
+            GateKind::Xor => Xor.into(),
+        }
+    } else {
+        composite(comp.name, inputs * width, width, |netlist, bits| {
+            (0..width)
+                .map(|b| {
+                    let values = (0..inputs)
+                        .map(|i| {
+                            let bit = bits[i * width + b];
+                            if negated[i] {
+                                netlist.not(bit)
+                            } else {
+                                bit
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    let result = values[1..].iter().fold(values[0], |acc, &bit| match kind {
+                        GateKind::And => netlist.and(acc, bit),
+                        GateKind::Or => netlist.or(acc, bit),
+                        GateKind::Xor => netlist.xor(acc, bit),
+                    });
+                    if negate_output {
+                        netlist.not(result)
+                    } else {
+                        result
+                    }
+                })
+                .collect()
+        })
+    };
+
+    let half = axis_length.max(size) / 2;
+    Ok(Part {
+        function: Function::Cell(component),
+        ports,
+        bounds: facing.rotate_bounds(((-axis_length, -half), (0, half))),
+    })
+}
+
+fn inverter(comp: &Comp) -> Result<Part, LogisimError> {
+    let facing = comp.facing(Facing::East)?;
+    let width = comp.width()?;
+    let invert = comp.name == "NOT Gate";
+    let length = match (invert, comp.attr("size")) {
+        (false, _) => 20,
+        (true, None | Some("wide" | "30")) => 30,
+        (true, Some("narrow" | "20")) => 20,
+        (true, Some(value)) => return Err(comp.invalid("size", value)),
+    };
+
+    let component = if invert && width == 1 {
+        Not.into()
+    } else {
+        composite(comp.name, width, width, |netlist, bits| {
+            bits.iter()
+                .map(|&bit| if invert { netlist.not(bit) } else { bit })
+                .collect()
+        })
+    };
+
+    Ok(Part {
+        function: Function::Cell(component),
+        ports: vec![
+            Port::new(facing.rotate((-length, 0)), width, PortKind::Input),
+            Port::new((0, 0), width, PortKind::Output),
+        ],
+        bounds: facing.rotate_bounds(((-length, -10), (0, 10))),
+    })
+}
+
+fn splitter(comp: &Comp) -> Result<Part, LogisimError> {
+    let facing = comp.facing(Facing::East)?;
+    let fanout = match comp.number("fanout", 2)? {
+        fanout @ 1..=64 => fanout as usize,
+        _ => return Err(comp.invalid("fanout", comp.attr("fanout").unwrap())),
+    };
+    let incoming = match comp.number("incoming", 2)? {
+        incoming @ 1..=64 => incoming as usize,
+        _ => return Err(comp.invalid("incoming", comp.attr("incoming").unwrap())),
+    };
+
+    let chunk = 1 + (incoming - 1) / fanout;
+    let mut ends = Vec::new();
+    for bit in 0..incoming {
+        let end = match comp.attr(&format!("bit{bit}")) {
+            None => Some(bit / chunk),
+            Some("none") => None,
+            Some(value) => match parse_number(value) {
+                Some(end) if (end as usize) < fanout => Some(end as usize),
+                _ => return Err(comp.invalid(&format!("bit{bit}"), value)),
+            },
+        };
+        ends.push(end);
+    }
+
+    let fanout_i = fanout as i32;
+    let first = match comp.attr("appear") {
+        None | Some("left" | "legacy") => -10 * fanout_i,
+        Some("right") => 10,
+        Some("center") => -10 * (fanout_i / 2),
+        Some(value) => return Err(comp.invalid("appear", value)),
+    };
+    let end_y = |end: i32| {
+        let y = first + 10 * end;
+        if comp.attr("appear") == Some("center") && fanout % 2 == 0 && y >= 0 {
+            y + 10
+        } else {
+            y
+        }
+    };
+
+    let mut ports = vec![Port::new((0, 0), incoming, PortKind::Input)];
+    for end in 0..fanout {
+        let width = ends.iter().filter(|&&e| e == Some(end)).count();
+        let offset = facing.rotate((20, end_y(end as i32)));
+        ports.push(Port::new(offset, width, PortKind::Input));
+    }
+
+    let (top, bottom) = (end_y(0).min(0), end_y(fanout_i - 1).max(0));
+    Ok(Part {
+        function: Function::Splitter(ends),
+        ports,
+        bounds: facing.rotate_bounds(((0, top), (20, bottom))),
+    })
+}
+
+fn trigger(comp: &Comp) -> Result<Trigger, LogisimError> {
+    match comp.attr("trigger") {
+        None | Some("rising") => Ok(Trigger::RisingEdge),
+        Some("falling") => Ok(Trigger::FallingEdge),
+        Some("high") => Ok(Trigger::HighLevel),
+        Some("low") => Ok(Trigger::LowLevel),
+        Some(value) => Err(comp.invalid("trigger", value)),
+    }
+}
+
+fn flip_flop(comp: &Comp, kind: FlipFlopKind) -> Result<Part, LogisimError> {
+    let flip_flop = FlipFlop {
+        kind,
+        trigger: trigger(comp)?,
+        ..Default::default()
+    };
+
+    let mut ports = match kind.data_inputs() {
+        1 => vec![(-40, 0), (-40, 20)],
+        _ => vec![(-40, 0), (-40, 20), (-40, 10)],
+    }
+    .into_iter()
+    .chain([(-10, 30), (-30, 30)])
+    .map(|offset| Port::new(offset, 1, PortKind::Input))
+    .collect::<Vec<_>>();
+    ports.push(Port::new((0, 0), 1, PortKind::Output));
+    ports.push(Port::new((0, 20), 1, PortKind::Output));
+    ports.push(Port::new((-20, 30), 1, PortKind::Unused));
+
+    Ok(Part {
+        function: Function::Cell(flip_flop.into()),
+        ports,
+        bounds: ((-40, -10), (0, 30)),
+    })
+}
+
+fn memory_widths(comp: &Comp) -> Result<(u8, u8), LogisimError> {
+    let address = match comp.number("addrWidth", 8)? {
+        width @ 1..=16 => width as u8,
+        _ => return Err(comp.invalid("addrWidth", comp.attr("addrWidth").unwrap())),
+    };
+    let data = match comp.number("dataWidth", 8)? {
+        width @ 1..=64 => width as u8,
+        _ => return Err(comp.invalid("dataWidth", comp.attr("dataWidth").unwrap())),
+    };
+    Ok((address, data))
+}
+
+/// Parses memory contents like `addr/data: 8 8\n0 1 4*0 ff`.
+fn memory_contents(comp: &Comp) -> Result<Vec<u64>, LogisimError> {
+    let Some(text) = comp.attr("contents") else {
+        return Ok(Vec::new());
+    };
+    let invalid = || comp.invalid("contents", text);
+
+    let mut contents = Vec::new();
+    for word in text.lines().skip(1).flat_map(str::split_whitespace) {
+        let (count, value) = match word.split_once('*') {
+            Some((count, value)) => (count.parse().map_err(|_| invalid())?, value),
+            None => (1, word),
+        };
+        let value = u64::from_str_radix(value, 16).map_err(|_| invalid())?;
+        contents.resize(contents.len() + count, value);
+    }
+    Ok(contents)
+}
+
+fn rom(comp: &Comp) -> Result<Part, LogisimError> {
+    let (address, data) = memory_widths(comp)?;
+    let mut contents = memory_contents(comp)?;
+    contents.truncate(1 << address);
+
+    Ok(Part {
+        function: Function::Cell(Rom::new(address, data, contents).into()),
+        ports: vec![
+            Port::new((-140, 0), address as usize, PortKind::Input),
+            Port::new((0, 0), data as usize, PortKind::Output),
+            Port::new((-90, 40), 1, PortKind::Unused),
+        ],
+        bounds: ((-140, -40), (0, 40)),
+    })
+}
+
+fn ram(comp: &Comp) -> Result<Part, LogisimError> {
+    if comp.attr("bus") != Some("separate") {
+        return Err(comp.unsupported("memories with a shared data bus"));
+    }
+    let (address, data) = memory_widths(comp)?;
+    let mut ram = Ram::new(address, data);
+    ram.trigger = trigger(comp)?;
+
+    let input = |offset, width| Port::new(offset, width, PortKind::Input);
+    Ok(Part {
+        function: Function::Cell(ram.into()),
+        ports: vec![
+            input((-140, 0), address as usize),
+            input((-140, 20), data as usize),
+            input((-110, 40), 1),
+            input((-70, 40), 1),
+            input((-30, 40), 1),
+            Port::new((0, 0), data as usize, PortKind::Output),
+            Port::new((-90, 40), 1, PortKind::Unused),
+            Port::new((-50, 40), 1, PortKind::Unused),
+        ],
+        bounds: ((-140, -40), (0, 40)),
+    })
+}
@@ -0,0 +1,1014 @@
+//! Import of Logisim and Logisim-evolution `.circ` projects.
+//!
+//! Supported components: gates (any number of inputs, negated inputs, multi-bit), NOT gates, buffers,
+//! pins, clocks, buttons, probes, LEDs, constants, power, ground, tunnels, splitters,
+//! D/T/J-K/S-R flip-flops, ROMs, RAMs with a separate data input and subcircuits from the same file.
+//! Connections are found from the geometry of the wires, like Logisim does it.
+//! Pins of components are placed like in their classic appearance,
+//! subcircuits use their custom appearance if the file has one.
+
+mod library;
+mod xml;
+
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::Display,
+};
+
+use crate::{
+    components::{simple::Constant, subcircuit::Subcircuit},
+    graph::{id::ComponentId, Graph},
+};
+
+use self::{
+    library::{Comp, Facing, Function, Part, PortKind},
+    xml::Element,
+};
+
+use super::netlist::{Bit, Built, Netlist};
+
+/// Position on the Logisim canvas, the grid is 10 units wide.
+pub type Point = (i32, i32);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogisimError {
+    Xml(String),
+    NoCircuit,
+    UnknownCircuit {
+        name: String,
+    },
+    RecursiveCircuit {
+        name: String,
+    },
+    UnknownComponent {
+        name: String,
+        location: Point,
+    },
+    InvalidAttribute {
+        component: String,
+        location: Point,
+        attribute: String,
+        value: String,
+    },
+    Unsupported {
+        component: String,
+        location: Point,
+        feature: String,
+    },
+    WidthMismatch {
+        circuit: String,
+        location: Point,
+    },
+    MultipleDrivers {
+        circuit: String,
+        net: String,
+    },
+}
+
+impl Display for LogisimError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogisimError::Xml(message) => write!(f, "invalid project file: {message}"),
+            LogisimError::NoCircuit => write!(f, "the project has no circuits"),
+            LogisimError::UnknownCircuit { name } => write!(f, "unknown circuit `{name}`"),
+            LogisimError::RecursiveCircuit { name } => {
+                write!(f, "circuit `{name}` contains itself")
+            }
+            LogisimError::UnknownComponent { name, location } => {
+                write!(f, "{location:?}: unknown component `{name}`")
+            }
+            LogisimError::InvalidAttribute {
+                component,
+                location,
+                attribute,
+                value,
+            } => write!(
+                f,
+                "{location:?}: invalid value `{value}` of `{attribute}` in `{component}`"
+            ),
+            LogisimError::Unsupported {
+                component,
+                location,
+                feature,
+            } => write!(
+                f,
+                "{location:?}: `{component}` uses {feature}, which is not supported"
+            ),
+            LogisimError::WidthMismatch { circuit, location } => {
+                write!(
+                    f,
+                    "{location:?}: incompatible widths in circuit `{circuit}`"
+                )
+            }
+            LogisimError::MultipleDrivers { circuit, net } => {
+                write!(
+                    f,
+                    "net {net} in circuit `{circuit}` has more than one driver"
+                )
+            }
+        }
+    }
+}
+
+impl Error for LogisimError {}
+
+pub struct LogisimCircuit {
+    pub graph: Graph,
+    /// Original drawing of the circuit, as far as it can be kept.
+    pub layout: Layout,
+}
+
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub components: Vec<PlacedComponent>,
+    /// Straight wire segments, split wherever something is connected to them.
+    pub wires: Vec<[Point; 2]>,
+    /// Why parts of the drawing were left out, their components and nets have to be laid out anew.
+    pub unplaced: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlacedComponent {
+    pub id: ComponentId,
+    /// Top left and bottom right corner.
+    pub bounds: (Point, Point),
+    /// Positions of the input slots.
+    pub inputs: Vec<Point>,
+    /// Positions of the output slots.
+    pub outputs: Vec<Point>,
+}
+
+/// Builds a graph from a Logisim project.
+///
+/// `circuit` selects the circuit to build, by default it's the main circuit of the project.
+/// Input pins, clocks and buttons become named `Constant`s, output pins, probes and LEDs named `DebugOutput`s,
+/// multi-bit ones get one component per bit, named like `data[3]`. Other circuits used by it become `Subcircuit`s.
+pub fn from_logisim(source: &str, circuit: Option<&str>) -> Result<LogisimCircuit, LogisimError> {
+    let project = xml::parse(source).map_err(LogisimError::Xml)?;
+    if project.name != "project" {
+        return Err(LogisimError::Xml("not a Logisim project".to_string()));
+    }
+
+    let circuits = project
+        .children_named("circuit")
+        .filter_map(|c| Some((c.attr("name")?, c)))
+        .collect::<HashMap<_, _>>();
+
+    let name = match circuit {
+        Some(name) => name,
+        None => project
+            .children_named("main")
+            .find_map(|main| main.attr("name"))
+            .or_else(|| {
+                project
+                    .children_named("circuit")
+                    .find_map(|c| c.attr("name"))
+            })
+            .ok_or(LogisimError::NoCircuit)?,
+    };
+
+    // Logisim 2 defaulted to gates with 5 inputs, Logisim-evolution to 2.
+    let default_inputs = match project.attr("source") {
+        Some(version) if version.starts_with("2.") => 5,
+        _ => 2,
+    };
+
+    let mut elaborator = Elaborator {
+        circuits,
+        default_inputs,
+        stack: Vec::new(),
+    };
+    let elaborated = elaborator.circuit(name)?;
+
+    Ok(LogisimCircuit {
+        graph: elaborated.built.graph,
+        layout: elaborated.layout,
+    })
+}
+
+fn parse_point(text: &str) -> Option<Point> {
+    let (x, y) = text
+        .trim()
+        .strip_prefix('(')?
+        .strip_suffix(')')?
+        .split_once(',')?;
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+fn add((ax, ay): Point, (bx, by): Point) -> Point {
+    (ax + bx, ay + by)
+}
+
+/// Pin of a circuit, seen from the outside when it's used as a subcircuit.
+struct InterfacePin {
+    location: Point,
+    facing: Facing,
+    width: usize,
+    output: bool,
+    /// Indices of its bits in `Built::inputs` or `Built::outputs`.
+    bits: Vec<usize>,
+}
+
+struct Elaborated {
+    built: Built,
+    /// Sorted top to bottom, then left to right.
+    interface: Vec<InterfacePin>,
+    layout: Layout,
+}
+
+/// Component of the graph a part became.
+#[derive(Debug, Clone, Copy)]
+enum Ref {
+    Input(usize),
+    Output(usize),
+    Cell(usize),
+}
+
+struct Placed {
+    name: String,
+    location: Point,
+    facing: Facing,
+    part: Part,
+}
+
+impl Placed {
+    fn port_point(&self, port: usize) -> Point {
+        add(self.location, self.part.ports[port].offset)
+    }
+}
+
+/// Union-find over points of the canvas.
+#[derive(Default)]
+struct Points {
+    index: HashMap<Point, usize>,
+    points: Vec<Point>,
+    parent: Vec<usize>,
+}
+
+impl Points {
+    fn id(&mut self, point: Point) -> usize {
+        *self.index.entry(point).or_insert_with(|| {
+            self.points.push(point);
+            self.parent.push(self.parent.len());
+            self.parent.len() - 1
+        })
+    }
+
+    fn find(&mut self, point: Point) -> usize {
+        let mut id = self.id(point);
+        while self.parent[id] != id {
+            self.parent[id] = self.parent[self.parent[id]];
+            id = self.parent[id];
+        }
+        id
+    }
+
+    fn union(&mut self, a: Point, b: Point) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parent[a] = b;
+    }
+}
+
+struct Elaborator<'a> {
+    circuits: HashMap<&'a str, &'a Element>,
+    default_inputs: u64,
+    stack: Vec<String>,
+}
+
+impl<'a> Elaborator<'a> {
+    fn circuit(&mut self, name: &str) -> Result<Elaborated, LogisimError> {
+        let element = *self
+            .circuits
+            .get(name)
+            .ok_or_else(|| LogisimError::UnknownCircuit {
+                name: name.to_string(),
+            })?;
+        if self.stack.iter().any(|n| n == name) {
+            return Err(LogisimError::RecursiveCircuit {
+                name: name.to_string(),
+            });
+        }
+        self.stack.push(name.to_string());
+
+        let mut parts = Vec::new();
+        for comp in element.children_named("comp") {
+            parts.push(self.part(comp)?);
+        }
+
+        let mut wires = Vec::new();
+        for wire in element.children_named("wire") {
+            let from = wire.attr("from").and_then(parse_point);
+            let to = wire.attr("to").and_then(parse_point);
+            let (Some(from), Some(to)) = (from, to) else {
+                return Err(LogisimError::Xml(format!(
+                    "wire in circuit `{name}` without valid ends"
+                )));
+            };
+            if from != to {
+                wires.push([from, to]);
+            }
+        }
+
+        let mut ends = wires.iter().flatten().copied().collect::<HashSet<_>>();
+        let port_points = parts
+            .iter()
+            .flat_map(|p: &Placed| (0..p.part.ports.len()).map(|i| p.port_point(i)))
+            .collect::<Vec<_>>();
+        ends.extend(&port_points);
+        let wires = split_wires(&wires, &ends);
+        let wire_ends = wires.iter().flatten().copied().collect::<HashSet<_>>();
+
+        let mut points = Points::default();
+        for &[from, to] in &wires {
+            points.union(from, to);
+        }
+        let mut tunnels = HashMap::new();
+        for placed in &parts {
+            if let Function::Tunnel(label) = &placed.part.function {
+                let point = placed.port_point(0);
+                match tunnels.get(label) {
+                    Some(&other) => points.union(point, other),
+                    None => {
+                        tunnels.insert(label.clone(), point);
+                    }
+                }
+            }
+        }
+
+        // Ports attached to every net and the width of its bus.
+        let mut attached: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
+        let mut widths = HashMap::new();
+        for (p, placed) in parts.iter().enumerate() {
+            for (i, port) in placed.part.ports.iter().enumerate() {
+                let point = placed.port_point(i);
+                let class = points.find(point);
+                attached.entry(class).or_default().push((p, i));
+                if port.kind == PortKind::Unused {
+                    continue;
+                }
+                if *widths.entry(class).or_insert(port.width) != port.width {
+                    return Err(LogisimError::WidthMismatch {
+                        circuit: name.to_string(),
+                        location: point,
+                    });
+                }
+            }
+        }
+
+        for placed in &parts {
+            for (i, port) in placed.part.ports.iter().enumerate() {
+                let point = placed.port_point(i);
+                let class = points.find(point);
+                if port.kind == PortKind::Unused
+                    && (attached[&class].len() > 1 || wire_ends.contains(&point))
+                {
+                    return Err(LogisimError::Unsupported {
+                        component: placed.name.clone(),
+                        location: placed.location,
+                        feature: format!("the pin at {point:?}"),
+                    });
+                }
+            }
+        }
+
+        let mut netlist = Netlist::default();
+        let mut nets: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut port_nets = |points: &mut Points, netlist: &mut Netlist, point: Point| {
+            let class = points.find(point);
+            let width = widths[&class];
+            nets.entry(class)
+                .or_insert_with(|| {
+                    let (x, y) = points.points[class];
+                    (0..width)
+                        .map(|b| match width {
+                            1 => netlist.new_net(Some(format!("({x},{y})"))),
+                            _ => netlist.new_net(Some(format!("({x},{y})[{b}]"))),
+                        })
+                        .collect()
+                })
+                .clone()
+        };
+
+        let (mut input_count, mut output_count, mut cell_count) = (0, 0, 0);
+        let mut refs = Vec::new();
+        let mut interface = Vec::new();
+        for placed in &mut parts {
+            let mut part_refs = Vec::new();
+            let mut port_bits = Vec::new();
+            for port in &placed.part.ports {
+                if port.kind == PortKind::Unused {
+                    port_bits.push(Vec::new());
+                } else {
+                    let point = add(placed.location, port.offset);
+                    port_bits.push(port_nets(&mut points, &mut netlist, point));
+                }
+            }
+
+            let bit_name = |name: &str, width: usize, b: usize| match width {
+                1 => name.to_string(),
+                _ => format!("{name}[{b}]"),
+            };
+
+            match std::mem::replace(&mut placed.part.function, Function::Decoration) {
+                Function::InputPin(pin) => {
+                    let bits = &port_bits[0];
+                    for (b, &net) in bits.iter().enumerate() {
+                        netlist.add_input(bit_name(&pin, bits.len(), b), net);
+                        part_refs.push(Ref::Input(input_count));
+                        input_count += 1;
+                    }
+                }
+                Function::OutputPin(pin) => {
+                    let bits = &port_bits[0];
+                    for (b, &net) in bits.iter().enumerate() {
+                        netlist.add_output(bit_name(&pin, bits.len(), b), Bit::Net(net));
+                        part_refs.push(Ref::Output(output_count));
+                        output_count += 1;
+                    }
+                }
+                Function::Constant(value) => {
+                    for (b, &net) in port_bits[0].iter().enumerate() {
+                        let state = (value >> b) & 1 == 1;
+                        let out = netlist.add_cell(Constant { state }.into(), Vec::new(), 1);
+                        netlist.connect(net, Bit::Net(out[0]));
+                        part_refs.push(Ref::Cell(cell_count));
+                        cell_count += 1;
+                    }
+                }
+                Function::Cell(component) => {
+                    let kinds = placed.part.ports.iter().map(|p| p.kind);
+                    let bits_of = |kind| {
+                        kinds
+                            .clone()
+                            .zip(&port_bits)
+                            .filter(move |(k, _)| *k == kind)
+                            .flat_map(|(_, bits)| bits.iter().copied())
+                    };
+                    let inputs = bits_of(PortKind::Input).map(Bit::Net).collect();
+                    let outputs = bits_of(PortKind::Output).collect::<Vec<_>>();
+                    let driven = netlist.add_cell(component, inputs, outputs.len());
+                    for (net, out) in outputs.into_iter().zip(driven) {
+                        netlist.connect(net, Bit::Net(out));
+                    }
+                    part_refs.push(Ref::Cell(cell_count));
+                    cell_count += 1;
+                }
+                Function::Splitter(ends) => {
+                    let mut taken = vec![0; placed.part.ports.len() - 1];
+                    for (b, end) in ends.iter().enumerate() {
+                        let Some(end) = *end else { continue };
+                        let end_net = port_bits[end + 1][taken[end]];
+                        taken[end] += 1;
+                        netlist.connect(end_net, Bit::Net(port_bits[0][b]));
+                    }
+                    placed.part.function = Function::Splitter(ends);
+                }
+                function @ (Function::Tunnel(_) | Function::Decoration) => {
+                    placed.part.function = function;
+                }
+            }
+
+            if placed.name == "Pin" {
+                interface.push(InterfacePin {
+                    location: placed.location,
+                    facing: placed.facing,
+                    width: placed.part.ports[0].width,
+                    output: placed.part.ports[0].kind == PortKind::Input,
+                    bits: part_refs
+                        .iter()
+                        .map(|r| match *r {
+                            Ref::Input(i) | Ref::Output(i) | Ref::Cell(i) => i,
+                        })
+                        .collect(),
+                });
+            }
+            refs.push(part_refs);
+        }
+        interface.sort_by_key(|pin| (pin.location.1, pin.location.0));
+
+        if let Some(net) = netlist.multiple_drivers() {
+            return Err(LogisimError::MultipleDrivers {
+                circuit: name.to_string(),
+                net,
+            });
+        }
+        let built = netlist.build();
+
+        // Parts keep their place in the drawing if they became a single component
+        // and none of their slots sits on the slot of another one, there is no way
+        // to draw a cable between those.
+        let mut unplaced = Vec::new();
+        let mut slot_points = HashSet::new();
+        let placed_parts = parts
+            .iter()
+            .zip(&refs)
+            .map(|(placed, refs)| {
+                let at = format!("`{}` at {:?}", placed.name, placed.location);
+                let single_bit = placed
+                    .part
+                    .ports
+                    .iter()
+                    .all(|p| p.kind == PortKind::Unused || p.width == 1);
+                let part_points = (0..placed.part.ports.len())
+                    .filter(|&i| placed.part.ports[i].kind != PortKind::Unused)
+                    .map(|i| placed.port_point(i))
+                    .collect::<Vec<_>>();
+                if part_points.is_empty() {
+                    return false;
+                }
+                let reason = match placed.part.function {
+                    Function::Splitter(_) | Function::Tunnel(_) => {
+                        format!("{at} is left out, the nets through it are routed automatically")
+                    }
+                    _ if !single_bit || refs.len() != 1 => {
+                        format!("{at} becomes one component per bit")
+                    }
+                    _ => match part_points.iter().find(|p| slot_points.contains(*p)) {
+                        Some(point) => format!("{at} touches another component at {point:?}"),
+                        None => {
+                            slot_points.extend(part_points);
+                            return true;
+                        }
+                    },
+                };
+                unplaced.push(reason);
+                false
+            })
+            .collect::<Vec<_>>();
+
+        // Wires are only kept for nets between placed parts, the others are routed anew.
+        let mut routed = HashSet::new();
+        for (placed, _) in parts.iter().zip(&placed_parts).filter(|(_, &p)| !p) {
+            for i in 0..placed.part.ports.len() {
+                routed.insert(points.find(placed.port_point(i)));
+            }
+        }
+        let wires = wires
+            .into_iter()
+            .filter(|&[from, _]| !routed.contains(&points.find(from)))
+            .collect();
+
+        let layout = Layout {
+            components: parts
+                .iter()
+                .zip(&refs)
+                .zip(&placed_parts)
+                .filter(|(_, &placed)| placed)
+                .map(|((placed, refs), _)| {
+                    let id = match refs[0] {
+                        Ref::Input(i) => built.inputs[i].into(),
+                        Ref::Output(o) => built.outputs[o].into(),
+                        Ref::Cell(c) => built.cells[c],
+                    };
+                    let slots = |kind| {
+                        (0..placed.part.ports.len())
+                            .filter(|&i| placed.part.ports[i].kind == kind)
+                            .map(|i| placed.port_point(i))
+                            .collect()
+                    };
+                    let (min, max) = placed.part.bounds;
+                    PlacedComponent {
+                        id,
+                        bounds: (add(placed.location, min), add(placed.location, max)),
+                        inputs: slots(PortKind::Input),
+                        outputs: slots(PortKind::Output),
+                    }
+                })
+                .collect(),
+            wires,
+            unplaced,
+        };
+
+        self.stack.pop();
+        Ok(Elaborated {
+            built,
+            interface,
+            layout,
+        })
+    }
+
+    fn part(&mut self, element: &'a Element) -> Result<Placed, LogisimError> {
+        let name = element.attr("name").unwrap_or_default();
+        let location = element.attr("loc").and_then(parse_point).ok_or_else(|| {
+            LogisimError::InvalidAttribute {
+                component: name.to_string(),
+                location: (0, 0),
+                attribute: "loc".to_string(),
+                value: element.attr("loc").unwrap_or_default().to_string(),
+            }
+        })?;
+        let attributes = element
+            .children_named("a")
+            .filter_map(|a| Some((a.attr("name")?, a.attr("val").unwrap_or(&a.text))))
+            .collect();
+        let comp = Comp {
+            name,
+            location,
+            attributes,
+        };
+
+        let part = match element.attr("lib") {
+            Some(_) => library::part(&comp, self.default_inputs)?,
+            None if self.circuits.contains_key(name) => Some(self.subcircuit(&comp)?),
+            None => None,
+        };
+        let Some(part) = part else {
+            return Err(LogisimError::UnknownComponent {
+                name: name.to_string(),
+                location,
+            });
+        };
+
+        Ok(Placed {
+            name: name.to_string(),
+            location,
+            facing: comp.facing(Facing::East)?,
+            part,
+        })
+    }
+
+    fn subcircuit(&mut self, comp: &Comp) -> Result<Part, LogisimError> {
+        let elaborated = self.circuit(comp.name)?;
+        let element = self.circuits[comp.name];
+        let facing = comp.facing(Facing::East)?;
+
+        let (offsets, bounds) = custom_appearance(element, &elaborated.interface)
+            .unwrap_or_else(|| classic_appearance(&elaborated.interface));
+
+        let ports = elaborated
+            .interface
+            .iter()
+            .zip(offsets)
+            .map(|(pin, offset)| library::Port {
+                offset: facing.rotate(offset),
+                width: pin.width,
+                kind: if pin.output {
+                    PortKind::Output
+                } else {
+                    PortKind::Input
+                },
+            })
+            .collect();
+
+        let Built {
+            graph,
+            inputs,
+            outputs,
+            ..
+        } = elaborated.built;
+        let bits = |output: bool| {
+            elaborated
+                .interface
+                .iter()
+                .filter(move |pin| pin.output == output)
+                .flat_map(|pin| pin.bits.iter().copied())
+        };
+        let sub_inputs = bits(false).map(|i| inputs[i]).collect();
+        let sub_outputs = bits(true).map(|o| outputs[o]).collect();
+
+        Ok(Part {
+            function: Function::Cell(
+                Subcircuit::new(comp.name, graph, sub_inputs, sub_outputs).into(),
+            ),
+            ports,
+            bounds: facing.rotate_bounds(bounds),
+        })
+    }
+}
+
+/// Splits wires at every point of `ends` lying inside of them.
+fn split_wires(wires: &[[Point; 2]], ends: &HashSet<Point>) -> Vec<[Point; 2]> {
+    let mut result = Vec::new();
+    for &[from, to] in wires {
+        let inside = |&&(x, y): &&Point| {
+            let within = |v, a: i32, b: i32| a.min(b) < v && v < a.max(b);
+            (from.0 == to.0 && x == from.0 && within(y, from.1, to.1))
+                || (from.1 == to.1 && y == from.1 && within(x, from.0, to.0))
+        };
+        let mut cuts = ends.iter().filter(inside).copied().collect::<Vec<_>>();
+        cuts.sort_by_key(|&(x, y)| (x - from.0).abs() + (y - from.1).abs());
+
+        let mut start = from;
+        for cut in cuts {
+            result.push([start, cut]);
+            start = cut;
+        }
+        result.push([start, to]);
+    }
+    result
+}
+
+/// Port positions from the `<appear>` element of the circuit, relative to its anchor.
+fn custom_appearance(
+    element: &Element,
+    interface: &[InterfacePin],
+) -> Option<(Vec<Point>, (Point, Point))> {
+    let appear = element.children_named("appear").next()?;
+    let center = |e: &Element| -> Option<Point> {
+        let number = |name| e.attr(name)?.parse::<i32>().ok();
+        Some((
+            number("x")? + number("width")? / 2,
+            number("y")? + number("height")? / 2,
+        ))
+    };
+    let anchor = center(appear.children_named("circ-anchor").next()?)?;
+
+    let mut ports = HashMap::new();
+    for port in appear.children_named("circ-port") {
+        let pin = parse_point(&format!("({})", port.attr("pin")?))?;
+        ports.insert(pin, center(port)?);
+    }
+
+    let offsets = interface
+        .iter()
+        .map(|pin| {
+            let (x, y) = ports.get(&pin.location)?;
+            Some((x - anchor.0, y - anchor.1))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let (mut min, mut max) = ((0, 0), (0, 0));
+    for &(x, y) in &offsets {
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+    Some((offsets, (min, max)))
+}
+
+/// Port positions of the default rectangular appearance, relative to its anchor.
+///
+/// Pins are put on the side opposite to where they face, in the order they are drawn in.
+fn classic_appearance(interface: &[InterfacePin]) -> (Vec<Point>, (Point, Point)) {
+    let edge = |pin: &InterfacePin| match pin.facing {
+        Facing::East => Facing::West,
+        Facing::West => Facing::East,
+        Facing::North => Facing::South,
+        Facing::South => Facing::North,
+    };
+    let on_edge = |side: Facing| {
+        let mut pins = (0..interface.len())
+            .filter(|&i| edge(&interface[i]) == side)
+            .collect::<Vec<_>>();
+        pins.sort_by_key(|&i| {
+            let (x, y) = interface[i].location;
+            match side {
+                Facing::East | Facing::West => (y, x),
+                Facing::North | Facing::South => (x, y),
+            }
+        });
+        pins
+    };
+    let [north, south, east, west] =
+        [Facing::North, Facing::South, Facing::East, Facing::West].map(on_edge);
+
+    let max_vert = north.len().max(south.len()) as i32;
+    let max_horz = east.len().max(west.len()) as i32;
+
+    let offset = |facing: usize, opposite: usize, max_others: i32| {
+        let max_this = facing.max(opposite) as i32;
+        let max_offset = match max_this {
+            0 | 1 if max_others == 0 => 15,
+            0..=2 => 10,
+            _ if max_others == 0 => 5,
+            _ => 10,
+        };
+        max_offset + 10 * ((max_this - facing as i32) / 2)
+    };
+    let dimension = |max_this: i32, max_others: i32| {
+        if max_this < 3 {
+            30
+        } else if max_others == 0 {
+            10 * max_this
+        } else {
+            10 * max_this + 10
+        }
+    };
+
+    let offset_north = offset(north.len(), south.len(), max_horz);
+    let offset_south = offset(south.len(), north.len(), max_horz);
+    let offset_east = offset(east.len(), west.len(), max_vert);
+    let offset_west = offset(west.len(), east.len(), max_vert);
+    let width = dimension(max_vert, max_horz);
+    let height = dimension(max_horz, max_vert);
+
+    let anchor = if !east.is_empty() {
+        (width, offset_east)
+    } else if !north.is_empty() {
+        (offset_north, 0)
+    } else if !west.is_empty() {
+        (0, offset_west)
+    } else if !south.is_empty() {
+        (offset_south, height)
+    } else {
+        (0, 0)
+    };
+
+    let mut offsets = vec![(0, 0); interface.len()];
+    let sides = [
+        (&north, (offset_north, 0), (10, 0)),
+        (&south, (offset_south, height), (10, 0)),
+        (&east, (width, offset_east), (0, 10)),
+        (&west, (0, offset_west), (0, 10)),
+    ];
+    for (pins, (x, y), (dx, dy)) in sides {
+        for (n, &i) in pins.iter().enumerate() {
+            let n = n as i32;
+            offsets[i] = (x + dx * n - anchor.0, y + dy * n - anchor.1);
+        }
+    }
+
+    let bounds = (
+        (-anchor.0, -anchor.1),
+        (width - anchor.0, height - anchor.1),
+    );
+    (offsets, bounds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Component;
+
+    fn set(graph: &mut Graph, name: &str, state: bool) {
+        let id = graph.find_by_name(name).unwrap();
+        if let Component::Constant(c) = &mut graph[id] {
+            c.state = state;
+        }
+        graph.propagate_from(id);
+    }
+
+    fn get(graph: &Graph, name: &str) -> bool {
+        let id = graph.find_by_name(name).unwrap();
+        match &graph[id] {
+            Component::DebugOutput(d) => d.state,
+            _ => panic!("`{name}` is not an output"),
+        }
+    }
+
+    const FULL_ADDER: &str = r##"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<project source="3.8.0" version="1.0">
+  <lib desc="#Wiring" name="0"/>
+  <lib desc="#Gates" name="1"/>
+  <main name="main"/>
+  <circuit name="half">
+    <wire from="(40,40)" to="(140,40)"/>
+    <wire from="(40,80)" to="(140,80)"/>
+    <wire from="(100,40)" to="(100,140)"/>
+    <wire from="(100,140)" to="(150,140)"/>
+    <wire from="(120,80)" to="(120,180)"/>
+    <wire from="(120,180)" to="(150,180)"/>
+    <wire from="(200,60)" to="(300,60)"/>
+    <wire from="(200,160)" to="(300,160)"/>
+    <comp lib="0" loc="(40,40)" name="Pin"><a name="label" val="A"/></comp>
+    <comp lib="0" loc="(40,80)" name="Pin"><a name="label" val="B"/></comp>
+    <comp lib="0" loc="(300,60)" name="Pin">
+      <a name="facing" val="west"/><a name="output" val="true"/><a name="label" val="S"/>
+    </comp>
+    <comp lib="0" loc="(300,160)" name="Pin">
+      <a name="facing" val="west"/><a name="output" val="true"/><a name="label" val="C"/>
+    </comp>
+    <comp lib="1" loc="(200,60)" name="XOR Gate"/>
+    <comp lib="1" loc="(200,160)" name="AND Gate"/>
+  </circuit>
+  <circuit name="main">
+    <wire from="(100,100)" to="(170,100)"/>
+    <wire from="(100,110)" to="(170,110)"/>
+    <wire from="(100,300)" to="(250,300)"/>
+    <wire from="(250,300)" to="(250,110)"/>
+    <wire from="(250,110)" to="(270,110)"/>
+    <wire from="(200,100)" to="(270,100)"/>
+    <wire from="(200,110)" to="(220,110)"/>
+    <wire from="(220,110)" to="(220,180)"/>
+    <wire from="(220,180)" to="(350,180)"/>
+    <wire from="(300,110)" to="(320,110)"/>
+    <wire from="(320,110)" to="(320,220)"/>
+    <wire from="(320,220)" to="(350,220)"/>
+    <wire from="(300,100)" to="(500,100)"/>
+    <wire from="(400,200)" to="(500,200)"/>
+    <comp lib="0" loc="(100,100)" name="Pin"><a name="label" val="a"/></comp>
+    <comp lib="0" loc="(100,110)" name="Pin"><a name="label" val="b"/></comp>
+    <comp lib="0" loc="(100,300)" name="Pin"><a name="label" val="cin"/></comp>
+    <comp lib="0" loc="(500,100)" name="Pin">
+      <a name="facing" val="west"/><a name="output" val="true"/><a name="label" val="sum"/>
+    </comp>
+    <comp lib="0" loc="(500,200)" name="Pin">
+      <a name="facing" val="west"/><a name="output" val="true"/><a name="label" val="cout"/>
+    </comp>
+    <comp loc="(200,100)" name="half"/>
+    <comp loc="(300,100)" name="half"/>
+    <comp lib="1" loc="(400,200)" name="OR Gate"/>
+  </circuit>
+</project>
+"##;
+
+    #[test]
+    fn full_adder_from_half_adders() {
+        let LogisimCircuit { mut graph, layout } = from_logisim(FULL_ADDER, None).unwrap();
+
+        for input in 0..8 {
+            let [a, b, c] = [0, 1, 2].map(|i| (input >> i) & 1 == 1);
+            set(&mut graph, "a", a);
+            set(&mut graph, "b", b);
+            set(&mut graph, "cin", c);
+            let total = a as u8 + b as u8 + c as u8;
+            assert_eq!(get(&graph, "sum"), total & 1 == 1);
+            assert_eq!(get(&graph, "cout"), total >> 1 == 1);
+        }
+
+        assert_eq!(layout.components.len(), 8);
+        assert!(layout.unplaced.is_empty());
+        let or = layout
+            .components
+            .iter()
+            .find(|c| matches!(graph[c.id], Component::Or(_)))
+            .unwrap();
+        assert_eq!(or.inputs, vec![(350, 180), (350, 220)]);
+        assert_eq!(or.outputs, vec![(400, 200)]);
+        // Cin joins its wire at a corner, the wire crossing it must stay whole.
+        assert!(layout.wires.contains(&[(220, 180), (350, 180)]));
+    }
+
+    #[test]
+    fn flip_flop_through_tunnels() {
+        let source = r#"<project source="3.8.0" version="1.0">
+  <circuit name="main">
+    <wire from="(100,100)" to="(160,100)"/>
+    <wire from="(200,100)" to="(300,100)"/>
+    <comp lib="0" loc="(100,100)" name="Pin"><a name="label" val="d"/></comp>
+    <comp lib="0" loc="(100,200)" name="Pin"><a name="label" val="clk"/></comp>
+    <comp lib="0" loc="(100,200)" name="Tunnel"><a name="label" val="clock"/></comp>
+    <comp lib="0" loc="(160,120)" name="Tunnel">
+      <a name="facing" val="east"/><a name="label" val="clock"/>
+    </comp>
+    <comp lib="4" loc="(200,100)" name="D Flip-Flop"/>
+    <comp lib="0" loc="(300,100)" name="Pin">
+      <a name="facing" val="west"/><a name="output" val="true"/><a name="label" val="q"/>
+    </comp>
+  </circuit>
+</project>"#;
+        let LogisimCircuit { mut graph, layout } = from_logisim(source, None).unwrap();
+        // Only the tunnels are left out, the clock net is the only one without its wires.
+        assert_eq!(layout.unplaced.len(), 2);
+        assert_eq!(layout.components.len(), 4);
+        let d = graph.find_by_name("d").unwrap();
+        let d = layout.components.iter().find(|c| c.id == d).unwrap();
+        assert_eq!(d.outputs, vec![(100, 100)]);
+        let flip_flop = layout
+            .components
+            .iter()
+            .find(|c| matches!(graph[c.id], Component::FlipFlop(_)))
+            .unwrap();
+        assert!(flip_flop.inputs.contains(&(160, 100)));
+        assert!(flip_flop.inputs.contains(&(160, 120)));
+        assert_eq!(
+            layout.wires,
+            vec![[(100, 100), (160, 100)], [(200, 100), (300, 100)]]
+        );
+
+        set(&mut graph, "d", true);
+        assert!(!get(&graph, "q"));
+        set(&mut graph, "clk", true);
+        assert!(get(&graph, "q"));
+        set(&mut graph, "d", false);
+        set(&mut graph, "clk", false);
+        assert!(get(&graph, "q"));
+        set(&mut graph, "clk", true);
+        assert!(!get(&graph, "q"));
+    }
+
+    #[test]
+    fn errors() {
+        let circuit = |body: &str| {
+            format!(r#"<project source="3.8.0"><circuit name="main">{body}</circuit></project>"#)
+        };
+
+        assert!(matches!(
+            from_logisim(
+                &circuit(r#"<comp lib="9" loc="(10,10)" name="Flux Capacitor"/>"#),
+                None
+            ),
+            Err(LogisimError::UnknownComponent { .. })
+        ));
+        assert!(matches!(
+            from_logisim(&circuit(r#"<comp loc="(10,10)" name="main"/>"#), None),
+            Err(LogisimError::RecursiveCircuit { .. })
+        ));
+        let two_drivers = r#"<comp lib="0" loc="(10,10)" name="Pin"/>
+            <comp lib="0" loc="(10,10)" name="Constant"/>"#;
+        assert!(matches!(
+            from_logisim(&circuit(two_drivers), None),
+            Err(LogisimError::MultipleDrivers { .. })
+        ));
+        let widths = r#"<comp lib="0" loc="(10,10)" name="Pin"><a name="width" val="2"/></comp>
+            <comp lib="0" loc="(10,10)" name="Probe"/>"#;
+        assert!(matches!(
+            from_logisim(&circuit(widths), None),
+            Err(LogisimError::WidthMismatch { .. })
+        ));
+        assert!(matches!(
+            from_logisim("<project/>", None),
+            Err(LogisimError::NoCircuit)
+        ));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod logisim;
+pub mod verilog;
+
+pub(crate) mod netlist;
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+
+use crate::{
+    components::{
+        gates::{And, Not, Or, Xor},
+        simple::{Constant, DebugOutput, Fork},
+        subcircuit::Subcircuit,
+        Component,
+    },
+    graph::{
+        id::{ComponentId, TypedId},
+        Graph,
+    },
+};
+
+/// Single bit of a signal, either a net or a constant value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bit {
+    Net(usize),
+    Const(bool),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Driver {
+    Cell(usize, usize),
+    Input(usize),
+    Const(bool),
+}
+
+type SlotRef = (ComponentId, usize);
+
+struct Cell {
+    component: Component,
+    inputs: Vec<Bit>,
+}
+
+/// Flat, bit-level netlist of a single circuit, built before being turned into a `Graph`.
+///
+/// Nets joined by plain assignments are merged, so every net ends up with at most one driver.
+#[derive(Default)]
+pub struct Netlist {
+    parent: Vec<usize>,
+    names: Vec<Option<String>>,
+    drivers: Vec<Vec<Driver>>,
+    cells: Vec<Cell>,
+    inputs: Vec<(String, usize)>,
+    outputs: Vec<(String, Bit)>,
+}
+
+pub struct Built {
+    pub graph: Graph,
+    pub inputs: Vec<TypedId<Constant>>,
+    pub outputs: Vec<TypedId<DebugOutput>>,
+    /// Ids of the components added with [`Netlist::add_cell`], in order.
+    pub cells: Vec<ComponentId>,
+}
+
+impl Netlist {
+    pub fn new_net(&mut self, name: Option<String>) -> usize {
+        self.parent.push(self.parent.len());
+        self.names.push(name);
+        self.drivers.push(Vec::new());
+        self.parent.len() - 1
+    }
+
+    pub fn add_input(&mut self, name: String, net: usize) {
+        let root = self.find(net);
+        self.drivers[root].push(Driver::Input(self.inputs.len()));
+        self.inputs.push((name, net));
+    }
+
+    pub fn add_output(&mut self, name: String, bit: Bit) {
+        self.outputs.push((name, bit));
+    }
+
+    /// Adds a component, returning the fresh nets driven by its outputs.
+    pub fn add_cell(
+        &mut self,
+        component: Component,
+        inputs: Vec<Bit>,
+        output_size: usize,
+    ) -> Vec<usize> {
+        let cell = self.cells.len();
+        self.cells.push(Cell { component, inputs });
+        (0..output_size)
+            .map(|slot| {
+                let net = self.new_net(None);
+                self.drivers[net].push(Driver::Cell(cell, slot));
+                net
+            })
+            .collect()
+    }
+
+    pub fn gate(&mut self, component: impl Into<Component>, inputs: Vec<Bit>) -> Bit {
+        Bit::Net(self.add_cell(component.into(), inputs, 1)[0])
+    }
+
+    pub fn not(&mut self, bit: Bit) -> Bit {
+        match bit {
+            Bit::Const(value) => Bit::Const(!value),
+            bit => self.gate(Not, vec![bit]),
+        }
+    }
+
+    pub fn and(&mut self, a: Bit, b: Bit) -> Bit {
+        match (a, b) {
+            (Bit::Const(false), _) | (_, Bit::Const(false)) => Bit::Const(false),
+            (Bit::Const(true), other) | (other, Bit::Const(true)) => other,
+            (a, b) => self.gate(And, vec![a, b]),
+        }
+    }
+
+    pub fn or(&mut self, a: Bit, b: Bit) -> Bit {
+        match (a, b) {
+            (Bit::Const(true), _) | (_, Bit::Const(true)) => Bit::Const(true),
+            (Bit::Const(false), other) | (other, Bit::Const(false)) => other,
+            (a, b) => self.gate(Or, vec![a, b]),
+        }
+    }
+
+    pub fn xor(&mut self, a: Bit, b: Bit) -> Bit {
+        match (a, b) {
+            (Bit::Const(x), Bit::Const(y)) => Bit::Const(x ^ y),
+            (Bit::Const(false), other) | (other, Bit::Const(false)) => other,
+            (Bit::Const(true), other) | (other, Bit::Const(true)) => self.not(other),
+            (a, b) => self.gate(Xor, vec![a, b]),
+        }
+    }
+
+    /// Makes `net` carry the value of `bit`.
+    pub fn connect(&mut self, net: usize, bit: Bit) {
+        let root = self.find(net);
+        match bit {
+            Bit::Const(value) => self.drivers[root].push(Driver::Const(value)),
+            Bit::Net(other) => {
+                let other = self.find(other);
+                if other == root {
+                    return;
+                }
+                let (keep, merged) = match self.names[root] {
+                    Some(_) => (root, other),
+                    None => (other, root),
+                };
+                self.parent[merged] = keep;
+                let drivers = std::mem::take(&mut self.drivers[merged]);
+                self.drivers[keep].extend(drivers);
+                if self.names[keep].is_none() {
+                    self.names[keep] = self.names[merged].take();
+                }
+            }
+        }
+    }
+
+    fn find(&mut self, net: usize) -> usize {
+        let mut root = net;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut net = net;
+        while self.parent[net] != root {
+            let next = self.parent[net];
+            self.parent[net] = root;
+            net = next;
+        }
+        root
+    }
+
+    /// Returns the name of a net with more than one driver, if there is one.
+    pub fn multiple_drivers(&mut self) -> Option<String> {
+        (0..self.parent.len())
+            .find(|&net| self.find(net) == net && self.drivers[net].len() > 1)
+            .map(|net| self.names[net].clone().unwrap_or_else(|| format!("_{net}")))
+    }
+
+    pub fn build(mut self) -> Built {
+        let mut graph = Graph::new();
+
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|(name, _)| {
+                let id = graph.add_comp(Constant::default());
+                graph.set_name(id, name.as_str());
+                id
+            })
+            .collect::<Vec<_>>();
+
+        let cells = std::mem::take(&mut self.cells);
+        let mut sinks = Vec::new();
+        let mut cell_ids = Vec::new();
+        for cell in cells {
+            let id: ComponentId = graph.add_comp(cell.component).into();
+            sinks.extend(
+                cell.inputs
+                    .into_iter()
+                    .enumerate()
+                    .map(|(slot, bit)| (id, slot, bit)),
+            );
+            cell_ids.push(id);
+        }
+
+        let outputs = std::mem::take(&mut self.outputs)
+            .into_iter()
+            .map(|(name, bit)| {
+                let id = graph.add_comp(DebugOutput::default());
+                graph.set_name(id, name);
+                sinks.push((id.into(), 0, bit));
+                id
+            })
+            .collect::<Vec<_>>();
+
+        // Constant sources of bits tied low and high, unconnected inputs may not read as low.
+        let mut ties = [None; 2];
+        let mut sources: Vec<(SlotRef, Vec<SlotRef>)> = Vec::new();
+        let mut source_index = HashMap::new();
+
+        for (sink, slot, bit) in sinks {
+            let driver = match bit {
+                Bit::Const(value) => Some(Driver::Const(value)),
+                Bit::Net(net) => {
+                    let root = self.find(net);
+                    self.drivers[root].first().copied()
+                }
+            };
+            let source = match driver {
+                None => continue,
+                Some(Driver::Const(state)) => {
+                    let id = *ties[state as usize]
+                        .get_or_insert_with(|| graph.add_comp(Constant { state }).into());
+                    (id, 0)
+                }
+                Some(Driver::Input(port)) => (inputs[port].into(), 0),
+                Some(Driver::Cell(cell, out)) => (cell_ids[cell], out),
+            };
+
+            let index = *source_index.entry(source).or_insert_with(|| {
+                sources.push((source, Vec::new()));
+                sources.len() - 1
+            });
+            sources[index].1.push((sink, slot));
+        }
+
+        for ((node, slot), sinks) in sources {
+            fan_out(&mut graph, node, slot, &sinks);
+        }
+
+        graph.propagate_all();
+
+        Built {
+            graph,
+            inputs,
+            outputs,
+            cells: cell_ids,
+        }
+    }
+}
+
+/// Component with the given number of input and output bits, built from a small netlist.
+pub fn composite(
+    name: &str,
+    input_count: usize,
+    output_count: usize,
+    build: impl FnOnce(&mut Netlist, &[Bit]) -> Vec<Bit>,
+) -> Component {
+    let mut netlist = Netlist::default();
+    let inputs = (0..input_count)
+        .map(|i| {
+            let net = netlist.new_net(Some(format!("in{i}")));
+            netlist.add_input(format!("in{i}"), net);
+            Bit::Net(net)
+        })
+        .collect::<Vec<_>>();
+
+    let outputs = build(&mut netlist, &inputs);
+    debug_assert_eq!(outputs.len(), output_count);
+    for (o, bit) in outputs.into_iter().enumerate() {
+        netlist.add_output(format!("out{o}"), bit);
+    }
+
+    let built = netlist.build();
+    Subcircuit::new(name, built.graph, built.inputs, built.outputs).into()
+}
+
+fn fan_out(graph: &mut Graph, node: ComponentId, slot: usize, sinks: &[SlotRef]) {
+    if let [(sink, sink_slot)] = sinks {
+        graph.add_conn(node, slot, *sink, *sink_slot);
+        return;
+    }
+
+    let max = u8::MAX as usize;
+    let (direct, rest) = if sinks.len() > max {
+        sinks.split_at(max - 1)
+    } else {
+        (sinks, &[][..])
+    };
+
+    let outputs = direct.len() + !rest.is_empty() as usize;
+    let fork = graph.add_comp(Fork::new(1, outputs as u8));
+    graph.add_conn(node, slot, fork, 0);
+    for (o, &(sink, sink_slot)) in direct.iter().enumerate() {
+        graph.add_conn(fork, o, sink, sink_slot);
+    }
+    if !rest.is_empty() {
+        fan_out(graph, fork.into(), direct.len(), rest);
+    }
+}
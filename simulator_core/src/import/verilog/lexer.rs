@@ -0,0 +1,163 @@
+use super::VerilogError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    Ident(String),
+    /// Literal value, least significant bit first, with width if it was given.
+    Number(Vec<bool>, Option<usize>),
+    Symbol(&'static str),
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub line: usize,
+}
+
+const SYMBOLS: &[&str] = &[
+    "(*", "*)", "~^", "^~", "~&", "~|", "&&", "||", "==", "!=", "(", ")", "[", "]", "{", "}", ",",
+    ";", ":", ".", "=", "&", "|", "^", "~", "!", "#", "@", "?", "+", "-", "*", "/", "<", ">",
+];
+
+pub fn tokenize(source: &str) -> Result<Vec<Token>, VerilogError> {
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if (c == '/' && chars.get(i + 1) == Some(&'/')) || c == '`' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err(VerilogError::Syntax {
+                    line,
+                    message: "unterminated block comment".to_string(),
+                });
+            }
+            i += 2;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+            {
+                i += 1;
+            }
+            tokens.push(Token {
+                kind: TokenKind::Ident(chars[start..i].iter().collect()),
+                line,
+            });
+        } else if c == '\\' {
+            let start = i + 1;
+            while i < chars.len() && !chars[i].is_whitespace() {
+                i += 1;
+            }
+            tokens.push(Token {
+                kind: TokenKind::Ident(chars[start..i].iter().collect()),
+                line,
+            });
+        } else if c.is_ascii_digit() || c == '\'' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '\'')
+            {
+                i += 1;
+            }
+            let text = chars[start..i].iter().collect::<String>();
+            let (bits, width) = parse_number(&text).ok_or_else(|| VerilogError::Syntax {
+                line,
+                message: format!("invalid number `{text}`"),
+            })?;
+            tokens.push(Token {
+                kind: TokenKind::Number(bits, width),
+                line,
+            });
+        } else {
+            let rest = chars[i..].iter().take(2).collect::<String>();
+            let Some(&symbol) = SYMBOLS.iter().find(|s| rest.starts_with(*s)) else {
+                return Err(VerilogError::Syntax {
+                    line,
+                    message: format!("unexpected character `{c}`"),
+                });
+            };
+            tokens.push(Token {
+                kind: TokenKind::Symbol(symbol),
+                line,
+            });
+            i += symbol.len();
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_number(text: &str) -> Option<(Vec<bool>, Option<usize>)> {
+    let text = text.replace('_', "");
+
+    let Some((width, rest)) = text.split_once('\'') else {
+        return Some((to_bits(text.parse::<u64>().ok()?), None));
+    };
+
+    let width = match width {
+        "" => None,
+        width => Some(width.parse::<usize>().ok()?),
+    };
+
+    let rest = rest.strip_prefix(['s', 'S']).unwrap_or(rest);
+    let mut digits = rest.chars();
+    let radix: u32 = match digits.next()?.to_ascii_lowercase() {
+        'b' => 2,
+        'o' => 8,
+        'd' => 10,
+        'h' => 16,
+        _ => return None,
+    };
+    let digits = digits.as_str();
+    if digits.is_empty() {
+        return None;
+    }
+
+    let mut bits = if radix == 10 {
+        to_bits(digits.parse::<u64>().ok()?)
+    } else {
+        let per_digit = radix.trailing_zeros() as usize;
+        let mut bits = Vec::new();
+        for digit in digits.chars().rev() {
+            let value = digit.to_digit(radix)?;
+            bits.extend((0..per_digit).map(|b| (value >> b) & 1 == 1));
+        }
+        bits
+    };
+
+    if let Some(width) = width {
+        bits.resize(width, false);
+    }
+
+    Some((bits, width))
+}
+
+fn to_bits(mut value: u64) -> Vec<bool> {
+    let mut bits = vec![value & 1 == 1];
+    value >>= 1;
+    while value != 0 {
+        bits.push(value & 1 == 1);
+        value >>= 1;
+    }
+    bits
+}
@@ -0,0 +1,756 @@
+//! Import of structural Verilog.
+//!
+//! Supported subset: `module`s with ANSI or non-ANSI port lists, `input`/`output`/`wire`
+//! declarations (scalars and `[msb:lsb]` vectors), `assign` with `~ ! & | ^ ~^`, bit and part
+//! selects, concatenations and literals, gate primitives (`and`, `or`, `xor`, `nand`, `nor`,
+//! `xnor`, `not`, `buf`) and instances of other modules from the same source,
+//! which become `Subcircuit`s. Everything else is reported as [`VerilogError::Unsupported`].
+
+mod lexer;
+mod parser;
+
+use std::{collections::HashMap, error::Error, fmt::Display};
+
+use crate::{
+    components::{
+        gates::{And, Or, Xor},
+        subcircuit::Subcircuit,
+        Component,
+    },
+    graph::Graph,
+};
+
+use super::netlist::{Bit, Built, Netlist};
+
+use self::parser::{BinaryOp, Connections, Direction, Expr, Item, Module, Parser};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerilogError {
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    Unsupported {
+        line: usize,
+        construct: String,
+    },
+    UnknownModule {
+        line: usize,
+        name: String,
+    },
+    UnknownPort {
+        line: usize,
+        module: String,
+        port: String,
+    },
+    BadConnection {
+        line: usize,
+        message: String,
+    },
+    IndexOutOfRange {
+        line: usize,
+        net: String,
+        index: i64,
+    },
+    Redeclared {
+        line: usize,
+        name: String,
+    },
+    MultipleDrivers {
+        module: String,
+        net: String,
+    },
+    RecursiveModule {
+        name: String,
+    },
+    NoTopModule,
+    AmbiguousTopModule {
+        candidates: Vec<String>,
+    },
+}
+
+impl Display for VerilogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerilogError::Syntax { line, message } => write!(f, "line {line}: {message}"),
+            VerilogError::Unsupported { line, construct } => {
+                write!(f, "line {line}: {construct} is not supported")
+            }
+            VerilogError::UnknownModule { line, name } => {
+                write!(f, "line {line}: unknown module `{name}`")
+            }
+            VerilogError::UnknownPort { line, module, port } => {
+                write!(f, "line {line}: module `{module}` has no port `{port}`")
+            }
+            VerilogError::BadConnection { line, message } => write!(f, "line {line}: {message}"),
+            VerilogError::IndexOutOfRange { line, net, index } => {
+                write!(f, "line {line}: index {index} is out of range of `{net}`")
+            }
+            VerilogError::Redeclared { line, name } => {
+                write!(f, "line {line}: `{name}` is declared more than once")
+            }
+            VerilogError::MultipleDrivers { module, net } => {
+                write!(
+                    f,
+                    "net `{net}` in module `{module}` has more than one driver"
+                )
+            }
+            VerilogError::RecursiveModule { name } => {
+                write!(f, "module `{name}` instantiates itself")
+            }
+            VerilogError::NoTopModule => write!(f, "no top module found"),
+            VerilogError::AmbiguousTopModule { candidates } => {
+                write!(
+                    f,
+                    "top module is ambiguous, candidates: {}",
+                    candidates.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl Error for VerilogError {}
+
+/// Builds a graph from structural Verilog.
+///
+/// `top` selects the module to build, by default it's the only one not instantiated by other modules.
+/// Its input ports become named `Constant`s and its output ports named `DebugOutput`s,
+/// vector ports get one component per bit, named like `data[3]`.
+pub fn from_verilog(source: &str, top: Option<&str>) -> Result<Graph, VerilogError> {
+    let tokens = lexer::tokenize(source)?;
+    let modules = Parser::new(tokens).modules()?;
+
+    let top = match top {
+        Some(top) => top.to_string(),
+        None => find_top(&modules)?,
+    };
+
+    let mut elaborator = Elaborator {
+        modules: modules.iter().map(|m| (m.name.clone(), m)).collect(),
+        stack: Vec::new(),
+    };
+
+    if !elaborator.modules.contains_key(top.as_str()) {
+        return Err(VerilogError::UnknownModule { line: 1, name: top });
+    }
+
+    Ok(elaborator.module(&top)?.built.graph)
+}
+
+fn find_top(modules: &[Module]) -> Result<String, VerilogError> {
+    let instantiated = modules
+        .iter()
+        .flat_map(|m| &m.items)
+        .filter_map(|item| match item {
+            Item::Instance { module, .. } => Some(module.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let candidates = modules
+        .iter()
+        .filter(|m| !instantiated.contains(&m.name.as_str()))
+        .map(|m| m.name.clone())
+        .collect::<Vec<_>>();
+
+    match candidates.len() {
+        0 => Err(VerilogError::NoTopModule),
+        1 => Ok(candidates.into_iter().next().unwrap()),
+        _ => Err(VerilogError::AmbiguousTopModule { candidates }),
+    }
+}
+
+struct Signal {
+    nets: Vec<usize>,
+    range: Option<(i64, i64)>,
+}
+
+impl Signal {
+    /// Position of the bit with the given index, counting from the least significant bit.
+    fn position(&self, index: i64) -> Option<usize> {
+        let (msb, lsb) = self.range.unwrap_or((0, 0));
+        let position = if msb >= lsb { index - lsb } else { lsb - index };
+        (0..self.nets.len() as i64)
+            .contains(&position)
+            .then_some(position as usize)
+    }
+
+    fn bit_name(&self, name: &str, position: usize) -> String {
+        match self.range {
+            None => name.to_string(),
+            Some((msb, lsb)) => {
+                let index = if msb >= lsb {
+                    lsb + position as i64
+                } else {
+                    lsb - position as i64
+                };
+                format!("{name}[{index}]")
+            }
+        }
+    }
+}
+
+struct Port {
+    name: String,
+    direction: Direction,
+    width: usize,
+}
+
+struct Elaborated {
+    built: Built,
+    ports: Vec<Port>,
+}
+
+struct Elaborator<'a> {
+    modules: HashMap<String, &'a Module>,
+    stack: Vec<String>,
+}
+
+impl<'a> Elaborator<'a> {
+    fn module(&mut self, name: &str) -> Result<Elaborated, VerilogError> {
+        if self.stack.iter().any(|n| n == name) {
+            return Err(VerilogError::RecursiveModule {
+                name: name.to_string(),
+            });
+        }
+        self.stack.push(name.to_string());
+        let module = self.modules[name];
+
+        let mut scope = Scope {
+            netlist: Netlist::default(),
+            signals: HashMap::new(),
+        };
+
+        let mut directions = HashMap::new();
+        for decl in &module.declarations {
+            let width = match decl.range {
+                Some((msb, lsb)) => (msb - lsb).unsigned_abs() as usize + 1,
+                None => 1,
+            };
+
+            if let Some(direction) = decl.direction {
+                if directions.insert(decl.name.clone(), direction).is_some() {
+                    return Err(VerilogError::Redeclared {
+                        line: decl.line,
+                        name: decl.name.clone(),
+                    });
+                }
+            }
+
+            if let Some(signal) = scope.signals.get(&decl.name) {
+                // `output [3:0] y; wire [3:0] y;` declares the same net twice, which is fine.
+                if signal.nets.len() != width {
+                    return Err(VerilogError::Redeclared {
+                        line: decl.line,
+                        name: decl.name.clone(),
+                    });
+                }
+                continue;
+            }
+
+            let mut signal = Signal {
+                nets: Vec::new(),
+                range: decl.range,
+            };
+            signal.nets = (0..width)
+                .map(|p| scope.netlist.new_net(Some(signal.bit_name(&decl.name, p))))
+                .collect();
+            scope.signals.insert(decl.name.clone(), signal);
+        }
+
+        let mut ports = Vec::new();
+        for port in &module.ports {
+            let (Some(&direction), Some(signal)) = (directions.get(port), scope.signals.get(port))
+            else {
+                return Err(VerilogError::Syntax {
+                    line: module.line,
+                    message: format!("port `{port}` of module `{}` has no direction", module.name),
+                });
+            };
+            for (p, &net) in signal.nets.iter().enumerate() {
+                let bit_name = signal.bit_name(port, p);
+                match direction {
+                    Direction::Input => scope.netlist.add_input(bit_name, net),
+                    Direction::Output => scope.netlist.add_output(bit_name, Bit::Net(net)),
+                }
+            }
+            ports.push(Port {
+                name: port.clone(),
+                direction,
+                width: signal.nets.len(),
+            });
+        }
+
+        for item in &module.items {
+            match item {
+                Item::Assign {
+                    target,
+                    value,
+                    line,
+                } => {
+                    let target = scope.target(target, *line)?;
+                    let value = scope.value(value, *line)?;
+                    scope.assign(&target, value);
+                }
+                Item::Instance {
+                    module,
+                    connections,
+                    line,
+                } => {
+                    if let Some(gate) = Primitive::from_name(module) {
+                        scope.primitive(gate, connections, *line)?;
+                    } else if self.modules.contains_key(module) {
+                        let sub = self.module(module)?;
+                        scope.instance(module, sub, connections, *line)?;
+                    } else {
+                        return Err(VerilogError::UnknownModule {
+                            line: *line,
+                            name: module.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        if let Some(net) = scope.netlist.multiple_drivers() {
+            return Err(VerilogError::MultipleDrivers {
+                module: module.name.clone(),
+                net,
+            });
+        }
+
+        self.stack.pop();
+
+        Ok(Elaborated {
+            built: scope.netlist.build(),
+            ports,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Primitive {
+    And,
+    Or,
+    Xor,
+    Nand,
+    Nor,
+    Xnor,
+    Not,
+    Buf,
+}
+
+impl Primitive {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "and" => Primitive::And,
+            "or" => Primitive::Or,
+            "xor" => Primitive::Xor,
+            "nand" => Primitive::Nand,
+            "nor" => Primitive::Nor,
+            "xnor" => Primitive::Xnor,
+            "not" => Primitive::Not,
+            "buf" => Primitive::Buf,
+            _ => return None,
+        })
+    }
+}
+
+struct Scope {
+    netlist: Netlist,
+    signals: HashMap<String, Signal>,
+}
+
+impl Scope {
+    fn signal(&mut self, name: &str) -> &Signal {
+        if !self.signals.contains_key(name) {
+            let net = self.netlist.new_net(Some(name.to_string()));
+            self.signals.insert(
+                name.to_string(),
+                Signal {
+                    nets: vec![net],
+                    range: None,
+                },
+            );
+        }
+        &self.signals[name]
+    }
+
+    fn select(
+        &mut self,
+        name: &str,
+        msb: i64,
+        lsb: i64,
+        line: usize,
+    ) -> Result<Vec<usize>, VerilogError> {
+        let signal = self.signal(name);
+        let out_of_range = |index| VerilogError::IndexOutOfRange {
+            line,
+            net: name.to_string(),
+            index,
+        };
+        let from = signal.position(lsb).ok_or_else(|| out_of_range(lsb))?;
+        let to = signal.position(msb).ok_or_else(|| out_of_range(msb))?;
+        if from <= to {
+            Ok(signal.nets[from..=to].to_vec())
+        } else {
+            Ok(signal.nets[to..=from].iter().rev().copied().collect())
+        }
+    }
+
+    /// Nets written by an assignment target or an output connection, least significant first.
+    fn target(&mut self, expr: &Expr, line: usize) -> Result<Vec<usize>, VerilogError> {
+        match expr {
+            Expr::Ident(name) => Ok(self.signal(name).nets.clone()),
+            Expr::Bit(name, index) => self.select(name, *index, *index, line),
+            Expr::Part(name, msb, lsb) => self.select(name, *msb, *lsb, line),
+            Expr::Concat(parts) => {
+                let mut nets = Vec::new();
+                for part in parts.iter().rev() {
+                    nets.extend(self.target(part, line)?);
+                }
+                Ok(nets)
+            }
+            _ => Err(VerilogError::BadConnection {
+                line,
+                message: "only nets, selects and concatenations can be assigned to".to_string(),
+            }),
+        }
+    }
+
+    /// Bits of an expression, least significant first.
+    fn value(&mut self, expr: &Expr, line: usize) -> Result<Vec<Bit>, VerilogError> {
+        Ok(match expr {
+            Expr::Ident(_) | Expr::Bit(..) | Expr::Part(..) => {
+                self.target(expr, line)?.into_iter().map(Bit::Net).collect()
+            }
+            Expr::Number(bits, _) => bits.iter().map(|&b| Bit::Const(b)).collect(),
+            Expr::Concat(parts) => {
+                let mut bits = Vec::new();
+                for part in parts.iter().rev() {
+                    bits.extend(self.value(part, line)?);
+                }
+                bits
+            }
+            Expr::Not(inner) => {
+                let bits = self.value(inner, line)?;
+                bits.into_iter().map(|b| self.netlist.not(b)).collect()
+            }
+            Expr::LogicalNot(inner) => {
+                let bits = self.value(inner, line)?;
+                let any = bits
+                    .into_iter()
+                    .fold(Bit::Const(false), |acc, b| self.netlist.or(acc, b));
+                vec![self.netlist.not(any)]
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let mut lhs = self.value(lhs, line)?;
+                let mut rhs = self.value(rhs, line)?;
+                let width = lhs.len().max(rhs.len());
+                lhs.resize(width, Bit::Const(false));
+                rhs.resize(width, Bit::Const(false));
+                lhs.into_iter()
+                    .zip(rhs)
+                    .map(|(a, b)| match op {
+                        BinaryOp::And => self.netlist.and(a, b),
+                        BinaryOp::Or => self.netlist.or(a, b),
+                        BinaryOp::Xor => self.netlist.xor(a, b),
+                        BinaryOp::Xnor => {
+                            let xor = self.netlist.xor(a, b);
+                            self.netlist.not(xor)
+                        }
+                    })
+                    .collect()
+            }
+        })
+    }
+
+    /// Drives the target nets with the value, truncating or zero-extending it like Verilog does.
+    fn assign(&mut self, target: &[usize], mut value: Vec<Bit>) {
+        value.resize(target.len(), Bit::Const(false));
+        for (&net, bit) in target.iter().zip(value) {
+            self.netlist.connect(net, bit);
+        }
+    }
+
+    fn scalar(&mut self, expr: &Option<Expr>, line: usize) -> Result<Bit, VerilogError> {
+        let Some(expr) = expr else {
+            return Ok(Bit::Const(false));
+        };
+        match self.value(expr, line)?.as_slice() {
+            [bit] => Ok(*bit),
+            _ => Err(VerilogError::BadConnection {
+                line,
+                message: "gate primitive terminals have to be 1 bit wide".to_string(),
+            }),
+        }
+    }
+
+    fn primitive(
+        &mut self,
+        gate: Primitive,
+        connections: &Connections,
+        line: usize,
+    ) -> Result<(), VerilogError> {
+        let Connections::Positional(terminals) = connections else {
+            return Err(VerilogError::BadConnection {
+                line,
+                message: "gate primitives take positional connections only".to_string(),
+            });
+        };
+        if terminals.len() < 2 {
+            return Err(VerilogError::BadConnection {
+                line,
+                message: "gate primitives need an output and at least one input".to_string(),
+            });
+        }
+
+        let (outputs, inputs) = match gate {
+            Primitive::Not | Primitive::Buf => terminals.split_at(terminals.len() - 1),
+            _ => terminals.split_at(1),
+        };
+
+        let mut bits = Vec::new();
+        for input in inputs {
+            bits.push(self.scalar(input, line)?);
+        }
+
+        let result = match gate {
+            Primitive::And | Primitive::Nand => self.reduce(And, &bits),
+            Primitive::Or | Primitive::Nor => self.reduce(Or, &bits),
+            Primitive::Xor | Primitive::Xnor => self.reduce(Xor, &bits),
+            Primitive::Not | Primitive::Buf => bits[0],
+        };
+        let result = match gate {
+            Primitive::Nand | Primitive::Nor | Primitive::Xnor | Primitive::Not => {
+                self.netlist.not(result)
+            }
+            _ => result,
+        };
+
+        for output in outputs.iter().flatten() {
+            let target = self.target(output, line)?;
+            if target.len() != 1 {
+                return Err(VerilogError::BadConnection {
+                    line,
+                    message: "gate primitive terminals have to be 1 bit wide".to_string(),
+                });
+            }
+            self.assign(&target, vec![result]);
+        }
+
+        Ok(())
+    }
+
+    /// Chains 2-input gates, so that `and (y, a, b, c)` becomes `(a & b) & c`.
+    fn reduce(&mut self, gate: impl Into<Component> + Copy, bits: &[Bit]) -> Bit {
+        let mut acc = bits[0];
+        for &bit in &bits[1..] {
+            acc = self.netlist.gate(gate, vec![acc, bit]);
+        }
+        acc
+    }
+
+    fn instance(
+        &mut self,
+        name: &str,
+        sub: Elaborated,
+        connections: &Connections,
+        line: usize,
+    ) -> Result<(), VerilogError> {
+        let mut connected: Vec<Option<&Expr>> = vec![None; sub.ports.len()];
+        match connections {
+            Connections::Positional(exprs) => {
+                if exprs.len() > sub.ports.len() {
+                    return Err(VerilogError::BadConnection {
+                        line,
+                        message: format!("too many connections for module `{name}`"),
+                    });
+                }
+                for (slot, expr) in connected.iter_mut().zip(exprs) {
+                    *slot = expr.as_ref();
+                }
+            }
+            Connections::Named(named) => {
+                for (port, expr) in named {
+                    let Some(index) = sub.ports.iter().position(|p| &p.name == port) else {
+                        return Err(VerilogError::UnknownPort {
+                            line,
+                            module: name.to_string(),
+                            port: port.clone(),
+                        });
+                    };
+                    connected[index] = expr.as_ref();
+                }
+            }
+        }
+
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        for (port, expr) in sub.ports.iter().zip(connected) {
+            match port.direction {
+                Direction::Input => {
+                    let mut bits = match expr {
+                        Some(expr) => self.value(expr, line)?,
+                        None => Vec::new(),
+                    };
+                    let is_literal = matches!(expr, None | Some(Expr::Number(_, None)));
+                    if bits.len() != port.width && !is_literal {
+                        return Err(width_mismatch(name, port, bits.len(), line));
+                    }
+                    bits.resize(port.width, Bit::Const(false));
+                    inputs.extend(bits);
+                }
+                Direction::Output => match expr {
+                    Some(expr) => {
+                        let target = self.target(expr, line)?;
+                        if target.len() != port.width {
+                            return Err(width_mismatch(name, port, target.len(), line));
+                        }
+                        outputs.extend(target.into_iter().map(Some));
+                    }
+                    None => outputs.extend((0..port.width).map(|_| None)),
+                },
+            }
+        }
+
+        let Built {
+            graph,
+            inputs: sub_inputs,
+            outputs: sub_outputs,
+            ..
+        } = sub.built;
+        let subcircuit = Subcircuit::new(name, graph, sub_inputs, sub_outputs);
+        let nets = self
+            .netlist
+            .add_cell(subcircuit.into(), inputs, outputs.len());
+        for (target, net) in outputs.into_iter().zip(nets) {
+            if let Some(target) = target {
+                self.netlist.connect(target, Bit::Net(net));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn width_mismatch(module: &str, port: &Port, width: usize, line: usize) -> VerilogError {
+    VerilogError::BadConnection {
+        line,
+        message: format!(
+            "port `{}` of module `{module}` is {} bits wide, but {width} bits are connected",
+            port.name, port.width
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{id::ComponentId, logic::Logic};
+
+    fn set(graph: &mut Graph, name: &str, state: bool) {
+        let id = graph.find_by_name(name).unwrap();
+        if let Component::Constant(c) = &mut graph[id] {
+            c.state = state;
+        }
+        graph.propagate_from(id);
+    }
+
+    fn get(graph: &Graph, name: &str) -> bool {
+        let id: ComponentId = graph.find_by_name(name).unwrap();
+        match &graph[id] {
+            Component::DebugOutput(d) => d.state,
+            _ => panic!("`{name}` is not an output"),
+        }
+    }
+
+    #[test]
+    fn full_adder_from_half_adders() {
+        let source = "
+            module half_adder(input a, input b, output s, output c);
+                xor (s, a, b);
+                and g1 (c, a, b);
+            endmodule
+
+            // Full adder built from two half adders
+            module full_adder(a, b, cin, sum, cout);
+                input a, b, cin;
+                output sum, cout;
+                wire s1, c1, c2;
+                half_adder h1 (.a(a), .b(b), .s(s1), .c(c1));
+                half_adder h2 (s1, cin, sum, c2);
+                assign cout = c1 | c2;
+            endmodule
+        ";
+        let mut graph = from_verilog(source, None).unwrap();
+
+        for input in 0..8 {
+            let [a, b, cin] = [0, 1, 2].map(|i| input >> i & 1 == 1);
+            set(&mut graph, "a", a);
+            set(&mut graph, "b", b);
+            set(&mut graph, "cin", cin);
+            let total = a as u8 + b as u8 + cin as u8;
+            assert_eq!(get(&graph, "sum"), total & 1 == 1);
+            assert_eq!(get(&graph, "cout"), total >> 1 == 1);
+        }
+    }
+
+    #[test]
+    fn vectors_and_constants() {
+        let source = "
+            module m(input [1:0] a, output [2:0] y, output z);
+                assign y = {~a[1], a ^ 2'b11};
+                assign z = !a;
+            endmodule
+        ";
+        let mut graph = from_verilog(source, None).unwrap();
+        assert!(get(&graph, "y[2]") && get(&graph, "y[1]") && get(&graph, "y[0]"));
+        assert!(get(&graph, "z"));
+
+        set(&mut graph, "a[1]", true);
+        assert!(!get(&graph, "y[2]") && !get(&graph, "y[1]") && get(&graph, "y[0]"));
+        assert!(!get(&graph, "z"));
+    }
+
+    #[test]
+    fn tied_low_bits_are_driven() {
+        let source = "module m(output y);\n assign y = 1'b0;\nendmodule";
+        let mut graph = from_verilog(source, None).unwrap();
+        graph.set_four_valued(true);
+        let y = graph.find_by_name("y").unwrap();
+        assert_eq!(graph.input_level(y, 0), Logic::Zero);
+    }
+
+    #[test]
+    fn errors() {
+        let err = |source| from_verilog(source, None).unwrap_err();
+
+        assert_eq!(
+            err("module m(input a, output y);\n always @(a) y = a;\nendmodule"),
+            VerilogError::Unsupported {
+                line: 2,
+                construct: "`always`".to_string()
+            }
+        );
+        assert_eq!(
+            err("module m(input a, output y);\n assign y = a;\n not (y, a);\nendmodule"),
+            VerilogError::MultipleDrivers {
+                module: "m".to_string(),
+                net: "y".to_string()
+            }
+        );
+        assert_eq!(
+            err("module m(input a, output y);\n foo f (a, y);\nendmodule"),
+            VerilogError::UnknownModule {
+                line: 2,
+                name: "foo".to_string()
+            }
+        );
+        assert!(matches!(
+            err("module m(input a output y); endmodule"),
+            VerilogError::Syntax { line: 1, .. }
+        ));
+    }
+}
@@ -0,0 +1,488 @@
+use super::{
+    lexer::{Token, TokenKind},
+    VerilogError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Input,
+    Output,
+}
+
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub name: String,
+    pub direction: Option<Direction>,
+    /// Declared `[msb:lsb]` range.
+    pub range: Option<(i64, i64)>,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    And,
+    Or,
+    Xor,
+    Xnor,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Ident(String),
+    Bit(String, i64),
+    Part(String, i64, i64),
+    Number(Vec<bool>, Option<usize>),
+    Concat(Vec<Expr>),
+    Not(Box<Expr>),
+    LogicalNot(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone)]
+pub enum Connections {
+    Positional(Vec<Option<Expr>>),
+    Named(Vec<(String, Option<Expr>)>),
+}
+
+#[derive(Debug, Clone)]
+pub enum Item {
+    Assign {
+        target: Expr,
+        value: Expr,
+        line: usize,
+    },
+    Instance {
+        module: String,
+        connections: Connections,
+        line: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub name: String,
+    pub ports: Vec<String>,
+    pub declarations: Vec<Declaration>,
+    pub items: Vec<Item>,
+    pub line: usize,
+}
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self { tokens, pos: 0 }
+    }
+
+    pub fn modules(mut self) -> Result<Vec<Module>, VerilogError> {
+        let mut modules = Vec::new();
+        while self.pos < self.tokens.len() {
+            match self.peek_ident() {
+                Some("module") => modules.push(self.module()?),
+                Some(other) => {
+                    return Err(self.unsupported(&format!("`{other}` outside of a module")))
+                }
+                None => return Err(self.syntax("expected `module`")),
+            }
+        }
+        Ok(modules)
+    }
+
+    fn module(&mut self) -> Result<Module, VerilogError> {
+        let line = self.line();
+        self.expect_keyword("module")?;
+        let name = self.ident()?;
+
+        if self.peek_symbol("#") {
+            return Err(self.unsupported("module parameters"));
+        }
+
+        let mut module = Module {
+            name,
+            ports: Vec::new(),
+            declarations: Vec::new(),
+            items: Vec::new(),
+            line,
+        };
+
+        if self.eat_symbol("(") && !self.eat_symbol(")") {
+            let mut direction = None;
+            let mut range = None;
+            loop {
+                let line = self.line();
+                if let Some(dir) = self.direction()? {
+                    self.eat_keyword("wire");
+                    direction = Some(dir);
+                    range = self.range()?;
+                }
+                let port = self.ident()?;
+                if direction.is_some() {
+                    module.declarations.push(Declaration {
+                        name: port.clone(),
+                        direction,
+                        range,
+                        line,
+                    });
+                }
+                module.ports.push(port);
+
+                if self.eat_symbol(")") {
+                    break;
+                }
+                self.expect_symbol(",")?;
+            }
+        }
+        self.expect_symbol(";")?;
+
+        loop {
+            let line = self.line();
+            let Some(word) = self.peek_ident().map(str::to_string) else {
+                if self.peek_symbol("(*") {
+                    return Err(self.unsupported("attributes"));
+                }
+                return Err(self.syntax("expected a module item"));
+            };
+
+            match word.as_str() {
+                "endmodule" => {
+                    self.pos += 1;
+                    break;
+                }
+                "input" | "output" | "wire" => {
+                    let direction = self.direction()?;
+                    if direction.is_some() {
+                        self.eat_keyword("wire");
+                    } else {
+                        self.expect_keyword("wire")?;
+                    }
+                    let range = self.range()?;
+                    loop {
+                        let name = self.ident()?;
+                        if self.peek_symbol("=") {
+                            return Err(self.unsupported("net declaration assignment"));
+                        }
+                        module.declarations.push(Declaration {
+                            name,
+                            direction,
+                            range,
+                            line,
+                        });
+                        if !self.eat_symbol(",") {
+                            break;
+                        }
+                    }
+                    self.expect_symbol(";")?;
+                }
+                "assign" => {
+                    self.pos += 1;
+                    if self.peek_symbol("#") {
+                        return Err(self.unsupported("delays"));
+                    }
+                    loop {
+                        let target = self.primary()?;
+                        self.expect_symbol("=")?;
+                        let value = self.expr()?;
+                        module.items.push(Item::Assign {
+                            target,
+                            value,
+                            line,
+                        });
+                        if !self.eat_symbol(",") {
+                            break;
+                        }
+                    }
+                    self.expect_symbol(";")?;
+                }
+                "inout" | "reg" | "always" | "initial" | "generate" | "function" | "task"
+                | "parameter" | "localparam" | "integer" | "supply0" | "supply1" | "tri"
+                | "specify" => {
+                    return Err(self.unsupported(&format!("`{word}`")));
+                }
+                _ => {
+                    self.pos += 1;
+                    if self.peek_symbol("#") {
+                        return Err(self.unsupported("parameter overrides and delays"));
+                    }
+                    loop {
+                        if self.peek_ident().is_some() {
+                            self.pos += 1;
+                            if self.peek_symbol("[") {
+                                return Err(self.unsupported("instance arrays"));
+                            }
+                        }
+                        let connections = self.connections()?;
+                        module.items.push(Item::Instance {
+                            module: word.clone(),
+                            connections,
+                            line,
+                        });
+                        if !self.eat_symbol(",") {
+                            break;
+                        }
+                    }
+                    self.expect_symbol(";")?;
+                }
+            }
+        }
+
+        Ok(module)
+    }
+
+    fn connections(&mut self) -> Result<Connections, VerilogError> {
+        self.expect_symbol("(")?;
+        if self.eat_symbol(")") {
+            return Ok(Connections::Positional(Vec::new()));
+        }
+
+        if self.peek_symbol(".") {
+            let mut named = Vec::new();
+            loop {
+                self.expect_symbol(".")?;
+                let port = self.ident()?;
+                self.expect_symbol("(")?;
+                let expr = if self.peek_symbol(")") {
+                    None
+                } else {
+                    Some(self.expr()?)
+                };
+                self.expect_symbol(")")?;
+                named.push((port, expr));
+                if self.eat_symbol(")") {
+                    return Ok(Connections::Named(named));
+                }
+                self.expect_symbol(",")?;
+            }
+        }
+
+        let mut positional = Vec::new();
+        loop {
+            if self.peek_symbol(",") || self.peek_symbol(")") {
+                positional.push(None);
+            } else {
+                positional.push(Some(self.expr()?));
+            }
+            if self.eat_symbol(")") {
+                return Ok(Connections::Positional(positional));
+            }
+            self.expect_symbol(",")?;
+        }
+    }
+
+    fn direction(&mut self) -> Result<Option<Direction>, VerilogError> {
+        let direction = match self.peek_ident() {
+            Some("input") => Direction::Input,
+            Some("output") => Direction::Output,
+            Some("inout") => return Err(self.unsupported("`inout`")),
+            _ => return Ok(None),
+        };
+        self.pos += 1;
+        if self.peek_ident() == Some("reg") {
+            return Err(self.unsupported("`reg`"));
+        }
+        Ok(Some(direction))
+    }
+
+    fn range(&mut self) -> Result<Option<(i64, i64)>, VerilogError> {
+        if !self.eat_symbol("[") {
+            return Ok(None);
+        }
+        let msb = self.integer()?;
+        self.expect_symbol(":")?;
+        let lsb = self.integer()?;
+        self.expect_symbol("]")?;
+        Ok(Some((msb, lsb)))
+    }
+
+    /// Operator precedence, from the loosest: `|`, `^` and `~^`, `&`.
+    fn expr(&mut self) -> Result<Expr, VerilogError> {
+        let mut lhs = self.xor_expr()?;
+        while self.eat_symbol("|") {
+            let rhs = self.xor_expr()?;
+            lhs = Expr::Binary(BinaryOp::Or, Box::new(lhs), Box::new(rhs));
+        }
+        self.reject_operator()?;
+        Ok(lhs)
+    }
+
+    fn xor_expr(&mut self) -> Result<Expr, VerilogError> {
+        let mut lhs = self.and_expr()?;
+        loop {
+            let op = if self.eat_symbol("^") {
+                BinaryOp::Xor
+            } else if self.eat_symbol("~^") || self.eat_symbol("^~") {
+                BinaryOp::Xnor
+            } else {
+                return Ok(lhs);
+            };
+            let rhs = self.and_expr()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, VerilogError> {
+        let mut lhs = self.unary()?;
+        while self.eat_symbol("&") {
+            let rhs = self.unary()?;
+            lhs = Expr::Binary(BinaryOp::And, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, VerilogError> {
+        if self.eat_symbol("~") {
+            Ok(Expr::Not(Box::new(self.unary()?)))
+        } else if self.eat_symbol("!") {
+            Ok(Expr::LogicalNot(Box::new(self.unary()?)))
+        } else if self.eat_symbol("(") {
+            let expr = self.expr()?;
+            self.expect_symbol(")")?;
+            Ok(expr)
+        } else {
+            self.reject_operator()?;
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, VerilogError> {
+        if self.eat_symbol("{") {
+            let mut parts = vec![self.expr()?];
+            if self.peek_symbol("{") {
+                return Err(self.unsupported("replication"));
+            }
+            while self.eat_symbol(",") {
+                parts.push(self.expr()?);
+            }
+            self.expect_symbol("}")?;
+            return Ok(Expr::Concat(parts));
+        }
+
+        if let Some(TokenKind::Number(bits, width)) = self.peek().map(|t| t.kind.clone()) {
+            self.pos += 1;
+            return Ok(Expr::Number(bits, width));
+        }
+
+        let name = self.ident()?;
+        if !self.eat_symbol("[") {
+            return Ok(Expr::Ident(name));
+        }
+        let msb = self.integer()?;
+        if self.eat_symbol(":") {
+            let lsb = self.integer()?;
+            self.expect_symbol("]")?;
+            return Ok(Expr::Part(name, msb, lsb));
+        }
+        self.expect_symbol("]")?;
+        Ok(Expr::Bit(name, msb))
+    }
+
+    fn reject_operator(&self) -> Result<(), VerilogError> {
+        match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::Symbol(
+                op @ ("~&" | "~|" | "&&" | "||" | "==" | "!=" | "?" | "+" | "-" | "*" | "/" | "<"
+                | ">"),
+            )) => Err(self.unsupported(&format!("operator `{op}`"))),
+            _ => Ok(()),
+        }
+    }
+
+    fn integer(&mut self) -> Result<i64, VerilogError> {
+        match self.peek().map(|t| t.kind.clone()) {
+            Some(TokenKind::Number(bits, _)) if bits.len() < 63 => {
+                self.pos += 1;
+                Ok(bits
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, &bit| (acc << 1) | bit as i64))
+            }
+            _ => Err(self.syntax("expected an integer")),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, VerilogError> {
+        match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::Ident(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(self.syntax("expected an identifier")),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_ident(&self) -> Option<&str> {
+        match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::Ident(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn peek_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek().map(|t| &t.kind), Some(TokenKind::Symbol(s)) if *s == symbol)
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = self.peek_symbol(symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_ident() == Some(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), VerilogError> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.syntax(&format!("expected `{symbol}`")))
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), VerilogError> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.syntax(&format!("expected `{keyword}`")))
+        }
+    }
+
+    fn line(&self) -> usize {
+        self.peek()
+            .or(self.tokens.last())
+            .map(|t| t.line)
+            .unwrap_or(1)
+    }
+
+    fn syntax(&self, message: &str) -> VerilogError {
+        let found = match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::Ident(name)) => format!("`{name}`"),
+            Some(TokenKind::Symbol(symbol)) => format!("`{symbol}`"),
+            Some(TokenKind::Number(..)) => "a number".to_string(),
+            None => "end of file".to_string(),
+        };
+        VerilogError::Syntax {
+            line: self.line(),
+            message: format!("{message}, found {found}"),
+        }
+    }
+
+    fn unsupported(&self, construct: &str) -> VerilogError {
+        VerilogError::Unsupported {
+            line: self.line(),
+            construct: construct.to_string(),
+        }
+    }
+}
@@ -1,3 +1,6 @@
 pub mod graph;
 pub mod components;
-pub mod util;
\ No newline at end of file
+pub mod util;
+pub mod export;
+pub mod import;
+pub mod analysis;
\ No newline at end of file
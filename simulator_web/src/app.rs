@@ -1,9 +1,16 @@
-use egui::{Sense, warn_if_debug_build};
-use log::info;
+use egui::warn_if_debug_build;
 
 use crate::{
     state::{self, modes::Mode, app::AppState},
-    widgets::{ui::side_menu, nodegraph::{self, widget::nodegraph_widget}},
+    widgets::{
+        nodegraph::widget::nodegraph_widget,
+        ui::{
+            event_log::show_event_log_window, export::show_dot_window, faults::show_faults_window,
+            hazards::show_hazards_window, import::show_import_window, karnaugh::show_karnaugh_window,
+            model_checking::show_model_checking_window, side_menu, synthesis::show_synthesis_window,
+            truth_table::show_truth_table_window,
+        },
+    },
 };
 
 #[derive(Default, serde::Deserialize, serde::Serialize)]
@@ -47,9 +54,51 @@ impl eframe::App for EmulatorApp {
                 if ui.button("Clear").clicked() {
                     self.app_state = AppState::default();
                 }
+                if ui.button("Import").clicked() {
+                    self.app_state.import_state.open = true;
+                }
+                if ui.button("Graphviz DOT").clicked() {
+                    self.app_state.export_state.open = true;
+                }
+                if ui.button("Truth table").clicked() {
+                    self.app_state.truth_table_state.open = true;
+                }
+                if ui.button("Synthesis").clicked() {
+                    self.app_state.synthesis_state.open = true;
+                }
+                if ui.button("Karnaugh map").clicked() {
+                    self.app_state.karnaugh_state.open = true;
+                }
+                if ui.button("Model checking").clicked() {
+                    self.app_state.model_checking_state.open = true;
+                }
+                if ui.button("Faults").clicked() {
+                    self.app_state.fault_state.open = true;
+                }
+                if ui.button("Hazards").clicked() {
+                    self.app_state.hazard_state.open = true;
+                }
+                if ui.button("Event log").clicked() {
+                    self.app_state.event_log_state.open = true;
+                }
             });
             ui.separator();
             side_menu::show_mode_choice(ui, &mut self.app_state.mode_state);
+            if self.app_state.mode_state.mode == Mode::Running {
+                side_menu::show_history_controls(
+                    ui,
+                    &mut self.app_state.history_state,
+                    &mut self.app_state.node_graph,
+                );
+                side_menu::show_animation_controls(ui, &mut self.app_state.animation_state);
+                if side_menu::show_logic_choice(ui, &mut self.app_state.node_graph) {
+                    // Recorded states have no levels to go back to.
+                    self.app_state.history_state.history = None;
+                }
+            } else {
+                // Edits make the recorded states meaningless.
+                self.app_state.history_state.history = None;
+            }
             if self.app_state.mode_state.mode == Mode::Adding {
                 ui.separator();
                 side_menu::show_adding_choice(
@@ -60,6 +109,16 @@ impl eframe::App for EmulatorApp {
             }
         });
 
+        show_import_window(ctx, &mut self.app_state);
+        show_dot_window(ctx, &mut self.app_state);
+        show_truth_table_window(ctx, &mut self.app_state);
+        show_synthesis_window(ctx, &mut self.app_state);
+        show_karnaugh_window(ctx, &mut self.app_state);
+        show_model_checking_window(ctx, &mut self.app_state);
+        show_faults_window(ctx, &mut self.app_state);
+        show_hazards_window(ctx, &mut self.app_state);
+        show_event_log_window(ctx, &mut self.app_state);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             warn_if_debug_build(ui);
             nodegraph_widget(ui, &mut self.app_state);
@@ -2,7 +2,9 @@ use std::fmt::Debug;
 
 use simulator_core::components::{
     gates::{And, Not, Or, Xor},
+    memory::{FlipFlop, FlipFlopKind},
     simple::{Constant, DebugOutput},
+    wired::{OpenCollector, PullResistor, Transceiver},
     Component,
 };
 
@@ -43,6 +45,11 @@ impl ComponentRegistry {
             new_entry("Xor", Xor::default),
             new_entry("Constant", Constant::default),
             new_entry("Debug Output", DebugOutput::default),
+            new_entry("D Flip-Flop", || FlipFlop::new(FlipFlopKind::D)),
+            new_entry("Pull-up", PullResistor::up),
+            new_entry("Pull-down", PullResistor::down),
+            new_entry("Open Collector", OpenCollector::default),
+            new_entry("Transceiver", Transceiver::default),
         ])
     }
 
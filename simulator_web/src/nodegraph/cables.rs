@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use egui::{Color32, Pos2, Vec2};
+use egui::{Color32, Pos2};
 use simulator_core::{graph::id::{TypedId, ComponentId}, components::simple::Fork};
 use slotmap::new_key_type;
 
@@ -36,6 +36,8 @@ impl CableEnd {
 impl Cable {
     pub const DEFAULT_COLOR: Color32 = Color32::WHITE;
     pub const ACTIVATED_COLOR: Color32 = Color32::LIGHT_GREEN;
+    pub const UNKNOWN_COLOR: Color32 = Color32::RED;
+    pub const FLOATING_COLOR: Color32 = Color32::GRAY;
 
     pub fn new() -> Self {
         Self {
@@ -1,9 +1,8 @@
-use egui::{Vec2, Pos2};
+use egui::Pos2;
 
-use crate::{
-    components::registry::ComponentRid,
-    util::{ivec2, IRect, IVec2},
-};
+use simulator_core::components::BidirectionalPin;
+
+use crate::util::{ivec2, IRect, IVec2};
 
 use super::cables::CableId;
 
@@ -14,19 +13,17 @@ pub struct ComponentNode {
     pub output_slots: Vec<IVec2>,
     pub input_cables: Vec<Option<CableId>>,
     pub output_cables: Vec<Option<CableId>>,
-    pub base_entry_rid: ComponentRid,
     pub highlight_level: u8,
 }
 
 impl ComponentNode {
-    pub fn new(rect: IRect, entry_rid: ComponentRid) -> Self {
+    pub fn new(rect: IRect) -> Self {
         Self {
             rect,
             input_slots: Vec::new(),
             output_slots: Vec::new(),
             input_cables: Vec::new(),
             output_cables: Vec::new(),
-            base_entry_rid: entry_rid,
             highlight_level: 0
         }
     }
@@ -48,6 +45,23 @@ impl ComponentNode {
         self
     }
 
+    /// Puts the input slot of each bidirectional pin on its output slot and spaces
+    /// the remaining inputs evenly again.
+    pub fn with_bidirectional_pins(mut self, pins: &[BidirectionalPin]) -> Self {
+        for pin in pins {
+            self.input_slots[pin.input] = self.output_slots[pin.output];
+        }
+        let plain_inputs = (0..self.input_slots.len())
+            .filter(|&i| pins.iter().all(|pin| pin.input != i))
+            .collect::<Vec<_>>();
+        let input_gap = self.rect.size.y as usize / (plain_inputs.len() + 1);
+        for (n, i) in plain_inputs.into_iter().enumerate() {
+            self.input_slots[i] = ivec2(0, ((n + 1) * input_gap) as i32);
+        }
+
+        self
+    }
+
     pub fn move_middle(&mut self, new_pos: IVec2) {
         self.rect.pos = new_pos - self.rect.size / 2;
     }
@@ -57,6 +71,11 @@ impl ComponentNode {
 pub enum ComponentIntersection {
     InputSlot(usize),
     OutputSlot(usize),
+    /// Input and output slot of a bidirectional pin, sharing one position.
+    Bidirectional {
+        input: usize,
+        output: usize,
+    },
     Inside,
 }
 
@@ -64,7 +83,7 @@ impl ComponentNode {
     pub fn intersection_test(&self, pos: Pos2, epsilon: f32) -> Option<ComponentIntersection> {
         for (i, &input) in self.input_slots.iter().enumerate() {
             if (self.rect.pos + input).equals_with_rounding(pos, epsilon) {
-                return Some(ComponentIntersection::InputSlot(i));
+                return Some(self.input_intersection(i));
             }
         }
 
@@ -84,7 +103,7 @@ impl ComponentNode {
     pub fn intersection_test_exact(&self, pos: IVec2) -> Option<ComponentIntersection> {
         for (i, &input) in self.input_slots.iter().enumerate() {
             if self.rect.pos + input == pos {
-                return Some(ComponentIntersection::InputSlot(i));
+                return Some(self.input_intersection(i));
             }
         }
 
@@ -100,6 +119,14 @@ impl ComponentNode {
 
         None
     }
+
+    fn input_intersection(&self, input: usize) -> ComponentIntersection {
+        let slot = self.input_slots[input];
+        match self.output_slots.iter().position(|&output| output == slot) {
+            Some(output) => ComponentIntersection::Bidirectional { input, output },
+            None => ComponentIntersection::InputSlot(input),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -109,9 +136,33 @@ mod tests {
     #[test]
     fn slots_pos_test() {
         let comp =
-            ComponentNode::new(IRect::new(ivec2(0, 0), ivec2(0, 6)), 0).with_default_slots(2, 1);
+            ComponentNode::new(IRect::new(ivec2(0, 0), ivec2(0, 6))).with_default_slots(2, 1);
 
         assert_eq!(comp.input_slots, vec![ivec2(0, 2), ivec2(0, 4)]);
         assert_eq!(comp.output_slots, vec![ivec2(0, 3)]);
     }
+
+    #[test]
+    fn bidirectional_slots_test() {
+        let pins = [BidirectionalPin {
+            input: 1,
+            output: 0,
+        }];
+        let comp = ComponentNode::new(IRect::new(ivec2(0, 0), ivec2(4, 6)))
+            .with_default_slots(2, 1)
+            .with_bidirectional_pins(&pins);
+
+        assert_eq!(comp.input_slots, vec![ivec2(0, 3), ivec2(4, 3)]);
+        assert_eq!(
+            comp.intersection_test_exact(ivec2(4, 3)),
+            Some(ComponentIntersection::Bidirectional {
+                input: 1,
+                output: 0
+            })
+        );
+        assert_eq!(
+            comp.intersection_test_exact(ivec2(0, 3)),
+            Some(ComponentIntersection::InputSlot(0))
+        );
+    }
 }
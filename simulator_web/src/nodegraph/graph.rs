@@ -1,11 +1,10 @@
 use std::{array::from_fn, collections::HashSet};
 
-use egui::{Pos2, Vec2};
+use egui::Pos2;
 use simulator_core::{
     components::{simple::Fork, ComponentBehaviour},
     graph::{id::ComponentId, Graph},
 };
-use log::warn;
 use slotmap::{SecondaryMap, SlotMap};
 
 use crate::{
@@ -71,8 +70,9 @@ impl NodeGraph {
             .expect("ComponentRid not found")
             .create)();
         let size = get_size(&component);
-        let node = ComponentNode::new(IRect::new(pos - size / 2, size), entry_rid)
-            .with_default_slots(component.input_size(), component.output_size());
+        let node = ComponentNode::new(IRect::new(pos - size / 2, size))
+            .with_default_slots(component.input_size(), component.output_size())
+            .with_bidirectional_pins(&component.bidirectional_pins());
 
         let id = self.graph.add_comp(component).into();
         self.components.insert(id, node);
@@ -175,6 +175,10 @@ impl NodeGraph {
                         ComponentIntersection::OutputSlot(o) => {
                             self.comp_mut(comp_id).output_cables[o] = Some(id);
                         }
+                        ComponentIntersection::Bidirectional { input, output } => {
+                            self.comp_mut(comp_id).input_cables[input] = Some(id);
+                            self.comp_mut(comp_id).output_cables[output] = Some(id);
+                        }
                         ComponentIntersection::Inside => {
                             continue;
                         }
@@ -213,6 +217,10 @@ impl NodeGraph {
                     ComponentIntersection::OutputSlot(o) => {
                         group_inputs.push((comp_id, o));
                     }
+                    ComponentIntersection::Bidirectional { input, output } => {
+                        group_inputs.push((comp_id, output));
+                        group_outputs.push((comp_id, input));
+                    }
                     ComponentIntersection::Inside => {}
                 }
             }
@@ -346,7 +354,7 @@ impl NodeGraph {
     }
 
     ///TODO
-    pub fn merge(&mut self, id_a: CableId, id_b: CableId) {}
+    pub fn merge(&mut self, _id_a: CableId, _id_b: CableId) {}
 
     pub fn move_cable_point(&mut self, id: CableId, point_id: usize, new_pos: IVec2) -> usize {
         self.cables[id].move_point_aligned(point_id, new_pos)
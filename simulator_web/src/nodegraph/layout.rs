@@ -0,0 +1,912 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use simulator_core::{
+    components::{Component, ComponentBehaviour},
+    graph::{id::ComponentId, Graph},
+    import::logisim::{LogisimCircuit, Point},
+};
+
+use crate::{
+    components::add_data::get_size,
+    util::{ivec2, IRect, IVec2},
+};
+
+use super::{cables::Cable, components::ComponentNode, graph::NodeGraph};
+
+/// Net of the logical graph, with `Fork`s folded into it.
+#[derive(Debug, Default)]
+struct Net {
+    drivers: Vec<(ComponentId, usize)>,
+    sinks: Vec<(ComponentId, usize)>,
+}
+
+impl NodeGraph {
+    /// Moves all components of `graph` into the node graph, laid out in columns
+    /// to the right of everything already placed, and draws cables between them.
+    ///
+    /// `Fork`s are dropped, the cables recreate them like they would for hand drawn ones.
+    /// Every component gets its own rows and every net its own vertical track,
+    /// so that no cable ends on a cable of a different net.
+    pub fn insert_graph(&mut self, graph: Graph) -> Vec<ComponentId> {
+        let nets = collect_nets(&graph);
+        let Graph { mut nodes, .. } = graph;
+
+        let old_ids = nodes
+            .iter()
+            .filter(|(_, node)| !matches!(node.component, Component::Fork(_)))
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        let index = old_ids
+            .iter()
+            .enumerate()
+            .map(|(i, &id)| (id, i))
+            .collect::<HashMap<_, _>>();
+
+        let edges = nets
+            .iter()
+            .flat_map(|net| {
+                net.drivers
+                    .iter()
+                    .flat_map(|(d, _)| net.sinks.iter().map(|(s, _)| (index[d], index[s])))
+            })
+            .collect::<Vec<_>>();
+        let layers = layers(old_ids.len(), &edges);
+        let column_count = layers.iter().max().map_or(0, |&l| l + 1);
+
+        let mut ids = Vec::new();
+        let mut comp_nodes = Vec::new();
+        for &old_id in &old_ids {
+            let node = nodes.remove(old_id).unwrap();
+            let size = get_size(&node.component);
+            let comp_node = ComponentNode::new(IRect::new(ivec2(0, 0), size))
+                .with_default_slots(node.component.input_size(), node.component.output_size())
+                .with_bidirectional_pins(&node.component.bidirectional_pins());
+            let id: ComponentId = self.graph.add_comp(node.component).into();
+            if let Some(name) = node.name {
+                self.graph.set_name(id, name);
+            }
+            ids.push(id);
+            comp_nodes.push(comp_node);
+        }
+
+        let driver_column = |net: &Net| {
+            net.drivers
+                .iter()
+                .map(|(d, _)| layers[index[d]])
+                .max()
+                .unwrap_or(0)
+        };
+        let is_detour = |net: &Net, sink: ComponentId| layers[index[&sink]] <= driver_column(net);
+
+        let mut trunk_count = vec![0; column_count + 1];
+        let mut approach_count = vec![0; column_count];
+        for net in &nets {
+            trunk_count[driver_column(net) + 1] += 1;
+            for &(sink, _) in &net.sinks {
+                if is_detour(net, sink) {
+                    approach_count[layers[index[&sink]]] += 1;
+                }
+            }
+        }
+
+        let origin = self.free_origin();
+        let mut channel_x = Vec::new();
+        let mut column_x = Vec::new();
+        let mut x = origin.x;
+        for column in 0..=column_count {
+            channel_x.push(x + 1);
+            x += trunk_count[column] + 2;
+            if column == column_count {
+                break;
+            }
+            x += approach_count[column];
+            column_x.push(x);
+            x += comp_nodes
+                .iter()
+                .zip(&layers)
+                .filter(|(_, &l)| l == column)
+                .map(|(n, _)| n.rect.size.x)
+                .max()
+                .unwrap_or(0);
+        }
+
+        let mut used_rows = HashSet::new();
+        let mut column_y = vec![origin.y; column_count];
+        let mut bottom = origin.y;
+        for (comp_node, &layer) in comp_nodes.iter_mut().zip(&layers) {
+            let mut top = column_y[layer];
+            let rows = |top: i32| {
+                comp_node
+                    .input_slots
+                    .iter()
+                    .chain(&comp_node.output_slots)
+                    .map(move |slot| top + slot.y)
+            };
+            while rows(top).any(|y| used_rows.contains(&y)) {
+                top += 1;
+            }
+            used_rows.extend(rows(top));
+            comp_node.rect.pos = ivec2(column_x[layer], top);
+            column_y[layer] = top + comp_node.rect.size.y + 2;
+            bottom = bottom.max(column_y[layer]);
+        }
+
+        let mut trunk_x = channel_x.clone();
+        let mut approach_x = column_x
+            .iter()
+            .zip(&approach_count)
+            .map(|(x, count)| x - count - 1)
+            .collect::<Vec<_>>();
+        let mut detour_y = bottom;
+        let mut new_cables = Vec::new();
+
+        for net in &nets {
+            let column = driver_column(net);
+            let xt = trunk_x[column + 1];
+            trunk_x[column + 1] += 1;
+
+            let output_pos = |(id, slot): (ComponentId, usize)| {
+                let n = &comp_nodes[index[&id]];
+                n.rect.pos + n.output_slots[slot]
+            };
+            let input_pos = |(id, slot): (ComponentId, usize)| {
+                let n = &comp_nodes[index[&id]];
+                n.rect.pos + n.input_slots[slot]
+            };
+
+            let drivers = net
+                .drivers
+                .iter()
+                .map(|&d| output_pos(d))
+                .collect::<Vec<_>>();
+            let sinks = net
+                .sinks
+                .iter()
+                .map(|&(id, slot)| {
+                    let pos = input_pos((id, slot));
+                    if is_detour(net, id) {
+                        let layer = layers[index[&id]];
+                        let xa = approach_x[layer];
+                        approach_x[layer] += 1;
+                        detour_y += 1;
+                        vec![
+                            ivec2(xt, detour_y),
+                            ivec2(xa, detour_y),
+                            ivec2(xa, pos.y),
+                            pos,
+                        ]
+                    } else {
+                        vec![ivec2(xt, pos.y), pos]
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let mut net_cables = Vec::new();
+            if let ([driver], [sink]) = (drivers.as_slice(), sinks.as_slice()) {
+                let mut points = vec![*driver, ivec2(xt, driver.y)];
+                points.extend(sink);
+                net_cables.push(points);
+            } else {
+                let mut junctions = drivers
+                    .iter()
+                    .map(|d| d.y)
+                    .chain(sinks.iter().map(|s| s[0].y))
+                    .collect::<Vec<_>>();
+                junctions.sort();
+                junctions.dedup();
+
+                for pair in junctions.windows(2) {
+                    net_cables.push(vec![ivec2(xt, pair[0]), ivec2(xt, pair[1])]);
+                }
+                for driver in &drivers {
+                    net_cables.push(vec![*driver, ivec2(xt, driver.y)]);
+                }
+                net_cables.extend(sinks);
+            }
+
+            let net_ids = net_cables
+                .into_iter()
+                .map(|points| {
+                    let mut cable = Cable {
+                        points,
+                        ..Default::default()
+                    };
+                    cable.clean_flat_points();
+                    self.cables.insert(cable)
+                })
+                .collect::<Vec<_>>();
+            new_cables.push(net_ids);
+        }
+
+        for (&id, comp_node) in ids.iter().zip(comp_nodes) {
+            self.components.insert(id, comp_node);
+        }
+
+        for &cable_id in new_cables.iter().flatten() {
+            self.repair_cable_neighbours(cable_id);
+        }
+        for net_ids in &new_cables {
+            self.fix_after_moving_cable(net_ids[0]);
+        }
+
+        for &id in &ids {
+            self.graph.propagate_from(id);
+        }
+
+        ids
+    }
+
+    /// Moves an imported Logisim circuit into the node graph, keeping what it can of its drawing.
+    ///
+    /// Positions are scaled down so that the narrowest pin spacing Logisim uses is a single grid step,
+    /// the wires become cables which recreate the connections. Components without a place in the
+    /// drawing are stacked to the right of it, nets without wires get new cables routed around all.
+    /// Returns the new components and why parts of the circuit were laid out automatically.
+    pub fn insert_logisim(&mut self, circuit: LogisimCircuit) -> (Vec<ComponentId>, Vec<String>) {
+        const SCALE: i32 = 5;
+
+        let LogisimCircuit { graph, layout } = circuit;
+        let mut unplaced = layout.unplaced;
+
+        let scale = |(x, y): Point| {
+            ivec2(
+                (x as f32 / SCALE as f32).round() as i32,
+                (y as f32 / SCALE as f32).round() as i32,
+            )
+        };
+        let top_left = layout
+            .components
+            .iter()
+            .map(|c| c.bounds.0)
+            .chain(layout.wires.iter().flatten().copied())
+            .map(scale)
+            .reduce(|a, b| ivec2(a.x.min(b.x), a.y.min(b.y)))
+            .unwrap_or(ivec2(0, 0));
+        let origin = self.free_origin();
+        let shift = origin - top_left;
+        let position = |point: Point| scale(point) + shift;
+
+        let mut comp_nodes = Vec::new();
+        for placed in &layout.components {
+            let inputs = placed
+                .inputs
+                .iter()
+                .map(|&p| position(p))
+                .collect::<Vec<_>>();
+            let outputs = placed
+                .outputs
+                .iter()
+                .map(|&p| position(p))
+                .collect::<Vec<_>>();
+
+            let (mut min, mut max) = (position(placed.bounds.0), position(placed.bounds.1));
+            for slot in inputs.iter().chain(&outputs) {
+                min = ivec2(min.x.min(slot.x), min.y.min(slot.y));
+                max = ivec2(max.x.max(slot.x), max.y.max(slot.y));
+            }
+
+            let mut comp_node = ComponentNode::new(IRect::new(min, max - min));
+            comp_node.input_slots = inputs.iter().map(|&p| p - min).collect();
+            comp_node.output_slots = outputs.iter().map(|&p| p - min).collect();
+            comp_node.input_cables = vec![None; inputs.len()];
+            comp_node.output_cables = vec![None; outputs.len()];
+            comp_nodes.push((placed.id, comp_node));
+        }
+        let wires = layout
+            .wires
+            .iter()
+            .map(|&[from, to]| vec![position(from), position(to)])
+            .collect::<Vec<_>>();
+
+        let nets = collect_nets(&graph);
+        let right = comp_nodes
+            .iter()
+            .map(|(_, n)| n.rect.pos.x + n.rect.size.x)
+            .chain(wires.iter().flatten().map(|p| p.x))
+            .max()
+            .unwrap_or(origin.x);
+        let mut top = origin.y;
+        for (id, node) in graph.nodes.iter() {
+            if matches!(node.component, Component::Fork(_))
+                || comp_nodes.iter().any(|(placed, _)| *placed == id)
+            {
+                continue;
+            }
+            let pos = ivec2(right + nets.len() as i32 + 4, top);
+            let comp_node = ComponentNode::new(IRect::new(pos, get_size(&node.component)))
+                .with_default_slots(node.component.input_size(), node.component.output_size())
+                .with_bidirectional_pins(&node.component.bidirectional_pins());
+            top += comp_node.rect.size.y + 2;
+            comp_nodes.push((id, comp_node));
+        }
+        let index = comp_nodes
+            .iter()
+            .enumerate()
+            .map(|(i, (id, _))| (*id, i))
+            .collect::<HashMap<_, _>>();
+
+        // Nets through splitters, tunnels or automatically placed components have no wires.
+        let wire_ends = wires.iter().flatten().copied().collect::<HashSet<_>>();
+        let mut router = Router::new(
+            self.cables
+                .values()
+                .map(|c| c.points.as_slice())
+                .chain(wires.iter().map(Vec::as_slice)),
+            self.components
+                .values()
+                .chain(comp_nodes.iter().map(|(_, n)| n)),
+            2 * nets.len() as i32 + 2,
+        );
+        let mut routes = Vec::new();
+        for net in &nets {
+            let mut terminals = Vec::new();
+            for &(id, o) in &net.drivers {
+                let n = &comp_nodes[index[&id]].1;
+                terminals.push(n.rect.pos + n.output_slots[o]);
+            }
+            for &(id, i) in &net.sinks {
+                let n = &comp_nodes[index[&id]].1;
+                terminals.push(n.rect.pos + n.input_slots[i]);
+            }
+            if terminals.iter().any(|t| wire_ends.contains(t)) {
+                continue;
+            }
+            terminals.sort();
+            terminals.dedup();
+            match router.route_net(&terminals) {
+                Some(paths) => routes.extend(paths),
+                None => {
+                    unplaced.push(
+                        "there is no way to route every net through the drawing, \
+                        the whole circuit is laid out automatically"
+                            .to_string(),
+                    );
+                    return (self.insert_graph(graph), unplaced);
+                }
+            }
+        }
+
+        let Graph { mut nodes, .. } = graph;
+        let mut ids = Vec::new();
+        for (old_id, comp_node) in comp_nodes {
+            let node = nodes.remove(old_id).unwrap();
+            let id: ComponentId = self.graph.add_comp(node.component).into();
+            if let Some(name) = node.name {
+                self.graph.set_name(id, name);
+            }
+            self.components.insert(id, comp_node);
+            ids.push(id);
+        }
+
+        let new_cables = wires
+            .into_iter()
+            .chain(routes)
+            .map(|points| {
+                self.cables.insert(Cable {
+                    points,
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>();
+        for &cable_id in &new_cables {
+            if self.cables.contains_key(cable_id) {
+                self.repair_cable_neighbours(cable_id);
+            }
+        }
+        // Cables split while repairing are reached through their neighbours.
+        let mut fixed = HashSet::new();
+        for &cable_id in &new_cables {
+            if self.cables.contains_key(cable_id) && fixed.insert(cable_id) {
+                fixed.extend(self.travel_cable_group(cable_id));
+                self.fix_after_moving_cable(cable_id);
+            }
+        }
+
+        for &id in &ids {
+            self.graph.propagate_from(id);
+        }
+
+        (ids, unplaced)
+    }
+
+    /// Top left corner of the free space to the right of all components and cables.
+    fn free_origin(&self) -> IVec2 {
+        let corners = self
+            .components
+            .values()
+            .flat_map(|c| [c.rect.pos, c.rect.pos + c.rect.size])
+            .chain(self.cables.values().flat_map(|c| c.points.iter().copied()));
+
+        let (mut max_x, mut min_y) = (None, None);
+        for corner in corners {
+            max_x = max_x.max(Some(corner.x));
+            min_y = Some(min_y.map_or(corner.y, |y: i32| y.min(corner.y)));
+        }
+
+        match (max_x, min_y) {
+            (Some(x), Some(y)) => ivec2(x + 4, y),
+            _ => ivec2(
+                self.bounds.x.start().round() as i32 + 2,
+                self.bounds.y.start().round() as i32 + 2,
+            ),
+        }
+    }
+}
+
+fn collect_nets(graph: &Graph) -> Vec<Net> {
+    let is_fork = |id: ComponentId| matches!(graph.nodes[id].component, Component::Fork(_));
+
+    let mut roots = HashMap::new();
+    for (id, _) in graph.nodes.iter().filter(|(id, _)| is_fork(*id)) {
+        roots.insert(id, id);
+    }
+    fn find(roots: &mut HashMap<ComponentId, ComponentId>, id: ComponentId) -> ComponentId {
+        let parent = roots[&id];
+        if parent == id {
+            return id;
+        }
+        let root = find(roots, parent);
+        roots.insert(id, root);
+        root
+    }
+    for (id, node) in graph.nodes.iter().filter(|(id, _)| is_fork(*id)) {
+        for slot in node.output_slots.iter().flatten() {
+            if is_fork(slot.target_node) {
+                let (a, b) = (find(&mut roots, id), find(&mut roots, slot.target_node));
+                roots.insert(a, b);
+            }
+        }
+    }
+
+    let mut nets = Vec::new();
+    let mut fork_nets = HashMap::new();
+    for (id, node) in graph.nodes.iter() {
+        let fork_root = is_fork(id).then(|| find(&mut roots, id));
+        for (o, slot) in node.output_slots.iter().enumerate() {
+            let Some(slot) = slot else { continue };
+            if is_fork(slot.target_node) {
+                if fork_root.is_none() {
+                    let root = find(&mut roots, slot.target_node);
+                    let net = *fork_nets.entry(root).or_insert_with(|| {
+                        nets.push(Net::default());
+                        nets.len() - 1
+                    });
+                    nets[net].drivers.push((id, o));
+                }
+            } else if let Some(root) = fork_root {
+                let net = *fork_nets.entry(root).or_insert_with(|| {
+                    nets.push(Net::default());
+                    nets.len() - 1
+                });
+                nets[net].sinks.push((slot.target_node, slot.target_slot));
+            } else {
+                nets.push(Net {
+                    drivers: vec![(id, o)],
+                    sinks: vec![(slot.target_node, slot.target_slot)],
+                });
+            }
+        }
+    }
+
+    nets.retain(|net| !net.drivers.is_empty() && !net.sinks.is_empty());
+    nets
+}
+
+/// Assigns every component a column, so that most connections go from left to right.
+/// Feedback loops are broken at the first unplaced component.
+fn layers(count: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+    let mut in_degree = vec![0; count];
+    let mut successors = vec![Vec::new(); count];
+    for &(from, to) in edges {
+        in_degree[to] += 1;
+        successors[from].push(to);
+    }
+
+    let mut layers = vec![0; count];
+    let mut placed = vec![false; count];
+    let mut stack = (0..count)
+        .rev()
+        .filter(|&i| in_degree[i] == 0)
+        .collect::<Vec<_>>();
+
+    for _ in 0..count {
+        let next = stack
+            .pop()
+            .unwrap_or_else(|| (0..count).find(|&i| !placed[i]).unwrap());
+        placed[next] = true;
+
+        for &succ in &successors[next] {
+            if placed[succ] {
+                continue;
+            }
+            layers[succ] = layers[succ].max(layers[next] + 1);
+            in_degree[succ] -= 1;
+            if in_degree[succ] == 0 {
+                stack.push(succ);
+            }
+        }
+    }
+
+    layers
+}
+
+/// How a point of the canvas is already used, cables of other nets may only cross straight lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Used {
+    Horizontal,
+    Vertical,
+    /// End or corner of a cable, or a slot.
+    Blocked,
+}
+
+const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+/// Routes cables between components that can't be moved, so that no cable ends on or runs
+/// along a cable of another net.
+struct Router {
+    used: HashMap<IVec2, Used>,
+    /// Points covered by components, only crossed if there is no way around them.
+    covered: HashSet<IVec2>,
+    min: IVec2,
+    max: IVec2,
+}
+
+impl Router {
+    fn new<'a>(
+        cables: impl Iterator<Item = &'a [IVec2]>,
+        components: impl Iterator<Item = &'a ComponentNode>,
+        margin: i32,
+    ) -> Self {
+        let mut router = Router {
+            used: HashMap::new(),
+            covered: HashSet::new(),
+            min: ivec2(i32::MAX, i32::MAX),
+            max: ivec2(i32::MIN, i32::MIN),
+        };
+        for points in cables {
+            router.add_cable(points);
+        }
+        for comp_node in components {
+            let IRect { pos, size } = comp_node.rect;
+            for x in pos.x..=pos.x + size.x {
+                for y in pos.y..=pos.y + size.y {
+                    router.covered.insert(ivec2(x, y));
+                }
+            }
+            for &slot in comp_node.input_slots.iter().chain(&comp_node.output_slots) {
+                router.used.insert(pos + slot, Used::Blocked);
+            }
+            router.extend(pos);
+            router.extend(pos + size);
+        }
+        router.min -= ivec2(margin, margin);
+        router.max += ivec2(margin, margin);
+        router
+    }
+
+    fn extend(&mut self, point: IVec2) {
+        self.min = ivec2(self.min.x.min(point.x), self.min.y.min(point.y));
+        self.max = ivec2(self.max.x.max(point.x), self.max.y.max(point.y));
+    }
+
+    /// Marks every point of a cable, returns the ones it shares with other cables.
+    fn add_cable(&mut self, points: &[IVec2]) -> HashSet<IVec2> {
+        let mut marks = Vec::new();
+        for pair in points.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            marks.push((from, Used::Blocked));
+            let step = ivec2((to.x - from.x).signum(), (to.y - from.y).signum());
+            let along = if step.y == 0 {
+                Used::Horizontal
+            } else {
+                Used::Vertical
+            };
+            let mut point = from + step;
+            while from != to && point != to {
+                marks.push((point, along));
+                point += step;
+            }
+        }
+        marks.extend(points.last().map(|&last| (last, Used::Blocked)));
+
+        let mut shared = HashSet::new();
+        for (point, used) in marks {
+            self.extend(point);
+            if self.used.insert(point, used).is_some() {
+                self.used.insert(point, Used::Blocked);
+                shared.insert(point);
+            }
+        }
+        shared
+    }
+
+    /// Connects all slots of a net, each cable starts at a slot and ends on a slot or a cable.
+    fn route_net(&mut self, terminals: &[IVec2]) -> Option<Vec<Vec<IVec2>>> {
+        let (&first, rest) = terminals.split_first()?;
+        let mut targets = HashSet::from([first]);
+        let mut paths = Vec::new();
+        for &terminal in rest {
+            let path = self.route(terminal, &targets)?;
+            let shared = self.add_cable(&path);
+            for pair in path.windows(2) {
+                let step = ivec2(
+                    (pair[1].x - pair[0].x).signum(),
+                    (pair[1].y - pair[0].y).signum(),
+                );
+                let mut point = pair[0];
+                while point != pair[1] {
+                    point += step;
+                    targets.insert(point);
+                }
+            }
+            targets.retain(|point| !shared.contains(point) && !terminals.contains(point));
+            paths.push(path);
+        }
+        Some(paths)
+    }
+
+    /// Cheapest path from `from` to one of `targets`, long detours are preferred over corners
+    /// and going through components. Returns the corners of the path.
+    fn route(&self, from: IVec2, targets: &HashSet<IVec2>) -> Option<Vec<IVec2>> {
+        let width = self.max.x - self.min.x + 1;
+        let height = self.max.y - self.min.y + 1;
+        let index = |point: IVec2, direction: usize| {
+            ((point.y - self.min.y) * width + point.x - self.min.x) as usize * 4 + direction
+        };
+        let point_at = |index: usize| {
+            let cell = (index / 4) as i32;
+            ivec2(cell % width + self.min.x, cell / width + self.min.y)
+        };
+
+        let mut cost = vec![u32::MAX; (width * height) as usize * 4];
+        let mut previous = vec![usize::MAX; cost.len()];
+        let mut queue = BinaryHeap::new();
+        for direction in 0..4 {
+            cost[index(from, direction)] = 0;
+            queue.push(Reverse((0, index(from, direction))));
+        }
+
+        while let Some(Reverse((c, state))) = queue.pop() {
+            if c > cost[state] {
+                continue;
+            }
+            let (point, direction) = (point_at(state), state % 4);
+            if point != from && targets.contains(&point) {
+                let mut path = vec![point];
+                let mut state = state;
+                while previous[state] != usize::MAX {
+                    let before = previous[state];
+                    if before % 4 != state % 4 || previous[before] == usize::MAX {
+                        path.push(point_at(before));
+                    }
+                    state = before;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for (next_direction, &(dx, dy)) in DIRECTIONS.iter().enumerate() {
+                let turn = next_direction != direction;
+                if next_direction == (direction + 2) % 4
+                    || turn && (point == from || self.used.contains_key(&point))
+                {
+                    continue;
+                }
+                let next = point + ivec2(dx, dy);
+                if next.x < self.min.x
+                    || next.y < self.min.y
+                    || next.x > self.max.x
+                    || next.y > self.max.y
+                {
+                    continue;
+                }
+                if !targets.contains(&next) {
+                    match self.used.get(&next) {
+                        None => {}
+                        Some(Used::Horizontal) if dx == 0 => {}
+                        Some(Used::Vertical) if dy == 0 => {}
+                        _ => continue,
+                    }
+                }
+
+                let mut next_cost = c + 1;
+                if turn {
+                    next_cost += 4;
+                }
+                if self.covered.contains(&next) {
+                    next_cost += 20;
+                }
+                let next_state = index(next, next_direction);
+                if next_cost < cost[next_state] {
+                    cost[next_state] = next_cost;
+                    previous[next_state] = state;
+                    queue.push(Reverse((next_cost, next_state)));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use simulator_core::{
+        analysis::synthesis::{parse_equations, synthesize, GateStyle},
+        import::{logisim::from_logisim, verilog::from_verilog},
+    };
+
+    use super::*;
+
+    fn set(nodegraph: &mut NodeGraph, name: &str, state: bool) {
+        let id = nodegraph.graph.find_by_name(name).unwrap();
+        if let Component::Constant(c) = &mut nodegraph.graph[id] {
+            c.state = state;
+        }
+        nodegraph.graph.propagate_from(id);
+    }
+
+    fn get(nodegraph: &NodeGraph, name: &str) -> bool {
+        let id = nodegraph.graph.find_by_name(name).unwrap();
+        match &nodegraph.graph[id] {
+            Component::DebugOutput(d) => d.state,
+            _ => panic!("`{name}` is not an output"),
+        }
+    }
+
+    #[test]
+    fn inserted_circuits_keep_connections() {
+        let source = "
+            module top(input a, input b, input c, input s, input r, output sum, output carry, output q);
+                wire nq;
+                assign sum = a ^ b ^ c;
+                assign carry = (a & b) | (c & (a ^ b));
+                nor (q, r, nq);
+                nor (nq, s, q);
+            endmodule
+        ";
+        let mut nodegraph = NodeGraph::new();
+        nodegraph.insert_graph(from_verilog(source, None).unwrap());
+        // A second copy must not get tangled with the first one.
+        nodegraph.insert_graph(from_verilog(source, None).unwrap());
+
+        for input in 0..8 {
+            let [a, b, c] = [0, 1, 2].map(|i| (input >> i) & 1 == 1);
+            set(&mut nodegraph, "a", a);
+            set(&mut nodegraph, "b", b);
+            set(&mut nodegraph, "c", c);
+            let total = a as u8 + b as u8 + c as u8;
+            assert_eq!(get(&nodegraph, "sum"), total & 1 == 1);
+            assert_eq!(get(&nodegraph, "carry"), total >> 1 == 1);
+        }
+
+        set(&mut nodegraph, "s", true);
+        set(&mut nodegraph, "s", false);
+        assert!(get(&nodegraph, "q"));
+        set(&mut nodegraph, "r", true);
+        set(&mut nodegraph, "r", false);
+        assert!(!get(&nodegraph, "q"));
+    }
+
+    #[test]
+    fn synthesized_networks_are_placed() {
+        let equations = parse_equations("Q = (A & !B) | C").unwrap();
+        let mut nodegraph = NodeGraph::new();
+        nodegraph.insert_graph(synthesize(&equations, GateStyle::NorOnly));
+
+        for input in 0..8 {
+            let [a, b, c] = [0, 1, 2].map(|i| (input >> i) & 1 == 1);
+            set(&mut nodegraph, "A", a);
+            set(&mut nodegraph, "B", b);
+            set(&mut nodegraph, "C", c);
+            assert_eq!(get(&nodegraph, "Q"), (a && !b) || c);
+        }
+    }
+
+    #[test]
+    fn logisim_drawing_is_kept() {
+        let source = r#"<project source="3.8.0" version="1.0">
+  <circuit name="half">
+    <wire from="(40,40)" to="(140,40)"/>
+    <wire from="(40,80)" to="(140,80)"/>
+    <wire from="(100,40)" to="(100,140)"/>
+    <wire from="(100,140)" to="(150,140)"/>
+    <wire from="(120,80)" to="(120,180)"/>
+    <wire from="(120,180)" to="(150,180)"/>
+    <wire from="(200,60)" to="(300,60)"/>
+    <wire from="(200,160)" to="(300,160)"/>
+    <comp lib="0" loc="(40,40)" name="Pin"><a name="label" val="a"/></comp>
+    <comp lib="0" loc="(40,80)" name="Pin"><a name="label" val="b"/></comp>
+    <comp lib="0" loc="(300,60)" name="Pin">
+      <a name="facing" val="west"/><a name="output" val="true"/><a name="label" val="sum"/>
+    </comp>
+    <comp lib="0" loc="(300,160)" name="Pin">
+      <a name="facing" val="west"/><a name="output" val="true"/><a name="label" val="carry"/>
+    </comp>
+    <comp lib="1" loc="(200,60)" name="XOR Gate"/>
+    <comp lib="1" loc="(200,160)" name="AND Gate"/>
+  </circuit>
+</project>"#;
+        let mut nodegraph = NodeGraph::new();
+        let (ids, unplaced) = nodegraph.insert_logisim(from_logisim(source, None).unwrap());
+
+        assert!(unplaced.is_empty());
+        assert_eq!(ids.len(), 6);
+        assert_eq!(nodegraph.cables.len(), 10);
+        let a = nodegraph.graph.find_by_name("a").unwrap();
+        let xor = ids
+            .iter()
+            .copied()
+            .find(|&id| matches!(nodegraph.graph[id], Component::Xor(_)))
+            .unwrap();
+        let (a, xor) = (nodegraph.comp(a), nodegraph.comp(xor));
+        assert_eq!(
+            xor.rect.pos + xor.input_slots[0] - (a.rect.pos + a.output_slots[0]),
+            ivec2(20, 0)
+        );
+
+        for input in 0..4 {
+            let [a, b] = [0, 1].map(|i| (input >> i) & 1 == 1);
+            set(&mut nodegraph, "a", a);
+            set(&mut nodegraph, "b", b);
+            assert_eq!(get(&nodegraph, "sum"), a ^ b);
+            assert_eq!(get(&nodegraph, "carry"), a && b);
+        }
+    }
+
+    #[test]
+    fn logisim_tunnels_are_routed() {
+        let source = r#"<project source="3.8.0" version="1.0">
+  <circuit name="half">
+    <wire from="(200,60)" to="(300,60)"/>
+    <wire from="(200,160)" to="(300,160)"/>
+    <comp lib="0" loc="(40,40)" name="Pin"><a name="label" val="a"/></comp>
+    <comp lib="0" loc="(40,80)" name="Pin"><a name="label" val="b"/></comp>
+    <comp lib="0" loc="(40,40)" name="Tunnel"><a name="label" val="a"/></comp>
+    <comp lib="0" loc="(40,80)" name="Tunnel"><a name="label" val="b"/></comp>
+    <comp lib="0" loc="(140,40)" name="Tunnel"><a name="label" val="a"/></comp>
+    <comp lib="0" loc="(140,80)" name="Tunnel"><a name="label" val="b"/></comp>
+    <comp lib="0" loc="(150,140)" name="Tunnel"><a name="label" val="a"/></comp>
+    <comp lib="0" loc="(150,180)" name="Tunnel"><a name="label" val="b"/></comp>
+    <comp lib="0" loc="(300,60)" name="Pin">
+      <a name="facing" val="west"/><a name="output" val="true"/><a name="label" val="sum"/>
+    </comp>
+    <comp lib="0" loc="(300,160)" name="Pin">
+      <a name="facing" val="west"/><a name="output" val="true"/><a name="label" val="carry"/>
+    </comp>
+    <comp lib="1" loc="(200,60)" name="XOR Gate"/>
+    <comp lib="1" loc="(200,160)" name="AND Gate"/>
+  </circuit>
+</project>"#;
+        let mut nodegraph = NodeGraph::new();
+        let (ids, unplaced) = nodegraph.insert_logisim(from_logisim(source, None).unwrap());
+
+        assert_eq!(ids.len(), 6);
+        assert_eq!(unplaced.len(), 6);
+        let find = |pred: fn(&Component) -> bool| {
+            let id = ids.iter().copied().find(|&id| pred(&nodegraph.graph[id]));
+            nodegraph.comp(id.unwrap())
+        };
+        let a = nodegraph.comp(nodegraph.graph.find_by_name("a").unwrap());
+        let carry = nodegraph.comp(nodegraph.graph.find_by_name("carry").unwrap());
+        let xor = find(|c| matches!(c, Component::Xor(_)));
+        let and = find(|c| matches!(c, Component::And(_)));
+        let a_slot = a.rect.pos + a.output_slots[0];
+        assert_eq!(xor.rect.pos + xor.input_slots[0] - a_slot, ivec2(20, 0));
+        assert_eq!(and.rect.pos + and.input_slots[0] - a_slot, ivec2(22, 20));
+        assert_eq!(
+            carry.rect.pos + carry.input_slots[0] - a_slot,
+            ivec2(52, 24)
+        );
+
+        for input in 0..4 {
+            let [a, b] = [0, 1].map(|i| (input >> i) & 1 == 1);
+            set(&mut nodegraph, "a", a);
+            set(&mut nodegraph, "b", b);
+            assert_eq!(get(&nodegraph, "sum"), a ^ b);
+            assert_eq!(get(&nodegraph, "carry"), a && b);
+        }
+    }
+}
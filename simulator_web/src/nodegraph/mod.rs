@@ -1,4 +1,5 @@
 pub mod components;
 pub mod cables;
 pub mod graph;
+pub mod layout;
 pub mod transform;
\ No newline at end of file
@@ -0,0 +1,26 @@
+use simulator_core::graph::{id::ComponentId, propagation::Propagation};
+
+#[derive(Debug, Clone)]
+pub struct AnimationState {
+    /// Clicks propagate one evaluated component at a time.
+    pub enabled: bool,
+    /// Evaluations per second.
+    pub speed: f32,
+    pub propagation: Option<Propagation>,
+    /// App time in seconds of the last evaluation.
+    pub last_step: f64,
+    /// Component evaluated last and its output slots which changed.
+    pub evaluated: Option<(ComponentId, Vec<usize>)>,
+}
+
+impl Default for AnimationState {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            speed: 4.0,
+            propagation: None,
+            last_step: 0.0,
+            evaluated: None,
+        }
+    }
+}
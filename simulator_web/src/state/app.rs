@@ -1,6 +1,11 @@
 use crate::{components::registry::ComponentRegistry, nodegraph::graph::NodeGraph};
 
-use super::{modes::ModeState, selection::{Selection, SelectionState}};
+use super::{
+    animation::AnimationState, event_log::EventLogState, export::ExportState, faults::FaultState,
+    hazards::HazardState, history::HistoryState, import::ImportState, karnaugh::KarnaughState,
+    model_checking::ModelCheckingState, modes::ModeState, selection::SelectionState,
+    synthesis::SynthesisState, truth_table::TruthTableState,
+};
 
 #[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
@@ -10,4 +15,26 @@ pub struct AppState {
     pub node_graph: NodeGraph,
     #[serde(skip)]
     pub registry: ComponentRegistry,
-}
\ No newline at end of file
+    #[serde(skip)]
+    pub import_state: ImportState,
+    #[serde(skip)]
+    pub export_state: ExportState,
+    #[serde(skip)]
+    pub truth_table_state: TruthTableState,
+    #[serde(skip)]
+    pub synthesis_state: SynthesisState,
+    #[serde(skip)]
+    pub karnaugh_state: KarnaughState,
+    #[serde(skip)]
+    pub model_checking_state: ModelCheckingState,
+    #[serde(skip)]
+    pub fault_state: FaultState,
+    #[serde(skip)]
+    pub history_state: HistoryState,
+    #[serde(skip)]
+    pub event_log_state: EventLogState,
+    #[serde(skip)]
+    pub animation_state: AnimationState,
+    #[serde(skip)]
+    pub hazard_state: HazardState,
+}
@@ -0,0 +1,22 @@
+use simulator_core::graph::{event_log::EventLog, history::InputEvent};
+
+#[derive(Debug, Default, Clone)]
+pub struct EventLogState {
+    pub open: bool,
+    /// App time in seconds when the recording started, while recording.
+    pub started: Option<f64>,
+    pub log: EventLog,
+    /// Log text to copy, or pasted to be replayed.
+    pub text: String,
+    pub error: Option<String>,
+}
+
+impl EventLogState {
+    /// Adds the event to the log while recording, `time` being the app time in seconds.
+    pub fn record(&mut self, time: f64, event: InputEvent) {
+        if let Some(started) = self.started {
+            let millis = ((time - started) * 1000.0).max(0.0) as u64;
+            self.log.push(millis, event);
+        }
+    }
+}
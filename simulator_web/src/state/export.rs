@@ -0,0 +1,5 @@
+#[derive(Debug, Default, Clone)]
+pub struct ExportState {
+    pub open: bool,
+    pub cluster_subcircuits: bool,
+}
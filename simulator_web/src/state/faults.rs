@@ -0,0 +1,38 @@
+use simulator_core::{
+    analysis::fault_simulation::FaultReport,
+    graph::{
+        fault::{FaultValue, Pin},
+        id::ComponentId,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct FaultState {
+    pub open: bool,
+    /// Pin and value of the next fault to inject.
+    pub node: Option<ComponentId>,
+    pub pin: Pin,
+    pub value: FaultValue,
+    /// Chosen `Constant`s and `DebugOutput`s the vectors are applied to and read from.
+    pub inputs: Vec<ComponentId>,
+    pub outputs: Vec<ComponentId>,
+    pub vectors: String,
+    pub report: Option<FaultReport>,
+    pub error: Option<String>,
+}
+
+impl Default for FaultState {
+    fn default() -> Self {
+        Self {
+            open: false,
+            node: None,
+            pin: Pin::Output(0),
+            value: FaultValue::StuckAt(false),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            vectors: String::new(),
+            report: None,
+            error: None,
+        }
+    }
+}
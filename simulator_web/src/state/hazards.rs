@@ -0,0 +1,17 @@
+use simulator_core::{
+    analysis::{hazards::HazardReport, races::Race},
+    graph::id::ComponentId,
+};
+
+/// Shuffled evaluation orders tried for each input.
+pub const RACE_ORDERS: usize = 32;
+
+#[derive(Debug, Default, Clone)]
+pub struct HazardState {
+    pub open: bool,
+    /// Chosen `Constant`s toggled one at a time.
+    pub inputs: Vec<ComponentId>,
+    pub reports: Option<Vec<HazardReport>>,
+    /// Outputs racing when each input is toggled.
+    pub races: Option<Vec<(ComponentId, Vec<Race>)>>,
+}
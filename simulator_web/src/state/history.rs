@@ -0,0 +1,21 @@
+use simulator_core::graph::{
+    history::{History, InputEvent},
+    Graph,
+};
+
+/// Events between checkpoints of the history.
+pub const CHECKPOINT_INTERVAL: usize = 64;
+
+/// Clicks on the running circuit, started on the first one and dropped when the circuit is edited.
+#[derive(Debug, Default, Clone)]
+pub struct HistoryState {
+    pub history: Option<History>,
+}
+
+impl HistoryState {
+    pub fn record(&mut self, graph: &mut Graph, event: InputEvent) {
+        self.history
+            .get_or_insert_with(|| History::new(graph, CHECKPOINT_INTERVAL))
+            .record(graph, event);
+    }
+}
@@ -0,0 +1,17 @@
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    #[default]
+    Verilog,
+    Logisim,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ImportState {
+    pub open: bool,
+    pub format: ImportFormat,
+    pub source: String,
+    pub top: String,
+    pub error: Option<String>,
+    /// Why parts of the last imported circuit were laid out automatically.
+    pub notes: Vec<String>,
+}
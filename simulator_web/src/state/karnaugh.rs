@@ -0,0 +1,71 @@
+use simulator_core::{analysis::synthesis::GateStyle, graph::id::ComponentId};
+
+pub const MIN_VARS: usize = 2;
+pub const MAX_VARS: usize = 6;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CellValue {
+    #[default]
+    Zero,
+    One,
+    DontCare,
+}
+
+impl CellValue {
+    pub fn next(self) -> Self {
+        match self {
+            CellValue::Zero => CellValue::One,
+            CellValue::One => CellValue::DontCare,
+            CellValue::DontCare => CellValue::Zero,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KarnaughState {
+    pub open: bool,
+    /// Variable names, the first half labels the rows and the rest the columns.
+    pub names: Vec<String>,
+    pub output: String,
+    /// Cell values by minterm, the first variable being the most significant bit.
+    pub cells: Vec<CellValue>,
+    /// Highlight every prime implicant instead of the chosen cover.
+    pub all_primes: bool,
+    pub style: GateStyle,
+    /// Components the map is loaded from.
+    pub inputs: Vec<ComponentId>,
+    pub source_output: Option<ComponentId>,
+    pub error: Option<String>,
+}
+
+impl KarnaughState {
+    pub fn vars(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Changes the number of variables, clearing the map.
+    pub fn set_vars(&mut self, vars: usize) {
+        self.names = (0..vars)
+            .map(|var| char::from(b'A' + var as u8).to_string())
+            .collect();
+        self.cells = vec![CellValue::Zero; 1 << vars];
+    }
+}
+
+impl Default for KarnaughState {
+    fn default() -> Self {
+        let mut state = Self {
+            open: false,
+            names: Vec::new(),
+            output: "Q".to_string(),
+            cells: Vec::new(),
+            all_primes: false,
+            style: GateStyle::default(),
+            inputs: Vec::new(),
+            source_output: None,
+            error: None,
+        };
+        state.set_vars(4);
+        state
+    }
+}
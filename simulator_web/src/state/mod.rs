@@ -1,3 +1,14 @@
 pub mod modes;
 pub mod selection;
-pub mod app;
\ No newline at end of file
+pub mod app;
+pub mod import;
+pub mod export;
+pub mod truth_table;
+pub mod synthesis;
+pub mod karnaugh;
+pub mod model_checking;
+pub mod faults;
+pub mod event_log;
+pub mod history;
+pub mod animation;
+pub mod hazards;
\ No newline at end of file
@@ -0,0 +1,31 @@
+use simulator_core::{
+    analysis::bmc::{BmcError, BmcResult},
+    graph::id::ComponentId,
+};
+
+#[derive(Debug, Clone)]
+pub struct ModelCheckingState {
+    pub open: bool,
+    pub property: String,
+    /// Whether the property must always hold, or never.
+    pub always: bool,
+    pub cycles: usize,
+    pub clock: Option<ComponentId>,
+    pub result: Option<Result<BmcResult, BmcError>>,
+    /// Steps of the counterexample already applied to the graph.
+    pub replayed: usize,
+}
+
+impl Default for ModelCheckingState {
+    fn default() -> Self {
+        Self {
+            open: false,
+            property: String::new(),
+            always: false,
+            cycles: 8,
+            clock: None,
+            result: None,
+            replayed: 0,
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use simulator_core::analysis::synthesis::GateStyle;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SynthesisSource {
+    #[default]
+    Equations,
+    TruthTable,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SynthesisState {
+    pub open: bool,
+    pub source_kind: SynthesisSource,
+    pub source: String,
+    pub style: GateStyle,
+    pub error: Option<String>,
+}
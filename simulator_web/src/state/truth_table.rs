@@ -0,0 +1,19 @@
+use simulator_core::{
+    analysis::{
+        minimize::MinimizationReport,
+        truth_table::{TruthTable, TruthTableError},
+    },
+    graph::id::ComponentId,
+};
+
+#[derive(Debug, Default, Clone)]
+pub struct TruthTableState {
+    pub open: bool,
+    /// Chosen `Constant`s, in column order.
+    pub inputs: Vec<ComponentId>,
+    /// Chosen `DebugOutput`s, in column order.
+    pub outputs: Vec<ComponentId>,
+    pub result: Option<Result<TruthTable, TruthTableError>>,
+    /// Expressions of the outputs, present along with a table.
+    pub report: Option<MinimizationReport>,
+}
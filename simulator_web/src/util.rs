@@ -188,7 +188,7 @@ impl Div<IVec2> for i32 {
 
 #[cfg(test)]
 mod tests {
-    use egui::{vec2, pos2};
+    use egui::pos2;
 
     use super::*;
 
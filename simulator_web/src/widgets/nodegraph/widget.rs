@@ -4,12 +4,12 @@ use egui::{
     epaint::PathShape, pos2, vec2, Align2, Color32, FontId, Painter, Pos2, Rect, Response, Sense,
     Stroke, Ui, Vec2,
 };
-use simulator_core::components::Component;
-use log::info;
+use simulator_core::{
+    components::{memory::FlipFlopKind, Component},
+    graph::{history::InputEvent, logic::Logic},
+};
 
 use crate::{
-    app,
-    components::registry::ComponentRegistry,
     nodegraph::{
         cables::Cable,
         components::ComponentIntersection,
@@ -17,8 +17,11 @@ use crate::{
         transform::{NodeGraphBounds, NodeGraphTransform},
     },
     state::{
+        animation::AnimationState,
         app::AppState,
-        modes::{AddingOptions, Mode, ModeState},
+        event_log::EventLogState,
+        history::HistoryState,
+        modes::{AddingOptions, Mode},
         selection::{Selection, SelectionAction, SelectionState},
     },
     util::{IRect, IVec2},
@@ -38,10 +41,12 @@ pub fn nodegraph_widget(ui: &mut Ui, app_state: &mut AppState) {
         .map(|pos| transform.point_to_bounds(pos));
 
     reset_highlights(&mut app_state.node_graph);
+    animate(ui, app_state);
 
     match (app_state.selection_state.action, click_pos, hover_pos) {
         (SelectionAction::Nothing, Some(pos), _) => {
-            use_mode_click_handling(app_state, pos);
+            let time = ui.input(|i| i.time);
+            use_mode_click_handling(app_state, pos, time);
         }
         (SelectionAction::Nothing, _, Some(pos)) => {
             highlight_hovered(&mut app_state.node_graph, pos);
@@ -62,22 +67,31 @@ pub fn nodegraph_widget(ui: &mut Ui, app_state: &mut AppState) {
     grid_controls(ui, &response, &mut app_state.node_graph.bounds, &transform);
     grid(&painter, &transform);
 
-    comps(&painter, &mut app_state.node_graph, &transform);
-    cables(&painter, &mut app_state.node_graph, &transform)
+    comps(&painter, &app_state.node_graph, &transform);
+    cables(&painter, &app_state.node_graph, &transform)
 }
 
-fn use_mode_click_handling(app_state: &mut AppState, pos: Pos2) {
+/// Handles a click at `pos`, `time` being the app time in seconds.
+fn use_mode_click_handling(app_state: &mut AppState, pos: Pos2, time: f64) {
     let AppState {
         mode_state,
         selection_state,
         node_graph,
         registry,
+        history_state,
+        event_log_state,
+        animation_state,
+        ..
     } = app_state;
 
     match mode_state.mode {
+        Mode::Running if animation_state.enabled => {
+            animated_clicked_controls(node_graph, animation_state, event_log_state, pos, time);
+            history_state.history = None;
+        }
         Mode::Running => {
-            comps_clicked_controls(&mut app_state.node_graph, pos);
-            output_cables_coloring(&mut app_state.node_graph);
+            comps_clicked_controls(node_graph, history_state, event_log_state, pos, time);
+            output_cables_coloring(node_graph);
         }
         Mode::Adding => match mode_state.add_opt {
             AddingOptions::Cable => {
@@ -269,16 +283,16 @@ pub fn highlight_hovered(nodegraph: &mut NodeGraph, pos: Pos2) {
 
 pub fn output_cables_coloring(nodegraph: &mut NodeGraph) {
     for c_id in nodegraph.components.keys() {
-        let output = &nodegraph.graph.outputs[c_id];
         for (i, maybe_output_cable_id) in
             nodegraph.components[c_id].output_cables.iter().enumerate()
         {
             if let &Some(output_cable_id) = maybe_output_cable_id {
                 let group = nodegraph.travel_cable_group(output_cable_id);
-                let color = if output[i] {
-                    Cable::ACTIVATED_COLOR
-                } else {
-                    Cable::DEFAULT_COLOR
+                let color = match nodegraph.graph.output_level(c_id, i) {
+                    Logic::One => Cable::ACTIVATED_COLOR,
+                    Logic::Zero => Cable::DEFAULT_COLOR,
+                    Logic::X => Cable::UNKNOWN_COLOR,
+                    Logic::Z => Cable::FLOATING_COLOR,
                 };
                 for &group_cable_id in &group {
                     nodegraph.cables[group_cable_id].color = color;
@@ -366,6 +380,23 @@ pub fn comps(painter: &Painter, nodegraph: &NodeGraph, transform: &NodeGraphTran
                 draw_constant(painter, transform, comp.rect, s.state as u32)
             }
             Component::Constant(s) => draw_constant(painter, transform, comp.rect, s.state as u32),
+            Component::Subcircuit(ref s) => draw_box(painter, transform, comp.rect, &s.name),
+            Component::FlipFlop(ref f) => {
+                let label = match f.kind {
+                    FlipFlopKind::D => "D",
+                    FlipFlopKind::T => "T",
+                    FlipFlopKind::JK => "JK",
+                    FlipFlopKind::SR => "SR",
+                };
+                draw_box(painter, transform, comp.rect, label)
+            }
+            Component::Rom(_) => draw_box(painter, transform, comp.rect, "ROM"),
+            Component::Ram(_) => draw_box(painter, transform, comp.rect, "RAM"),
+            Component::PullResistor(p) => {
+                draw_box(painter, transform, comp.rect, if p.level { "PU" } else { "PD" })
+            }
+            Component::OpenCollector(_) => draw_box(painter, transform, comp.rect, "OC"),
+            Component::Transceiver(_) => draw_box(painter, transform, comp.rect, "XCVR"),
         }
 
         draw_slots(
@@ -378,20 +409,112 @@ pub fn comps(painter: &Painter, nodegraph: &NodeGraph, transform: &NodeGraphTran
     }
 }
 
-pub fn comps_clicked_controls(nodegraph: &mut NodeGraph, clicked_pos: Pos2) {
+pub fn comps_clicked_controls(
+    nodegraph: &mut NodeGraph,
+    history_state: &mut HistoryState,
+    event_log_state: &mut EventLogState,
+    clicked_pos: Pos2,
+    time: f64,
+) {
     let comps = nodegraph
         .components_intersecting(clicked_pos, 0.2)
         .map(|(id, _)| id)
         .collect::<Vec<_>>();
 
     for c_id in comps {
-        let comp = &mut nodegraph.graph[c_id];
+        let comp = &nodegraph.graph[c_id];
 
         if let Component::Constant(x) = comp {
-            x.state = !x.state;
+            let event = InputEvent::SetConstant {
+                node: c_id,
+                state: !x.state,
+            };
+            history_state.record(&mut nodegraph.graph, event);
+            event_log_state.record(time, event);
+        } else {
+            nodegraph.graph.propagate_from(c_id);
         }
+    }
+}
 
-        nodegraph.graph.propagate_from(c_id);
+/// Starts propagating the click one component at a time, finishing an earlier propagation first.
+fn animated_clicked_controls(
+    nodegraph: &mut NodeGraph,
+    animation_state: &mut AnimationState,
+    event_log_state: &mut EventLogState,
+    clicked_pos: Pos2,
+    time: f64,
+) {
+    let clicked = nodegraph.components_intersecting(clicked_pos, 0.2);
+    let Some(c_id) = clicked.map(|(id, _)| id).last() else {
+        return;
+    };
+    let graph = &mut nodegraph.graph;
+    if let Some(mut propagation) = animation_state.propagation.take() {
+        propagation.finish(graph);
+    }
+    if let Component::Constant(x) = &mut graph[c_id] {
+        x.state = !x.state;
+        let event = InputEvent::SetConstant {
+            node: c_id,
+            state: x.state,
+        };
+        event_log_state.record(time, event);
+    }
+    animation_state.propagation = Some(graph.start_propagation(c_id));
+    animation_state.last_step = time;
+    output_cables_coloring(nodegraph);
+}
+
+/// Makes the next step of the animated propagation when it is time,
+/// highlighting the evaluated component and the cables it changed.
+fn animate(ui: &Ui, app_state: &mut AppState) {
+    let AppState {
+        node_graph,
+        mode_state,
+        animation_state: state,
+        ..
+    } = app_state;
+
+    let Some(propagation) = &mut state.propagation else {
+        state.evaluated = None;
+        return;
+    };
+    if !state.enabled || mode_state.mode != Mode::Running {
+        propagation.finish(&mut node_graph.graph);
+        state.propagation = None;
+        output_cables_coloring(node_graph);
+        return;
+    }
+
+    let time = ui.input(|i| i.time);
+    if time - state.last_step >= 1.0 / state.speed as f64 {
+        state.last_step = time;
+        match propagation.step_details(&mut node_graph.graph) {
+            Some(step) => {
+                state.evaluated = Some((step.node, step.changed));
+                output_cables_coloring(node_graph);
+            }
+            None => state.propagation = None,
+        }
+    }
+    ui.ctx().request_repaint();
+
+    let Some((node, changed)) = &state.evaluated else {
+        return;
+    };
+    let Some(comp) = node_graph.components.get_mut(*node) else {
+        return;
+    };
+    comp.highlight_level = 2;
+    let cables = changed
+        .iter()
+        .filter_map(|&slot| comp.output_cables.get(slot).copied().flatten())
+        .collect::<Vec<_>>();
+    for cable in cables {
+        for id in node_graph.travel_cable_group(cable) {
+            node_graph.cable_mut(id).highlight_level = 2;
+        }
     }
 }
 
@@ -403,14 +526,23 @@ fn draw_slots(
     output_slots: &[IVec2],
 ) {
     for &input in input_slots {
+        // A bidirectional pin has its input slot on its output slot.
+        let color = if output_slots.contains(&input) {
+            Color32::YELLOW
+        } else {
+            Color32::RED
+        };
         painter.circle_filled(
             transform.point_i_to_screen(origin + input),
             8.0 * transform.bounds.zoom,
-            Color32::RED,
+            color,
         );
     }
 
-    for &output in output_slots {
+    let plain_outputs = output_slots
+        .iter()
+        .filter(|output| !input_slots.contains(output));
+    for &output in plain_outputs {
         painter.circle_filled(
             transform.point_i_to_screen(origin + output),
             8.0 * transform.bounds.zoom,
@@ -464,6 +596,10 @@ fn draw_gate(painter: &Painter, transform: &NodeGraphTransform, rect: IRect, poi
 }
 
 fn draw_constant(painter: &Painter, transform: &NodeGraphTransform, rect: IRect, state: u32) {
+    draw_box(painter, transform, rect, &format!("{state}"));
+}
+
+fn draw_box(painter: &Painter, transform: &NodeGraphTransform, rect: IRect, label: &str) {
     let stroke = Stroke::new(5.0 * transform.bounds.zoom, Color32::WHITE);
 
     painter.rect(
@@ -481,7 +617,7 @@ fn draw_constant(painter: &Painter, transform: &NodeGraphTransform, rect: IRect,
     painter.text(
         transform.point_to_screen(pos + size / 2.0),
         Align2::CENTER_CENTER,
-        format!("{state}"),
+        label,
         FontId::default(),
         Color32::WHITE,
     );
@@ -1,6 +1,6 @@
 use egui::Ui;
 
-use crate::{components::registry::{ComponentRegistry, ComponentRid, ComponentEntry}, nodegraph::components::ComponentNode};
+use crate::{components::registry::ComponentEntry, nodegraph::components::ComponentNode};
 
 pub fn show_default_component_menu(
     ui: &mut Ui,
@@ -0,0 +1,60 @@
+use egui::{Color32, Context, ScrollArea, TextEdit, Window};
+use simulator_core::graph::event_log::EventLog;
+
+use crate::{state::app::AppState, widgets::nodegraph::widget::output_cables_coloring};
+
+/// Records the clicks on the running circuit as a timestamped log and replays pasted logs.
+pub fn show_event_log_window(ctx: &Context, app_state: &mut AppState) {
+    let AppState {
+        node_graph,
+        history_state,
+        event_log_state: state,
+        ..
+    } = app_state;
+
+    let time = ctx.input(|i| i.time);
+    Window::new("Event log")
+        .open(&mut state.open)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if state.started.is_none() {
+                    if ui.button("Record").clicked() {
+                        state.started = Some(time);
+                        state.log = EventLog::default();
+                    }
+                } else if ui.button("Stop").clicked() {
+                    state.started = None;
+                    state.text = state.log.to_text(&node_graph.graph);
+                }
+                ui.label(format!("{} events", state.log.events.len()));
+            });
+
+            ui.horizontal(|ui| {
+                if ui.button("Copy").clicked() {
+                    ui.output_mut(|o| o.copied_text = state.text.clone());
+                }
+                if ui.button("Replay").clicked() {
+                    match EventLog::parse(&state.text, &node_graph.graph) {
+                        Ok(log) => {
+                            for timed in &log.events {
+                                history_state.record(&mut node_graph.graph, timed.event);
+                            }
+                            output_cables_coloring(node_graph);
+                            state.error = None;
+                        }
+                        Err(err) => state.error = Some(err.to_string()),
+                    }
+                }
+            });
+            if let Some(error) = &state.error {
+                ui.colored_label(Color32::RED, error);
+            }
+            ScrollArea::vertical().show(ui, |ui| {
+                ui.add(
+                    TextEdit::multiline(&mut state.text)
+                        .code_editor()
+                        .hint_text("0 set clock 1"),
+                );
+            });
+        });
+}
@@ -0,0 +1,32 @@
+use egui::{Context, ScrollArea, TextEdit, Window};
+use simulator_core::export::dot::{to_dot, DotOptions};
+
+use crate::state::app::AppState;
+
+/// Shows the logical graph, hidden `Fork`s included, as Graphviz DOT source.
+pub fn show_dot_window(ctx: &Context, app_state: &mut AppState) {
+    let AppState {
+        node_graph,
+        export_state,
+        ..
+    } = app_state;
+
+    Window::new("Graphviz DOT")
+        .open(&mut export_state.open)
+        .show(ctx, |ui| {
+            ui.checkbox(&mut export_state.cluster_subcircuits, "Cluster subcircuits");
+
+            let mut dot = to_dot(
+                &node_graph.graph,
+                DotOptions {
+                    cluster_subcircuits: export_state.cluster_subcircuits,
+                },
+            );
+            if ui.button("Copy").clicked() {
+                ui.output_mut(|o| o.copied_text = dot.clone());
+            }
+            ScrollArea::vertical().show(ui, |ui| {
+                ui.add(TextEdit::multiline(&mut dot).code_editor().interactive(false));
+            });
+        });
+}
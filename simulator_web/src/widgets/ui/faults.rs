@@ -0,0 +1,198 @@
+use egui::{
+    Button, CollapsingHeader, Color32, ComboBox, Context, ScrollArea, TextEdit, Ui, Window,
+};
+use simulator_core::{
+    analysis::{
+        atpg::generate_tests,
+        fault_simulation::{describe_fault, fault_simulation, stuck_at_faults},
+        test_vectors::{parse_vectors, TestVectors},
+        truth_table::describe,
+    },
+    components::Component,
+    graph::fault::{Fault, FaultValue, Pin},
+};
+
+use crate::{
+    nodegraph::graph::NodeGraph,
+    state::{app::AppState, faults::FaultState},
+    widgets::{nodegraph::widget::output_cables_coloring, ui::truth_table::choose},
+};
+
+/// Injects faults into the running circuit and grades test vectors by the faults they detect.
+pub fn show_faults_window(ctx: &Context, app_state: &mut AppState) {
+    let AppState {
+        node_graph,
+        fault_state: state,
+        ..
+    } = app_state;
+
+    let graph = &node_graph.graph;
+    if state.node.is_some_and(|id| !graph.nodes.contains_key(id)) {
+        state.node = None;
+    }
+    state.inputs.retain(|&id| graph.nodes.contains_key(id));
+    state.outputs.retain(|&id| graph.nodes.contains_key(id));
+
+    let mut open = state.open;
+    Window::new("Faults").open(&mut open).show(ctx, |ui| {
+        inject(ui, node_graph, state);
+        ui.separator();
+        CollapsingHeader::new("Fault simulation").show(ui, |ui| {
+            simulate(ui, node_graph, state);
+        });
+    });
+    state.open = open;
+}
+
+fn inject(ui: &mut Ui, node_graph: &mut NodeGraph, state: &mut FaultState) {
+    let graph = &node_graph.graph;
+    ui.horizontal(|ui| {
+        ComboBox::from_id_source("fault node")
+            .selected_text(
+                state
+                    .node
+                    .map_or("Component".to_string(), |id| describe(graph, id)),
+            )
+            .show_ui(ui, |ui| {
+                for id in graph.nodes.keys() {
+                    ui.selectable_value(&mut state.node, Some(id), describe(graph, id));
+                }
+            });
+
+        let Some(node) = state.node.map(|id| &graph.nodes[id]) else {
+            return;
+        };
+        let inputs = (0..node.input_slots.len()).map(Pin::Input);
+        let pins = inputs.chain((0..node.output_slots.len()).map(Pin::Output));
+        ComboBox::from_id_source("fault pin")
+            .selected_text(pin_label(state.pin))
+            .show_ui(ui, |ui| {
+                for pin in pins {
+                    ui.selectable_value(&mut state.pin, pin, pin_label(pin));
+                }
+            });
+    });
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut state.value, FaultValue::StuckAt(false), "Stuck at 0");
+        ui.radio_value(&mut state.value, FaultValue::StuckAt(true), "Stuck at 1");
+        ui.radio_value(&mut state.value, FaultValue::Floating, "Floating");
+    });
+
+    let valid = state.node.is_some_and(|id| {
+        let node = &graph.nodes[id];
+        match state.pin {
+            Pin::Input(slot) => slot < node.input_slots.len(),
+            Pin::Output(slot) => slot < node.output_slots.len(),
+        }
+    });
+    if ui.add_enabled(valid, Button::new("Inject")).clicked() {
+        node_graph.graph.inject_fault(Fault {
+            node: state.node.unwrap(),
+            pin: state.pin,
+            value: state.value,
+        });
+        output_cables_coloring(node_graph);
+    }
+
+    let faults = node_graph.graph.faults().collect::<Vec<_>>();
+    let mut cleared = None;
+    for fault in &faults {
+        ui.horizontal(|ui| {
+            ui.label(describe_fault(&node_graph.graph, fault));
+            if ui.small_button("Clear").clicked() {
+                cleared = Some(*fault);
+            }
+        });
+    }
+    if let Some(fault) = cleared {
+        node_graph.graph.clear_fault(fault.node, fault.pin);
+        output_cables_coloring(node_graph);
+    }
+    if !faults.is_empty() && ui.button("Clear all").clicked() {
+        node_graph.graph.clear_faults();
+        output_cables_coloring(node_graph);
+    }
+}
+
+fn pin_label(pin: Pin) -> String {
+    match pin {
+        Pin::Input(slot) => format!("Input {slot}"),
+        Pin::Output(slot) => format!("Output {slot}"),
+    }
+}
+
+fn simulate(ui: &mut Ui, node_graph: &NodeGraph, state: &mut FaultState) {
+    let graph = &node_graph.graph;
+    ui.columns(2, |columns| {
+        columns[0].label("Inputs");
+        choose(&mut columns[0], graph, &mut state.inputs, "Constant", |c| {
+            matches!(c, Component::Constant(_))
+        });
+        columns[1].label("Outputs");
+        choose(&mut columns[1], graph, &mut state.outputs, "Output", |c| {
+            matches!(c, Component::DebugOutput(_))
+        });
+    });
+    ui.add(
+        TextEdit::multiline(&mut state.vectors)
+            .code_editor()
+            .desired_rows(8)
+            .hint_text("One vector per line, like 0 1 1"),
+    );
+
+    let inputs = state.inputs.iter().map(|&id| id.into()).collect::<Vec<_>>();
+    let outputs = state
+        .outputs
+        .iter()
+        .map(|&id| id.into())
+        .collect::<Vec<_>>();
+    ui.horizontal(|ui| {
+        if ui.button("Run").clicked() {
+            state.report = None;
+            match parse_vectors(&state.vectors, inputs.len()) {
+                Ok(vectors) => {
+                    let vectors = TestVectors {
+                        inputs: inputs.clone(),
+                        outputs: outputs.clone(),
+                        vectors,
+                    };
+                    let faults = stuck_at_faults(graph);
+                    state.report = Some(fault_simulation(graph, &vectors, &faults));
+                    state.error = None;
+                }
+                Err(err) => state.error = Some(err.to_string()),
+            }
+        }
+        if ui.button("Generate tests").clicked() {
+            state.report = None;
+            match generate_tests(graph, &inputs, &outputs) {
+                Ok(set) => {
+                    state.vectors = set.vectors.to_text();
+                    let faults = stuck_at_faults(graph);
+                    state.report = Some(fault_simulation(graph, &set.vectors, &faults));
+                    state.error = None;
+                }
+                Err(err) => state.error = Some(err.to_string()),
+            }
+        }
+    });
+
+    if let Some(error) = &state.error {
+        ui.colored_label(Color32::RED, error);
+    }
+    let Some(report) = &state.report else {
+        return;
+    };
+    ui.label(format!(
+        "Coverage {:.1}%: {} of {} faults detected",
+        report.coverage() * 100.0,
+        report.detected.len(),
+        report.detected.len() + report.undetected.len()
+    ));
+    ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+        let undetected = report.undetected.iter();
+        for fault in undetected.filter(|fault| graph.nodes.contains_key(fault.node)) {
+            ui.label(describe_fault(graph, fault));
+        }
+    });
+}
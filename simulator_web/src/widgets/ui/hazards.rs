@@ -0,0 +1,104 @@
+use egui::{Context, ScrollArea, Window};
+use simulator_core::{
+    analysis::{
+        hazards::{find_hazards, HazardKind},
+        races::find_races,
+        truth_table::describe,
+    },
+    components::Component,
+};
+
+use crate::{
+    state::{app::AppState, hazards::RACE_ORDERS},
+    widgets::ui::truth_table::choose,
+};
+
+/// Lists the nets which glitch and the outputs which race when a chosen input is toggled.
+pub fn show_hazards_window(ctx: &Context, app_state: &mut AppState) {
+    let AppState {
+        node_graph,
+        hazard_state: state,
+        ..
+    } = app_state;
+
+    let graph = &node_graph.graph;
+    state.inputs.retain(|&id| graph.nodes.contains_key(id));
+
+    Window::new("Hazards")
+        .open(&mut state.open)
+        .show(ctx, |ui| {
+            ui.label("Inputs");
+            choose(ui, graph, &mut state.inputs, "Constant", |c| {
+                matches!(c, Component::Constant(_))
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Check glitches").clicked() {
+                    let inputs = state.inputs.iter().map(|&id| id.into()).collect::<Vec<_>>();
+                    state.reports = Some(find_hazards(graph, &inputs));
+                }
+                if ui.button("Check races").clicked() {
+                    let races = state.inputs.iter().enumerate().map(|(seed, &id)| {
+                        (id, find_races(graph, id.into(), RACE_ORDERS, seed as u64))
+                    });
+                    state.races = Some(races.collect());
+                }
+            });
+
+            if let Some(races) = &state.races {
+                let races = races
+                    .iter()
+                    .filter(|(input, _)| graph.nodes.contains_key(*input));
+                for (input, races) in races {
+                    ui.strong(format!("{} toggled", describe(graph, *input)));
+                    if races.is_empty() {
+                        ui.label("Settles the same in every order.");
+                    }
+                    for race in races {
+                        let output = race.output.into();
+                        if graph.nodes.contains_key(output) {
+                            ui.label(format!(
+                                "{} is 1 in {} of {} orders",
+                                describe(graph, output),
+                                race.ones,
+                                race.orders
+                            ));
+                        }
+                    }
+                }
+                ui.separator();
+            }
+
+            let Some(reports) = &state.reports else {
+                return;
+            };
+            if reports.is_empty() {
+                ui.label("No net glitches.");
+            }
+            ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                for report in reports {
+                    let input = report.input.into();
+                    if !graph.nodes.contains_key(input) {
+                        continue;
+                    }
+                    ui.strong(format!(
+                        "{} set to {}",
+                        describe(graph, input),
+                        report.state as u8
+                    ));
+                    let glitches = report.glitches.iter();
+                    for glitch in glitches.filter(|glitch| graph.nodes.contains_key(glitch.node)) {
+                        let kind = match glitch.kind() {
+                            HazardKind::Static => "static",
+                            HazardKind::Dynamic => "dynamic",
+                        };
+                        ui.label(format!(
+                            "{} output {}: {} transitions, {kind} hazard",
+                            describe(graph, glitch.node),
+                            glitch.slot,
+                            glitch.transitions
+                        ));
+                    }
+                }
+            });
+        });
+}
@@ -0,0 +1,78 @@
+use egui::{Color32, Context, TextEdit, Window};
+use simulator_core::import::{logisim::from_logisim, verilog::from_verilog};
+
+use crate::{
+    state::{app::AppState, import::ImportFormat},
+    widgets::nodegraph::widget::output_cables_coloring,
+};
+
+pub fn show_import_window(ctx: &Context, app_state: &mut AppState) {
+    let AppState {
+        node_graph,
+        import_state,
+        ..
+    } = app_state;
+
+    let mut open = import_state.open;
+    let mut imported = false;
+
+    Window::new("Import").open(&mut open).show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut import_state.format, ImportFormat::Verilog, "Verilog");
+            ui.selectable_value(&mut import_state.format, ImportFormat::Logisim, "Logisim");
+        });
+        let (source_label, top_label) = match import_state.format {
+            ImportFormat::Verilog => ("Structural Verilog source:", "Top module:"),
+            ImportFormat::Logisim => ("Contents of the .circ file:", "Circuit:"),
+        };
+
+        ui.label(source_label);
+        ui.add(
+            TextEdit::multiline(&mut import_state.source)
+                .code_editor()
+                .desired_rows(16),
+        );
+        ui.horizontal(|ui| {
+            ui.label(top_label);
+            ui.add(TextEdit::singleline(&mut import_state.top).hint_text("automatic"));
+        });
+
+        if ui.button("Import").clicked() {
+            import_state.notes.clear();
+            let top = Some(import_state.top.trim()).filter(|t| !t.is_empty());
+            let result = match import_state.format {
+                ImportFormat::Verilog => from_verilog(&import_state.source, top)
+                    .map(|graph| {
+                        node_graph.insert_graph(graph);
+                    })
+                    .map_err(|err| err.to_string()),
+                ImportFormat::Logisim => from_logisim(&import_state.source, top)
+                    .map(|circuit| {
+                        import_state.notes = node_graph.insert_logisim(circuit).1;
+                    })
+                    .map_err(|err| err.to_string()),
+            };
+            match result {
+                Ok(()) => {
+                    output_cables_coloring(node_graph);
+                    import_state.error = None;
+                    imported = true;
+                }
+                Err(err) => import_state.error = Some(err),
+            }
+        }
+
+        if let Some(error) = &import_state.error {
+            ui.colored_label(Color32::RED, error);
+        }
+        if !import_state.notes.is_empty() {
+            ui.label("Laid out automatically:");
+            for note in &import_state.notes {
+                ui.label(note);
+            }
+        }
+    });
+
+    // Stay open to show what was laid out automatically.
+    import_state.open = open && !(imported && import_state.notes.is_empty());
+}
@@ -0,0 +1,244 @@
+use egui::{
+    vec2, Align2, CollapsingHeader, Color32, Context, FontId, Grid, Rect, Sense, Stroke, TextEdit,
+    Ui, Window,
+};
+use simulator_core::{
+    analysis::{
+        minimize::{minimal_cover, prime_implicants, sum_of_products, Implicant},
+        synthesis::{synthesize, Equations, GateStyle},
+        truth_table::truth_table,
+    },
+    components::Component,
+    graph::Graph,
+};
+
+use crate::{
+    state::{
+        app::AppState,
+        karnaugh::{CellValue, KarnaughState, MAX_VARS, MIN_VARS},
+    },
+    widgets::{nodegraph::widget::output_cables_coloring, ui::truth_table::choose},
+};
+
+const CELL_SIZE: f32 = 32.0;
+
+#[rustfmt::skip]
+const GROUP_COLORS: [Color32; 8] = [
+    Color32::RED, Color32::BLUE, Color32::GREEN, Color32::GOLD,
+    Color32::from_rgb(200, 0, 200), Color32::from_rgb(0, 180, 180),
+    Color32::from_rgb(255, 128, 0), Color32::GRAY,
+];
+
+/// Karnaugh map editor, showing the groups of the minimized expression.
+pub fn show_karnaugh_window(ctx: &Context, app_state: &mut AppState) {
+    let AppState {
+        node_graph,
+        karnaugh_state: state,
+        ..
+    } = app_state;
+
+    state
+        .inputs
+        .retain(|&id| node_graph.graph.nodes.contains_key(id));
+
+    let mut open = state.open;
+    Window::new("Karnaugh map").open(&mut open).show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Variables:");
+            for vars in MIN_VARS..=MAX_VARS {
+                if ui
+                    .selectable_label(state.vars() == vars, vars.to_string())
+                    .clicked()
+                {
+                    state.set_vars(vars);
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            for name in &mut state.names {
+                ui.add(TextEdit::singleline(name).desired_width(40.0));
+            }
+            ui.label("->");
+            ui.add(TextEdit::singleline(&mut state.output).desired_width(40.0));
+        });
+
+        CollapsingHeader::new("From circuit").show(ui, |ui| {
+            load_from_circuit(ui, &mut node_graph.graph, state);
+        });
+        ui.separator();
+
+        let vars = state.vars();
+        let minterms = |value| {
+            let cells = state.cells.iter().enumerate();
+            cells
+                .filter(|(_, &cell)| cell == value)
+                .map(|(m, _)| m as u32)
+                .collect::<Vec<_>>()
+        };
+        let (ones, dont_cares) = (minterms(CellValue::One), minterms(CellValue::DontCare));
+        let primes = prime_implicants(vars, &ones, &dont_cares);
+        let cover = minimal_cover(vars, &primes, &ones);
+        let groups = if state.all_primes { &primes } else { &cover };
+
+        show_map(ui, state, groups);
+        ui.checkbox(&mut state.all_primes, "Show all prime implicants");
+
+        for (i, group) in groups.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let (rect, _) = ui.allocate_exact_size(vec2(12.0, 12.0), Sense::hover());
+                ui.painter()
+                    .rect_filled(rect, 0.0, GROUP_COLORS[i % GROUP_COLORS.len()]);
+                ui.monospace(group.to_expr(vars).display(&state.names).to_string());
+            });
+        }
+
+        let minimized = sum_of_products(vars, &cover);
+        ui.monospace(format!(
+            "{} = {}",
+            state.output,
+            minimized.display(&state.names)
+        ));
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut state.style, GateStyle::Mixed, "Any gates");
+            ui.radio_value(&mut state.style, GateStyle::NandOnly, "NAND only");
+            ui.radio_value(&mut state.style, GateStyle::NorOnly, "NOR only");
+        });
+        if ui.button("Insert circuit").clicked() {
+            let equations = Equations {
+                inputs: state.names.clone(),
+                outputs: vec![(state.output.clone(), minimized)],
+            };
+            node_graph.insert_graph(synthesize(&equations, state.style));
+            output_cables_coloring(node_graph);
+        }
+
+        if let Some(error) = &state.error {
+            ui.colored_label(Color32::RED, error);
+        }
+    });
+    state.open = open;
+}
+
+fn load_from_circuit(ui: &mut Ui, graph: &mut Graph, state: &mut KarnaughState) {
+    ui.columns(2, |columns| {
+        columns[0].label("Inputs");
+        choose(&mut columns[0], graph, &mut state.inputs, "Constant", |c| {
+            matches!(c, Component::Constant(_))
+        });
+        columns[1].label("Output");
+        let outputs = graph
+            .nodes
+            .iter()
+            .filter(|(_, node)| matches!(node.component, Component::DebugOutput(_)));
+        for (n, (id, node)) in outputs.enumerate() {
+            let label = node.name.clone().unwrap_or_else(|| format!("Output {n}"));
+            columns[1].radio_value(&mut state.source_output, Some(id), label);
+        }
+    });
+
+    if ui.button("Load").clicked() {
+        state.error = None;
+        let Some(output) = state.source_output.filter(|&id| graph.nodes.contains_key(id)) else {
+            state.error = Some("choose an output".to_string());
+            return;
+        };
+        if !(MIN_VARS..=MAX_VARS).contains(&state.inputs.len()) {
+            state.error = Some(format!("choose between {MIN_VARS} and {MAX_VARS} inputs"));
+            return;
+        }
+
+        let inputs = state.inputs.iter().map(|&id| id.into()).collect::<Vec<_>>();
+        match truth_table(graph, &inputs, &[output.into()]) {
+            Ok(table) => {
+                state.names = table.inputs;
+                state.output = table.outputs[0].clone();
+                state.cells = table
+                    .rows
+                    .iter()
+                    .map(|row| match row[0] {
+                        true => CellValue::One,
+                        false => CellValue::Zero,
+                    })
+                    .collect();
+            }
+            Err(err) => state.error = Some(err.to_string()),
+        }
+    }
+}
+
+fn gray(i: usize) -> usize {
+    i ^ (i >> 1)
+}
+
+fn bits(value: usize, count: usize) -> String {
+    (0..count)
+        .rev()
+        .map(|bit| if value >> bit & 1 == 1 { '1' } else { '0' })
+        .collect()
+}
+
+/// Draws the map with rows and columns in Gray code order, cells cycle `0`, `1`, `x` when clicked.
+fn show_map(ui: &mut Ui, state: &mut KarnaughState, groups: &[Implicant]) {
+    let vars = state.vars();
+    let row_vars = vars / 2;
+    let col_vars = vars - row_vars;
+
+    Grid::new("karnaugh map")
+        .spacing(vec2(2.0, 2.0))
+        .show(ui, |ui| {
+            ui.label(format!(
+                "{} \\ {}",
+                state.names[..row_vars].concat(),
+                state.names[row_vars..].concat()
+            ));
+            for col in 0..1 << col_vars {
+                ui.monospace(bits(gray(col), col_vars));
+            }
+            ui.end_row();
+
+            for row in 0..1 << row_vars {
+                ui.monospace(bits(gray(row), row_vars));
+                for col in 0..1 << col_vars {
+                    let minterm = gray(row) << col_vars | gray(col);
+                    let (rect, response) =
+                        ui.allocate_exact_size(vec2(CELL_SIZE, CELL_SIZE), Sense::click());
+                    if response.clicked() {
+                        state.cells[minterm] = state.cells[minterm].next();
+                    }
+                    paint_cell(ui, rect, state.cells[minterm], minterm as u32, groups);
+                }
+                ui.end_row();
+            }
+        });
+}
+
+fn paint_cell(ui: &Ui, rect: Rect, value: CellValue, minterm: u32, groups: &[Implicant]) {
+    let painter = ui.painter();
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 0.0, visuals.extreme_bg_color);
+    painter.rect_stroke(rect, 0.0, visuals.widgets.noninteractive.bg_stroke);
+
+    // Every group gets its own frame, nested ones a bit further in.
+    for (i, group) in groups.iter().enumerate() {
+        if group.covers(minterm) {
+            let color = GROUP_COLORS[i % GROUP_COLORS.len()];
+            let inset = 2.0 + 2.0 * (i % 6) as f32;
+            painter.rect_stroke(rect.shrink(inset), 2.0, Stroke::new(1.5, color));
+        }
+    }
+
+    let text = match value {
+        CellValue::Zero => "0",
+        CellValue::One => "1",
+        CellValue::DontCare => "x",
+    };
+    painter.text(
+        rect.center(),
+        Align2::CENTER_CENTER,
+        text,
+        FontId::monospace(14.0),
+        visuals.text_color(),
+    );
+}
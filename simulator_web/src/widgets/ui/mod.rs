@@ -1,2 +1,11 @@
 pub mod side_menu;
-pub mod components;
\ No newline at end of file
+pub mod components;
+pub mod import;
+pub mod export;
+pub mod truth_table;
+pub mod synthesis;
+pub mod karnaugh;
+pub mod model_checking;
+pub mod faults;
+pub mod event_log;
+pub mod hazards;
\ No newline at end of file
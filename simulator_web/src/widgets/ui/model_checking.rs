@@ -0,0 +1,128 @@
+use egui::{Button, Color32, ComboBox, Context, DragValue, Grid, ScrollArea, TextEdit, Ui, Window};
+use simulator_core::{
+    analysis::bmc::{check_property, BmcOptions, BmcResult, Property, Trace},
+    components::Component,
+};
+
+use crate::{
+    nodegraph::graph::NodeGraph, state::app::AppState,
+    widgets::nodegraph::widget::output_cables_coloring,
+};
+
+/// Bounded model checking of a property, with the counterexample replayable step by step.
+pub fn show_model_checking_window(ctx: &Context, app_state: &mut AppState) {
+    let AppState {
+        node_graph,
+        model_checking_state: state,
+        ..
+    } = app_state;
+
+    if state
+        .clock
+        .is_some_and(|id| !node_graph.graph.nodes.contains_key(id))
+    {
+        state.clock = None;
+    }
+
+    let mut open = state.open;
+    Window::new("Model checking")
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut state.always, false, "Never");
+                ui.radio_value(&mut state.always, true, "Always");
+                ui.add(TextEdit::singleline(&mut state.property).hint_text("en_a & en_b"));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Cycles:");
+                ui.add(DragValue::new(&mut state.cycles).clamp_range(1..=64));
+                ui.label("Clock:");
+                let graph = &node_graph.graph;
+                let name = |id| graph.name(id).unwrap_or("unnamed").to_string();
+                ComboBox::from_id_source("model checking clock")
+                    .selected_text(state.clock.map_or("none".to_string(), name))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut state.clock, None, "none");
+                        let constants = graph
+                            .nodes
+                            .iter()
+                            .filter(|(_, node)| matches!(node.component, Component::Constant(_)));
+                        for (id, _) in constants {
+                            ui.selectable_value(&mut state.clock, Some(id), name(id));
+                        }
+                    });
+            });
+
+            if ui.button("Check").clicked() {
+                let property = if state.always {
+                    Property::Always(state.property.clone())
+                } else {
+                    Property::Never(state.property.clone())
+                };
+                let options = BmcOptions {
+                    cycles: state.cycles,
+                    clock: state.clock.map(Into::into),
+                };
+                state.result = Some(check_property(&node_graph.graph, &property, &options));
+                state.replayed = 0;
+            }
+
+            match &state.result {
+                Some(Ok(BmcResult::Holds)) => {
+                    ui.label(format!("The property holds for {} cycles.", state.cycles));
+                }
+                Some(Ok(BmcResult::Violated(trace))) => {
+                    ui.separator();
+                    show_counterexample(ui, node_graph, trace, &mut state.replayed);
+                }
+                Some(Err(err)) => {
+                    ui.colored_label(Color32::RED, err.to_string());
+                }
+                None => {}
+            }
+        });
+    state.open = open;
+}
+
+fn show_counterexample(
+    ui: &mut Ui,
+    node_graph: &mut NodeGraph,
+    trace: &Trace,
+    replayed: &mut usize,
+) {
+    ui.label(format!("Violated after {} steps.", trace.steps()));
+    ui.horizontal(|ui| {
+        let next = ui.add_enabled(*replayed < trace.steps(), Button::new("Replay step"));
+        if next.clicked() {
+            trace.apply(&mut node_graph.graph, *replayed);
+            output_cables_coloring(node_graph);
+            *replayed += 1;
+        }
+        if ui.button("Copy VCD").clicked() {
+            ui.output_mut(|o| o.copied_text = trace.to_vcd());
+        }
+    });
+
+    ScrollArea::both().max_height(300.0).show(ui, |ui| {
+        Grid::new("counterexample").striped(true).show(ui, |ui| {
+            ui.strong("Step");
+            for step in 0..trace.steps() {
+                let text = step.to_string();
+                if step < *replayed {
+                    ui.strong(text);
+                } else {
+                    ui.label(text);
+                }
+            }
+            ui.end_row();
+
+            for (i, name) in trace.signals.iter().enumerate() {
+                ui.label(name);
+                for values in &trace.values {
+                    ui.monospace((values[i] as u8).to_string());
+                }
+                ui.end_row();
+            }
+        });
+    });
+}
@@ -1,8 +1,14 @@
-use egui::Ui;
+use egui::{Button, Slider, Ui};
 
 use crate::{
     components::registry::ComponentRegistry,
-    state::modes::{AddingOptions, Mode, ModeState},
+    nodegraph::graph::NodeGraph,
+    state::{
+        animation::AnimationState,
+        history::HistoryState,
+        modes::{AddingOptions, Mode, ModeState},
+    },
+    widgets::nodegraph::widget::output_cables_coloring,
 };
 
 pub fn show_mode_choice(ui: &mut Ui, state: &mut ModeState) {
@@ -12,6 +18,53 @@ pub fn show_mode_choice(ui: &mut Ui, state: &mut ModeState) {
     ui.radio_value(&mut state.mode, Mode::Deleting, "Deleting");
 }
 
+/// Steps the running circuit back and forth through the recorded clicks.
+pub fn show_history_controls(ui: &mut Ui, state: &mut HistoryState, node_graph: &mut NodeGraph) {
+    let (time, len) = state
+        .history
+        .as_ref()
+        .map_or((0, 0), |history| (history.time(), history.len()));
+    ui.horizontal(|ui| {
+        let back = ui.add_enabled(time > 0, Button::new("⏴ Back"));
+        let forward = ui.add_enabled(time < len, Button::new("Forward ⏵"));
+        ui.label(format!("{time}/{len}"));
+
+        let Some(history) = &mut state.history else {
+            return;
+        };
+        let graph = &mut node_graph.graph;
+        let stepped = (back.clicked() && history.step_back(graph))
+            || (forward.clicked() && history.step_forward(graph));
+        if stepped {
+            output_cables_coloring(node_graph);
+        }
+    });
+}
+
+pub fn show_animation_controls(ui: &mut Ui, state: &mut AnimationState) {
+    ui.checkbox(&mut state.enabled, "Animate propagation");
+    if state.enabled {
+        ui.add(
+            Slider::new(&mut state.speed, 0.5..=30.0)
+                .logarithmic(true)
+                .text("steps/s"),
+        );
+    }
+}
+
+/// Switches the circuit to four-valued logic, returns whether it was switched.
+pub fn show_logic_choice(ui: &mut Ui, node_graph: &mut NodeGraph) -> bool {
+    let mut four_valued = node_graph.graph.is_four_valued();
+    let changed = ui
+        .checkbox(&mut four_valued, "Four-valued logic (X, Z)")
+        .changed();
+    if changed {
+        node_graph.graph.set_four_valued(four_valued);
+        output_cables_coloring(node_graph);
+    }
+    changed
+}
+
 pub fn show_adding_choice(ui: &mut Ui, state: &mut ModeState, registry: &ComponentRegistry) {
     ui.radio_value(&mut state.add_opt, AddingOptions::Cable, "Cable");
     for (rid, entry) in registry.iter() {
@@ -0,0 +1,66 @@
+use egui::{Color32, Context, TextEdit, Window};
+use simulator_core::analysis::synthesis::{
+    parse_equations, parse_truth_table, synthesize, GateStyle,
+};
+
+use crate::{
+    state::{app::AppState, synthesis::SynthesisSource},
+    widgets::nodegraph::widget::output_cables_coloring,
+};
+
+/// Builds a gate network from equations or a truth table and places it next to the drawing.
+pub fn show_synthesis_window(ctx: &Context, app_state: &mut AppState) {
+    let AppState {
+        node_graph,
+        synthesis_state: state,
+        ..
+    } = app_state;
+
+    let mut open = state.open;
+    let mut inserted = false;
+
+    Window::new("Synthesis").open(&mut open).show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            let kind = &mut state.source_kind;
+            ui.selectable_value(kind, SynthesisSource::Equations, "Equations");
+            ui.selectable_value(kind, SynthesisSource::TruthTable, "Truth table");
+        });
+        let hint = match state.source_kind {
+            SynthesisSource::Equations => "Q = (A & !B) | C",
+            SynthesisSource::TruthTable => "A B | Q\n0 0 | 0\n0 1 | 1\n1 0 | 1\n1 1 | x",
+        };
+        ui.add(
+            TextEdit::multiline(&mut state.source)
+                .code_editor()
+                .desired_rows(12)
+                .hint_text(hint),
+        );
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut state.style, GateStyle::Mixed, "Any gates");
+            ui.radio_value(&mut state.style, GateStyle::NandOnly, "NAND only");
+            ui.radio_value(&mut state.style, GateStyle::NorOnly, "NOR only");
+        });
+
+        if ui.button("Insert").clicked() {
+            let equations = match state.source_kind {
+                SynthesisSource::Equations => parse_equations(&state.source),
+                SynthesisSource::TruthTable => parse_truth_table(&state.source),
+            };
+            match equations {
+                Ok(equations) => {
+                    node_graph.insert_graph(synthesize(&equations, state.style));
+                    output_cables_coloring(node_graph);
+                    state.error = None;
+                    inserted = true;
+                }
+                Err(err) => state.error = Some(err.to_string()),
+            }
+        }
+
+        if let Some(error) = &state.error {
+            ui.colored_label(Color32::RED, error);
+        }
+    });
+
+    state.open = open && !inserted;
+}
@@ -0,0 +1,143 @@
+use egui::{Color32, Context, Grid, ScrollArea, Ui, Window};
+use simulator_core::{
+    analysis::{
+        minimize::{minimize_outputs, MinimizationReport},
+        truth_table::{truth_table, TruthTable},
+    },
+    components::Component,
+    graph::{id::ComponentId, Graph},
+};
+
+use crate::state::app::AppState;
+
+/// Lets the user choose inputs and outputs and shows the truth table between them.
+pub fn show_truth_table_window(ctx: &Context, app_state: &mut AppState) {
+    let AppState {
+        node_graph,
+        truth_table_state: state,
+        ..
+    } = app_state;
+    let graph = &mut node_graph.graph;
+
+    state.inputs.retain(|&id| graph.nodes.contains_key(id));
+    state.outputs.retain(|&id| graph.nodes.contains_key(id));
+
+    Window::new("Truth table")
+        .open(&mut state.open)
+        .show(ctx, |ui| {
+            ui.columns(2, |columns| {
+                columns[0].label("Inputs");
+                choose(&mut columns[0], graph, &mut state.inputs, "Constant", |c| {
+                    matches!(c, Component::Constant(_))
+                });
+                columns[1].label("Outputs");
+                choose(&mut columns[1], graph, &mut state.outputs, "Output", |c| {
+                    matches!(c, Component::DebugOutput(_))
+                });
+            });
+
+            if ui.button("Generate").clicked() {
+                let inputs = state.inputs.iter().map(|&id| id.into()).collect::<Vec<_>>();
+                let outputs = state
+                    .outputs
+                    .iter()
+                    .map(|&id| id.into())
+                    .collect::<Vec<_>>();
+                state.result = Some(truth_table(graph, &inputs, &outputs));
+                state.report = minimize_outputs(graph, &inputs, &outputs).ok();
+            }
+
+            match &state.result {
+                Some(Ok(table)) => {
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button("Copy CSV").clicked() {
+                            ui.output_mut(|o| o.copied_text = table.to_csv());
+                        }
+                        if ui.button("Copy Markdown").clicked() {
+                            ui.output_mut(|o| o.copied_text = table.to_markdown());
+                        }
+                    });
+                    show_table(ui, table);
+                    if let Some(report) = &state.report {
+                        ui.separator();
+                        show_report(ui, report);
+                    }
+                }
+                Some(Err(err)) => {
+                    ui.colored_label(Color32::RED, err.to_string());
+                }
+                None => {}
+            }
+        });
+}
+
+/// Checkboxes for the components matching `filter`, ticked ones are kept in `chosen` in ticking order.
+pub fn choose(
+    ui: &mut Ui,
+    graph: &Graph,
+    chosen: &mut Vec<ComponentId>,
+    kind: &str,
+    filter: impl Fn(&Component) -> bool,
+) {
+    let candidates = graph
+        .nodes
+        .iter()
+        .filter(|(_, node)| filter(&node.component));
+    for (n, (id, node)) in candidates.enumerate() {
+        let label = node.name.clone().unwrap_or_else(|| format!("{kind} {n}"));
+        let mut checked = chosen.contains(&id);
+        if ui.checkbox(&mut checked, label).changed() {
+            if checked {
+                chosen.push(id);
+            } else {
+                chosen.retain(|&other| other != id);
+            }
+        }
+    }
+}
+
+fn show_report(ui: &mut Ui, report: &MinimizationReport) {
+    for output in &report.outputs {
+        ui.monospace(format!(
+            "{} = {}",
+            output.name,
+            output.expression.display(&report.inputs)
+        ));
+        ui.monospace(format!(
+            "{} = {} (minimized)",
+            output.name,
+            output.minimized.display(&report.inputs)
+        ));
+        ui.label(format!(
+            "Gates: {} in the circuit, {} in the minimized form",
+            output.circuit_gates, output.minimized_gates
+        ));
+    }
+}
+
+fn show_table(ui: &mut Ui, table: &TruthTable) {
+    ScrollArea::both().max_height(400.0).show(ui, |ui| {
+        Grid::new("truth table").striped(true).show(ui, |ui| {
+            for name in &table.inputs {
+                ui.strong(name);
+            }
+            ui.separator();
+            for name in &table.outputs {
+                ui.strong(name);
+            }
+            ui.end_row();
+
+            for (row, outputs) in table.rows.iter().enumerate() {
+                for input in 0..table.inputs.len() {
+                    ui.label((table.input(row, input) as u8).to_string());
+                }
+                ui.separator();
+                for output in outputs.iter().by_vals() {
+                    ui.label((output as u8).to_string());
+                }
+                ui.end_row();
+            }
+        });
+    });
+}